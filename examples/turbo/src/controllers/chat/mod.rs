@@ -1,7 +1,5 @@
 //! Chat controller
 
-use std::collections::HashMap;
-
 use crate::models::{ChatMessage, User};
 use rwf::prelude::*;
 
@@ -59,23 +57,20 @@ impl PageController for ChatController {
         let mut conn = Pool::connection().await?;
         let user = request.user_required::<User>(&mut conn).await?;
 
-        let users = User::all().fetch_all(&mut conn).await?;
-        let messages = User::related::<ChatMessage>(&users)
-            .order("id")
+        let messages = ChatMessage::order("id")
+            .preload::<User>()
             .fetch_all(&mut conn)
             .await?;
 
-        let users = users
-            .into_iter()
-            .map(|user| (user.id.unwrap(), user))
-            .collect::<HashMap<_, _>>();
-
         let messages = messages
             .into_iter()
-            .map(|message| UserMessage {
-                user: users[&message.user_id].clone(),
-                mine: users[&message.user_id].id() == user.id(),
-                message,
+            .filter_map(|message| {
+                let author = message.preloaded::<User>().first()?.clone();
+                Some(UserMessage {
+                    mine: author.id() == user.id(),
+                    user: author,
+                    message: message.into_inner(),
+                })
             })
             .collect::<Vec<_>>();
 
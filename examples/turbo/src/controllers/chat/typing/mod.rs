@@ -22,7 +22,7 @@ impl Controller for TypingController {
             })
             .into())
         } else {
-            Ok(Response::new().redirect("/signup"))
+            Ok(Response::new().redirect_see_other("/signup"))
         }
     }
 }
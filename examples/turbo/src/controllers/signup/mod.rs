@@ -57,7 +57,7 @@ impl PageController for SignupController {
             })
             .await?;
 
-        Ok(request.login(user.id.unwrap()).redirect("/chat"))
+        Ok(request.login(user.id.unwrap()).redirect_see_other("/chat"))
     }
 }
 
@@ -70,6 +70,6 @@ impl Controller for LogoutController {
     async fn handle(&self, request: &Request) -> Result<Response, Error> {
         // Remove the user session from the cookie
         // and redirect to signup.
-        Ok(request.logout().redirect("/signup"))
+        Ok(request.logout().redirect_see_other("/signup"))
     }
 }
@@ -1,5 +1,5 @@
 // use rwf::model::Error;
-use rwf::crypto::{hash, hash_validate};
+use rwf::crypto::{hash_password, verify_password};
 use rwf::prelude::*;
 use tokio::task::spawn_blocking;
 
@@ -20,8 +20,8 @@ pub struct User {
 impl User {
     /// Create new user with email and password.
     pub async fn signup(email: &str, password: &str) -> Result<UserLogin, Error> {
-        let hash_password = password.to_owned();
-        let encrypted_password = spawn_blocking(move || hash(hash_password.as_bytes()))
+        let plain_password = password.to_owned();
+        let encrypted_password = spawn_blocking(move || hash_password(&plain_password))
             .await
             .unwrap()?;
 
@@ -50,7 +50,13 @@ impl User {
             .fetch_optional(Pool::pool())
             .await?
         {
-            if hash_validate(password.as_bytes(), &user.password)? {
+            let password = password.to_owned();
+            let hash = user.password.clone();
+
+            if spawn_blocking(move || verify_password(&password, &hash))
+                .await
+                .unwrap()
+            {
                 return Ok(UserLogin::Ok(user));
             } else {
                 return Ok(UserLogin::WrongPassword);
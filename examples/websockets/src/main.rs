@@ -0,0 +1,55 @@
+use rwf::http::{self, Server};
+use rwf::prelude::*;
+
+/// Sends every message back to the client which sent it.
+#[derive(Default, macros::WebsocketController)]
+struct Echo;
+
+#[async_trait]
+impl WebsocketController for Echo {
+    async fn client_message(&self, client: &SessionId, message: Message) -> Result<(), Error> {
+        Comms::websocket(client).send(message)?;
+        Ok(())
+    }
+}
+
+/// Sends every message to all clients in the chat room.
+#[derive(Default, macros::WebsocketController)]
+struct Chat;
+
+#[async_trait]
+impl WebsocketController for Chat {
+    async fn client_connected(&self, client: &SessionId) -> Result<(), Error> {
+        Comms::subscribe(client, "chat");
+        Comms::channel("chat").send(format!("{} joined", client))?;
+
+        // Tell the new client who else is here.
+        let online = Comms::presence("chat")
+            .iter()
+            .map(|member| member.session_id.to_string())
+            .collect::<Vec<_>>();
+        Comms::websocket(client).send(format!("online: {}", online.join(", ")))?;
+
+        Ok(())
+    }
+
+    async fn client_message(&self, _client: &SessionId, message: Message) -> Result<(), Error> {
+        Comms::channel("chat").send(message)?;
+        Ok(())
+    }
+
+    async fn client_disconnected(&self, client: &SessionId) -> Result<(), Error> {
+        Comms::unsubscribe(client, "chat");
+        Comms::channel("chat").send(format!("{} left", client))?;
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), http::Error> {
+    Logger::init();
+
+    Server::new(vec![route!("/echo" => Echo), route!("/chat" => Chat)])
+        .launch()
+        .await
+}
@@ -71,7 +71,7 @@ impl PageController for ModelController {
                         Row::find_by_sql(
                             format!(
                                 "SELECT * FROM \"{}\" {}LIMIT 25{}",
-                                table_name.escape(),
+                                table_name.escape_identifier(),
                                 order_by,
                                 format!(" OFFSET {}", (page - 1) * 25),
                             ),
@@ -134,11 +134,11 @@ impl PageController for NewModelController {
 
         for (column, value) in query {
             if column == "rwf_table_name" {
-                table_name.push(value.escape());
+                table_name.push(value.escape_identifier());
                 continue;
             }
 
-            columns.push(format!("\"{}\"", column.escape()));
+            columns.push(format!("\"{}\"", column.escape_identifier()));
             values.push(if value.is_empty() {
                 "NULL".to_string()
             } else {
@@ -18,16 +18,13 @@ pub async fn migrate(version: Option<i64>) {
 
 pub async fn revert(version: Option<i64>) {
     let migrations = Migrations::sync().await.expect("failed to sync migrations");
-    let version = if let Some(version) = version {
-        Some(version)
-    } else {
-        migrations.migrations().last().map(|v| v.version)
-    };
 
-    migrations
-        .apply(Direction::Down, version)
-        .await
-        .expect("failed to apply migrations");
+    if let Some(version) = version {
+        migrations.apply(Direction::Down, Some(version)).await
+    } else {
+        migrations.revert_last().await
+    }
+    .expect("failed to apply migrations");
 }
 
 pub async fn add(name: &str) {
@@ -12,19 +12,23 @@ use quote::quote;
 mod model;
 mod prelude;
 mod render;
+mod scopes;
 
 /// The `#[derive(Model)]` macro.
 ///
 /// This derive generates code which implements the `rwf::model::Model` trait. It uses
 /// the struct name and fields to generate implementations for the following methods:
 ///
-/// - `Model::table_name` returns the name of the struct, lowercased and pluralized
+/// - `Model::table_name` returns the name of the struct in snake_case, pluralized using English rules, e.g. `ProductCategory` becomes `"product_categories"`
 /// - `Model::column_names` returns the list of struct fields, in the order they are defined
 /// - `Model::values`, given an instance of the struct, returns the list of field values, converted
 /// to `Value` in the order they are defined on the struct; the field values must implement the `ToValue` trait
 /// - `Model::id` returns the value of the primary key, which is assumed to be the `id` field
 /// - `Model::foreign_key` returns the name of the foreign key column refering to this model; this is stylized as struct name, lowercased, concatenated with `"_id"`
 ///
+/// The `FromRow` trait is implemented as well. Columns that are `NULL` become `None` for `Option` fields, and the default value
+/// for fields with a type implementing `Default`, so a `NULL` doesn't fail the whole query.
+///
 /// Using this derive removes a lot of boilerplate code required by `rwf::model::Model` trait. That being said, using
 /// this derive is not required, and implementing the trait manually is feasible.
 ///
@@ -32,8 +36,14 @@ mod render;
 ///
 /// This derive accepts several attributes:
 ///
-/// - `table_name` overrides the value returned by `Model::table_name` implementation
+/// - `table_name` overrides the value returned by `Model::table_name` implementation, e.g. `#[table_name("people")]` or `#[table_name = "people"]`
+/// - `schema` sets the schema of the table returned by `Model::schema`
 /// - `foreign_key` overrides the value returned by `Model::foreign_key` implementation
+/// - `persisted` names a method of the struct used by `Model::is_persisted`
+/// - `soft_delete` enables soft delete using the `deleted_at` column, or the column given as argument, e.g. `#[soft_delete("removed_at")]`
+/// - `validate` calls the `Validate` implementation of the struct in `Model::validation`, before the model is saved
+/// - `callbacks` calls the `Callbacks` implementation of the struct when the model is saved, created and updated
+/// - `lock_version` overrides the column used for optimistic locking by `Model::lock_version`, `"lock_version"` by default if the struct has that field
 /// - `belongs_to` annotates the struct with a "belongs to" relationship to anoter model
 /// - `has_many` annotates the struct with a "has many" relationship to another model
 ///
@@ -127,7 +137,21 @@ mod render;
 /// }
 /// ```
 ///
-#[proc_macro_derive(Model, attributes(belongs_to, has_many, table_name, foreign_key))]
+#[proc_macro_derive(
+    Model,
+    attributes(
+        belongs_to,
+        has_many,
+        table_name,
+        schema,
+        foreign_key,
+        persisted,
+        lock_version,
+        soft_delete,
+        validate,
+        callbacks
+    )
+)]
 pub fn derive_model(input: TokenStream) -> TokenStream {
     model::impl_derive_model(input)
 }
@@ -317,7 +341,8 @@ pub fn derive_rest_controller(input: TokenStream) -> TokenStream {
 }
 
 /// Automatically implement the `FromRow` trait.
-/// Converts database rows to Rust struct fields.
+/// Converts database rows to Rust struct fields. Fields that are `NULL` in the row are set to
+/// `None` if the field is an `Option`, or the default value of the type, if it implements `Default`.
 #[proc_macro_derive(FromRow)]
 pub fn derive_from_row(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -326,12 +351,7 @@ pub fn derive_from_row(input: TokenStream) -> TokenStream {
         Data::Struct(ref data) => {
             let ident = input.ident;
 
-            let from_row_fields = data.fields.iter().map(|field| {
-                let ident = &field.ident;
-                quote! {
-                    #ident: row.try_get(stringify!(#ident))?,
-                }
-            });
+            let from_row_fields = data.fields.iter().map(model::from_row_field);
 
             quote! {
                 #[automatically_derived]
@@ -350,6 +370,112 @@ pub fn derive_from_row(input: TokenStream) -> TokenStream {
     }
 }
 
+/// Map a Rust enum to a Postgres `ENUM` type.
+///
+/// Implements `ToValue` and `FromSql`, so the enum can be used as a model field and in filters.
+/// Variants must not have fields. Each variant is stored as its name in snake_case, unless
+/// the label is set with the `#[label("...")]` attribute.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Clone, Debug, PartialEq, macros::Enum)]
+/// enum Status {
+///     Active,
+///     #[label("on_hold")]
+///     Paused,
+/// }
+/// ```
+#[proc_macro_derive(Enum, attributes(label))]
+pub fn derive_enum(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match input.data {
+        Data::Enum(ref data) => {
+            let ident = input.ident;
+
+            let variants = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    if !variant.fields.is_empty() {
+                        panic!("enum variants can't have fields");
+                    }
+
+                    let label = variant
+                        .attrs
+                        .iter()
+                        .find(|attr| attr.path().is_ident("label"))
+                        .map(|attr| {
+                            attr.parse_args::<syn::LitStr>()
+                                .expect("label must be a string")
+                                .value()
+                        })
+                        .unwrap_or_else(|| snake_case(&variant.ident.to_string()));
+
+                    (&variant.ident, label)
+                })
+                .collect::<Vec<_>>();
+
+            let to_label = variants.iter().map(|(variant, label)| {
+                quote! {
+                    Self::#variant => #label,
+                }
+            });
+
+            let from_label = variants.iter().map(|(variant, label)| {
+                quote! {
+                    #label => Ok(Self::#variant),
+                }
+            });
+
+            quote! {
+                #[automatically_derived]
+                impl rwf::model::ToValue for #ident {
+                    fn to_value(&self) -> rwf::model::Value {
+                        let label = match self {
+                            #(#to_label)*
+                        };
+
+                        rwf::model::Value::String(label.to_string())
+                    }
+                }
+
+                #[automatically_derived]
+                impl rwf::model::ToValue for Option<#ident> {
+                    fn to_value(&self) -> rwf::model::Value {
+                        rwf::model::Value::Optional(Box::new(self.as_ref().map(|v| v.to_value())))
+                    }
+                }
+
+                #[automatically_derived]
+                impl<'a> rwf::tokio_postgres::types::FromSql<'a> for #ident {
+                    fn from_sql(
+                        ty: &rwf::tokio_postgres::types::Type,
+                        raw: &'a [u8],
+                    ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+                        // Enum values are sent as their labels.
+                        let label = <&str as rwf::tokio_postgres::types::FromSql>::from_sql(ty, raw)?;
+
+                        match label {
+                            #(#from_label)*
+                            label => Err(format!("unknown {} label \"{}\"", stringify!(#ident), label).into()),
+                        }
+                    }
+
+                    fn accepts(ty: &rwf::tokio_postgres::types::Type) -> bool {
+                        matches!(ty.kind(), rwf::tokio_postgres::types::Kind::Enum(_))
+                            || <&str as rwf::tokio_postgres::types::FromSql>::accepts(ty)
+                    }
+                }
+            }
+            .into()
+        }
+
+        _ => panic!("macro can only be used on enums"),
+    }
+}
+
 /// Automatically implement the `ToTemplateValue` trait
 /// for the Rust struct. This allows to use the struct
 /// directly in template contexts.
@@ -642,6 +768,35 @@ pub fn turbo_stream(input: TokenStream) -> TokenStream {
     render::turbo_stream_impl(input)
 }
 
+/// Define named scopes on a model. Each function takes the query the scope is applied to as its first argument,
+/// and returns the scoped query.
+///
+/// The macro generates a function on the model with the same name, which applies the scope to all records,
+/// and a `{Model}Scopes` trait, which applies the scope to an existing query, so scopes can be chained together.
+///
+/// ### Example
+///
+/// ```ignore
+/// use rwf::macros::scopes;
+///
+/// #[scopes]
+/// impl User {
+///     fn active(query: Scope<User>) -> Scope<User> {
+///         query.filter_null("deleted_at")
+///     }
+///
+///     fn created_after(query: Scope<User>, time: OffsetDateTime) -> Scope<User> {
+///         query.filter_gt("created_at", time)
+///     }
+/// }
+///
+/// let users = User::active().created_after(OffsetDateTime::now_utc()).order("id");
+/// ```
+#[proc_macro_attribute]
+pub fn scopes(_args: TokenStream, input: TokenStream) -> TokenStream {
+    scopes::impl_scopes(input)
+}
+
 fn snake_case(string: &str) -> String {
     let mut result = "".to_string();
 
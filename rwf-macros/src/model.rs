@@ -1,5 +1,6 @@
 use super::*;
 use parse::Parse;
+use quote::ToTokens;
 use syn::*;
 
 pub fn impl_derive_model(input: TokenStream) -> TokenStream {
@@ -10,12 +11,7 @@ pub fn impl_derive_model(input: TokenStream) -> TokenStream {
     match input.data {
         Data::Struct(ref data) => {
             let ident = input.ident.clone();
-            let from_row_fields = data.fields.iter().map(|field| {
-                let ident = field.ident.clone();
-                quote! {
-                    #ident: row.try_get(stringify!(#ident))?,
-                }
-            });
+            let from_row_fields = data.fields.iter().map(from_row_field);
             let has_id = data
                 .fields
                 .iter()
@@ -82,6 +78,88 @@ pub fn impl_derive_model(input: TokenStream) -> TokenStream {
                 &input.attrs,
             );
 
+            let persisted = handle_override("persisted", quote! {}, &input.attrs);
+            let schema = handle_override("schema", quote! {}, &input.attrs);
+
+            let validate = input
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("validate"));
+            let validation = if validate {
+                quote! {
+                    fn validation(&self) -> Result<(), rwf::model::ValidationErrors> {
+                        rwf::model::Validate::validate(self)
+                    }
+                }
+            } else {
+                quote! {}
+            };
+
+            let has_soft_delete = input
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("soft_delete"));
+            let soft_delete = handle_override(
+                "soft_delete",
+                if has_soft_delete {
+                    quote! {
+                        fn soft_delete_column() -> Option<&'static str> {
+                            Some("deleted_at")
+                        }
+                    }
+                } else {
+                    quote! {}
+                },
+                &input.attrs,
+            );
+
+            let has_callbacks = input
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("callbacks"));
+            let callbacks = if has_callbacks {
+                quote! {
+                    fn before_save(&mut self) {
+                        rwf::model::Callbacks::before_save(self)
+                    }
+
+                    fn after_create<'a>(
+                        &'a self,
+                        conn: &'a mut rwf::model::ConnectionGuard,
+                    ) -> impl std::future::Future<Output = Result<(), rwf::model::Error>> + Send + 'a {
+                        rwf::model::Callbacks::after_create(self, conn)
+                    }
+
+                    fn after_update<'a>(
+                        &'a self,
+                        conn: &'a mut rwf::model::ConnectionGuard,
+                    ) -> impl std::future::Future<Output = Result<(), rwf::model::Error>> + Send + 'a {
+                        rwf::model::Callbacks::after_update(self, conn)
+                    }
+                }
+            } else {
+                quote! {}
+            };
+
+            let has_lock_version = data
+                .fields
+                .iter()
+                .any(|field| field.ident.clone().unwrap() == "lock_version");
+
+            let lock_version = handle_override(
+                "lock_version",
+                if has_lock_version {
+                    quote! {
+                        fn lock_version() -> Option<&'static str> {
+                            Some("lock_version")
+                        }
+                    }
+                } else {
+                    quote! {}
+                },
+                &input.attrs,
+            );
+
             quote! {
                 #[automatically_derived]
                 impl rwf::model::FromRow for #ident {
@@ -96,6 +174,12 @@ pub fn impl_derive_model(input: TokenStream) -> TokenStream {
                 impl rwf::model::Model for #ident {
                     #table_name
                     #foreign_key
+                    #schema
+                    #persisted
+                    #lock_version
+                    #soft_delete
+                    #validation
+                    #callbacks
 
                     fn column_names() -> &'static[&'static str] {
                         &[
@@ -122,6 +206,37 @@ pub fn impl_derive_model(input: TokenStream) -> TokenStream {
     }
 }
 
+/// Get the value of a field from the row. `NULL` becomes `None` for `Option` fields
+/// and the default value for fields with a type that implements `Default`.
+pub(crate) fn from_row_field(field: &Field) -> proc_macro2::TokenStream {
+    let ident = &field.ident;
+    let ty = &field.ty;
+
+    let optional = match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident == "Option")
+            .unwrap_or(false),
+        _ => false,
+    };
+
+    if optional {
+        quote! {
+            #ident: row.try_get(stringify!(#ident))?,
+        }
+    } else {
+        quote! {
+            #ident: {
+                use rwf::model::row::DefaultForNull;
+                let default = (&&rwf::model::row::NullDefault::<#ty>::new()).default_for_null();
+                rwf::model::row::try_get_or_default(&row, stringify!(#ident), default)?
+            },
+        }
+    }
+}
+
 fn handle_override(
     name: &str,
     default_value: proc_macro2::TokenStream,
@@ -130,45 +245,75 @@ fn handle_override(
     let mut overrides = attributes
         .iter()
         .filter(|attr| {
-            attr.path()
-                .segments
-                .first()
-                .expect("segment")
-                .ident
-                .to_string()
-                == name
+            // Attributes without arguments, e.g. `#[soft_delete]`, use the default value.
+            matches!(attr.meta, Meta::List(_) | Meta::NameValue(_))
+                && attr
+                    .path()
+                    .segments
+                    .first()
+                    .expect("segment")
+                    .ident
+                    .to_string()
+                    == name
         })
-        .map(|attr| match &attr.meta {
-            Meta::List(list) => {
-                let segment = list.path.segments.first();
+        .map(|attr| {
+            let tokens = match &attr.meta {
+                Meta::List(list) => list.tokens.clone(),
+                Meta::NameValue(name_value) => name_value.value.to_token_stream(),
+                Meta::Path(_) => return quote! {},
+            };
 
-                if let Some(_) = segment {
-                    let tokens = &list.tokens;
-                    match name {
-                        "table_name" => {
-                            quote! {
-                                fn table_name() -> &'static str {
-                                    #tokens
-                                }
-                            }
+            match name {
+                "table_name" => {
+                    quote! {
+                        fn table_name() -> &'static str {
+                            #tokens
                         }
+                    }
+                }
 
-                        "foreign_key" => {
-                            quote! {
-                                fn foreign_key() -> &'static str {
-                                    #tokens
-                                }
-                            }
+                "foreign_key" => {
+                    quote! {
+                        fn foreign_key() -> &'static str {
+                            #tokens
                         }
+                    }
+                }
 
-                        _ => panic!("unexpected attribute: {}", name),
+                "schema" => {
+                    quote! {
+                        fn schema() -> Option<&'static str> {
+                            Some(#tokens)
+                        }
+                    }
+                }
+
+                "persisted" => {
+                    quote! {
+                        fn is_persisted(&self) -> bool {
+                            self.#tokens()
+                        }
+                    }
+                }
+
+                "lock_version" => {
+                    quote! {
+                        fn lock_version() -> Option<&'static str> {
+                            Some(#tokens)
+                        }
                     }
-                } else {
-                    quote! {}
                 }
-            }
 
-            _ => quote! {},
+                "soft_delete" => {
+                    quote! {
+                        fn soft_delete_column() -> Option<&'static str> {
+                            Some(#tokens)
+                        }
+                    }
+                }
+
+                _ => panic!("unexpected attribute: {}", name),
+            }
         })
         .collect::<Vec<_>>();
 
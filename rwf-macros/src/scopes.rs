@@ -0,0 +1,107 @@
+use super::prelude::*;
+use quote::format_ident;
+
+pub fn impl_scopes(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ItemImpl);
+
+    match scopes(&input) {
+        Ok(output) => output.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn scopes(input: &ItemImpl) -> Result<proc_macro2::TokenStream> {
+    let model = &input.self_ty;
+    let ident = match model.as_ref() {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.clone()),
+        _ => None,
+    }
+    .ok_or_else(|| Error::new_spanned(model, "scopes can only be defined on a model"))?;
+    let scopes_trait = format_ident!("{}Scopes", ident);
+
+    let mut scopes = vec![];
+    let mut declarations = vec![];
+    let mut implementations = vec![];
+    let mut constructors = vec![];
+
+    for item in &input.items {
+        let function = match item {
+            ImplItem::Fn(function) => function,
+            item => return Err(Error::new_spanned(item, "expected a scope function")),
+        };
+
+        let name = &function.sig.ident;
+        let scope = format_ident!("__scope_{}", name);
+        let vis = &function.vis;
+        let attrs = &function.attrs;
+        let output = &function.sig.output;
+        let block = &function.block;
+
+        // The first argument is the query the scope is applied to.
+        let query = match function.sig.inputs.first() {
+            Some(FnArg::Typed(query)) => query,
+            _ => {
+                return Err(Error::new_spanned(
+                    &function.sig,
+                    "the first argument of a scope should be the query it's applied to, e.g. `query: Scope<Self>`",
+                ))
+            }
+        };
+
+        let args = function.sig.inputs.iter().skip(1).collect::<Vec<_>>();
+        let arg_names = args
+            .iter()
+            .map(|arg| match arg {
+                FnArg::Typed(PatType { pat, .. }) => match pat.as_ref() {
+                    Pat::Ident(pat) => Ok(pat.ident.clone()),
+                    pat => Err(Error::new_spanned(pat, "expected an argument name")),
+                },
+                arg => Err(Error::new_spanned(arg, "expected an argument name")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        scopes.push(quote! {
+            #[doc(hidden)]
+            fn #scope(#query, #(#args),*) #output #block
+        });
+
+        declarations.push(quote! {
+            #(#attrs)*
+            fn #name(self, #(#args),*) -> Self;
+        });
+
+        implementations.push(quote! {
+            fn #name(self, #(#args),*) -> Self {
+                <#model>::#scope(self, #(#arg_names),*)
+            }
+        });
+
+        constructors.push(quote! {
+            #(#attrs)*
+            #vis fn #name(#(#args),*) #output {
+                Self::#scope(<Self as rwf::model::Model>::all(), #(#arg_names),*)
+            }
+        });
+    }
+
+    Ok(quote! {
+        impl #model {
+            #(#scopes)*
+            #(#constructors)*
+        }
+
+        /// Scopes of the model, applied to an existing query.
+        pub trait #scopes_trait {
+            #(#declarations)*
+        }
+
+        #[automatically_derived]
+        impl #scopes_trait for rwf::model::Scope<#model> {
+            #(#implementations)*
+        }
+    })
+}
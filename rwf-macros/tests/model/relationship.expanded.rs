@@ -9,7 +9,12 @@ impl rwf::model::FromRow for User {
     fn from_row(row: rwf::tokio_postgres::Row) -> Result<Self, rwf::model::Error> {
         Ok(Self {
             id: row.try_get("id")?,
-            email: row.try_get("email")?,
+            email: {
+                use rwf::model::row::DefaultForNull;
+                let default = (&&rwf::model::row::NullDefault::<String>::new())
+                    .default_for_null();
+                rwf::model::row::try_get_or_default(&row, "email", default)?
+            },
         })
     }
 }
@@ -59,7 +64,12 @@ impl rwf::model::FromRow for Task {
     fn from_row(row: rwf::tokio_postgres::Row) -> Result<Self, rwf::model::Error> {
         Ok(Self {
             id: row.try_get("id")?,
-            user_id: row.try_get("user_id")?,
+            user_id: {
+                use rwf::model::row::DefaultForNull;
+                let default = (&&rwf::model::row::NullDefault::<i64>::new())
+                    .default_for_null();
+                rwf::model::row::try_get_or_default(&row, "user_id", default)?
+            },
         })
     }
 }
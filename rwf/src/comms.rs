@@ -6,6 +6,7 @@
 //!
 //! * Send messages between clients connected to different Rwf servers
 //! * ORM-triggered events, e.g. callbacks
+use crate::config::get_config;
 use crate::controller::auth::SessionId;
 use crate::http::websocket::Message;
 use crate::http::ToMessage;
@@ -14,10 +15,18 @@ use crate::model::{Model, Value};
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
 use std::collections::HashMap;
+use std::marker::PhantomData;
 use std::sync::Arc;
+use std::time::Instant;
 
+use serde::{de::DeserializeOwned, Serialize};
 use thiserror::Error;
-use tokio::sync::broadcast::{channel, error::SendError, Receiver, Sender};
+use time::OffsetDateTime;
+use tokio::sync::broadcast::{
+    channel,
+    error::{RecvError, SendError},
+    Receiver, Sender,
+};
 use tracing::debug;
 
 /// Error returned by comms.
@@ -26,6 +35,14 @@ pub enum Error {
     /// Error sending message through Tokio channel.
     #[error("{0}")]
     SendError(#[from] SendError<Message>),
+
+    /// Error receiving message from Tokio channel.
+    #[error("{0}")]
+    RecvError(#[from] RecvError),
+
+    /// Published value couldn't be (de)serialized.
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
 }
 
 static MESSAGES: Lazy<Messages> = Lazy::new(|| Messages::new());
@@ -70,22 +87,250 @@ impl Websocket {
     }
 }
 
+/// A session subscribed to a channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    /// The member's session.
+    pub session_id: SessionId,
+    /// When the session joined the channel.
+    pub joined_at: OffsetDateTime,
+    last_seen: Instant,
+}
+
+impl Member {
+    fn new(session_id: &SessionId) -> Self {
+        Self {
+            session_id: session_id.clone(),
+            joined_at: OffsetDateTime::now_utc(),
+            last_seen: Instant::now(),
+        }
+    }
+}
+
+/// A session joined or left a channel.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Presence {
+    /// The member subscribed to the channel.
+    Join {
+        /// Name of the channel.
+        channel: String,
+        /// The member who joined.
+        member: Member,
+    },
+    /// The member unsubscribed from the channel, disconnected, or stopped responding.
+    Leave {
+        /// Name of the channel.
+        channel: String,
+        /// The member who left.
+        member: Member,
+    },
+}
+
 /// Global messages channel.
 pub struct Messages {
     websocket: Arc<Mutex<HashMap<SessionId, Websocket>>>,
+    channels: Arc<Mutex<HashMap<String, HashMap<SessionId, Member>>>>,
+    presence: Sender<Presence>,
+    subscribers: Arc<Mutex<HashMap<String, Sender<Message>>>>,
 }
 
 impl Messages {
     /// Create new messages channel.
     pub fn new() -> Self {
+        let (presence, _) = channel(1024);
+
         Self {
             websocket: Arc::new(Mutex::new(HashMap::new())),
+            channels: Arc::new(Mutex::new(HashMap::new())),
+            presence,
+            subscribers: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
     fn websocket_disconnect(&self, session_id: &SessionId) {
         debug!("websocket session \"{:?}\" closed", session_id);
         self.websocket.lock().remove(session_id);
+
+        let mut left = vec![];
+        let mut channels = self.channels.lock();
+        for (channel, members) in channels.iter_mut() {
+            if let Some(member) = members.remove(session_id) {
+                left.push((channel.clone(), member));
+            }
+        }
+        channels.retain(|_, members| !members.is_empty());
+        drop(channels);
+
+        for (channel, member) in left {
+            self.leave(channel, member);
+        }
+    }
+
+    fn leave(&self, channel: String, member: Member) {
+        // No one may be listening.
+        let _ = self.presence.send(Presence::Leave { channel, member });
+    }
+
+    /// Subscribe a session to a channel. The subscription ends when all
+    /// WebSocket connections with this session are closed.
+    pub fn subscribe(&self, session_id: &SessionId, channel: &str) {
+        let member = {
+            let mut channels = self.channels.lock();
+            let members = channels.entry(channel.to_string()).or_default();
+
+            if let Some(member) = members.get_mut(session_id) {
+                member.last_seen = Instant::now();
+                return;
+            }
+
+            let member = Member::new(session_id);
+            members.insert(session_id.clone(), member.clone());
+            member
+        };
+
+        let _ = self.presence.send(Presence::Join {
+            channel: channel.to_string(),
+            member,
+        });
+    }
+
+    /// Unsubscribe a session from a channel.
+    pub fn unsubscribe(&self, session_id: &SessionId, channel: &str) {
+        let mut channels = self.channels.lock();
+        if let Some(members) = channels.get_mut(channel) {
+            let member = members.remove(session_id);
+
+            if members.is_empty() {
+                channels.remove(channel);
+            }
+
+            drop(channels);
+
+            if let Some(member) = member {
+                self.leave(channel.to_string(), member);
+            }
+        }
+    }
+
+    /// Mark the session as alive in all the channels it's subscribed to.
+    pub fn heartbeat(&self, session_id: &SessionId) {
+        let now = Instant::now();
+        for members in self.channels.lock().values_mut() {
+            if let Some(member) = members.get_mut(session_id) {
+                member.last_seen = now;
+            }
+        }
+    }
+
+    /// Remove members of the channel which haven't been seen for longer than the timeout. This happens
+    /// if a connection is lost without being closed, so the server is never told about it.
+    fn expire(&self, channel: &str, now: Instant) {
+        let timeout = get_config().websocket.presence_timeout().unsigned_abs();
+
+        let mut expired = vec![];
+        let mut channels = self.channels.lock();
+
+        if let Some(members) = channels.get_mut(channel) {
+            members.retain(|_, member| {
+                let alive = now.saturating_duration_since(member.last_seen) <= timeout;
+                if !alive {
+                    expired.push(member.clone());
+                }
+                alive
+            });
+
+            if members.is_empty() {
+                channels.remove(channel);
+            }
+        }
+
+        drop(channels);
+
+        for member in expired {
+            debug!(
+                "session \"{:?}\" timed out in channel \"{}\"",
+                member.session_id, channel
+            );
+            self.leave(channel.to_string(), member);
+        }
+    }
+
+    /// Get the members of the channel, in the order they joined it.
+    pub fn presence(&self, channel: &str) -> Vec<Member> {
+        self.expire(channel, Instant::now());
+
+        let mut members = self
+            .channels
+            .lock()
+            .get(channel)
+            .map(|members| members.values().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        members.sort_by_key(|member| member.joined_at);
+
+        members
+    }
+
+    /// Get a receiver for join and leave events of all channels.
+    pub fn presence_events(&self) -> Receiver<Presence> {
+        self.presence.subscribe()
+    }
+
+    /// Send a message to all sessions subscribed to the channel and to all subscribers in this process.
+    pub fn publish(&self, channel: &str, message: Message) -> Result<(), Error> {
+        {
+            let mut subscribers = self.subscribers.lock();
+            if let Some(sender) = subscribers.get(channel) {
+                if sender.send(message.clone()).is_err() {
+                    // Everyone unsubscribed.
+                    subscribers.remove(channel);
+                }
+            }
+        }
+
+        self.websocket_channel(channel).send(message)
+    }
+
+    /// Get a receiver for messages published to the channel.
+    pub fn subscriber(&self, name: &str) -> Receiver<Message> {
+        self.subscribers
+            .lock()
+            .entry(name.to_string())
+            .or_insert_with(|| channel(1024).0)
+            .subscribe()
+    }
+
+    /// Get a websocket message sender that will send messages to all sessions subscribed to the channel.
+    pub fn websocket_channel(&self, channel: &str) -> Broadcast {
+        self.expire(channel, Instant::now());
+
+        let sessions = self
+            .channels
+            .lock()
+            .get(channel)
+            .map(|members| members.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        let guard = self.websocket.lock();
+        let entries = sessions
+            .iter()
+            .filter_map(|session_id| guard.get(session_id))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        Broadcast { everyone: entries }
+    }
+
+    /// Get a websocket message sender that will send messages to all connections of an authenticated user.
+    /// Unlike [`Messages::websocket_sender`], users who aren't connected are not registered.
+    pub fn websocket_user(&self, user_id: i64) -> Broadcast {
+        let entries = self
+            .websocket
+            .lock()
+            .get(&SessionId::Authenticated(user_id))
+            .cloned()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        Broadcast { everyone: entries }
     }
 
     /// Check that a session has an active WebSocket connection.
@@ -222,6 +467,115 @@ impl Broadcast {
     }
 }
 
+/// A channel of values of one type, e.g. chat messages, which are serialized to JSON.
+///
+/// Values are sent as JSON text messages to all WebSocket sessions subscribed to the channel
+/// with [`Comms::subscribe`], and are deserialized for subscribers in the application created with
+/// [`Channel::subscribe`].
+///
+/// # Example
+///
+/// ```
+/// # use rwf::comms::Channel;
+/// # use rwf::prelude::*;
+/// #[derive(Serialize, Deserialize)]
+/// struct ChatMessage {
+///     from: String,
+///     body: String,
+/// }
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() -> Result<(), rwf::comms::Error> {
+/// let chat = Channel::<ChatMessage>::new("chat");
+/// let mut subscriber = chat.subscribe();
+///
+/// chat.publish(&ChatMessage { from: "alice".into(), body: "hi".into() })?;
+///
+/// let message = subscriber.recv().await?;
+/// assert_eq!(message.body, "hi");
+/// # Ok(())
+/// # }
+/// ```
+pub struct Channel<T> {
+    name: String,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Channel<T> {
+    /// Create a handle for the channel with the name. Handles with the same name
+    /// share the same channel.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Name of the channel.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<T: Serialize> Channel<T> {
+    /// Send the value to everyone subscribed to the channel.
+    pub fn publish(&self, value: &T) -> Result<(), Error> {
+        let message = Message::Text(serde_json::to_string(value)?);
+        get_comms().publish(&self.name, message)
+    }
+}
+
+impl<T: DeserializeOwned> Channel<T> {
+    /// Receive values published to the channel from now on.
+    pub fn subscribe(&self) -> Subscriber<T> {
+        Subscriber {
+            receiver: get_comms().subscriber(&self.name),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Receives values published to a [`Channel`].
+pub struct Subscriber<T> {
+    receiver: Receiver<Message>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> Subscriber<T> {
+    /// Wait for the next value. If the subscriber falls behind by more than 1024 values,
+    /// the oldest ones are skipped.
+    pub async fn recv(&mut self) -> Result<T, Error> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(message) => return Self::decode(message),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Get the next value, if one was published already.
+    pub fn try_recv(&mut self) -> Result<Option<T>, Error> {
+        use tokio::sync::broadcast::error::TryRecvError;
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => return Self::decode(message).map(Some),
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(TryRecvError::Empty) => return Ok(None),
+                Err(TryRecvError::Closed) => return Err(RecvError::Closed.into()),
+            }
+        }
+    }
+
+    fn decode(message: Message) -> Result<T, Error> {
+        Ok(match message {
+            Message::Text(text) => serde_json::from_str(&text)?,
+            Message::Binary(bytes) => serde_json::from_slice(&bytes)?,
+        })
+    }
+}
+
 /// Convert an object into a session.
 ///
 /// If a model is passed in, the `id` field is used.
@@ -294,10 +648,63 @@ impl Comms {
         get_comms().websocket_broadcast(&session_id, DEFAULT_TOPIC)
     }
 
+    /// Get a broadcast handle for a WebSocket message to every connection of the user, e.g. all of the
+    /// browser tabs and devices the user is logged in with.
+    ///
+    /// Sessions of authenticated users are identified by the user ID, so all of them share the same
+    /// connections, which are removed when the last one is closed. If the user isn't connected, the message is dropped.
+    pub fn to_user(user_id: i64) -> Broadcast {
+        get_comms().websocket_user(user_id)
+    }
+
     /// Used for dev server notifications (sent to every connected session).
     pub fn notify() -> Broadcast {
         get_comms().websocket_notify(DEFAULT_TOPIC)
     }
+
+    /// Subscribe the session to a channel, e.g. a chat room. Messages sent with [`Comms::channel`]
+    /// will be delivered to all of its WebSocket connections.
+    pub fn subscribe(session_id: impl IntoSessionId, channel: &str) {
+        let session_id = session_id.into_session_id();
+        get_comms().subscribe(&session_id, channel)
+    }
+
+    /// Unsubscribe the session from a channel.
+    pub fn unsubscribe(session_id: impl IntoSessionId, channel: &str) {
+        let session_id = session_id.into_session_id();
+        get_comms().unsubscribe(&session_id, channel)
+    }
+
+    /// Get a broadcast handle for a WebSocket message to every session subscribed to the channel.
+    pub fn channel(channel: &str) -> Broadcast {
+        get_comms().websocket_channel(channel)
+    }
+
+    /// Send the value, serialized to JSON, to everyone subscribed to the channel. See [`Channel`].
+    pub fn publish<T: Serialize>(channel: &str, value: &T) -> Result<(), Error> {
+        Channel::new(channel).publish(value)
+    }
+
+    /// Get the sessions currently subscribed to the channel, in the order they joined it.
+    ///
+    /// Sessions which haven't been heard from for longer than [`WebsocketConfig::presence_timeout`](crate::config::WebsocketConfig::presence_timeout)
+    /// are removed, even if their connection was never closed.
+    pub fn presence(channel: &str) -> Vec<Member> {
+        get_comms().presence(channel)
+    }
+
+    /// Receive an event every time a session joins or leaves any channel.
+    pub fn presence_events() -> Receiver<Presence> {
+        get_comms().presence_events()
+    }
+
+    /// Mark the session as alive in all of its channels. This is done automatically
+    /// by [`WebsocketController`](crate::controller::WebsocketController) every time the client responds to a ping
+    /// or sends a message.
+    pub fn heartbeat(session_id: impl IntoSessionId) {
+        let session_id = session_id.into_session_id();
+        get_comms().heartbeat(&session_id)
+    }
 }
 
 #[cfg(test)]
@@ -351,4 +758,160 @@ mod test {
         let websocket = Comms::websocket(&user);
         websocket.send(Message::Text("test2".into())).unwrap();
     }
+
+    #[test]
+    fn test_channel() {
+        let alice = SessionId::Authenticated(1001);
+        let bob = SessionId::Authenticated(1002);
+        let eve = SessionId::Authenticated(1003);
+
+        let mut alice_receiver = Comms::receiver(&alice);
+        let mut bob_receiver = Comms::receiver(&bob);
+        let mut eve_receiver = Comms::receiver(&eve);
+
+        Comms::subscribe(&alice, "room");
+        Comms::subscribe(&bob, "room");
+        Comms::channel("room").send("hello").unwrap();
+
+        for receiver in [&mut alice_receiver, &mut bob_receiver] {
+            match receiver.try_recv().unwrap() {
+                Message::Text(text) => assert_eq!(text, "hello"),
+                message => panic!("unexpected message: {:?}", message),
+            }
+        }
+        assert!(eve_receiver.try_recv().is_err());
+
+        Comms::unsubscribe(&bob, "room");
+        Comms::channel("room").send("bye").unwrap();
+        assert!(alice_receiver.try_recv().is_ok());
+        assert!(bob_receiver.try_recv().is_err());
+
+        // Subscriptions end when the session disconnects.
+        drop(alice_receiver);
+        assert!(get_comms().channels.lock().get("room").is_none());
+    }
+
+    #[test]
+    fn test_presence() {
+        let alice = SessionId::Authenticated(3001);
+        let bob = SessionId::Authenticated(3002);
+
+        let _alice_receiver = Comms::receiver(&alice);
+        let bob_receiver = Comms::receiver(&bob);
+        let mut events = Comms::presence_events();
+
+        Comms::subscribe(&alice, "lobby");
+        Comms::subscribe(&bob, "lobby");
+        Comms::subscribe(&bob, "lobby");
+
+        let members = Comms::presence("lobby");
+        assert_eq!(members.len(), 2);
+        assert!(members.iter().any(|member| member.session_id == alice));
+        assert!(members.iter().any(|member| member.session_id == bob));
+
+        // Lost connection.
+        drop(bob_receiver);
+        let members = Comms::presence("lobby");
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].session_id, alice);
+
+        // Connection is still open, but the client stopped responding.
+        let timeout = get_config().websocket.presence_timeout().unsigned_abs();
+        Comms::heartbeat(&alice);
+        get_comms().expire("lobby", Instant::now() + timeout / 2);
+        assert_eq!(Comms::presence("lobby").len(), 1);
+        get_comms().expire("lobby", Instant::now() + timeout * 2);
+        assert!(Comms::presence("lobby").is_empty());
+
+        // Other tests use other channels.
+        let mut lobby = vec![];
+        while let Ok(event) = events.try_recv() {
+            match event {
+                Presence::Join { channel, member } if channel == "lobby" => {
+                    lobby.push(("join", member.session_id))
+                }
+                Presence::Leave { channel, member } if channel == "lobby" => {
+                    lobby.push(("leave", member.session_id))
+                }
+                _ => (),
+            }
+        }
+
+        assert_eq!(
+            lobby,
+            vec![
+                ("join", alice.clone()),
+                ("join", bob.clone()),
+                ("leave", bob),
+                ("leave", alice),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_typed_channel() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Note {
+            id: i64,
+            title: String,
+        }
+
+        let notes = Channel::<Note>::new("notes");
+        let mut first = notes.subscribe();
+        let mut second = Channel::<Note>::new("notes").subscribe();
+
+        // WebSocket clients receive JSON.
+        let session = SessionId::Authenticated(4001);
+        let mut websocket = Comms::receiver(&session);
+        Comms::subscribe(&session, "notes");
+
+        let note = Note {
+            id: 1,
+            title: "groceries".into(),
+        };
+        notes.publish(&note).unwrap();
+
+        assert_eq!(first.recv().await.unwrap(), note);
+        assert_eq!(second.try_recv().unwrap(), Some(note));
+        assert_eq!(second.try_recv().unwrap(), None);
+
+        match websocket.try_recv().unwrap() {
+            Message::Text(text) => assert_eq!(text, r#"{"id":1,"title":"groceries"}"#),
+            message => panic!("unexpected message: {:?}", message),
+        }
+
+        // Raw messages which aren't a Note.
+        get_comms()
+            .publish("notes", Message::Text("hello".into()))
+            .unwrap();
+        assert!(matches!(first.recv().await, Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn test_to_user() {
+        // Two tabs with the same user logged in.
+        let mut first_tab = Comms::receiver(SessionId::Authenticated(2001));
+        let mut second_tab = Comms::receiver(SessionId::Authenticated(2001));
+        let mut other_user = Comms::receiver(SessionId::Authenticated(2002));
+
+        Comms::to_user(2001).send("new notification").unwrap();
+
+        for receiver in [&mut first_tab, &mut second_tab] {
+            match receiver.try_recv().unwrap() {
+                Message::Text(text) => assert_eq!(text, "new notification"),
+                message => panic!("unexpected message: {:?}", message),
+            }
+        }
+        assert!(other_user.try_recv().is_err());
+
+        // The user stays connected until the last tab is closed.
+        drop(first_tab);
+        assert!(get_comms().websocket_connected(&SessionId::Authenticated(2001)));
+        drop(second_tab);
+        assert!(!get_comms().websocket_connected(&SessionId::Authenticated(2001)));
+
+        // Users who aren't connected aren't registered.
+        Comms::to_user(2003).send("lost").unwrap();
+        assert!(!get_comms().websocket_connected(&SessionId::Authenticated(2003)));
+    }
 }
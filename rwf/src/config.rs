@@ -9,6 +9,7 @@ use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 use time::Duration;
 use tracing::{error, info, warn};
+use tracing_subscriber::filter::LevelFilter;
 
 use crate::controller::middleware::csrf::Csrf;
 use crate::controller::middleware::{request_tracker::RequestTracker, Middleware};
@@ -46,6 +47,14 @@ pub enum Error {
     /// Configuration was not loaded.
     #[error("config not found")]
     NoConfig,
+
+    /// Secret key is required, but it's not set.
+    #[error("secret key is not set, set \"secret_key\" in the [general] section or the RWF_SECRET_KEY environment variable")]
+    MissingSecretKey,
+
+    /// Environment variable has a value that can't be used.
+    #[error("environment variable {0} is not valid")]
+    InvalidEnv(&'static str),
 }
 
 /// Get application configuration.
@@ -127,6 +136,94 @@ impl Config {
         Self::default()
     }
 
+    /// Load the configuration from environment variables, without reading a configuration file.
+    /// Settings which aren't set are given their default values, except for the secret key,
+    /// which is required. See [`Config::from_file`] for the list of variables.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use rwf::config::Config;
+    /// Config::from_env()?.set()?;
+    /// # Ok::<(), rwf::config::Error>(())
+    /// ```
+    pub fn from_env() -> Result<Config, Error> {
+        Self::default().with_env(false, |name| var(name).ok())
+    }
+
+    /// Load the configuration from a TOML file, with environment variables overriding its settings.
+    /// Unlike [`Config::load`], the secret key is required, and must be set in the file or with `RWF_SECRET_KEY`.
+    ///
+    /// The following environment variables are supported:
+    ///
+    /// | Variable | Setting |
+    /// |----------|---------|
+    /// | `RWF_SECRET_KEY` | `general.secret_key` |
+    /// | `RWF_DATABASE_URL` or `DATABASE_URL` | `database.url` |
+    /// | `RWF_HOST` | `general.host` |
+    /// | `RWF_PORT` | `general.port` |
+    /// | `RWF_LOG_LEVEL` | `general.log_level` |
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config, Error> {
+        Self::from_file_with_env(path.as_ref(), |name| var(name).ok())
+    }
+
+    fn from_file_with_env(
+        path: &Path,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Config, Error> {
+        let file = read_to_string(path)?;
+        let table: toml::Table = toml::from_str(&file)?;
+        let has_secret_key = table
+            .get("general")
+            .and_then(|general| general.get("secret_key"))
+            .is_some();
+
+        let mut config: Self = toml::from_str(&file)?;
+        config.path = Some(path.to_owned());
+
+        config.with_env(has_secret_key, env)
+    }
+
+    /// Override settings with environment variables.
+    fn with_env(
+        mut self,
+        has_secret_key: bool,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, Error> {
+        match env("RWF_SECRET_KEY") {
+            Some(secret_key) => self.general.secret_key = secret_key,
+            None if !has_secret_key => return Err(Error::MissingSecretKey),
+            None => (),
+        }
+
+        if let Some(url) = env("RWF_DATABASE_URL").or_else(|| env("DATABASE_URL")) {
+            self.database.url = Some(url);
+        }
+
+        if let Some(host) = env("RWF_HOST") {
+            self.general.host = host;
+        }
+
+        if let Some(port) = env("RWF_PORT") {
+            self.general.port = port.parse().map_err(|_| Error::InvalidEnv("RWF_PORT"))?;
+        }
+
+        if let Some(log_level) = env("RWF_LOG_LEVEL") {
+            if log_level.parse::<LevelFilter>().is_err() {
+                return Err(Error::InvalidEnv("RWF_LOG_LEVEL"));
+            }
+            self.general.log_level = log_level;
+        }
+
+        self.transform()
+    }
+
+    /// Use this configuration for the rest of the application. Must be called at startup,
+    /// before the configuration is used by anything, e.g. the server or the database pool.
+    pub fn set(self) -> Result<(), Error> {
+        CONFIG.set(self).map_err(|_| Error::ConfigLoaded)
+    }
+
     /// Load configuration file from a specific path.
     pub fn load(path: impl AsRef<Path> + Copy) -> Result<Config, Error> {
         let file = read_to_string(path)?;
@@ -192,12 +289,29 @@ pub struct General {
     /// AES key used for encrypting secure identifiers.
     #[serde(skip)]
     pub secure_id_key: Key<AesGcmSiv<Aes128>>,
+    /// Log messages at this level and above, e.g. `"debug"`. Default: `"info"`. Overridden by `RUST_LOG`.
+    #[serde(default = "General::default_log_level")]
+    pub log_level: String,
+    /// Format of log lines, `"text"` or `"json"`. JSON logs have no colors and
+    /// record request and query details as separate fields. Default: `"text"`.
+    #[serde(default = "General::default_log_format")]
+    pub log_format: String,
     /// Enable logging all queries executed by the ORM.
     #[serde(default = "General::default_log_queries")]
     pub log_queries: bool,
-    /// Enable caching templates at runtime.
+    /// Queries which take longer than this are logged as warnings, even if `log_queries` is disabled,
+    /// while faster queries are logged at debug level. Not set by default, logging all queries the same way.
+    /// Configured in milliseconds.
+    /// Use [`General::slow_query_threshold`] to get a valid [`Duration`] struct.
+    #[serde(default = "General::default_slow_query_threshold")]
+    pub slow_query_threshold: Option<usize>,
+    /// Cache templates permanently, instead of compiling them again when their files change.
     #[serde(default = "General::default_cache_templates")]
     pub cache_templates: bool,
+    /// Folder relative paths of layouts and partials are resolved against.
+    /// Not set by default, resolving them against the current working directory.
+    #[serde(default = "General::default_templates_root")]
+    pub templates_root: Option<PathBuf>,
     /// Record HTTP requests made to the server in the database.
     #[serde(default = "General::default_track_requests")]
     pub track_requests: bool,
@@ -234,8 +348,12 @@ impl Default for General {
             secret_key: General::default_secret_key(),
             aes_key: Key::<AesGcmSiv<Aes128>>::default(),
             secure_id_key: Key::<AesGcmSiv<Aes128>>::default(),
+            log_level: General::default_log_level(),
+            log_format: General::default_log_format(),
             log_queries: General::default_log_queries(),
+            slow_query_threshold: General::default_slow_query_threshold(),
             cache_templates: General::default_cache_templates(),
+            templates_root: General::default_templates_root(),
             track_requests: General::default_track_requests(),
             csrf_protection: General::default_csrf_protection(),
             cookie_max_age: General::default_cookie_max_age(),
@@ -280,6 +398,24 @@ impl General {
         }
     }
 
+    fn default_log_level() -> String {
+        var("RWF_LOG_LEVEL").unwrap_or_else(|_| "info".into())
+    }
+
+    /// The log level, if it's valid.
+    pub fn log_level(&self) -> Option<LevelFilter> {
+        self.log_level.parse().ok()
+    }
+
+    fn default_log_format() -> String {
+        var("RWF_LOG_FORMAT").unwrap_or_else(|_| "text".into())
+    }
+
+    /// Log lines are formatted as JSON.
+    pub fn log_json(&self) -> bool {
+        self.log_format.eq_ignore_ascii_case("json")
+    }
+
     fn default_log_queries() -> bool {
         if true_from_env("RWF_LOG_QUERIES") {
             return true;
@@ -288,6 +424,18 @@ impl General {
         false
     }
 
+    fn default_slow_query_threshold() -> Option<usize> {
+        var("RWF_SLOW_QUERY_THRESHOLD")
+            .ok()
+            .and_then(|threshold| threshold.parse().ok())
+    }
+
+    /// Queries which take longer than this are slow.
+    pub fn slow_query_threshold(&self) -> Option<Duration> {
+        self.slow_query_threshold
+            .map(|threshold| Duration::milliseconds(threshold as i64))
+    }
+
     fn default_secret_key() -> String {
         if let Ok(key) = var("RWF_SECRET_KEY") {
             return key;
@@ -312,6 +460,10 @@ impl General {
         return true;
     }
 
+    fn default_templates_root() -> Option<PathBuf> {
+        var("RWF_TEMPLATES_ROOT").ok().map(PathBuf::from)
+    }
+
     fn default_track_requests() -> bool {
         if true_from_env("RWF_TRACK_REQUESTS") {
             return true;
@@ -412,6 +564,12 @@ impl WebsocketConfig {
     fn default_disconnect_count() -> usize {
         3
     }
+
+    /// How long a session stays in a channel's presence list without responding to pings
+    /// or sending messages. This is as long as the connection is allowed to miss pings before it's closed.
+    pub fn presence_timeout(&self) -> Duration {
+        self.ping_interval() * (self.ping_disconnect_count as i32 + 1)
+    }
 }
 
 /// Database connection configuration.
@@ -436,6 +594,13 @@ pub struct DatabaseConfig {
     /// in the pool.
     #[serde(default = "DatabaseConfig::default_pool_size")]
     pub pool_size: usize,
+    /// Number of idle connections the pool keeps open
+    /// even if they exceed the idle timeout.
+    #[serde(default = "DatabaseConfig::default_min_idle")]
+    pub min_idle: usize,
+    /// Connection URLs of read replicas.
+    #[serde(default)]
+    pub replicas: Vec<String>,
 }
 
 impl Default for DatabaseConfig {
@@ -447,6 +612,8 @@ impl Default for DatabaseConfig {
             idle_timeout: DatabaseConfig::default_idle_timeout(),
             checkout_timeout: DatabaseConfig::default_checkout_timeout(),
             pool_size: DatabaseConfig::default_pool_size(),
+            min_idle: DatabaseConfig::default_min_idle(),
+            replicas: vec![],
         }
     }
 }
@@ -490,6 +657,10 @@ impl DatabaseConfig {
         10
     }
 
+    fn default_min_idle() -> usize {
+        0
+    }
+
     /// Convert the connection config to a valid
     /// database URL as described by the
     /// Twelve Factor Application.
@@ -547,6 +718,100 @@ name = "test"
             assert_eq!(config.path, Some(PathBuf::from(config_path)));
         }
     }
+
+    const SECRET_KEY: &str = "BJ3Og8l/Q8f+fLvQpb9CP7uUu/VG1/+CN2a1f/QyHWY=";
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let tmp_dir = TempDir::new("test").unwrap();
+        let path = tmp_dir.path().join("rwf.toml");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(
+            format!(
+                r#"
+[general]
+secret_key = "{}"
+host = "127.0.0.1"
+port = 3000
+
+[database]
+url = "postgres://file/app"
+"#,
+                SECRET_KEY
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+
+        let config = Config::from_file_with_env(&path, env(&[])).unwrap();
+        assert_eq!(config.general.host, "127.0.0.1");
+        assert_eq!(config.general.port, 3000);
+        assert_eq!(config.database.database_url(), "postgres://file/app");
+        assert_eq!(config.path.as_deref(), Some(path.as_path()));
+
+        let config = Config::from_file_with_env(
+            &path,
+            env(&[
+                ("DATABASE_URL", "postgres://env/app"),
+                ("RWF_PORT", "8080"),
+                ("RWF_LOG_LEVEL", "debug"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.general.host, "127.0.0.1");
+        assert_eq!(config.general.port, 8080);
+        assert_eq!(config.general.log_level(), Some(LevelFilter::DEBUG));
+        assert_eq!(config.database.database_url(), "postgres://env/app");
+
+        // The prefixed variable wins.
+        let config = Config::from_file_with_env(
+            &path,
+            env(&[
+                ("DATABASE_URL", "postgres://env/app"),
+                ("RWF_DATABASE_URL", "postgres://rwf/app"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.database.database_url(), "postgres://rwf/app");
+
+        assert!(matches!(
+            Config::from_file_with_env(&path, env(&[("RWF_PORT", "http")])),
+            Err(Error::InvalidEnv("RWF_PORT"))
+        ));
+    }
+
+    #[test]
+    fn test_missing_secret_key() {
+        let tmp_dir = TempDir::new("test").unwrap();
+        let path = tmp_dir.path().join("rwf.toml");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(b"[general]\nport = 3000\n").unwrap();
+
+        let err = Config::from_file_with_env(&path, env(&[])).err().unwrap();
+        assert!(matches!(err, Error::MissingSecretKey));
+        assert!(err.to_string().contains("RWF_SECRET_KEY"));
+
+        assert!(matches!(
+            Config::default().with_env(false, env(&[])),
+            Err(Error::MissingSecretKey)
+        ));
+
+        let config = Config::from_file_with_env(&path, env(&[("RWF_SECRET_KEY", SECRET_KEY)]));
+        assert_eq!(config.unwrap().general.port, 3000);
+
+        assert!(matches!(
+            Config::default().with_env(false, env(&[("RWF_SECRET_KEY", "c2hvcnQ=")])),
+            Err(Error::SecretKey)
+        ));
+    }
 }
 
 /// Configuration for packaging Rwf apps built
@@ -210,6 +210,9 @@ pub struct Session {
     /// Type of session, e.g. guest or user.
     #[serde(rename = "s")]
     pub session_id: SessionId,
+    /// Flash messages set by the previous response, available for one request only.
+    #[serde(rename = "f", default, skip_serializing_if = "HashMap::is_empty")]
+    pub flash: HashMap<String, String>,
 }
 
 impl Default for Session {
@@ -230,6 +233,15 @@ impl ToTemplateValue for Session {
             "payload".into(),
             Value::String(serde_json::to_string(&self.payload).unwrap()),
         );
+        hash.insert(
+            "flash".into(),
+            Value::Hash(
+                self.flash
+                    .iter()
+                    .map(|(key, message)| (key.clone(), Value::String(message.clone())))
+                    .collect(),
+            ),
+        );
 
         Ok(Value::Hash(hash))
     }
@@ -253,6 +265,7 @@ impl Session {
             expiration: (OffsetDateTime::now_utc() + get_config().general.session_duration())
                 .unix_timestamp(),
             session_id: SessionId::default(),
+            flash: HashMap::new(),
         })
     }
 
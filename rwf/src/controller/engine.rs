@@ -3,7 +3,7 @@
 //!
 //! Currently a work in progress. The closest analogy in other frameworks
 //! are [Rails engines](https://guides.rubyonrails.org/engines.html).
-use crate::http::{Handler, Path, Request, Response, Router};
+use crate::http::{Handler, Match, Path, Request, Response, Router};
 
 use super::{AuthHandler, Controller, Error};
 
@@ -55,12 +55,15 @@ impl Controller for Engine {
         }
 
         let path = request.path().pop_base(&self.mount);
-        let handler = self.router.find(&path);
-
-        if let Some(handler) = handler {
-            handler.handle(request).await
-        } else {
-            Ok(Response::not_found())
+        match self.router.find_method(request.method(), &path) {
+            Match::Handler(handler) => handler.handle(request).await,
+            Match::MethodNotAllowed(allow) => Ok(Response::method_not_allowed().allow(&allow)),
+            Match::Redirect(path) => Ok(Response::new().redirect_permanent(format!(
+                "{}{}",
+                self.mount.base().trim_end_matches('/'),
+                path
+            ))),
+            Match::NotFound => Ok(Response::not_found()),
         }
     }
 }
@@ -29,7 +29,11 @@ pub use secure_id::SecureId;
 pub mod csrf;
 pub mod request_tracker;
 
+pub mod request_id;
+pub use request_id::RequestId;
+
 /// The result of middleware processing a request.
+#[allow(clippy::large_enum_variant)] // Boxing the response would allocate on every intercepted request.
 pub enum Outcome {
     /// Forward the request to the next middleware in the chain, or if none are left,
     /// to the controller.
@@ -85,6 +89,11 @@ pub trait Middleware: Send + Sync {
 
     /// Process the response returned by a controller. You can modify it
     /// or forward it without modification.
+    ///
+    /// Responses go through the middleware in the reverse order of requests, so the first middleware
+    /// in a [`MiddlewareSet`] sees the request first and the response last. This runs only if this middleware
+    /// forwarded the request, including when the controller returned an error or a later middleware
+    /// stopped the request.
     async fn handle_response(
         &self,
         request: &Request,
@@ -214,8 +223,89 @@ impl MiddlewareSet {
         Ok(response)
     }
 
+    /// The set doesn't contain any middleware.
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+
     /// Returns a clone of the middleware wrappers. They cannot be executed manually.
     pub fn handlers(&self) -> Vec<MiddlewareHandler> {
         self.handlers.clone()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Append(&'static str);
+
+    #[async_trait]
+    impl Middleware for Append {
+        async fn handle_request(&self, request: Request) -> Result<Outcome, Error> {
+            if self.0 == "stop" {
+                Ok(Outcome::Stop(request, Response::forbidden()))
+            } else {
+                Ok(Outcome::Forward(request))
+            }
+        }
+
+        async fn handle_response(
+            &self,
+            _request: &Request,
+            response: Response,
+        ) -> Result<Response, Error> {
+            let order = match response.headers().get("x-order") {
+                Some(order) => format!("{}, {}", order, self.0),
+                None => self.0.to_string(),
+            };
+
+            Ok(response.header("x-order", order))
+        }
+    }
+
+    async fn handle(set: &MiddlewareSet) -> Response {
+        let request = Request::default();
+        let (outcome, executed) = set.handle_request(request.clone()).await.unwrap();
+        let response = match outcome {
+            Outcome::Forward(_) => Response::default(),
+            Outcome::Stop(_, response) => response,
+        };
+
+        set.handle_response(&request, response, executed)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_handle_response() {
+        let set = MiddlewareSet::without_default(vec![
+            Append("first").middleware(),
+            Append("second").middleware(),
+        ]);
+        let response = handle(&set).await;
+        assert_eq!(
+            response
+                .headers()
+                .get("x-order")
+                .map(|order| order.as_str()),
+            Some("second, first")
+        );
+
+        // Only middleware which forwarded the request sees the response.
+        let set = MiddlewareSet::without_default(vec![
+            Append("first").middleware(),
+            Append("stop").middleware(),
+            Append("third").middleware(),
+        ]);
+        let response = handle(&set).await;
+        assert_eq!(response.status().code(), 403);
+        assert_eq!(
+            response
+                .headers()
+                .get("x-order")
+                .map(|order| order.as_str()),
+            Some("first")
+        );
+    }
+}
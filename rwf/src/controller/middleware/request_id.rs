@@ -0,0 +1,118 @@
+//! Assign a unique identifier to each request, to correlate log lines.
+//!
+//! The identifier is read from the `X-Request-Id` header, if the client (or a proxy) provided one,
+//! or generated otherwise. It's available to controllers with [`Request::request_id`], is included
+//! in the query log while the controller is running, and is returned to the client in the `X-Request-Id` response header.
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::controller::middleware::prelude::*;
+
+static HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Request identifier middleware.
+#[derive(Default)]
+pub struct RequestId;
+
+impl RequestId {
+    /// Create new request identifier middleware.
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Identifier of the request currently being handled by this task, if any.
+    pub fn current() -> Option<String> {
+        REQUEST_ID.try_with(|id| id.clone()).ok()
+    }
+
+    /// Run the future with the request's identifier set as the current one.
+    pub(crate) async fn scope<F: Future>(request: &Request, f: F) -> F::Output {
+        match request.request_id() {
+            Some(id) => REQUEST_ID.scope(id.to_string(), f).await,
+            None => f.await,
+        }
+    }
+
+    /// Accept identifiers which are safe to log and send back in a header.
+    fn valid(id: &str) -> bool {
+        !id.is_empty() && id.len() <= 128 && id.chars().all(|c| c.is_ascii_graphic())
+    }
+}
+
+#[async_trait]
+impl Middleware for RequestId {
+    async fn handle_request(&self, request: Request) -> Result<Outcome, Error> {
+        let id = match request.header(HEADER) {
+            Some(id) if Self::valid(id) => id.clone(),
+            _ => Uuid::new_v4().to_string(),
+        };
+
+        Ok(Outcome::Forward(request.set_request_id(id)))
+    }
+
+    async fn handle_response(
+        &self,
+        request: &Request,
+        response: Response,
+    ) -> Result<Response, Error> {
+        match request.request_id() {
+            Some(id) => Ok(response.header(HEADER, id)),
+            None => Ok(response),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn handle(request: Request) -> (Request, Response) {
+        let request = match RequestId.handle_request(request).await.unwrap() {
+            Outcome::Forward(request) => request,
+            Outcome::Stop(_, _) => panic!("request id middleware should not stop requests"),
+        };
+        let response = RequestId
+            .handle_response(&request, Response::default())
+            .await
+            .unwrap();
+
+        (request, response)
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        let (request, response) = handle(Request::default()).await;
+        let id = request.request_id().expect("request id").to_string();
+        assert!(Uuid::parse_str(&id).is_ok());
+        assert_eq!(response.headers().get(HEADER), Some(&id));
+
+        let mut request = Request::default();
+        request
+            .head_mut()
+            .headers_mut()
+            .insert("X-Request-Id", "abc-123");
+        let (request, response) = handle(request).await;
+        assert_eq!(request.request_id(), Some("abc-123"));
+        assert_eq!(
+            response.headers().get(HEADER).map(|id| id.as_str()),
+            Some("abc-123")
+        );
+
+        let mut request = Request::default();
+        request
+            .head_mut()
+            .headers_mut()
+            .insert("X-Request-Id", "bad\r\nid");
+        let (request, _) = handle(request).await;
+        assert_ne!(request.request_id(), Some("bad\r\nid"));
+
+        assert_eq!(RequestId::current(), None);
+        let current = RequestId::scope(&request, async { RequestId::current() }).await;
+        assert_eq!(current.as_deref(), request.request_id());
+    }
+}
@@ -30,6 +30,7 @@ pub mod auth;
 pub mod engine;
 pub mod error;
 pub mod middleware;
+pub mod pagination;
 pub mod ser;
 pub mod static_files;
 pub mod turbo_stream;
@@ -47,7 +48,10 @@ pub use rack::RackController;
 pub use auth::{AllowAll, AuthHandler, Authentication, BasicAuth, DenyAll, Session, SessionId};
 pub use engine::Engine;
 pub use error::Error;
-pub use middleware::{Middleware, MiddlewareHandler, MiddlewareSet, Outcome, RateLimiter};
+pub use middleware::{
+    Middleware, MiddlewareHandler, MiddlewareSet, Outcome, RateLimiter, RequestId,
+};
+pub use pagination::Pagination;
 pub use static_files::{CacheControl, StaticFiles};
 pub use turbo_stream::TurboStream;
 
@@ -55,7 +59,7 @@ use super::http::{
     websocket::{self, DataFrame},
     Handler, Method, Request, Response, Stream, ToParameter,
 };
-use super::model::{get_connection, Insert, Model, Query, ToValue, Update, Value};
+use super::model::{get_connection, Insert, Model, Page, Query, ToValue, Update, Value};
 use crate::colors::MaybeColorize;
 use crate::comms::Comms;
 use crate::config::get_config;
@@ -223,36 +227,38 @@ pub trait Controller: Sync + Send {
         let outcome = self.middleware().handle_request(request).await?;
 
         let response = match outcome {
-            (Outcome::Forward(request), executed) => match self.handle(&request).await {
-                Ok(response) => {
-                    self.middleware()
-                        .handle_response(&request, response.from_request(&request)?, executed)
-                        .await?
-                }
-                Err(err) => {
-                    error!("{:?}", err);
-
-                    let response = match err {
-                        Error::HttpError(err) => match err.code() {
-                            400 => Response::bad_request(),
-                            401 => Response::unauthorized(None),
-                            413 => Response::content_too_large(),
-                            _ => Response::internal_error(err),
-                        },
-
-                        Error::ViewError(err) => {
-                            Response::error_pretty("Template error", err.to_string().as_str())
-                        }
-
-                        err => Response::internal_error(err),
-                    };
-
-                    // Run the middleware chain on the response anyway.
-                    self.middleware()
-                        .handle_response(&request, response, executed)
-                        .await?
+            (Outcome::Forward(request), executed) => {
+                match RequestId::scope(&request, self.handle(&request)).await {
+                    Ok(response) => {
+                        self.middleware()
+                            .handle_response(&request, response.from_request(&request)?, executed)
+                            .await?
+                    }
+                    Err(err) => {
+                        error!("{:?}", err);
+
+                        let response = match err {
+                            Error::HttpError(err) => match err.code() {
+                                400 => Response::bad_request(),
+                                401 => Response::unauthorized(None),
+                                413 => Response::content_too_large(),
+                                _ => Response::internal_error(err),
+                            },
+
+                            Error::ViewError(err) => {
+                                Response::error_pretty("Template error", err.to_string().as_str())
+                            }
+
+                            err => Response::internal_error(err),
+                        };
+
+                        // Run the middleware chain on the response anyway.
+                        self.middleware()
+                            .handle_response(&request, response, executed)
+                            .await?
+                    }
                 }
-            },
+            }
             (Outcome::Stop(request, response), executed) => {
                 self.middleware()
                     .handle_response(&request, response.from_request(&request)?, executed)
@@ -565,26 +571,64 @@ pub trait ModelController: Controller {
         Handler::rest(path, self)
     }
 
-    /// List all records for the model. Supports pagination with `page` parameter. Supports number of records per page with `page_size` parameter.
+    /// Pagination settings of the list endpoint, e.g. the maximum number of records
+    /// on a page and the names of the returned JSON fields.
+    fn pagination(&self) -> Pagination {
+        Pagination::default()
+    }
+
+    /// Fields of the model which are never returned to the client, e.g. `password`.
+    fn hidden_fields(&self) -> &[&str] {
+        &[]
+    }
+
+    /// Convert a record to the JSON returned to the client. By default, the model is serialized
+    /// with [`serde`] and the [`ModelController::hidden_fields`] are removed from it.
+    fn serialize(&self, model: &Self::Model) -> Result<serde_json::Value, serde_json::Error> {
+        let mut json = serde_json::to_value(model)?;
+
+        if let Some(object) = json.as_object_mut() {
+            for field in self.hidden_fields() {
+                object.remove(*field);
+            }
+        }
+
+        Ok(json)
+    }
+
+    /// List all records for the model. Supports pagination with the `page` parameter, and the number of records
+    /// per page with the `per_page` parameter, which is limited by [`Pagination::max_per_page`].
+    /// Returns the records and the page metadata, as described in [`pagination`].
     ///
     /// # Example
     ///
     /// ```text,ignore
-    /// GET /users?page=3&page_size=40
+    /// GET /users?page=3&per_page=40
     /// ```
     async fn list(&self, request: &Request) -> Result<Response, Error> {
+        let pagination = self.pagination();
+        let (page, per_page) = pagination.params(request.query());
+
         let mut conn = get_connection().await?;
-        let page_size = request.query().get::<i64>("page_size").unwrap_or(25);
-        let page = request.query().get::<i64>("page").unwrap_or(1);
-        let offset = (std::cmp::max(1, page) - 1) * page_size;
-
-        let models = Self::Model::all()
-            .limit(page_size)
-            .offset(offset)
-            .fetch_all(&mut conn)
-            .await?;
-        let response = match Response::new().json(models) {
-            Ok(response) => response,
+        let page = Self::Model::all().page(page, per_page, &mut conn).await?;
+        let items = match page
+            .items
+            .iter()
+            .map(|model| self.serialize(model))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(items) => items,
+            Err(err) => return Ok(Response::internal_error(err)),
+        };
+        let page = Page {
+            items,
+            total: page.total,
+            page: page.page,
+            per_page: page.per_page,
+        };
+
+        let response = match pagination.to_json(&page) {
+            Ok(json) => Response::new().json(json)?,
             Err(err) => Response::internal_error(err),
         };
 
@@ -599,8 +643,8 @@ pub trait ModelController: Controller {
             .fetch(&mut conn)
             .await
         {
-            Ok(model) => match Response::new().json(model) {
-                Ok(response) => Ok(response),
+            Ok(model) => match self.serialize(&model) {
+                Ok(json) => Ok(Response::new().json(json)?),
                 Err(err) => Ok(Response::internal_error(err)),
             },
 
@@ -627,7 +671,7 @@ pub trait ModelController: Controller {
         .fetch(&mut conn)
         .await?;
 
-        Ok(Response::new().code(201).json(model)?)
+        Ok(Response::new().code(201).json(self.serialize(&model)?)?)
     }
 
     /// Update existing model record.
@@ -643,7 +687,7 @@ pub trait ModelController: Controller {
 
         let mut conn = get_connection().await?;
         let model = model.save().fetch(&mut conn).await?;
-        Ok(Response::new().json(model)?)
+        Ok(Response::new().json(self.serialize(&model)?)?)
     }
 
     /// Partially update an existing model record.
@@ -675,7 +719,7 @@ pub trait ModelController: Controller {
             .fetch(&mut conn)
             .await?;
 
-        Ok(Response::new().json(model)?)
+        Ok(Response::new().json(self.serialize(&model)?)?)
     }
 }
 
@@ -718,6 +762,12 @@ pub trait WebsocketController: Controller {
         Ok(())
     }
 
+    /// Do something when a client's WebSocket connection is closed, either by the client,
+    /// or because it stopped responding to pings.
+    async fn client_disconnected(&self, session_id: &SessionId) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Handle the WebSocket TCP stream. Provides the WebSocket
     /// protocol implementation. You may not want to override this unless you
     /// want to change how WebSockets work in Rwf.
@@ -745,69 +795,169 @@ pub trait WebsocketController: Controller {
 
         self.client_connected(&session_id).await?;
 
-        loop {
-            select! {
-                _ = check.tick() => {
-                    debug!("{} check session \"{}\"", "websocket".purple(), session_id);
+        // Always tell the controller the client is gone, even if the connection was lost.
+        let result: Result<(), Error> = async {
+            loop {
+                select! {
+                    _ = check.tick() => {
+                        debug!("{} check session \"{}\"", "websocket".purple(), session_id);
+
+                        let closed = match timeout(
+                            config.websocket.ping_timeout().unsigned_abs(),
+                            DataFrame::new_ping().flush(&mut stream)
+                        ).await {
+                            Ok(Ok(_)) => false,
+                            _ => true,
+                        };
+
+                        lost_pings += 1;
+
+                        if closed || lost_pings as usize > config.websocket.ping_disconnect_count {
+                            break;
+                        }
+                    }
 
-                    let closed = match timeout(
-                        config.websocket.ping_timeout().unsigned_abs(),
-                        DataFrame::new_ping().flush(&mut stream)
-                    ).await {
-                        Ok(Ok(_)) => false,
-                        _ => true,
-                    };
+                    message = receiver.recv() => {
+                        match message {
+                            Ok(message) => {
+                                debug!("{} sending {:?} to session \"{}\"",
+                                    "websocket".purple(),
+                                    message, receiver.session_id());
+                                message.send(&mut stream).await?;
+                            }
+
+                            Err(RecvError::Closed) => break,
+
+                            // Lagging behind. This is best effort
+                            // message delivery, so we are ok dropping
+                            // messages if the client can't receive them
+                            // fast enough.
+                            Err(RecvError::Lagged(_)) => continue,
+                        }
+                    }
 
-                    lost_pings += 1;
+                    frame = DataFrame::read(&mut stream) => {
+                        let frame = frame?;
+
+                        if frame.is_pong() {
+                            debug!("{} session \"{}\" is alive", "websocket".purple(), session_id);
+                            Comms::heartbeat(&session_id);
+                            lost_pings -= 1;
+
+                            // Protect against weird clients.
+                            if lost_pings < 0 {
+                                lost_pings = 0;
+                            }
+
+                            continue;
+                        } else if frame.is_ping() {
+                            DataFrame::new_pong(frame).flush(&mut stream).await?;
+                            continue;
+                        } else if frame.is_close() {
+                            // Best effort, the client may have closed the TCP connection already.
+                            let _ = DataFrame::new_close().flush(&mut stream).await;
+                            break;
+                        }
 
-                    if closed || lost_pings as usize > config.websocket.ping_disconnect_count {
-                        break;
+                        Comms::heartbeat(&session_id);
+                        self.client_message(&session_id, frame.message()).await?;
                     }
+
                 }
+            }
 
-                message = receiver.recv() => {
-                    match message {
-                        Ok(message) => {
-                            debug!("{} sending {:?} to session \"{}\"",
-                                "websocket".purple(),
-                                message, receiver.session_id());
-                            message.send(&mut stream).await?;
-                        }
+            Ok(())
+        }
+        .await;
 
-                        Err(RecvError::Closed) => break,
+        info!(
+            "{} {} {} disconnected",
+            "websocket".purple(),
+            request.path().path().purple(),
+            self.controller_name().green(),
+        );
 
-                        // Lagging behind. This is best effort
-                        // message delivery, so we are ok dropping
-                        // messages if the client can't receive them
-                        // fast enough.
-                        Err(RecvError::Lagged(_)) => continue,
-                    }
-                }
+        self.client_disconnected(&session_id).await?;
+        result?;
+
+        Ok(false)
+    }
+}
 
-                frame = DataFrame::read(&mut stream) => {
-                    let frame = frame?;
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Clone, macros::Model, Serialize, Deserialize)]
+    #[table_name = "rwf_test_hidden_users"]
+    struct User {
+        id: Option<i64>,
+        email: String,
+        password: String,
+    }
 
-                    if frame.is_pong() {
-                        debug!("{} session \"{}\" is alive", "websocket".purple(), session_id);
-                        lost_pings -= 1;
+    #[derive(macros::ModelController)]
+    struct Users;
 
-                        // Protect against weird clients.
-                        if lost_pings < 0 {
-                            lost_pings = 0;
-                        }
+    #[async_trait]
+    impl ModelController for Users {
+        type Model = User;
 
-                        continue;
-                    } else if frame.is_ping() {
-                        DataFrame::new_pong(frame).flush(&mut stream).await?;
-                        continue;
-                    }
+        fn hidden_fields(&self) -> &[&str] {
+            &["password"]
+        }
+    }
 
-                    self.client_message(&session_id, frame.message()).await?;
-                }
+    #[tokio::test]
+    async fn test_hidden_fields() -> Result<(), Error> {
+        let user = User {
+            id: Some(1),
+            email: "test@test.com".into(),
+            password: "secret".into(),
+        };
 
-            }
+        let json = Users.serialize(&user).unwrap();
+        assert_eq!(json, serde_json::json!({"id": 1, "email": "test@test.com"}));
+
+        // The controller uses the global pool, so the table can't be temporary.
+        let pool = Pool::from_env();
+        let conn = pool.get().await?;
+        conn.client()
+            .batch_execute(
+                "DROP TABLE IF EXISTS rwf_test_hidden_users;
+                CREATE TABLE rwf_test_hidden_users (id BIGINT PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL);
+                INSERT INTO rwf_test_hidden_users VALUES (1, 'test@test.com', 'secret');",
+            )
+            .await
+            .unwrap();
+
+        let handler = Users.crud("/users");
+
+        for path in ["/users", "/users/1"] {
+            let request = Request::read(
+                "127.0.0.1:1234".parse().unwrap(),
+                format!("GET {} HTTP/1.1\r\n\r\n", path).as_bytes(),
+            )
+            .await?
+            .with_params(handler.path_with_regex().params());
+
+            let response = handler.handle_internal(request).await?;
+            assert_eq!(response.status().code(), 200, "{}", path);
+
+            let mut body = vec![];
+            response.send(&mut body).await?;
+            let body = String::from_utf8(body).unwrap();
+            assert!(body.contains("test@test.com"), "{}", body);
+            assert!(!body.contains("password"), "{}", body);
+            assert!(!body.contains("secret"), "{}", body);
         }
 
-        Ok(false)
+        conn.client()
+            .execute("DROP TABLE rwf_test_hidden_users", &[])
+            .await
+            .unwrap();
+
+        Ok(())
     }
 }
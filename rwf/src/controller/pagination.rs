@@ -0,0 +1,199 @@
+//! Pagination of records returned by the [`ModelController`](super::ModelController) list endpoint.
+//!
+//! Pages are selected with the `page` and `per_page` query parameters, e.g. `GET /users?page=2&per_page=50`,
+//! and returned as a JSON object with the records and metadata about the page:
+//!
+//! ```json
+//! {
+//!   "data": [{"id": 26, "email": "alice@example.com"}],
+//!   "meta": {"total": 26, "page": 2, "per_page": 25, "total_pages": 2}
+//! }
+//! ```
+use serde::Serialize;
+use serde_json::{json, Map};
+
+use crate::http::Query;
+use crate::model::Page;
+
+/// Names of the fields in the JSON returned for a page.
+#[derive(Debug, Clone)]
+pub struct Fields {
+    /// Records on the page.
+    pub data: String,
+    /// Object containing the fields below.
+    pub meta: String,
+    /// Total number of records, on all pages.
+    pub total: String,
+    /// Page number, starting at 1.
+    pub page: String,
+    /// Maximum number of records on a page.
+    pub per_page: String,
+    /// Total number of pages.
+    pub total_pages: String,
+}
+
+impl Default for Fields {
+    fn default() -> Self {
+        Self {
+            data: "data".into(),
+            meta: "meta".into(),
+            total: "total".into(),
+            page: "page".into(),
+            per_page: "per_page".into(),
+            total_pages: "total_pages".into(),
+        }
+    }
+}
+
+/// Pagination settings.
+///
+/// # Example
+///
+/// ```
+/// use rwf::controller::pagination::Pagination;
+/// use rwf::http::Query;
+///
+/// let pagination = Pagination::new().max_per_page(50);
+/// let query = Query::parse("page=3&per_page=1000");
+///
+/// assert_eq!(pagination.params(&query), (3, 50));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pagination {
+    per_page: i64,
+    max_per_page: i64,
+    fields: Fields,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            per_page: 25,
+            max_per_page: 100,
+            fields: Fields::default(),
+        }
+    }
+}
+
+impl Pagination {
+    /// Pages of 25 records, with clients allowed to request up to 100 records per page.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of records on a page if the client doesn't pass `per_page`.
+    pub fn per_page(mut self, per_page: i64) -> Self {
+        self.per_page = per_page.max(1);
+        self
+    }
+
+    /// Maximum number of records on a page. Larger `per_page` values are reduced to it.
+    pub fn max_per_page(mut self, max_per_page: i64) -> Self {
+        self.max_per_page = max_per_page.max(1);
+        self
+    }
+
+    /// Rename the fields of the returned JSON.
+    pub fn fields(mut self, fields: Fields) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Get the page number and the number of records per page requested in the query.
+    /// `page_size` is accepted as an alias for `per_page`.
+    pub fn params(&self, query: &Query) -> (i64, i64) {
+        let page = query.get::<i64>("page").unwrap_or(1).max(1);
+        let per_page = query
+            .get::<i64>("per_page")
+            .or_else(|| query.get::<i64>("page_size"))
+            .unwrap_or(self.per_page)
+            .clamp(1, self.max_per_page.max(self.per_page));
+
+        (page, per_page)
+    }
+
+    /// Convert the page to JSON, with its records and metadata.
+    pub fn to_json<T: Serialize>(
+        &self,
+        page: &Page<T>,
+    ) -> Result<serde_json::Value, serde_json::Error> {
+        let fields = &self.fields;
+
+        let mut meta = Map::new();
+        meta.insert(fields.total.clone(), json!(page.total));
+        meta.insert(fields.page.clone(), json!(page.page));
+        meta.insert(fields.per_page.clone(), json!(page.per_page));
+        meta.insert(fields.total_pages.clone(), json!(page.total_pages()));
+
+        let mut envelope = Map::new();
+        envelope.insert(fields.data.clone(), serde_json::to_value(&page.items)?);
+        envelope.insert(fields.meta.clone(), meta.into());
+
+        Ok(envelope.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_params() {
+        let pagination = Pagination::new();
+
+        assert_eq!(pagination.params(&Query::parse("")), (1, 25));
+        assert_eq!(
+            pagination.params(&Query::parse("page=3&per_page=10")),
+            (3, 10)
+        );
+        assert_eq!(
+            pagination.params(&Query::parse("page=2&page_size=40")),
+            (2, 40)
+        );
+        assert_eq!(
+            pagination.params(&Query::parse("page=-1&per_page=0")),
+            (1, 1)
+        );
+        assert_eq!(
+            pagination.params(&Query::parse("page=abc&per_page=5000")),
+            (1, 100)
+        );
+
+        let pagination = Pagination::new().per_page(10).max_per_page(20);
+        assert_eq!(pagination.params(&Query::parse("page=2")), (2, 10));
+        assert_eq!(pagination.params(&Query::parse("per_page=21")), (1, 20));
+    }
+
+    #[test]
+    fn test_to_json() {
+        let page = Page {
+            items: vec![1, 2],
+            total: 27,
+            page: 2,
+            per_page: 25,
+        };
+
+        assert_eq!(
+            Pagination::new().to_json(&page).unwrap(),
+            json!({
+                "data": [1, 2],
+                "meta": {"total": 27, "page": 2, "per_page": 25, "total_pages": 2},
+            })
+        );
+
+        let fields = Fields {
+            data: "items".into(),
+            meta: "pagination".into(),
+            total: "count".into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Pagination::new().fields(fields).to_json(&page).unwrap(),
+            json!({
+                "items": [1, 2],
+                "pagination": {"count": 27, "page": 2, "per_page": 25, "total_pages": 2},
+            })
+        );
+    }
+}
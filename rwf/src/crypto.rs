@@ -346,6 +346,40 @@ pub fn hash_validate(data: &[u8], hash: &str) -> Result<bool, Error> {
         .is_ok())
 }
 
+/// Hash a password with Argon2, using a random salt. This is the recommended way
+/// to store user passwords.
+///
+/// The returned string contains the salt and the Argon2 parameters, e.g. `$argon2id$v=19$m=19456,t=2,p=1$...`,
+/// so it's the only thing that needs to be saved to check the password later with [`verify_password`].
+///
+/// # Example
+///
+/// ```
+/// # use rwf::crypto::hash_password;
+/// let hash = hash_password("hunter2").unwrap();
+///
+/// assert!(hash.starts_with("$argon2id$"));
+/// ```
+pub fn hash_password(password: &str) -> Result<String, Error> {
+    hash(password.as_bytes())
+}
+
+/// Check that the password matches a hash created with [`hash_password`].
+/// Returns `false` if the hash is not a valid Argon2 hash.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::crypto::{hash_password, verify_password};
+/// let hash = hash_password("hunter2").unwrap();
+///
+/// assert!(verify_password("hunter2", &hash));
+/// assert!(!verify_password("hunter3", &hash));
+/// ```
+pub fn verify_password(password: &str, hash: &str) -> bool {
+    hash_validate(password.as_bytes(), hash).unwrap_or(false)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -365,8 +399,30 @@ mod test {
         let decrypted = decrypt_number(&encrypted).unwrap();
         assert_eq!(n, decrypted);
 
+        for n in [0, -1, 1, i64::MIN, i64::MAX] {
+            assert_eq!(decrypt_number(&encrypt_number(n).unwrap()).unwrap(), n);
+        }
+
+        // A random nonce is used every time.
+        assert_ne!(encrypt_number(n).unwrap(), encrypt_number(n).unwrap());
+
         let bad_input = "sdf";
         let result = decrypt_number(&bad_input);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_hash_password() {
+        let hash = hash_password("password").unwrap();
+
+        assert!(verify_password("password", &hash));
+        assert!(!verify_password("Password", &hash));
+        assert!(!verify_password("", &hash));
+        assert!(!verify_password("password", "not_encrypted"));
+
+        // Salted, so hashes of the same password are different.
+        let other = hash_password("password").unwrap();
+        assert_ne!(hash, other);
+        assert!(verify_password("password", &other));
+    }
 }
@@ -0,0 +1,182 @@
+//! Handles parsing the `Accept` header, used to pick the format of the response, e.g. HTML or JSON.
+
+/// A media range in the `Accept` header, e.g. `text/html;q=0.9` or `image/*`.
+#[derive(Debug, Clone, PartialEq)]
+struct MediaRange {
+    main: String,
+    sub: String,
+    quality: f32,
+}
+
+impl MediaRange {
+    fn parse(range: &str) -> Option<Self> {
+        let mut params = range.split(';');
+        let (main, sub) = params.next()?.trim().split_once('/')?;
+        let (main, sub) = (main.trim(), sub.trim());
+
+        if main.is_empty() || sub.is_empty() || (main == "*" && sub != "*") {
+            return None;
+        }
+
+        let mut quality = 1.0;
+        for param in params {
+            if let Some((name, value)) = param.split_once('=') {
+                if name.trim().eq_ignore_ascii_case("q") {
+                    quality = value.trim().parse::<f32>().ok()?.clamp(0.0, 1.0);
+                }
+            }
+        }
+
+        Some(Self {
+            main: main.to_ascii_lowercase(),
+            sub: sub.to_ascii_lowercase(),
+            quality,
+        })
+    }
+
+    /// How specific the range is if it matches the type: 3 for `type/subtype`,
+    /// 2 for `type/*` and 1 for `*/*`.
+    fn specificity(&self, main: &str, sub: &str) -> Option<u8> {
+        match (self.main.as_str(), self.sub.as_str()) {
+            ("*", "*") => Some(1),
+            (m, "*") if m == main => Some(2),
+            (m, s) if m == main && s == sub => Some(3),
+            _ => None,
+        }
+    }
+}
+
+/// `Accept` header.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::http::Accept;
+/// let accept = Accept::parse("text/html, application/json;q=0.9, */*;q=0.1");
+///
+/// assert_eq!(accept.preferred(&["application/json", "text/html"]), Some("text/html"));
+/// assert!(accept.accepts("image/png"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Accept {
+    ranges: Vec<MediaRange>,
+}
+
+impl Accept {
+    /// Parse the `Accept` header value. Invalid media ranges are ignored.
+    pub fn parse(header: &str) -> Self {
+        Self {
+            ranges: header.split(',').filter_map(MediaRange::parse).collect(),
+        }
+    }
+
+    /// Quality of the MIME type, e.g. `text/html`, between 0 and 1. It's taken from the most specific
+    /// media range matching the type, and is 0 if none do. If the client didn't send the header,
+    /// all types are acceptable.
+    pub fn quality(&self, mime: &str) -> f32 {
+        if self.ranges.is_empty() {
+            return 1.0;
+        }
+
+        let mime = mime.split(';').next().unwrap_or_default().trim();
+        let (main, sub) = match mime.split_once('/') {
+            Some((main, sub)) => (main.to_ascii_lowercase(), sub.to_ascii_lowercase()),
+            None => return 0.0,
+        };
+
+        self.ranges
+            .iter()
+            .filter_map(|range| {
+                range
+                    .specificity(&main, &sub)
+                    .map(|specificity| (specificity, range.quality))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, quality)| quality)
+            .unwrap_or(0.0)
+    }
+
+    /// The client accepts responses of this MIME type.
+    pub fn accepts(&self, mime: &str) -> bool {
+        self.quality(mime) > 0.0
+    }
+
+    /// Pick the MIME type the client prefers from the types the server can respond with.
+    /// If the client likes several of them equally, the first one is returned.
+    /// Returns `None` if none of them are acceptable.
+    pub fn preferred<'a>(&self, mimes: &[&'a str]) -> Option<&'a str> {
+        let mut preferred = None;
+        let mut best = 0.0;
+
+        for mime in mimes {
+            let quality = self.quality(mime);
+            if quality > best {
+                best = quality;
+                preferred = Some(*mime);
+            }
+        }
+
+        preferred
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_ranked() {
+        let accept = Accept::parse(
+            "text/html;level=1, application/json;q=0.8, text/*;q=0.5, */*;q=0.1, image/png;q=0",
+        );
+
+        assert_eq!(accept.quality("text/html"), 1.0);
+        assert_eq!(accept.quality("application/json"), 0.8);
+        assert_eq!(accept.quality("text/plain"), 0.5);
+        assert_eq!(accept.quality("application/xml"), 0.1);
+        assert_eq!(accept.quality("image/png"), 0.0);
+
+        assert!(accept.accepts("TEXT/HTML; charset=utf-8"));
+        assert!(!accept.accepts("image/png"));
+        assert!(!accept.accepts("nonsense"));
+
+        assert_eq!(
+            accept.preferred(&["application/json", "text/html"]),
+            Some("text/html")
+        );
+        assert_eq!(
+            accept.preferred(&["text/plain", "application/json"]),
+            Some("application/json")
+        );
+        assert_eq!(accept.preferred(&["image/png"]), None);
+        assert_eq!(accept.preferred(&[]), None);
+    }
+
+    #[test]
+    fn test_wildcards() {
+        let accept = Accept::parse("application/*;q=0.9, application/json;q=0.2");
+        assert_eq!(accept.quality("application/json"), 0.2);
+        assert_eq!(accept.quality("application/xml"), 0.9);
+        assert!(!accept.accepts("text/html"));
+
+        // Ties are won by the server's preference.
+        let accept = Accept::parse("*/*");
+        assert_eq!(
+            accept.preferred(&["application/json", "text/html"]),
+            Some("application/json")
+        );
+    }
+
+    #[test]
+    fn test_missing() {
+        // Without a valid header, everything is acceptable.
+        for header in ["", "garbage, */html, text/html;q=abc"] {
+            let accept = Accept::parse(header);
+            assert!(accept.accepts("application/json"));
+            assert_eq!(
+                accept.preferred(&["text/html", "application/json"]),
+                Some("text/html")
+            );
+        }
+    }
+}
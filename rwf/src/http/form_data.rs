@@ -20,22 +20,17 @@ impl FormData {
     /// Extract form data from request.
     pub fn from_request(request: &Request) -> Result<Self, Error> {
         let content_type = request
-            .header("content-type")
+            .content_type()
             .ok_or(Error::MalformedRequest("content-type header is required"))?;
 
-        if content_type.contains("application/x-www-form-urlencoded") {
+        if content_type.is("application/x-www-form-urlencoded") {
             Self::from_url_encoded(request)
-        } else if content_type.contains("multipart/form-data") {
-            // Extract the multipart boundary from the Content-Type header.
-            if let Some(boundary) = content_type.split(";").last() {
-                let boundary = boundary.split("=").last();
-                if let Some(boundary) = boundary {
-                    let multipart = Multipart::read(request.body(), boundary.trim())?;
-
-                    Ok(Self::Multipart(multipart))
-                } else {
-                    Err(Error::MalformedRequest("multipart missing boundary"))
-                }
+        } else if content_type.is("multipart/form-data") {
+            // The multipart boundary is a parameter of the Content-Type header.
+            if let Some(boundary) = content_type.param("boundary") {
+                let multipart = Multipart::read(request.body(), boundary)?;
+
+                Ok(Self::Multipart(multipart))
             } else {
                 Err(Error::MalformedRequest("multipart missing boundary"))
             }
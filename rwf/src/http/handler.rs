@@ -4,9 +4,9 @@
 //! See [`crate::http::router`] documentation for routing implementation details.
 use super::{
     path::{PathType, PathWithRegex},
-    Path,
+    Error, Method, Path, Request, Response,
 };
-use crate::controller::Controller;
+use crate::controller::{Controller, Error as ControllerError, MiddlewareSet, Outcome};
 
 use std::ops::Deref;
 
@@ -19,6 +19,10 @@ pub struct Handler {
     name: Option<String>,
     controller: Box<dyn Controller>,
     rank: i64,
+    middleware: MiddlewareSet,
+    methods: Vec<Method>,
+    head: bool,
+    max_body_size: Option<usize>,
 }
 
 impl Handler {
@@ -29,6 +33,10 @@ impl Handler {
             controller: Box::new(controller),
             name: None,
             rank: 0,
+            middleware: MiddlewareSet::without_default(vec![]),
+            methods: vec![],
+            head: true,
+            max_body_size: None,
         }
     }
 
@@ -89,6 +97,159 @@ impl Handler {
         self
     }
 
+    /// Only serve requests with these HTTP methods. If the path matches but the method doesn't,
+    /// the router returns `405 - Method Not Allowed`.
+    ///
+    /// By default, handlers accept all methods.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let handler = route!("/users" => Users).with_methods(&[Method::Get]);
+    /// ```
+    pub fn with_methods(mut self, methods: &[Method]) -> Self {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    /// HTTP methods this handler is restricted to. Empty if all methods are accepted.
+    pub fn methods(&self) -> &[Method] {
+        &self.methods
+    }
+
+    /// HTTP methods this handler accepts, including `HEAD` if it's served automatically
+    /// by the `GET` handler. Empty if all methods are accepted.
+    pub fn allowed_methods(&self) -> Vec<Method> {
+        let mut methods = self.methods.clone();
+        if self.head_from_get() && !methods.contains(&Method::Head) {
+            methods.push(Method::Head);
+        }
+        methods
+    }
+
+    /// Check if the handler accepts requests with this HTTP method.
+    pub fn allows(&self, method: &Method) -> bool {
+        self.methods.is_empty()
+            || self.methods.contains(method)
+            || (method == &Method::Head && self.head_from_get())
+    }
+
+    /// Don't serve `HEAD` requests by running the `GET` handler and removing the body.
+    ///
+    /// By default, `HEAD` requests are handled automatically. Disable this for routes
+    /// where `GET` is expensive and computing the response just to throw away the body
+    /// is wasteful.
+    pub fn without_head(mut self) -> Self {
+        self.head = false;
+        self
+    }
+
+    /// `HEAD` requests are served by the `GET` handler, unless the handler
+    /// explicitly accepts `HEAD` requests.
+    fn head_from_get(&self) -> bool {
+        self.head
+            && !self.methods.contains(&Method::Head)
+            && (self.methods.is_empty() || self.methods.contains(&Method::Get))
+    }
+
+    /// Maximum size of request bodies accepted by this route, in bytes, e.g. to allow large uploads
+    /// on one route only. Larger requests receive `413 - Content Too Large`.
+    ///
+    /// By default, the `max_request_size` [configuration](crate::config::General) setting is used.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let handler = route!("/uploads" => Uploads).with_max_body_size(100 * 1024 * 1024);
+    /// ```
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = Some(max_body_size);
+        self
+    }
+
+    /// Maximum size of request bodies accepted by this route, if it's different from the configured one.
+    pub fn max_body_size(&self) -> Option<usize> {
+        self.max_body_size
+    }
+
+    /// Mount this handler under a path prefix, e.g. `/users` with prefix `/admin`
+    /// becomes `/admin/users`.
+    pub fn prefix(mut self, prefix: &str) -> Result<Self, Error> {
+        let prefix = prefix.trim_end_matches('/');
+        let path = match self.path.path() {
+            "/" if !prefix.is_empty() => prefix.to_string(),
+            path => format!("{}{}", prefix, path),
+        };
+        let path_type = self.path.path_type().clone();
+
+        self.path = Path::parse(&path)?.with_regex(path_type)?;
+        Ok(self)
+    }
+
+    /// Remove the trailing slash from the handler path, so it matches
+    /// with or without it. Wildcard routes are left unchanged.
+    pub(crate) fn without_trailing_slash(mut self) -> Result<Self, Error> {
+        let path = self.path.path();
+
+        if path == "/" || !path.ends_with('/') || self.path.path_type() == &PathType::Wildcard {
+            return Ok(self);
+        }
+
+        let path_type = self.path.path_type().clone();
+        self.path = Path::parse(path.trim_end_matches('/'))?.with_regex(path_type)?;
+        Ok(self)
+    }
+
+    /// Run this middleware before the controller's own middleware. Used by route groups,
+    /// see [`crate::http::Router::group`].
+    ///
+    /// Middleware added later wraps middleware added earlier, so outer groups
+    /// run first.
+    pub fn with_middleware(mut self, middleware: MiddlewareSet) -> Self {
+        let mut handlers = middleware.handlers();
+        handlers.extend(self.middleware.handlers());
+        self.middleware = MiddlewareSet::without_default(handlers);
+        self
+    }
+
+    /// Pass the request through the route group middleware, if any, and then to the
+    /// controller. If the group middleware stops the request, the controller is not called.
+    ///
+    /// `HEAD` requests are passed to the controller as `GET` requests and the body is
+    /// removed from the response, unless disabled with [`Handler::without_head`].
+    pub async fn handle_internal(&self, mut request: Request) -> Result<Response, ControllerError> {
+        if request.method() == &Method::Head && self.head_from_get() {
+            request.head_mut().replace_method(Method::Get);
+            let response = self.handle_middleware(request).await?;
+            return Ok(response.without_body());
+        }
+
+        self.handle_middleware(request).await
+    }
+
+    async fn handle_middleware(&self, request: Request) -> Result<Response, ControllerError> {
+        if self.middleware.is_empty() {
+            return self.controller.handle_internal(request).await;
+        }
+
+        let response = match self.middleware.handle_request(request).await? {
+            (Outcome::Forward(request), executed) => {
+                let response = self.controller.handle_internal(request.clone()).await?;
+                self.middleware
+                    .handle_response(&request, response, executed)
+                    .await?
+            }
+
+            (Outcome::Stop(request, response), executed) => {
+                self.middleware
+                    .handle_response(&request, response.from_request(&request)?, executed)
+                    .await?
+            }
+        };
+
+        Ok(response)
+    }
+
     /// Get the controller name served by this route handler.
     pub fn controller_name(&self) -> &'static str {
         self.deref().controller_name()
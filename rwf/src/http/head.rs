@@ -6,7 +6,7 @@ use std::marker::Unpin;
 
 use tokio::io::{AsyncRead, AsyncReadExt};
 
-use super::{Authorization, Cookies, Error, Headers, Path, Query};
+use super::{Accept, Authorization, Cookies, Error, Headers, Mime, Path, Query};
 use crate::config::get_config;
 
 /// HTTP method, e.g. GET, POST, etc.
@@ -111,26 +111,8 @@ impl Head {
     pub async fn read(mut stream: impl AsyncRead + Unpin) -> Result<Self, Error> {
         let bytes_remaining = get_config().general.header_max_size; // avoid DDoS
 
-        let request = Self::read_line(&mut stream, bytes_remaining)
-            .await?
-            .split(" ")
-            .map(|s| s.to_string())
-            .collect::<Vec<_>>();
-
-        let method = request
-            .get(0)
-            .ok_or(Error::MalformedRequest("method"))?
-            .to_string();
-        let method = Method::try_from(method)?;
-
-        let path = request.get(1).ok_or(Error::MalformedRequest("path"))?;
-        let path = Path::parse(path)?;
-
-        let version = request
-            .get(2)
-            .ok_or(Error::MalformedRequest("version"))?
-            .to_string();
-        let version = Version::try_from(version)?;
+        let request = Self::read_line(&mut stream, bytes_remaining).await?;
+        let (method, path, version) = Self::parse_request_line(&request)?;
 
         let mut headers = Headers::new();
 
@@ -139,19 +121,16 @@ impl Head {
             if header.is_empty() {
                 break;
             } else {
-                let header = header
-                    .split(":")
-                    .map(|s| s.trim().to_string())
-                    .collect::<Vec<_>>();
-                let name = header
-                    .get(0)
-                    .ok_or(Error::MalformedRequest("header name"))?
-                    .to_lowercase();
-                let value = header
-                    .get(1)
-                    .ok_or(Error::MalformedRequest("header value"))?
-                    .clone();
-                headers.insert(name, value);
+                let (name, value) = header
+                    .split_once(":")
+                    .ok_or(Error::MalformedRequest("header value"))?;
+                let name = name.trim();
+
+                if name.is_empty() {
+                    return Err(Error::MalformedRequest("header name"));
+                }
+
+                headers.insert(name.to_lowercase(), value.trim().to_string());
             }
         }
 
@@ -163,6 +142,36 @@ impl Head {
         })
     }
 
+    /// Get the parsed `Accept` header. If the client didn't send one, all types are acceptable.
+    pub fn accept(&self) -> Accept {
+        Accept::parse(
+            self.header("accept")
+                .map(|h| h.as_str())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// The client accepts responses of this MIME type, according to its `Accept` header.
+    pub fn accepts(&self, mime: &str) -> bool {
+        self.accept().accepts(mime)
+    }
+
+    /// Pick the MIME type the client prefers from the types the server can respond with,
+    /// according to its `Accept` header.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// match request.preferred(&["text/html", "application/json"]) {
+    ///     Some("application/json") => Ok(Response::new().json(&users)?),
+    ///     Some(_) => Ok(Response::new().html(page)),
+    ///     None => Ok(Response::not_acceptable()),
+    /// }
+    /// ```
+    pub fn preferred<'a>(&self, mimes: &[&'a str]) -> Option<&'a str> {
+        self.accept().preferred(mimes)
+    }
+
     /// Get the value of the `Authorization` header, if any is set. The header is parsed and if the
     /// authorization type is supported, an [`crate::http::Authorization`] is returned.
     pub fn authorization(&self) -> Option<Authorization> {
@@ -233,6 +242,32 @@ impl Head {
         }
     }
 
+    /// The type of the request body, provided by the `Content-Type` header.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// match request.content_type() {
+    ///     Some(mime) if mime.is("application/json") => { /* ... */ }
+    ///     _ => return Ok(Response::bad_request()),
+    /// }
+    /// ```
+    pub fn content_type(&self) -> Option<Mime> {
+        self.headers
+            .get("content-type")
+            .and_then(|content_type| Mime::parse(content_type))
+    }
+
+    /// The request body is sent in chunks, i.e. `Transfer-Encoding: chunked`. If it is,
+    /// the `Content-Length` header is ignored.
+    pub fn chunked(&self) -> bool {
+        self.headers
+            .get("transfer-encoding")
+            .and_then(|encoding| encoding.rsplit(',').next())
+            .map(|encoding| encoding.trim().eq_ignore_ascii_case("chunked"))
+            .unwrap_or(false)
+    }
+
     /// Get all request headers.
     pub fn headers(&self) -> &Headers {
         &self.headers
@@ -266,38 +301,87 @@ impl Head {
                 .unwrap_or(false)
     }
 
-    /// Read a line from the stream, parsing out \r\n.
-    async fn read_line(
+    /// Parse the request line, e.g. `GET /path HTTP/1.1`, into its
+    /// method, path and HTTP version.
+    ///
+    /// Parts can be separated by any amount of whitespace. Absolute-form targets
+    /// sent to proxies, e.g. `GET http://host/path HTTP/1.1`, are accepted and
+    /// only the path is kept.
+    fn parse_request_line(line: &str) -> Result<(Method, Path, Version), Error> {
+        let mut parts = line.split_whitespace();
+
+        let method = parts.next().ok_or(Error::MalformedRequest("method"))?;
+        let target = parts.next().ok_or(Error::MalformedRequest("path"))?;
+        let version = parts.next().ok_or(Error::MalformedRequest("version"))?;
+
+        if parts.next().is_some() {
+            return Err(Error::MalformedRequest("request line"));
+        }
+
+        if !method.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(Error::MalformedRequest("method"));
+        }
+
+        if !version.starts_with("HTTP/") {
+            return Err(Error::MalformedRequest("version"));
+        }
+
+        let path = if target.starts_with("/") {
+            target
+        } else {
+            Self::absolute_form_path(target)?
+        };
+
+        Ok((
+            Method::try_from(method.to_string())?,
+            Path::parse(path)?,
+            Version::try_from(version.to_string())?,
+        ))
+    }
+
+    /// Extract the path from an absolute-form request target,
+    /// e.g. `/path?query` from `http://host/path?query`.
+    fn absolute_form_path(target: &str) -> Result<&str, Error> {
+        let (scheme, rest) = target
+            .split_once("://")
+            .ok_or(Error::MalformedRequest("path"))?;
+
+        if !["http", "https"].contains(&scheme.to_lowercase().as_str()) {
+            return Err(Error::MalformedRequest("path"));
+        }
+
+        match rest.find(['/', '?']) {
+            Some(start) => Ok(&rest[start..]),
+            None => Ok("/"),
+        }
+    }
+
+    /// Read a line from the stream, parsing out `\r\n`. Bare `\n`
+    /// line endings are accepted as well.
+    pub(crate) async fn read_line(
         mut stream: impl AsyncRead + Unpin,
         mut bytes_remaining: usize,
-    ) -> Result<String, std::io::Error> {
+    ) -> Result<String, Error> {
         let mut buf = Vec::new();
-        let (mut cr, mut lf) = (false, false);
+        let mut cr = false;
 
         while bytes_remaining > 0 {
             // `stream` should be buffered.
             let b = stream.read_u8().await?;
             bytes_remaining -= 1;
 
-            if b == '\r' as u8 {
+            if b == b'\n' {
+                return Ok(String::from_utf8_lossy(&buf).to_string());
+            } else if cr {
+                return Err(Error::MalformedRequest("cr without lf"));
+            } else if b == b'\r' {
                 cr = true;
-                if lf {
-                    return Err(std::io::Error::other(Error::MalformedRequest(
-                        "nl before cr",
-                    )));
-                }
-            } else if b == '\n' as u8 {
-                lf = true;
             } else {
                 buf.push(b);
             }
-
-            if cr && lf {
-                break;
-            }
         }
 
-        Ok(String::from_utf8_lossy(&buf).to_string())
+        Err(Error::MalformedRequest("line too long"))
     }
 
     /// Change the path of this request. This is used mostly internally
@@ -308,6 +392,12 @@ impl Head {
     pub fn replace_path(&mut self, path: Path) {
         self.path = path.clone();
     }
+
+    /// Change the method of this request. This is used internally
+    /// to serve `HEAD` requests with `GET` handlers.
+    pub fn replace_method(&mut self, method: Method) {
+        self.method = method;
+    }
 }
 
 #[cfg(test)]
@@ -357,13 +447,55 @@ mod test {
     }
 
     #[tokio::test]
-    async fn test_nl_before_cr() {
-        let err = Head::read("GET / HTTP/1.1\n\r".as_bytes())
+    async fn test_cr_without_lf() {
+        let err = Head::read("GET / HTTP/1.1\r\nAccept: */*\r\r\n\r\n".as_bytes())
             .await
             .expect_err("parser should throw err");
+        assert!(matches!(err, Error::MalformedRequest("cr without lf")));
+
+        // LF before CR is a bare LF followed by a stray CR.
+        assert!(Head::read("GET / HTTP/1.1\n\r".as_bytes()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bare_lf() {
+        let head = Head::read("GET /users?id=5 HTTP/1.1\nHost: localhost:8000\n\n".as_bytes())
+            .await
+            .expect("head");
+        assert_eq!(head.path().path(), "/users");
+        assert_eq!(head.query().get::<i64>("id"), Some(5));
+        assert_eq!(head.header("host"), Some(&String::from("localhost:8000")));
+    }
+
+    #[tokio::test]
+    async fn test_absolute_form() {
+        let head = Head::read("GET http://localhost:8000/users/5?a=b HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .expect("head");
+        assert_eq!(head.path().path(), "/users/5");
+        assert_eq!(head.query().get::<String>("a"), Some("b".into()));
 
-        // Hacky way to see what's in the error.
-        let msg = format!("{:?}", err);
-        assert!(msg.contains("nl before cr"));
+        let head = Head::read("GET  https://localhost   HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .expect("head");
+        assert_eq!(head.path().path(), "/");
+        assert!(head.http1());
+    }
+
+    #[tokio::test]
+    async fn test_malformed_request_line() {
+        for line in [
+            "GET /\r\n\r\n",
+            "GET\r\n\r\n",
+            "\r\n\r\n",
+            "GET / HTTP/1.1 extra\r\n\r\n",
+            "G(T / HTTP/1.1\r\n\r\n",
+            "GET / FTP/1.1\r\n\r\n",
+            "GET ftp://host/path HTTP/1.1\r\n\r\n",
+            "GET / HTTP/1.1\r\nno colon\r\n\r\n",
+        ] {
+            let err = Head::read(line.as_bytes()).await.expect_err(line);
+            assert!(matches!(err, Error::MalformedRequest(_)), "{}", line);
+        }
     }
 }
@@ -104,6 +104,11 @@ impl Headers {
 
 impl From<HashMap<String, String>> for Headers {
     fn from(headers: HashMap<String, String>) -> Self {
-        Self { headers }
+        Self {
+            headers: headers
+                .into_iter()
+                .map(|(name, value)| (name.to_lowercase(), value))
+                .collect(),
+        }
     }
 }
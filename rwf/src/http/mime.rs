@@ -0,0 +1,104 @@
+//! Handles parsing MIME types, e.g. the value of the `Content-Type` header.
+
+/// MIME type with its parameters, e.g. `text/html; charset=utf-8`.
+///
+/// # Example
+///
+/// ```
+/// # use rwf::http::Mime;
+/// let mime = Mime::parse("multipart/form-data; boundary=\"abc\"").unwrap();
+///
+/// assert_eq!(mime.essence(), "multipart/form-data");
+/// assert_eq!(mime.param("boundary"), Some("abc"));
+/// assert!(mime.is("Multipart/Form-Data"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mime {
+    main: String,
+    sub: String,
+    params: Vec<(String, String)>,
+}
+
+impl Mime {
+    /// Parse a MIME type. Type and parameter names are case insensitive, so they are lowercased.
+    /// Returns `None` if the type is missing its subtype.
+    pub fn parse(mime: &str) -> Option<Self> {
+        let mut parts = mime.split(';');
+        let (main, sub) = parts.next()?.trim().split_once('/')?;
+        let (main, sub) = (main.trim(), sub.trim());
+
+        if main.is_empty() || sub.is_empty() {
+            return None;
+        }
+
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(name, value)| {
+                (
+                    name.trim().to_ascii_lowercase(),
+                    value.trim().trim_matches('"').to_string(),
+                )
+            })
+            .collect();
+
+        Some(Self {
+            main: main.to_ascii_lowercase(),
+            sub: sub.to_ascii_lowercase(),
+            params,
+        })
+    }
+
+    /// Type and subtype, without parameters, e.g. `text/html`.
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.main, self.sub)
+    }
+
+    /// Type, e.g. `text` for `text/html`.
+    pub fn main(&self) -> &str {
+        &self.main
+    }
+
+    /// Subtype, e.g. `html` for `text/html`.
+    pub fn sub(&self) -> &str {
+        &self.sub
+    }
+
+    /// Get the value of a parameter, e.g. `charset`. Case insensitive.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The type is this one, ignoring parameters and case, e.g. `application/json`.
+    pub fn is(&self, mime: &str) -> bool {
+        match Mime::parse(mime) {
+            Some(mime) => mime.main == self.main && mime.sub == self.sub,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_mime() {
+        let mime = Mime::parse("Text/HTML; Charset=UTF-8; level=\"1\"").unwrap();
+        assert_eq!(mime.essence(), "text/html");
+        assert_eq!(mime.main(), "text");
+        assert_eq!(mime.sub(), "html");
+        assert_eq!(mime.param("charset"), Some("UTF-8"));
+        assert_eq!(mime.param("level"), Some("1"));
+        assert_eq!(mime.param("boundary"), None);
+        assert!(mime.is("text/html"));
+        assert!(!mime.is("text/plain"));
+
+        assert_eq!(Mime::parse("application/json").unwrap().params.len(), 0);
+        assert!(Mime::parse("json").is_none());
+        assert!(Mime::parse("text/").is_none());
+        assert!(Mime::parse("").is_none());
+    }
+}
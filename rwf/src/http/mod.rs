@@ -9,6 +9,7 @@
 //! You can put the Rwf application behind a load balancer (like nginx) that supports
 //! HTTP/2 to take advantage of its performance enhancements.
 #![allow(dead_code)]
+pub mod accept;
 pub mod authorization;
 pub mod body;
 pub mod cookies;
@@ -18,6 +19,7 @@ pub mod form_data;
 pub mod handler;
 pub mod head;
 pub mod headers;
+pub mod mime;
 pub mod path;
 pub mod request;
 pub mod response;
@@ -29,6 +31,7 @@ pub mod websocket;
 #[cfg(feature = "wsgi")]
 pub mod wsgi;
 
+pub use accept::Accept;
 pub use authorization::Authorization;
 pub use body::Body;
 pub use cookies::{Cookie, CookieBuilder, Cookies};
@@ -38,10 +41,11 @@ pub use form_data::FormData;
 pub use handler::Handler;
 pub use head::{Head, Method};
 pub use headers::Headers;
+pub use mime::Mime;
 pub use path::{Params, Path, Query, ToParameter};
 pub use request::Request;
 pub use response::Response;
-pub use router::Router;
+pub use router::{Match, Router, TrailingSlash};
 pub use server::{Server, Stream};
 pub use url::{urldecode, urlencode};
 pub use websocket::{Message, ToMessage};
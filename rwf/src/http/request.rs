@@ -30,6 +30,7 @@ pub struct Request {
     // Don't check for valid CSRF token.
     skip_csrf: bool,
     renew_session: bool,
+    request_id: Option<String>,
 }
 
 impl Default for Request {
@@ -42,6 +43,7 @@ impl Default for Request {
             received_at: OffsetDateTime::now_utc(),
             skip_csrf: false,
             renew_session: false,
+            request_id: None,
         }
     }
 }
@@ -72,31 +74,37 @@ impl Request {
     /// It's safe to clone since the contents are behind an [`std::sync::Arc`].
     pub async fn read(peer: SocketAddr, mut stream: impl AsyncRead + Unpin) -> Result<Self, Error> {
         let head = Head::read(&mut stream).await?;
-        let content_length = head.content_length().unwrap_or(0);
-
-        // Handle requests which are too large.
-        if content_length > get_config().general.max_request_size {
-            // Throw away whatever we receive.
-            let mut throw_away = vec![0u8; 4096];
-            let mut content_length = content_length as i64;
-
-            loop {
-                let read = stream.read(&mut throw_away).await?;
-                content_length -= read as i64;
-
-                if content_length <= 0 || read == 0 {
-                    break;
-                }
+        Self::read_body(peer, head, stream, get_config().general.max_request_size).await
+    }
+
+    /// Read the body of a request whose head was already read. Bodies larger than `max_body_size` bytes
+    /// are rejected with [`Error::ContentTooLarge`] before they are received, or as soon as
+    /// they exceed it when sent in chunks.
+    pub async fn read_body(
+        peer: SocketAddr,
+        head: Head,
+        mut stream: impl AsyncRead + Unpin,
+        max_body_size: usize,
+    ) -> Result<Self, Error> {
+        let body = if head.chunked() {
+            match Self::read_chunked(&mut stream, max_body_size).await? {
+                Some(body) => body,
+                None => return Err(Error::ContentTooLarge(head)),
             }
+        } else {
+            let content_length = head.content_length().unwrap_or(0);
 
-            return Err(Error::ContentTooLarge(head));
-        }
+            if content_length > max_body_size {
+                return Err(Error::ContentTooLarge(head));
+            }
 
-        let mut body = vec![0u8; content_length];
-        stream
-            .read_exact(&mut body)
-            .await
-            .map_err(|_| Error::MalformedRequest("incorrect content length"))?;
+            let mut body = vec![0u8; content_length];
+            stream
+                .read_exact(&mut body)
+                .await
+                .map_err(|_| Error::MalformedRequest("incorrect content length"))?;
+            body
+        };
 
         let cookies = head.cookies();
 
@@ -117,9 +125,55 @@ impl Request {
             received_at: OffsetDateTime::now_utc(),
             skip_csrf: false,
             renew_session,
+            request_id: None,
         })
     }
 
+    /// Read a body sent with `Transfer-Encoding: chunked`. Returns `None` as soon as
+    /// the body exceeds the maximum size, without reading the rest of it.
+    async fn read_chunked(
+        mut stream: impl AsyncRead + Unpin,
+        max_body_size: usize,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let line_max_size = get_config().general.header_max_size;
+        let mut body = vec![];
+
+        loop {
+            let line = Head::read_line(&mut stream, line_max_size).await?;
+            // Chunk extensions, e.g. `5;name=value`, are ignored.
+            let size = line.split(';').next().unwrap_or_default().trim();
+            let size = usize::from_str_radix(size, 16)
+                .map_err(|_| Error::MalformedRequest("chunk size"))?;
+
+            if size == 0 {
+                break;
+            }
+
+            if size > max_body_size.saturating_sub(body.len()) {
+                return Ok(None);
+            }
+
+            let start = body.len();
+            body.resize(start + size, 0);
+            stream
+                .read_exact(&mut body[start..])
+                .await
+                .map_err(|_| Error::MalformedRequest("incorrect chunk size"))?;
+
+            if !Head::read_line(&mut stream, 2).await?.is_empty() {
+                return Err(Error::MalformedRequest("chunk size"));
+            }
+        }
+
+        // Trailers aren't used, so they are skipped.
+        while !Head::read_line(&mut stream, line_max_size)
+            .await?
+            .is_empty()
+        {}
+
+        Ok(Some(body))
+    }
+
     /// Get the request source IP address.
     ///
     /// This is the IP address of the TCP socket, and does
@@ -220,6 +274,17 @@ impl Request {
         &self.session
     }
 
+    /// Get a flash message set by the previous response with [`Response::flash`].
+    /// Flash messages are removed from the session after this request.
+    pub fn flash(&self, key: &str) -> Option<&str> {
+        self.session.flash.get(key).map(|message| message.as_str())
+    }
+
+    /// All flash messages set by the previous response.
+    pub fn flashes(&self) -> &HashMap<String, String> {
+        &self.session.flash
+    }
+
     /// Was the CSRF protection bypassed on this request?
     ///
     /// Used internally to skip CSRF middleware, but
@@ -228,6 +293,12 @@ impl Request {
         self.skip_csrf
     }
 
+    /// Request identifier used to correlate log lines, set by the
+    /// [`crate::controller::middleware::RequestId`] middleware.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
     /// Return the timestamp of when the request was received by the server.
     pub fn received_at(&self) -> OffsetDateTime {
         self.received_at
@@ -309,6 +380,12 @@ impl Request {
         self
     }
 
+    /// Set the request identifier.
+    pub fn set_request_id(mut self, request_id: impl ToString) -> Self {
+        self.request_id = Some(request_id.to_string());
+        self
+    }
+
     /// Did the client request a HTTP connection upgrade to WebSocket?
     pub fn upgrade_websocket(&self) -> bool {
         self.headers()
@@ -410,6 +487,15 @@ impl ToTemplateValue for Request {
             self.path().query().to_string().to_template_value()?,
         );
         hash.insert("session".to_string(), self.session().to_template_value()?);
+        hash.insert(
+            "flash".to_string(),
+            Value::Hash(
+                self.flashes()
+                    .iter()
+                    .map(|(key, message)| (key.clone(), Value::String(message.clone())))
+                    .collect(),
+            ),
+        );
         Ok(Value::Hash(hash))
     }
 }
@@ -455,6 +541,43 @@ pub mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_headers() {
+        let request = dummy_request().await.unwrap();
+
+        assert_eq!(
+            request.header("Content-Type").map(|h| h.as_str()),
+            Some("application/json")
+        );
+        assert_eq!(
+            request.header("content-type"),
+            request.header("Content-Type")
+        );
+        assert_eq!(
+            request.header("CONTENT-TYPE"),
+            request.header("Content-Type")
+        );
+        assert_eq!(request.content_length(), Some(18));
+        assert!(request.content_type().unwrap().is("application/json"));
+
+        let mut request = Request::default();
+        request
+            .head_mut()
+            .headers_mut()
+            .insert("Content-Type", "text/plain; charset=utf-8");
+        let content_type = request.content_type().unwrap();
+        assert_eq!(content_type.essence(), "text/plain");
+        assert_eq!(content_type.param("charset"), Some("utf-8"));
+        assert_eq!(request.content_length(), None);
+
+        let headers = crate::http::Headers::from(HashMap::from([(
+            "Content-Type".to_string(),
+            "application/json".to_string(),
+        )]));
+        assert_eq!(headers.get("content-type"), headers.get("Content-Type"));
+        assert!(headers.get("content-type").is_some());
+    }
+
     #[tokio::test]
     async fn test_basic_req() {
         let normal = "GET /apples?hello=world HTTP/1.1\r\n".to_owned()
@@ -504,6 +627,62 @@ pub mod test {
         assert!(err.starts_with("ContentTooLarge"));
     }
 
+    #[tokio::test]
+    async fn test_chunked() {
+        let req = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\nX-Trailer: 1\r\n\r\nGET /";
+        let mut stream = req.as_bytes();
+        let req = Request::read(dummy_ip(), &mut stream).await.unwrap();
+        assert_eq!(req.body(), b"hello, world");
+        // The next request is left in the stream.
+        assert_eq!(stream, b"GET /");
+
+        let head = Head::read("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        let body = "5\r\nhello\r\n5\r\nworld\r\nffffffff\r\n";
+        let err = Request::read_body(dummy_ip(), head.clone(), body.as_bytes(), 8)
+            .await
+            .expect_err("should err");
+        assert!(matches!(err, Error::ContentTooLarge(_)));
+
+        let req = Request::read_body(
+            dummy_ip(),
+            head.clone(),
+            "5\r\nhello\r\n0\r\n\r\n".as_bytes(),
+            5,
+        )
+        .await
+        .unwrap();
+        assert_eq!(req.body(), b"hello");
+
+        for body in ["zz\r\nhello\r\n0\r\n\r\n", "2\r\nhello\r\n0\r\n\r\n"] {
+            let err = Request::read_body(dummy_ip(), head.clone(), body.as_bytes(), 1024)
+                .await
+                .expect_err("should err");
+            assert!(matches!(err, Error::MalformedRequest(_)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accept() {
+        let req = "GET / HTTP/1.1\r\nAccept: application/json;q=0.9, text/html;q=0.5\r\n\r\n";
+        let req = Request::read(dummy_ip(), req.as_bytes()).await.unwrap();
+        assert!(req.accepts("text/html"));
+        assert!(!req.accepts("image/png"));
+        assert_eq!(
+            req.preferred(&["text/html", "application/json"]),
+            Some("application/json")
+        );
+
+        let req = "GET / HTTP/1.1\r\n\r\n";
+        let req = Request::read(dummy_ip(), req.as_bytes()).await.unwrap();
+        assert!(req.accepts("image/png"));
+        assert_eq!(
+            req.preferred(&["text/html", "application/json"]),
+            Some("text/html")
+        );
+    }
+
     #[tokio::test]
     async fn test_login_logout() {
         let req = "GET / HTTP/1.1\r\nContent-Length: 0\r\n\r\n";
@@ -516,4 +695,47 @@ pub mod test {
         assert!(response.session().is_some());
         assert!(response.session().as_ref().unwrap().guest());
     }
+
+    #[tokio::test]
+    async fn test_flash() {
+        async fn next_request(response: Response, request: &Request) -> Request {
+            let mut response = response.from_request(request).unwrap();
+            let cookie = response
+                .cookies()
+                .get("rwf_session")
+                .unwrap()
+                .value()
+                .to_string();
+            let req = format!(
+                "GET / HTTP/1.1\r\nCookie: rwf_session={}\r\nContent-Length: 0\r\n\r\n",
+                cookie
+            );
+            Request::read(dummy_ip(), req.as_bytes()).await.unwrap()
+        }
+
+        let req = "GET / HTTP/1.1\r\nContent-Length: 0\r\n\r\n";
+        let req = Request::read(dummy_ip(), req.as_bytes()).await.unwrap();
+        assert!(req.flash("notice").is_none());
+
+        let response = Response::new()
+            .flash("notice", "Account created")
+            .redirect("/");
+        let req = next_request(response, &req).await;
+        assert_eq!(req.flash("notice"), Some("Account created"));
+
+        use crate::view::Value;
+        match req.to_template_value().unwrap() {
+            Value::Hash(hash) => match &hash["flash"] {
+                Value::Hash(flash) => {
+                    assert_eq!(flash["notice"], Value::String("Account created".into()))
+                }
+                _ => panic!("flash should be a hash"),
+            },
+            _ => panic!("request should be a hash"),
+        }
+
+        let req = next_request(Response::new(), &req).await;
+        assert!(req.flash("notice").is_none());
+        assert!(req.flashes().is_empty());
+    }
 }
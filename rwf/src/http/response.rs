@@ -17,8 +17,9 @@ use std::collections::HashMap;
 use std::marker::Unpin;
 use time::OffsetDateTime;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tracing::error;
 
-use super::{head::Version, Body, Cookie, Cookies, Error, Headers, Request};
+use super::{head::Version, Body, Cookie, Cookies, Error, Headers, Method, Request};
 use crate::view::{Template, TurboStream};
 use crate::{config::get_config, controller::Session};
 
@@ -190,6 +191,7 @@ pub struct Response {
     body: Body,
     cookies: Cookies,
     session: Option<Session>,
+    flash: HashMap<String, String>,
 }
 
 impl Default for Response {
@@ -221,23 +223,33 @@ impl Response {
             version: Version::Http1,
             cookies: Cookies::new(),
             session: None,
+            flash: HashMap::new(),
         }
     }
 
     /// Create a response from a request. *This is used internally automatically.*
     ///
-    /// This makes sure a valid session cookie is set on all responses.
+    /// This makes sure a valid session cookie is set on all responses. Flash messages
+    /// received with the request are removed from the session, and replaced with the ones set on this response.
     pub fn from_request(mut self, request: &Request) -> Result<Self, Error> {
+        let flash = std::mem::take(&mut self.flash);
+
         // Session set manually on the request already.
-        if let Some(ref session) = self.session {
+        if let Some(ref mut session) = self.session {
+            session.flash = flash;
             self.cookies.add_session(&session)?;
         } else {
             let session = request.session();
 
-            if session.should_renew() || request.renew_session() {
-                let session = session
+            if session.should_renew()
+                || request.renew_session()
+                || !session.flash.is_empty()
+                || !flash.is_empty()
+            {
+                let mut session = session
                     .clone()
                     .renew(get_config().general.session_duration());
+                session.flash = flash;
                 self.cookies.add_session(&session)?;
 
                 // Set the session on the response, so it can be
@@ -263,6 +275,13 @@ impl Response {
         self
     }
 
+    /// Remove the body from the response, keeping all headers, including `Content-Length`.
+    /// Used to reply to `HEAD` requests.
+    pub fn without_body(mut self) -> Self {
+        self.body = Body::bytes(vec![]);
+        self
+    }
+
     /// Get response status, e.g. 200 OK.
     pub fn status(&self) -> Status {
         self.code.into()
@@ -332,6 +351,24 @@ impl Response {
         self.body(Body::Text(body.to_string()))
     }
 
+    /// Create a response which the browser saves as a file instead of displaying it.
+    ///
+    /// The file name is sent in the `Content-Disposition` header. Names with quotes
+    /// or non-ASCII characters are escaped, and also sent UTF-8 encoded in `filename*` (RFC 5987).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rwf::http::Response;
+    ///
+    /// let response = Response::new().download("users.csv", "text/csv", b"id,email\n".to_vec());
+    /// ```
+    pub fn download(self, filename: &str, content_type: &str, bytes: Vec<u8>) -> Self {
+        self.body(Body::bytes(bytes))
+            .header("content-type", content_type)
+            .header("content-disposition", content_disposition(filename))
+    }
+
     /// Add a header to the response.
     ///
     /// Header name is lowercased automatically. The value is set as-is.
@@ -419,6 +456,20 @@ impl Response {
         self
     }
 
+    /// Set a flash message, which will be available on the next request only,
+    /// e.g. to show a notice after a redirect.
+    ///
+    /// ```
+    /// # use rwf::http::Response;
+    /// let response = Response::new()
+    ///     .flash("notice", "Account created")
+    ///     .redirect("/");
+    /// ```
+    pub fn flash(mut self, key: impl ToString, message: impl ToString) -> Self {
+        self.flash.insert(key.to_string(), message.to_string());
+        self
+    }
+
     /// Get the response session, if any is set.
     ///
     /// All requests should have a session unless the browser
@@ -454,6 +505,17 @@ impl Response {
         Self::error_pretty("405 - Method Not Allowed", "").code(405)
     }
 
+    /// Set the `Allow` header to the HTTP methods accepted by the resource.
+    /// Used with `405 - Method Not Allowed` responses.
+    pub fn allow(self, methods: &[Method]) -> Self {
+        let methods = methods
+            .iter()
+            .map(|method| method.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.header("allow", methods)
+    }
+
     /// Create a `400 - Bad Request` response.
     pub fn bad_request() -> Self {
         Self::error_pretty("400 - Bad Request", "").code(400)
@@ -468,6 +530,33 @@ impl Response {
         .code(400)
     }
 
+    /// Create `422 - Unprocessable Content` response, with the errors serialized as JSON,
+    /// e.g. [`ValidationErrors`](crate::model::ValidationErrors) returned by a failed validation.
+    ///
+    /// If the errors can't be serialized, the error is logged and `500 - Internal Server Error` is returned instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rwf::http::Response;
+    /// use rwf::model::ValidationErrors;
+    ///
+    /// let mut errors = ValidationErrors::new();
+    /// errors.add("email", "is not an email");
+    ///
+    /// let response = Response::unprocessable(&errors);
+    /// assert_eq!(response.status().code(), 422);
+    /// ```
+    pub fn unprocessable(errors: impl Serialize) -> Self {
+        match Self::new().json(errors) {
+            Ok(response) => response.code(422),
+            Err(err) => {
+                error!("failed to serialize errors: {}", err);
+                Self::internal_error(err)
+            }
+        }
+    }
+
     /// Create `501 - Not Implemented` response.
     pub fn not_implemented() -> Self {
         Self::error_pretty("501 - Not Implemented", "").code(501)
@@ -478,6 +567,12 @@ impl Response {
         Self::error_pretty("403 - Forbidden", "").code(403)
     }
 
+    /// Create `406 - Not Acceptable` response, e.g. when the client doesn't accept
+    /// any of the formats the server can respond with.
+    pub fn not_acceptable() -> Self {
+        Self::error_pretty("406 - Not Acceptable", "").code(406)
+    }
+
     /// Create `413 - Content Too Large` response.
     pub fn content_too_large() -> Self {
         Self::error_pretty("413 - Content Too Large", "").code(413)
@@ -527,9 +622,36 @@ impl Response {
 
     /// Create `302 - Found` response, also known as a redirect.
     pub fn redirect(self, to: impl ToString) -> Self {
+        self.redirect_with(to, 302)
+    }
+
+    /// Create `301 - Moved Permanently` response. Browsers remember it and
+    /// go to the new URL directly next time.
+    pub fn redirect_permanent(self, to: impl ToString) -> Self {
+        self.redirect_with(to, 301)
+    }
+
+    /// Create `302 - Found` response. Same as [`Response::redirect`].
+    pub fn redirect_found(self, to: impl ToString) -> Self {
+        self.redirect_with(to, 302)
+    }
+
+    /// Create `303 - See Other` response. The browser follows it with a `GET` request,
+    /// so this is the redirect to use after a form is submitted with `POST`.
+    pub fn redirect_see_other(self, to: impl ToString) -> Self {
+        self.redirect_with(to, 303)
+    }
+
+    /// Create `307 - Temporary Redirect` response. Unlike `302`, the browser must repeat
+    /// the request with the same method and body.
+    pub fn redirect_temporary(self, to: impl ToString) -> Self {
+        self.redirect_with(to, 307)
+    }
+
+    fn redirect_with(self, to: impl ToString, code: u16) -> Self {
         self.html("")
             .header("location", to)
-            .code(302)
+            .code(code)
             .header("content-length", 0)
             .header("cache-control", "no-cache")
     }
@@ -557,6 +679,47 @@ impl Response {
     }
 }
 
+/// `Content-Disposition` header value for a file download.
+fn content_disposition(filename: &str) -> String {
+    let fallback = filename
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => format!("\\{}", c),
+            c if c.is_ascii() && !c.is_ascii_control() => c.to_string(),
+            _ => "_".to_string(),
+        })
+        .collect::<String>();
+
+    let mut header = format!("attachment; filename=\"{}\"", fallback);
+
+    if fallback != filename {
+        let mut encoded = String::new();
+        for byte in filename.bytes() {
+            match byte {
+                b'a'..=b'z'
+                | b'A'..=b'Z'
+                | b'0'..=b'9'
+                | b'!'
+                | b'#'
+                | b'$'
+                | b'&'
+                | b'+'
+                | b'-'
+                | b'.'
+                | b'^'
+                | b'_'
+                | b'`'
+                | b'|'
+                | b'~' => encoded.push(byte as char),
+                byte => encoded.push_str(&format!("%{:02X}", byte)),
+            }
+        }
+        header.push_str(&format!("; filename*=UTF-8''{}", encoded));
+    }
+
+    header
+}
+
 impl From<serde_json::Value> for Response {
     fn from(value: serde_json::Value) -> Response {
         Response::new().json(value).unwrap()
@@ -580,3 +743,98 @@ impl From<Vec<TurboStream>> for Response {
         Response::new().turbo_stream(&value)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_json() {
+        #[derive(Serialize)]
+        struct Order {
+            id: i64,
+            items: Vec<&'static str>,
+        }
+
+        let response = Response::new()
+            .json(Order {
+                id: 5,
+                items: vec!["book"],
+            })
+            .unwrap();
+        assert_eq!(response.status().code(), 200);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+
+        let mut sent = vec![];
+        response.send(&mut sent).await.unwrap();
+        let sent = String::from_utf8(sent).unwrap();
+        assert!(sent.ends_with(r#"{"id":5,"items":["book"]}"#), "{}", sent);
+
+        let mut errors = crate::model::ValidationErrors::new();
+        errors.add("email", "is not an email");
+        let response = Response::unprocessable(&errors);
+        assert_eq!(response.status().code(), 422);
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "application/json"
+        );
+
+        let mut sent = vec![];
+        response.send(&mut sent).await.unwrap();
+        let sent = String::from_utf8(sent).unwrap();
+        assert!(
+            sent.ends_with(r#"{"email":["is not an email"]}"#),
+            "{}",
+            sent
+        );
+
+        // Maps with non-string keys can't be serialized to JSON.
+        let response = Response::unprocessable(HashMap::from([((1, 2), "error")]));
+        assert_eq!(response.status().code(), 500);
+    }
+
+    #[test]
+    fn test_download() {
+        let response = Response::new().download("report.csv", "text/csv", b"a,b\n".to_vec());
+        let headers = response.headers();
+        assert_eq!(
+            headers.get("content-disposition").unwrap(),
+            "attachment; filename=\"report.csv\""
+        );
+        assert_eq!(headers.get("content-type").unwrap(), "text/csv");
+        assert_eq!(headers.get("content-length").unwrap(), "4");
+
+        let response = Response::new().download("résumé \"final\".pdf", "application/pdf", vec![]);
+        assert_eq!(
+            response.headers().get("content-disposition").unwrap(),
+            "attachment; filename=\"r_sum_ \\\"final\\\".pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9%20%22final%22.pdf"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirects() {
+        for (response, code) in [
+            (Response::new().redirect("/signup"), 302),
+            (Response::new().redirect_permanent("/signup"), 301),
+            (Response::new().redirect_found("/signup"), 302),
+            (Response::new().redirect_see_other("/signup"), 303),
+            (Response::new().redirect_temporary("/signup"), 307),
+        ] {
+            assert_eq!(response.status().code(), code);
+            assert_eq!(
+                response.headers().get("location").map(|l| l.as_str()),
+                Some("/signup")
+            );
+
+            let mut sent = vec![];
+            response.send(&mut sent).await.unwrap();
+            let sent = String::from_utf8(sent).unwrap();
+            assert!(sent.starts_with(&format!("HTTP/1.1 {}", code)), "{}", sent);
+            assert!(sent.contains("content-length: 0\r\n"), "{}", sent);
+            assert!(sent.ends_with("\r\n\r\n"), "{}", sent);
+        }
+    }
+}
@@ -14,6 +14,28 @@
 //! If multiple controllers match a path, the last one added to the router is returned. This is ensured by the stable
 //! sorting property used by the router.
 //!
+//! Handlers can be restricted to specific HTTP methods with [`Handler::with_methods`]. If the best matching
+//! handlers don't accept the request method, [`Router::find_method`] returns [`Match::MethodNotAllowed`] with the list of
+//! methods that are accepted for that path, which the server sends back in the `Allow` header of a `405` response.
+//!
+//! `HEAD` requests are served automatically by handlers that accept `GET`: the request is passed to the controller
+//! as `GET` and the body is removed from the response, keeping all headers, including `Content-Length`. This can be
+//! disabled per route with [`Handler::without_head`].
+//!
+//! ### Trailing slash
+//!
+//! By default, paths are matched as registered: `/users` matches `/users` and `/users/`, but `/users/` only
+//! matches `/users/`. [`Router::trailing_slash`] changes this for the whole router: [`TrailingSlash::Merge`] treats
+//! both forms as the same path, and [`TrailingSlash::Redirect`] sends a `301` from `/users/` to `/users`.
+//! The root path `/` is never changed.
+//!
+//! ### Route groups
+//!
+//! Routers can be nested with [`Router::mount`], which moves all routes of another router under a path prefix.
+//! [`Router::group`] does the same and additionally attaches middleware to every route in the group. Group middleware
+//! runs before the controller's own middleware, outer groups first, and can stop the request before it reaches the
+//! controller.
+//!
 //! ### Safety note
 //!
 //!
@@ -23,17 +45,44 @@
 //! Currently, Rwf makes no effort to protect against poorly constructed regexes by the user. This will change
 //! in the future.
 //!
-use super::{Error, Handler, Path};
-use crate::{colors::MaybeColorize, http::path::PathType};
+use super::{Error, Handler, Method, Path};
+use crate::{colors::MaybeColorize, controller::MiddlewareSet, http::path::PathType};
 
 use regex::RegexSet;
 use tracing::info;
 
+/// Result of matching a request against the router.
+pub enum Match<'a> {
+    /// Handler accepting the request path and method.
+    Handler(&'a Handler),
+    /// The path matched, but none of the handlers accept the method.
+    /// Contains the methods accepted for this path.
+    MethodNotAllowed(Vec<Method>),
+    /// The path has a trailing slash and should be requested without it.
+    /// Contains the path to redirect to, including the query.
+    Redirect(Path),
+    /// No handler matches the path.
+    NotFound,
+}
+
+/// How the router handles a trailing slash in the request path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingSlash {
+    /// Match paths as registered.
+    #[default]
+    Strict,
+    /// Treat `/users/` and `/users` as the same path.
+    Merge,
+    /// Redirect `/users/` to `/users` with `301 - Moved Permanently`.
+    Redirect,
+}
+
 /// The HTTP request router.
 #[derive(Default)]
 pub struct Router {
     regex: RegexSet,
     handlers: Vec<Handler>,
+    trailing_slash: TrailingSlash,
 }
 
 impl Router {
@@ -45,13 +94,131 @@ impl Router {
             .collect::<Vec<_>>();
         let regex = RegexSet::new(paths)?;
 
-        Ok(Self { regex, handlers })
+        Ok(Self {
+            regex,
+            handlers,
+            trailing_slash: TrailingSlash::default(),
+        })
+    }
+
+    /// Set how the router handles a trailing slash in the request path.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let router = Router::new(vec![route!("/users" => Users)])?
+    ///     .trailing_slash(TrailingSlash::Redirect)?;
+    /// ```
+    pub fn trailing_slash(self, trailing_slash: TrailingSlash) -> Result<Self, Error> {
+        let handlers = match trailing_slash {
+            TrailingSlash::Strict => self.handlers,
+            TrailingSlash::Merge | TrailingSlash::Redirect => self
+                .handlers
+                .into_iter()
+                .map(|handler| handler.without_trailing_slash())
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        let mut router = Self::new(handlers)?;
+        router.trailing_slash = trailing_slash;
+
+        Ok(router)
+    }
+
+    /// Mount all routes of another router under the path prefix.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let api = Router::new(vec![route!("/users" => Users)])?;
+    /// let router = Router::new(vec![route!("/" => Index)])?.mount("/api", api)?;
+    /// ```
+    pub fn mount(self, prefix: &str, router: Router) -> Result<Self, Error> {
+        let trailing_slash = self.trailing_slash;
+        let mut handlers = self.handlers;
+
+        for handler in router.handlers {
+            handlers.push(handler.prefix(prefix)?);
+        }
+
+        Self::new(handlers)?.trailing_slash(trailing_slash)
+    }
+
+    /// Mount the routes under the path prefix and run the middleware on all of them,
+    /// before the controllers' own middleware.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let router = Router::new(vec![route!("/" => Index)])?.group(
+    ///     "/admin",
+    ///     MiddlewareSet::without_default(vec![RequireAdmin.middleware()]),
+    ///     vec![route!("/dashboard" => Dashboard)],
+    /// )?;
+    /// ```
+    pub fn group(
+        self,
+        prefix: &str,
+        middleware: MiddlewareSet,
+        routes: Vec<Handler>,
+    ) -> Result<Self, Error> {
+        let routes = routes
+            .into_iter()
+            .map(|handler| handler.with_middleware(middleware.clone()))
+            .collect();
+
+        self.mount(prefix, Router::new(routes)?)
     }
 
     /// Find the best handler for the request path.
     ///
     /// See [`crate::http::router`] documentation for route matching algorithm description.
     pub fn find(&self, path: &Path) -> Option<&Handler> {
+        self.matches(path).last().copied()
+    }
+
+    /// Find the best handler for the request path that accepts the HTTP method.
+    ///
+    /// If the path matches but none of the handlers accept the method, the methods
+    /// accepted for that path are returned instead.
+    ///
+    /// With [`TrailingSlash::Redirect`], a path with a trailing slash is redirected
+    /// to the same path without it, if that path has a handler.
+    pub fn find_method(&self, method: &Method, path: &Path) -> Match<'_> {
+        if self.trailing_slash == TrailingSlash::Redirect {
+            let base = path.base();
+
+            if base != "/" && base.ends_with('/') {
+                let redirect = Path::from_parts(base.trim_end_matches('/'), path.query());
+
+                if !self.matches(&redirect).is_empty() {
+                    return Match::Redirect(redirect);
+                }
+            }
+        }
+
+        let handlers = self.matches(path);
+
+        if let Some(handler) = handlers.iter().rev().find(|h| h.allows(method)) {
+            return Match::Handler(handler);
+        }
+
+        if handlers.is_empty() {
+            return Match::NotFound;
+        }
+
+        let mut allow = vec![];
+        for method in handlers.iter().flat_map(|h| h.allowed_methods()) {
+            if !allow.contains(&method) {
+                allow.push(method);
+            }
+        }
+
+        Match::MethodNotAllowed(allow)
+    }
+
+    /// All handlers matching the path, from least to most specific.
+    fn matches(&self, path: &Path) -> Vec<&Handler> {
         let matches = self.regex.matches(path.base());
         let mut handlers = self
             .handlers
@@ -71,8 +238,8 @@ impl Router {
             } else {
                 a_rank.cmp(&b_rank)
             }
-        }); // Get the most specific path (longest match).
-        handlers.last().copied()
+        }); // The most specific path (longest match) is last.
+        handlers
     }
 
     /// Pretty print all registered routes.
@@ -111,7 +278,7 @@ impl Router {
 mod test {
     use super::*;
     use crate::async_trait;
-    use crate::controller::{Controller, Error as ControllerError};
+    use crate::controller::{Controller, Error as ControllerError, Middleware, Outcome};
     use crate::http::{Request, Response};
 
     struct OrdersControler {}
@@ -145,4 +312,162 @@ mod test {
         let result = handler.handle(&Request::default()).await.unwrap();
         assert_eq!(result.status().code(), 200);
     }
+
+    struct RequireAdmin;
+
+    #[async_trait]
+    impl Middleware for RequireAdmin {
+        async fn handle_request(&self, request: Request) -> Result<Outcome, ControllerError> {
+            if request.header("x-admin").is_some() {
+                Ok(Outcome::Forward(request))
+            } else {
+                Ok(Outcome::Stop(request, Response::forbidden()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_group() {
+        let router = Router::new(vec![UsersController {}.route("/api/users")])
+            .unwrap()
+            .group(
+                "/admin",
+                MiddlewareSet::without_default(vec![RequireAdmin {}.middleware()]),
+                vec![
+                    OrdersControler {}.route("/"),
+                    OrdersControler {}.route("/orders"),
+                ],
+            )
+            .unwrap()
+            .mount(
+                "/v2",
+                Router::new(vec![UsersController {}.route("/users")]).unwrap(),
+            )
+            .unwrap();
+
+        for path in ["/admin", "/admin/orders"] {
+            let handler = router.find(&Path::parse(path).unwrap()).expect("to match");
+            assert_eq!(handler.path().path(), path);
+
+            let response = handler.handle_internal(Request::default()).await.unwrap();
+            assert_eq!(response.status().code(), 403);
+        }
+
+        let handler = router
+            .find(&Path::parse("/api/users").unwrap())
+            .expect("to match");
+        let response = handler.handle_internal(Request::default()).await.unwrap();
+        assert_eq!(response.status().code(), 200);
+
+        let handler = router
+            .find(&Path::parse("/v2/users").unwrap())
+            .expect("to match");
+        let response = handler.handle_internal(Request::default()).await.unwrap();
+        assert_eq!(response.status().code(), 200);
+
+        assert!(router.find(&Path::parse("/orders").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_find_method() {
+        let router = Router::new(vec![
+            UsersController {}
+                .route("/users")
+                .with_methods(&[Method::Get, Method::Head]),
+            OrdersControler {}
+                .route("/users")
+                .with_methods(&[Method::Put, Method::Get]),
+            OrdersControler {}.route("/orders"),
+        ])
+        .unwrap();
+        let users = Path::parse("/users").unwrap();
+
+        match router.find_method(&Method::Put, &users) {
+            Match::Handler(handler) => assert_eq!(handler.methods()[0], Method::Put),
+            _ => panic!("expected handler"),
+        }
+
+        match router.find_method(&Method::Post, &users) {
+            Match::MethodNotAllowed(allow) => {
+                assert_eq!(allow, vec![Method::Get, Method::Head, Method::Put])
+            }
+            _ => panic!("expected 405"),
+        }
+
+        let orders = Path::parse("/orders").unwrap();
+        assert!(matches!(
+            router.find_method(&Method::Delete, &orders),
+            Match::Handler(_)
+        ));
+
+        let missing = Path::parse("/missing").unwrap();
+        assert!(matches!(
+            router.find_method(&Method::Get, &missing),
+            Match::NotFound
+        ));
+    }
+
+    #[test]
+    fn test_trailing_slash_merge() {
+        let router = Router::new(vec![
+            UsersController {}.route("/users/"),
+            Handler::rest("/orders", OrdersControler {}),
+            OrdersControler {}.route("/"),
+        ])
+        .unwrap()
+        .trailing_slash(TrailingSlash::Merge)
+        .unwrap();
+
+        for path in ["/users", "/users/", "/orders/5", "/orders/5/", "/"] {
+            let path = Path::parse(path).unwrap();
+            assert!(
+                matches!(router.find_method(&Method::Get, &path), Match::Handler(_)),
+                "{}",
+                path
+            );
+        }
+
+        let handler = router.find(&Path::parse("/").unwrap()).unwrap();
+        assert_eq!(handler.path().path(), "/");
+    }
+
+    #[test]
+    fn test_trailing_slash_redirect() {
+        let router = Router::new(vec![
+            UsersController {}.route("/users"),
+            OrdersControler {}.route("/"),
+        ])
+        .unwrap()
+        .trailing_slash(TrailingSlash::Redirect)
+        .unwrap()
+        .mount(
+            "/api",
+            Router::new(vec![OrdersControler {}.route("/orders/")]).unwrap(),
+        )
+        .unwrap();
+
+        match router.find_method(&Method::Get, &Path::parse("/users/?page=2").unwrap()) {
+            Match::Redirect(path) => assert_eq!(path.to_string(), "/users?page=2"),
+            _ => panic!("expected redirect"),
+        }
+
+        match router.find_method(&Method::Get, &Path::parse("/api/orders/").unwrap()) {
+            Match::Redirect(path) => assert_eq!(path.to_string(), "/api/orders"),
+            _ => panic!("expected redirect"),
+        }
+
+        for path in ["/users", "/api/orders", "/"] {
+            let path = Path::parse(path).unwrap();
+            assert!(
+                matches!(router.find_method(&Method::Get, &path), Match::Handler(_)),
+                "{}",
+                path
+            );
+        }
+
+        assert!(matches!(
+            router.find_method(&Method::Get, &Path::parse("/missing/").unwrap()),
+            Match::NotFound
+        ));
+    }
 }
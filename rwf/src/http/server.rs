@@ -4,7 +4,7 @@
 //! If no handler is matched, return `404 - Not Found`.
 //!
 //! The server is using Tokio and can support millions of concurrent clients.
-use super::{Error, Handler, Request, Response, Router};
+use super::{Error, Handler, Head, Match, Request, Response, Router};
 
 use crate::colors::MaybeColorize;
 use crate::config::get_config;
@@ -52,6 +52,14 @@ impl Server {
         }
     }
 
+    /// Create new HTTP server from a router, e.g. one with
+    /// mounted routers or route groups.
+    pub fn from_router(router: Router) -> Self {
+        Server {
+            handlers: Arc::new(router),
+        }
+    }
+
     /// Launch the server. This blocks until the server is shut down (`SIGINT`/Ctrl-C).
     pub async fn launch(self) -> Result<(), Error> {
         let config = get_config();
@@ -105,38 +113,34 @@ impl Server {
             debug!("{} new connection from {:?}", "http".purple(), peer_addr);
 
             loop {
-                let request = match Request::read(peer_addr, &mut stream).await {
-                    Ok(request) => request,
-                    Err(ref err) => {
-                        match err {
-                            Error::ContentTooLarge(head) => {
-                                let response = Response::content_too_large();
-                                let _ = Self::send_response(&mut stream, response).await;
-
-                                info!(
-                                    "{} {} {} 413",
-                                    head.method().to_string().purple(),
-                                    head.path().base().purple(),
-                                    std::any::type_name::<Self>().green(),
-                                );
-                            }
-
-                            _ => (),
-                        }
-                        debug!(
-                            "{} client {:?} disconnected: {}",
-                            "http".purple(),
-                            peer_addr,
-                            err
-                        );
-                        return;
-                    }
+                let head = match Head::read(&mut stream).await {
+                    Ok(head) => head,
+                    Err(err) => return Self::read_error(&mut stream, peer_addr, err).await,
                 };
 
                 let start = Instant::now();
+                let found = handlers.find_method(head.method(), head.path());
+                let routing = start.elapsed();
+
+                // The route decides how large the body can be.
+                let max_body_size = match found {
+                    Match::Handler(handler) => handler.max_body_size(),
+                    _ => None,
+                }
+                .unwrap_or(get_config().general.max_request_size);
+
+                let request =
+                    match Request::read_body(peer_addr, head, &mut stream, max_body_size).await {
+                        Ok(request) => request,
+                        Err(err) => return Self::read_error(&mut stream, peer_addr, err).await,
+                    };
+
+                // We include the time to find the handler in the duration,
+                // but not the time to receive the body.
+                let start = Instant::now() - routing;
 
-                match handlers.find(request.path()) {
-                    Some(handler) => {
+                match found {
+                    Match::Handler(handler) => {
                         // Set the matching regex to extract parameters.
                         let request = request.with_params(handler.path_with_regex().params());
 
@@ -180,12 +184,20 @@ impl Server {
                         }
                     }
 
-                    None => {
+                    no_match => {
                         // Log duration of search.
                         let duration = Instant::now() - start;
 
-                        // Generate default not found response.
-                        let response = Response::not_found();
+                        // Generate default not found, method not allowed or redirect response.
+                        let response = match no_match {
+                            Match::MethodNotAllowed(allow) => {
+                                Response::method_not_allowed().allow(&allow)
+                            }
+                            Match::Redirect(path) => {
+                                Response::new().redirect_permanent(path.to_string())
+                            }
+                            _ => Response::not_found(),
+                        };
 
                         // Log the response.
                         Self::log(&request, std::any::type_name::<Self>(), &response, duration);
@@ -201,20 +213,73 @@ impl Server {
         })
     }
 
+    /// Reply to a request which couldn't be read, if possible. The connection is closed afterwards,
+    /// since the rest of the request may still be in the stream.
+    async fn read_error(mut stream: impl AsyncWrite + Unpin, peer_addr: SocketAddr, err: Error) {
+        match err {
+            Error::ContentTooLarge(ref head) => {
+                let response = Response::content_too_large().header("connection", "close");
+                let _ = Self::send_response(&mut stream, response).await;
+
+                info!(
+                    "{} {} {} 413",
+                    head.method().to_string().purple(),
+                    head.path().base().purple(),
+                    std::any::type_name::<Self>().green(),
+                );
+            }
+
+            Error::MalformedRequest(_) => {
+                let _ = Self::send_response(&mut stream, Response::bad_request()).await;
+            }
+
+            _ => (),
+        }
+
+        debug!(
+            "{} client {:?} disconnected: {}",
+            "http".purple(),
+            peer_addr,
+            err
+        );
+    }
+
     fn log(request: &Request, controller_name: &str, response: &Response, duration: Duration) {
+        let json = get_config().general.log_json();
+        Self::log_event(request, controller_name, response, duration, json);
+    }
+
+    fn log_event(
+        request: &Request,
+        controller_name: &str,
+        response: &Response,
+        duration: Duration,
+        json: bool,
+    ) {
         let method = request.method().to_string();
         let path = request.path().path();
         let code = response.status().code() as i32;
         let duration = (duration.as_secs_f64() * 1000.0) as f32;
 
-        info!(
-            "{} {} {} {} ({:.3} ms)",
-            method.purple(),
-            path.purple(),
-            controller_name.green(),
-            code,
-            duration,
-        );
+        if json {
+            info!(
+                method,
+                path,
+                controller = controller_name,
+                status = code,
+                duration_ms = duration,
+                request_id = request.request_id(),
+            );
+        } else {
+            info!(
+                "{} {} {} {} ({:.3} ms)",
+                method.purple(),
+                path.purple(),
+                controller_name.green(),
+                code,
+                duration,
+            );
+        }
     }
 
     async fn send_response(
@@ -227,3 +292,193 @@ impl Server {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::controller::{Controller, Error as ControllerError};
+    use crate::http::{Method, TrailingSlash};
+    use tokio::io::AsyncReadExt;
+
+    async fn send(request: &[u8]) -> String {
+        send_to(Router::new(vec![]).unwrap(), request).await
+    }
+
+    async fn send_to(router: Router, request: &[u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Arc::new(router);
+
+        let server = tokio::spawn(async move {
+            let (stream, peer_addr) = listener.accept().await.unwrap();
+            Server::handle_connection(router, stream, peer_addr)
+                .await
+                .unwrap();
+        });
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client.write_all(request).await.unwrap();
+        client.shutdown().await.unwrap();
+
+        let mut response = vec![];
+        client.read_to_end(&mut response).await.unwrap();
+        server.await.unwrap();
+
+        String::from_utf8_lossy(&response).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_malformed_request_line() {
+        let response = send(b"GET / HTTP/1.1 extra\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn test_pipelining() {
+        let response = send(
+            b"GET /a HTTP/1.1\r\nConnection: keep-alive\r\n\r\nGET /b HTTP/1.1\nConnection: close\n\n",
+        )
+        .await;
+        assert_eq!(response.matches("HTTP/1.1 404").count(), 2, "{}", response);
+    }
+
+    struct Index;
+
+    #[crate::async_trait]
+    impl Controller for Index {
+        async fn handle(&self, request: &Request) -> Result<Response, ControllerError> {
+            match request.method() {
+                Method::Get => Ok(Response::new().text("index")),
+                _ => Ok(Response::method_not_allowed()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_trailing_slash_redirect() {
+        let router = Router::new(vec![Index.route("/users")])
+            .unwrap()
+            .trailing_slash(TrailingSlash::Redirect)
+            .unwrap();
+
+        let response = send_to(router, b"GET /users/ HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 301"), "{}", response);
+        assert!(response.contains("location: /users\r\n"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn test_method_not_allowed() {
+        let router = Router::new(vec![Index.route("/").with_methods(&[Method::Get])]).unwrap();
+
+        let response = send_to(router, b"POST / HTTP/1.1\r\nContent-Length: 0\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 405"), "{}", response);
+        assert!(response.contains("allow: GET, HEAD\r\n"), "{}", response);
+    }
+
+    #[tokio::test]
+    async fn test_head() {
+        for handler in [
+            Index.route("/"),
+            Index.route("/").with_methods(&[Method::Get]),
+        ] {
+            let router = Router::new(vec![handler]).unwrap();
+
+            let response = send_to(router, b"HEAD / HTTP/1.1\r\n\r\n").await;
+            assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+            assert!(response.contains("content-length: 5\r\n"), "{}", response);
+            assert!(response.ends_with("\r\n\r\n"), "{}", response);
+        }
+
+        let router = Router::new(vec![Index
+            .route("/")
+            .with_methods(&[Method::Get])
+            .without_head()])
+        .unwrap();
+
+        let response = send_to(router, b"HEAD / HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 405"), "{}", response);
+        assert!(response.contains("allow: GET\r\n"), "{}", response);
+    }
+
+    struct Upload;
+
+    #[crate::async_trait]
+    impl Controller for Upload {
+        fn skip_csrf(&self) -> bool {
+            true
+        }
+
+        async fn handle(&self, request: &Request) -> Result<Response, ControllerError> {
+            Ok(Response::new().text(format!("{} bytes", request.body().len())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_content_too_large() {
+        // Rejected before the body is received.
+        let too_large = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            get_config().general.max_request_size + 1
+        );
+        let response = send_to(
+            Router::new(vec![Upload.route("/")]).unwrap(),
+            too_large.as_bytes(),
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+        assert!(response.contains("connection: close\r\n"), "{}", response);
+
+        let router = || Router::new(vec![Upload.route("/").with_max_body_size(10)]).unwrap();
+
+        let response = send_to(
+            router(),
+            b"POST / HTTP/1.1\r\nContent-Length: 10\r\nConnection: close\r\n\r\n0123456789",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("10 bytes"), "{}", response);
+
+        let response = send_to(router(), b"POST / HTTP/1.1\r\nContent-Length: 11\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+
+        // Chunked bodies are rejected as soon as they exceed the limit.
+        let response = send_to(
+            router(),
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\n01234\r\n6\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+
+        let response = send_to(
+            router(),
+            b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n5\r\n01234\r\n5\r\n56789\r\n0\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("10 bytes"), "{}", response);
+    }
+
+    #[test]
+    fn test_log_json() {
+        let request = Request::default().set_request_id("abc-123");
+        let lines = crate::logging::capture_json(|| {
+            Server::log_event(
+                &request,
+                "UsersController",
+                &Response::not_found(),
+                Duration::from_millis(5),
+                true,
+            )
+        });
+
+        assert_eq!(lines.len(), 1);
+        let line = &lines[0];
+        assert_eq!(line["level"], "INFO");
+        assert_eq!(line["method"], "GET");
+        assert_eq!(line["path"], "/");
+        assert_eq!(line["controller"], "UsersController");
+        assert_eq!(line["status"], 404);
+        assert_eq!(line["request_id"], "abc-123");
+        assert!(line["duration_ms"].as_f64().unwrap() >= 5.0);
+    }
+}
@@ -85,6 +85,11 @@ impl DataFrame {
         self.header.is_ping()
     }
 
+    /// The client is closing the connection.
+    pub fn is_close(&self) -> bool {
+        self.header.op_code == OpCode::Close
+    }
+
     /// Create new pong message.
     pub fn new_pong(ping: DataFrame) -> Self {
         let meta = Meta {
@@ -115,6 +120,18 @@ impl DataFrame {
         }
     }
 
+    /// Create new close message, sent in response to the client closing the connection.
+    pub fn new_close() -> Self {
+        Self {
+            header: Header {
+                fin: true,
+                op_code: OpCode::Close,
+            },
+            meta: Meta { len: 0, mask: None },
+            message: None,
+        }
+    }
+
     /// Get the message from the frame.
     pub fn message(self) -> Message {
         self.message.unwrap()
@@ -126,6 +143,7 @@ enum OpCode {
     Continuation,
     Text,
     Binary,
+    Close,
     Ping,
     Pong,
 }
@@ -147,6 +165,7 @@ impl Header {
             0 => OpCode::Continuation,
             0x1 => OpCode::Text,
             0x2 => OpCode::Binary,
+            0x8 => OpCode::Close,
             0x9 => OpCode::Ping,
             0xA => OpCode::Pong,
             _ => return Err(Error::MalformedRequest("websocket control code")),
@@ -160,6 +179,7 @@ impl Header {
             OpCode::Continuation => 0,
             OpCode::Text => 0x1,
             OpCode::Binary => 0x2,
+            OpCode::Close => 0x8,
             OpCode::Ping => 0x9,
             OpCode::Pong => 0xA,
         };
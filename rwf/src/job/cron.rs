@@ -1,26 +1,36 @@
 //! Implements the UNIX cron syntax.
 use super::Error;
-use std::ops::Range;
+use std::ops::RangeInclusive;
 use time::OffsetDateTime;
 
 #[derive(Clone, Debug)]
 enum CronValue {
     Exact(i64),
     Every(i64),
-    Range(Range<i64>),
+    Range(RangeInclusive<i64>),
+    List(Vec<CronValue>),
     Any,
 }
 
 impl CronValue {
     /// Parse a cron syntax.
     fn parse(value: &str) -> Result<Self, Error> {
+        if value.contains(",") {
+            return Ok(CronValue::List(
+                value
+                    .split(",")
+                    .map(Self::parse)
+                    .collect::<Result<Vec<_>, _>>()?,
+            ));
+        }
+
         match value.parse::<i64>() {
             Ok(value) => Ok(CronValue::Exact(value)),
             Err(_) => {
                 if value.starts_with("*/") {
                     match value.replace("*/", "").parse() {
-                        Ok(value) => Ok(CronValue::Every(value)),
-                        Err(_) => Err(Error::CronValueError),
+                        Ok(value) if value > 0 => Ok(CronValue::Every(value)),
+                        _ => Err(Error::CronValueError),
                     }
                 } else if value.contains("-") {
                     let range_parts = value.split("-").collect::<Vec<_>>();
@@ -40,7 +50,7 @@ impl CronValue {
                         return Err(Error::CronValueError);
                     };
 
-                    Ok(CronValue::Range(Range { start, end }))
+                    Ok(CronValue::Range(start..=end))
                 } else if value == "*" {
                     Ok(CronValue::Any)
                 } else {
@@ -56,6 +66,7 @@ impl CronValue {
             Self::Exact(value) => *value == time,
             Self::Every(value) => time % value == 0,
             Self::Range(value) => value.contains(&time),
+            Self::List(values) => values.iter().any(|value| value.matches(time)),
             Self::Any => true,
         }
     }
@@ -82,7 +93,7 @@ impl Cron {
     /// let cron = Cron::parse("* * * * *").unwrap();
     /// ```
     pub fn parse(value: &str) -> Result<Self, Error> {
-        let parts = value.split(" ").collect::<Vec<_>>();
+        let parts = value.split_whitespace().collect::<Vec<_>>();
 
         match parts.len() {
             // Second is specified
@@ -116,14 +127,15 @@ impl Cron {
         let hour = time.hour();
         let day = time.day();
         let month = time.month();
-        let weekday = time.weekday().number_from_sunday();
+        let weekday = time.weekday().number_days_from_sunday() as i64;
 
         self.second.matches(second as i64)
             && self.minute.matches(minute as i64)
             && self.hour.matches(hour as i64)
             && self.dom.matches(day as i64)
             && self.month.matches(month as i64)
-            && self.dow.matches(weekday as i64)
+            // Sunday is both 0 and 7.
+            && (self.dow.matches(weekday) || (weekday == 0 && self.dow.matches(7)))
     }
 }
 
@@ -167,5 +179,34 @@ mod test {
         let time = OffsetDateTime::now_utc().replace_second(0).unwrap();
 
         assert!(cron.should_run(&time));
+
+        assert!(Cron::parse("*/0 * * * *").is_err());
+        assert!(Cron::parse("* * * *").is_err());
+    }
+
+    #[test]
+    fn test_cron_standard() {
+        // Monday, 2024-01-01 09:30:00.
+        let monday = OffsetDateTime::from_unix_timestamp(1_704_101_400).unwrap();
+        let sunday = OffsetDateTime::from_unix_timestamp(1_704_015_000).unwrap();
+
+        // Ranges include both ends.
+        assert!(Cron::parse("30 9-17 * * *").unwrap().should_run(&monday));
+        assert!(Cron::parse("30 8-9 * * *").unwrap().should_run(&monday));
+        assert!(!Cron::parse("30 10-17 * * *").unwrap().should_run(&monday));
+
+        // Lists.
+        assert!(Cron::parse("0,15,30,45 * * * *")
+            .unwrap()
+            .should_run(&monday));
+        assert!(!Cron::parse("0,15,45 * * * *").unwrap().should_run(&monday));
+        assert!(Cron::parse("*/20,30 * * * *").unwrap().should_run(&monday));
+
+        // Day of week: Sunday is 0 or 7, Monday is 1.
+        assert!(Cron::parse("30 9 * * 1").unwrap().should_run(&monday));
+        assert!(Cron::parse("30 9 * * 1-5").unwrap().should_run(&monday));
+        assert!(!Cron::parse("30 9 * * 1-5").unwrap().should_run(&sunday));
+        assert!(Cron::parse("30 9 * * 0").unwrap().should_run(&sunday));
+        assert!(Cron::parse("30 9 * * 7").unwrap().should_run(&sunday));
     }
 }
@@ -6,10 +6,12 @@ pub mod clock;
 pub mod cron;
 pub mod error;
 pub mod model;
+pub mod scheduler;
 pub mod worker;
 
 pub use clock::Clock;
 pub use cron::Cron;
 pub use error::Error;
-pub use model::{queue_async, queue_delay, Job, JobHandler, JobModel};
+pub use model::{queue_async, queue_delay, Enqueue, Job, JobHandler, JobModel};
+pub use scheduler::Scheduler;
 pub use worker::Worker;
@@ -24,8 +24,13 @@ pub struct JobModel {
     pub retries: i64,
     pub completed_at: Option<OffsetDateTime>,
     pub error: Option<String>,
+    pub queue: String,
+    pub priority: i32,
 }
 
+/// Queue jobs are placed into unless specified otherwise.
+pub static DEFAULT_QUEUE: &str = "default";
+
 impl JobModel {
     fn new(name: &str, args: serde_json::Value) -> Self {
         Self {
@@ -39,16 +44,23 @@ impl JobModel {
             retries: 25,
             completed_at: None,
             error: None,
+            queue: DEFAULT_QUEUE.to_string(),
+            priority: 0,
         }
     }
 
     fn new_with_delay(name: &str, args: serde_json::Value, delay: Duration) -> Self {
+        Self::new_at(name, args, OffsetDateTime::now_utc() + delay)
+    }
+
+    fn new_at(name: &str, args: serde_json::Value, start_after: OffsetDateTime) -> Self {
         let mut job = Self::new(name, args);
-        job.start_after = OffsetDateTime::now_utc() + delay;
+        job.start_after = start_after;
         job
     }
 
-    /// Fetch the next job from the queue.
+    /// Fetch the next job from the queue. Jobs with the highest priority are fetched first,
+    /// and jobs with the same priority in the order they became due.
     ///
     /// Locks the job from being fetched by other workers.
     pub fn next() -> Scope<Self> {
@@ -56,12 +68,25 @@ impl JobModel {
             .filter("started_at", Value::Null)
             .filter_lt("attempts", JobModel::column("retries"))
             .filter_lte("start_after", Value::function("NOW")) // use database time
-            .order((JobModel::column("created_at"), "ASC"))
+            .order((JobModel::column("priority"), "DESC"))
+            .order((JobModel::column("start_after"), "ASC"))
+            .order((JobModel::column("id"), "ASC"))
             .take_one()
             .lock()
             .skip_locked()
     }
 
+    /// Fetch the next job from the specified queues only. If no queues are specified,
+    /// jobs from all queues are fetched.
+    pub fn next_in(queues: &[String]) -> Scope<Self> {
+        if queues.is_empty() {
+            Self::next()
+        } else {
+            let queues = queues.iter().map(|queue| queue.to_value()).collect();
+            Self::next().filter("queue", Value::List(queues))
+        }
+    }
+
     /// Fetch jobs that should be rescheduled.
     ///
     /// This happens if a worker crashed.
@@ -111,6 +136,8 @@ impl FromRow for JobModel {
             retries: row.try_get("retries")?,
             completed_at: row.try_get("completed_at")?,
             error: row.try_get("error")?,
+            queue: row.try_get("queue")?,
+            priority: row.try_get("priority")?,
         })
     }
 }
@@ -143,6 +170,8 @@ impl Model for JobModel {
             "retries",
             "completed_at",
             "error",
+            "queue",
+            "priority",
         ]
     }
 
@@ -157,6 +186,8 @@ impl Model for JobModel {
             self.retries.to_value(),
             self.completed_at.to_value(),
             self.error.to_value(),
+            self.queue.to_value(),
+            self.priority.to_value(),
         ]
     }
 }
@@ -205,6 +236,63 @@ pub trait Job: Sync + Send {
         Ok(())
     }
 
+    /// Schedule this job to run in the background at the specified time. Workers
+    /// won't pick it up until then.
+    async fn execute_at(&self, args: serde_json::Value, at: OffsetDateTime) -> Result<(), Error> {
+        let mut conn = get_connection().await?;
+        JobModel::new_at(self.job_name(), args, at)
+            .save()
+            .execute(&mut conn)
+            .await?;
+
+        info!("job {} scheduled to run at {}", self.job_name().green(), at);
+
+        Ok(())
+    }
+
+    /// Queue this job to run at the specified time, e.g. to send a reminder. The job
+    /// is serialized and passed to [`Job::execute`] as its arguments.
+    async fn enqueue_at(&self, at: OffsetDateTime) -> Result<(), Error>
+    where
+        Self: Serialize + Sized,
+    {
+        let args = serde_json::to_value(self)?;
+        self.execute_at(args, at).await
+    }
+
+    /// Queue this job to run after the specified delay. The job
+    /// is serialized and passed to [`Job::execute`] as its arguments.
+    async fn enqueue_in(&self, delay: Duration) -> Result<(), Error>
+    where
+        Self: Serialize + Sized,
+    {
+        let args = serde_json::to_value(self)?;
+        self.execute_delay(args, delay).await
+    }
+
+    /// Queue this job into the named queue, e.g. `"mailers"`. Workers can be configured
+    /// to only run jobs from some queues, see [`Worker::queues`](super::Worker::queues).
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// email.queue("mailers").priority(10).enqueue().await?;
+    /// ```
+    fn queue(&self, queue: &str) -> Enqueue<'_, Self>
+    where
+        Self: Sized,
+    {
+        Enqueue::new(self).queue(queue)
+    }
+
+    /// Queue this job with the priority. Jobs with a higher priority are run first. The default priority is 0.
+    fn priority(&self, priority: i32) -> Enqueue<'_, Self>
+    where
+        Self: Sized,
+    {
+        Enqueue::new(self).priority(priority)
+    }
+
     fn schedule(self, args: serde_json::Value, schedule: &str) -> Result<ScheduledJob, Error>
     where
         Self: Sized + 'static,
@@ -232,6 +320,84 @@ pub trait Job: Sync + Send {
     }
 }
 
+/// Options for placing a job into the queue, created by [`Job::queue`] or [`Job::priority`].
+pub struct Enqueue<'a, T> {
+    job: &'a T,
+    queue: String,
+    priority: i32,
+    start_after: Option<OffsetDateTime>,
+}
+
+impl<'a, T: Job> Enqueue<'a, T> {
+    fn new(job: &'a T) -> Self {
+        Self {
+            job,
+            queue: DEFAULT_QUEUE.to_string(),
+            priority: 0,
+            start_after: None,
+        }
+    }
+
+    /// Place the job into the named queue.
+    pub fn queue(mut self, queue: impl ToString) -> Self {
+        self.queue = queue.to_string();
+        self
+    }
+
+    /// Set the priority of the job. Jobs with a higher priority are run first.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Run the job at the specified time.
+    pub fn at(mut self, at: OffsetDateTime) -> Self {
+        self.start_after = Some(at);
+        self
+    }
+
+    /// Run the job after the specified delay.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.start_after = Some(OffsetDateTime::now_utc() + delay);
+        self
+    }
+
+    /// Create the job model without saving it.
+    fn model(&self, args: serde_json::Value) -> JobModel {
+        let mut job = match self.start_after {
+            Some(start_after) => JobModel::new_at(self.job.job_name(), args, start_after),
+            None => JobModel::new(self.job.job_name(), args),
+        };
+        job.queue = self.queue.clone();
+        job.priority = self.priority;
+        job
+    }
+
+    /// Place the job into the queue. The job is serialized and passed to [`Job::execute`] as its arguments.
+    pub async fn enqueue(self) -> Result<(), Error>
+    where
+        T: Serialize,
+    {
+        let args = serde_json::to_value(self.job)?;
+        self.enqueue_with_args(args).await
+    }
+
+    /// Place the job into the queue, passing it the specified arguments.
+    pub async fn enqueue_with_args(self, args: serde_json::Value) -> Result<(), Error> {
+        let mut conn = get_connection().await?;
+        self.model(args).save().execute(&mut conn).await?;
+
+        info!(
+            "job {} queued into \"{}\" with priority {}",
+            self.job.job_name().green(),
+            self.queue,
+            self.priority
+        );
+
+        Ok(())
+    }
+}
+
 /// Wrapper around the concrete job implementation.
 pub struct JobHandler {
     pub job: Box<dyn Job>,
@@ -260,3 +426,141 @@ pub async fn queue_delay<T: Job + Serialize>(job: &T, delay: Duration) -> Result
 pub async fn queue_async<T: Job + Serialize>(job: &T) -> Result<(), Error> {
     queue(job).await
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::Pool;
+
+    /// Create a job queue visible to this transaction only.
+    async fn jobs_table(transaction: &mut crate::model::pool::Transaction) -> Result<(), Error> {
+        // Shadows the real table, if any, for this transaction only.
+        transaction
+            .client()
+            .execute(
+                "CREATE TEMPORARY TABLE rwf_jobs (
+                    id BIGSERIAL PRIMARY KEY,
+                    name VARCHAR NOT NULL,
+                    args JSONB NOT NULL DEFAULT '{}'::jsonb,
+                    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                    start_after TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+                    started_at TIMESTAMPTZ,
+                    attempts INT NOT NULL DEFAULT 0,
+                    retries BIGINT NOT NULL DEFAULT 25,
+                    completed_at TIMESTAMPTZ,
+                    error VARCHAR,
+                    queue VARCHAR NOT NULL DEFAULT 'default',
+                    priority INT NOT NULL DEFAULT 0
+                ) ON COMMIT DROP",
+                &[],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_next_run_at() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        jobs_table(&mut transaction).await?;
+
+        let now = OffsetDateTime::now_utc();
+
+        JobModel::new_at(
+            "reminder",
+            serde_json::Value::Null,
+            now + Duration::hours(24),
+        )
+        .save()
+        .execute(&mut transaction)
+        .await?;
+
+        // Not due yet.
+        assert!(JobModel::next()
+            .fetch_optional(&mut transaction)
+            .await?
+            .is_none());
+
+        JobModel::new_at("late", serde_json::Value::Null, now - Duration::minutes(1))
+            .save()
+            .execute(&mut transaction)
+            .await?;
+        JobModel::new_at("early", serde_json::Value::Null, now - Duration::minutes(5))
+            .save()
+            .execute(&mut transaction)
+            .await?;
+
+        // The job which was due first is picked up first, even if it was created later.
+        let next = JobModel::next()
+            .fetch_optional(&mut transaction)
+            .await?
+            .expect("due job");
+        assert_eq!(next.name, "early");
+
+        assert_eq!(JobModel::queued().count(&mut transaction).await?, 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_next_priority() -> Result<(), Error> {
+        #[derive(Serialize)]
+        struct Email;
+
+        #[async_trait]
+        impl Job for Email {
+            async fn execute(&self, _args: serde_json::Value) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+        jobs_table(&mut transaction).await?;
+
+        let jobs = [
+            Email
+                .queue("mailers")
+                .model(serde_json::json!("newsletter")),
+            Email
+                .queue("mailers")
+                .priority(10)
+                .model(serde_json::json!("password reset")),
+            Email.priority(5).model(serde_json::json!("receipt")),
+            Email
+                .queue("mailers")
+                .priority(10)
+                .model(serde_json::json!("welcome")),
+        ];
+
+        // NOW() is the start of the transaction, so the jobs must be due before it.
+        let due = OffsetDateTime::now_utc() - Duration::minutes(1);
+
+        for mut job in jobs {
+            job.start_after = due;
+            job.save().execute(&mut transaction).await?;
+        }
+
+        let next = |queues: &[&str]| {
+            JobModel::next_in(&queues.iter().map(|q| q.to_string()).collect::<Vec<_>>())
+        };
+
+        // Highest priority first, then oldest first.
+        let job = next(&[]).fetch(&mut transaction).await?;
+        assert_eq!(job.args, serde_json::json!("password reset"));
+        assert_eq!(job.queue, "mailers");
+        assert_eq!(job.priority, 10);
+
+        let job = next(&["default"]).fetch(&mut transaction).await?;
+        assert_eq!(job.args, serde_json::json!("receipt"));
+
+        assert!(next(&["payments"])
+            .fetch_optional(&mut transaction)
+            .await?
+            .is_none());
+
+        Ok(())
+    }
+}
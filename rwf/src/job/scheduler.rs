@@ -0,0 +1,252 @@
+//! Runs jobs on a schedule inside the application process.
+//!
+//! Unlike the [`Clock`](super::Clock), which queues scheduled jobs into Postgres to be picked up
+//! by a [`Worker`](super::Worker), the scheduler executes the jobs directly, so it doesn't need
+//! a database or a worker. If several instances of the application are running, each one of them
+//! will run the jobs.
+//!
+//! # Example
+//!
+//! ```
+//! use rwf::job::{Error, Job, Scheduler};
+//! use rwf::prelude::*;
+//!
+//! #[derive(Default)]
+//! struct CleanupSessions;
+//!
+//! #[async_trait]
+//! impl Job for CleanupSessions {
+//!     async fn execute(&self, _args: serde_json::Value) -> Result<(), Error> {
+//!         // Do some cleanup.
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # fn main() -> Result<(), Error> {
+//! let scheduler = Scheduler::new()
+//!     // Every 15 minutes.
+//!     .every("*/15 * * * *", CleanupSessions::default())?;
+//! # Ok(())
+//! # }
+//! ```
+use super::{Cron, Error, Job};
+use crate::colors::MaybeColorize;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+use time::OffsetDateTime;
+use tokio::select;
+use tokio::signal::ctrl_c;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use tracing::{error, info, warn};
+
+/// A job and its schedule.
+struct Entry {
+    cron: Cron,
+    job: Box<dyn Job>,
+    args: serde_json::Value,
+    running: AtomicBool,
+}
+
+/// Marks the job as finished when the run ends, even if the job panicked.
+struct Running(Arc<Entry>);
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::Release);
+    }
+}
+
+/// Runs jobs on a cron schedule, in the same process.
+#[derive(Default, Clone)]
+pub struct Scheduler {
+    jobs: Vec<Arc<Entry>>,
+}
+
+impl Scheduler {
+    /// Create a scheduler without any jobs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the job on the cron schedule, e.g. `"0 * * * *"` to run it every hour. Seconds can be specified
+    /// by adding a sixth field at the start, e.g. `"*/10 * * * * *"` runs the job every 10 seconds.
+    /// The job is executed with `null` arguments.
+    pub fn every(self, cron: &str, job: impl Job + 'static) -> Result<Self, Error> {
+        self.every_with_args(cron, job, serde_json::Value::Null)
+    }
+
+    /// Run the job on the cron schedule, passing it the specified arguments.
+    pub fn every_with_args(
+        mut self,
+        cron: &str,
+        job: impl Job + 'static,
+        args: impl Serialize,
+    ) -> Result<Self, Error> {
+        self.jobs.push(Arc::new(Entry {
+            cron: Cron::parse(cron)?,
+            job: Box::new(job),
+            args: serde_json::to_value(args)?,
+            running: AtomicBool::new(false),
+        }));
+
+        Ok(self)
+    }
+
+    /// Run the scheduler in a background task until the server is shut down (`SIGINT`/Ctrl-C).
+    ///
+    /// This returns immediately, so it can be called before [`Server::launch`](crate::http::Server::launch).
+    pub fn start(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            self.run_until(async {
+                let _ = ctrl_c().await;
+            })
+            .await
+        })
+    }
+
+    /// Run the scheduler until the future completes.
+    pub async fn run_until(&self, shutdown: impl Future<Output = ()>) {
+        select! {
+            _ = shutdown => {
+                info!("Scheduler is shutting down");
+            }
+
+            _ = self.run() => {}
+        }
+    }
+
+    /// Run the scheduler. This blocks forever.
+    pub async fn run(&self) {
+        info!("Scheduler is running {} jobs", self.jobs.len());
+
+        loop {
+            // Wake up at the start of every second, so no second is skipped or checked twice.
+            let now = OffsetDateTime::now_utc();
+            let remaining = 1_000_000_000 - now.nanosecond() as u64;
+            sleep(Duration::from_nanos(remaining)).await;
+
+            self.tick(&OffsetDateTime::now_utc());
+        }
+    }
+
+    /// Start all jobs which should run at the specified time. Jobs which are still
+    /// running since the last time they were started are skipped.
+    fn tick(&self, time: &OffsetDateTime) -> Vec<JoinHandle<()>> {
+        let mut handles = vec![];
+
+        for entry in &self.jobs {
+            if !entry.cron.should_run(time) {
+                continue;
+            }
+
+            if entry.running.swap(true, Ordering::AcqRel) {
+                warn!(
+                    "job {} is still running, skipping this run",
+                    entry.job.job_name().green()
+                );
+                continue;
+            }
+
+            let running = Running(entry.clone());
+
+            handles.push(tokio::spawn(async move {
+                let entry = &running.0;
+
+                if let Err(err) = entry.job.execute(entry.args.clone()).await {
+                    error!("job {} failed: {:?}", entry.job.job_name().green(), err);
+                }
+            }));
+        }
+
+        handles
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::AtomicUsize;
+
+    #[derive(Clone, Default)]
+    struct Counter {
+        runs: Arc<AtomicUsize>,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl Job for Counter {
+        async fn execute(&self, _args: serde_json::Value) -> Result<(), Error> {
+            sleep(self.delay).await;
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn at(second: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(1_704_101_400 + second).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_tick() {
+        let counter = Counter::default();
+        let scheduler = Scheduler::new()
+            .every("*/2 * * * * *", counter.clone())
+            .unwrap();
+
+        for second in 0..10 {
+            for handle in scheduler.tick(&at(second)) {
+                handle.await.unwrap();
+            }
+        }
+
+        assert_eq!(counter.runs.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_no_overlap() {
+        let counter = Counter {
+            delay: Duration::from_millis(100),
+            ..Default::default()
+        };
+        let scheduler = Scheduler::new()
+            .every("* * * * * *", counter.clone())
+            .unwrap();
+
+        let first = scheduler.tick(&at(0));
+        assert_eq!(first.len(), 1);
+
+        // Still running.
+        assert!(scheduler.tick(&at(1)).is_empty());
+
+        for handle in first {
+            handle.await.unwrap();
+        }
+
+        for handle in scheduler.tick(&at(2)) {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(counter.runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_until() {
+        let counter = Counter::default();
+        let scheduler = Scheduler::new()
+            .every("* * * * * *", counter.clone())
+            .unwrap();
+
+        scheduler
+            .run_until(sleep(Duration::from_millis(2_500)))
+            .await;
+
+        let runs = counter.runs.load(Ordering::SeqCst);
+        assert!((2..=3).contains(&runs), "ran {} times", runs);
+    }
+}
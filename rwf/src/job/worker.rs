@@ -23,6 +23,7 @@ use std::time::Instant;
 pub struct Worker {
     jobs: Arc<HashMap<String, JobHandler>>,
     clock: Option<Clock>,
+    queues: Arc<Vec<String>>,
 }
 
 impl Worker {
@@ -37,9 +38,17 @@ impl Worker {
         Self {
             jobs: Arc::new(jobs),
             clock: None,
+            queues: Arc::new(vec![]),
         }
     }
 
+    /// Only run jobs from the specified queues, e.g. `&["payments"]`. Jobs are run in
+    /// priority order, regardless of which queue they are in. By default, the worker runs jobs from all queues.
+    pub fn queues(mut self, queues: &[&str]) -> Self {
+        self.queues = Arc::new(queues.iter().map(|queue| queue.to_string()).collect());
+        self
+    }
+
     /// Run the specified jobs on a schedule.
     pub fn clock(mut self, jobs: Vec<ScheduledJob>) -> Self {
         self.clock = Some(Clock::new(jobs));
@@ -82,10 +91,13 @@ impl Worker {
             let worker = self.clone();
             let run_result = tokio::spawn(async move {
                 let pool = get_pool();
+                let queues = worker.queues.clone();
 
                 let job = pool
                     .with_transaction(|mut transaction| async move {
-                        let job = JobModel::next().fetch_optional(&mut transaction).await?;
+                        let job = JobModel::next_in(&queues)
+                            .fetch_optional(&mut transaction)
+                            .await?;
 
                         let job = if let Some(mut job) = job {
                             job.started_at = Some(OffsetDateTime::now_utc());
@@ -108,6 +108,9 @@ pub use tokio;
 /// Asynchronous PostgreSQL driver.
 pub use tokio_postgres;
 
+// The macros refer to `rwf::`, this makes them usable inside this crate.
+extern crate self as rwf;
+
 use std::net::SocketAddr;
 
 /// Convert text to snake_case.
@@ -161,7 +164,12 @@ pub fn pascal_case(string: &str) -> String {
 /// Remove unsafe characters from a string printed
 /// inside an HTML template.
 pub fn safe_html(string: &str) -> String {
-    string.replace("<", "&lt;").replace(">", "&gt;")
+    string
+        .replace("&", "&amp;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;")
+        .replace("\"", "&quot;")
+        .replace("'", "&#x27;")
 }
 
 /// Extract the first socket address from a string.
@@ -1,6 +1,7 @@
 //! Wrapper around `tracing_subscriber` for logging.
 //!
 //! Configures application-wide logging to go to stderr at the `INFO` level.
+//! Log lines are colored text by default, or JSON objects if `log_format` is set to `"json"`.
 //! If you prefer to use your own logging subscriber, don't initialize the `Logger`.
 //!
 //! ### Example
@@ -33,15 +34,72 @@ impl Logger {
 }
 
 fn setup_logging() {
-    fmt()
-        .with_env_filter(
-            EnvFilter::builder()
-                .with_default_directive(LevelFilter::INFO.into())
-                .from_env_lossy(),
-        )
-        .with_ansi(get_config().general.tty)
-        .with_file(false)
+    let config = &get_config().general;
+    let filter = EnvFilter::builder()
+        .with_default_directive(config.log_level().unwrap_or(LevelFilter::INFO).into())
+        .from_env_lossy();
+
+    if config.log_json() {
+        fmt()
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .with_span_list(false)
+            .with_env_filter(filter)
+            .with_ansi(false)
+            .with_file(false)
+            .with_target(false)
+            .finish()
+            .init();
+    } else {
+        fmt()
+            .with_env_filter(filter)
+            .with_ansi(config.tty)
+            .with_file(false)
+            .with_target(false)
+            .finish()
+            .init();
+    }
+}
+
+/// Run the closure with JSON logging and return the logged lines.
+#[cfg(test)]
+pub(crate) fn capture_json(f: impl FnOnce()) -> Vec<serde_json::Value> {
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let capture = Capture::default();
+    let writer = capture.clone();
+    let subscriber = fmt()
+        .json()
+        .flatten_event(true)
+        .with_current_span(false)
+        .with_span_list(false)
+        .with_max_level(LevelFilter::DEBUG)
+        .with_ansi(false)
         .with_target(false)
-        .finish()
-        .init();
+        .with_writer(move || writer.clone())
+        .finish();
+
+    tracing::subscriber::with_default(subscriber, f);
+
+    let output = capture.0.lock().unwrap().clone();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("log line is not JSON"))
+        .collect()
 }
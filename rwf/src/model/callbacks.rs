@@ -1,3 +1,75 @@
 //! Implements automatically calling functions after model events, e.g. when a model is saved, created, etc.
 //!
-//! This is currently a work in progress.
+//! [`Callbacks::before_save`] is called by [`Model::save`](super::Model::save) before the query is built, so it can
+//! change the record, e.g. to normalize its fields. It runs before validation.
+//!
+//! [`Callbacks::after_create`] and [`Callbacks::after_update`] are called after the query returned
+//! by [`Model::save`](super::Model::save) is executed, for each record returned by the database. They receive the connection
+//! used to save the record, so they can run more queries, e.g. to enqueue a job, inside the same transaction. If a callback
+//! returns an error, executing the query returns that error.
+//!
+//! # Example
+//!
+//! ```
+//! use rwf::prelude::*;
+//! use rwf::model::{Callbacks, ConnectionGuard, Error};
+//!
+//! #[derive(Clone, macros::Model)]
+//! #[callbacks]
+//! struct User {
+//!     id: Option<i64>,
+//!     email: String,
+//! }
+//!
+//! #[derive(Clone, macros::Model)]
+//! struct Signup {
+//!     id: Option<i64>,
+//!     user_id: i64,
+//! }
+//!
+//! impl Callbacks for User {
+//!     fn before_save(&mut self) {
+//!         self.email = self.email.trim().to_lowercase();
+//!     }
+//!
+//!     async fn after_create(&self, conn: &mut ConnectionGuard) -> Result<(), Error> {
+//!         Signup::create(&[("user_id", self.id)]).execute(conn).await?;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let user = User { id: None, email: " Test@Example.com ".into() };
+//! let query = user.save();
+//!
+//! assert_eq!(query.to_sql(), r#"INSERT INTO "users" ("email") VALUES ($1) RETURNING *"#);
+//! ```
+use std::future::Future;
+
+use super::{ConnectionGuard, Error};
+
+/// Functions called when a model is saved. All of them do nothing by default.
+///
+/// To call them when saving the model, use the `#[callbacks]` attribute of the `Model` derive,
+/// or call them from the [`Model`](super::Model) functions with the same name when implementing the trait manually.
+pub trait Callbacks {
+    /// Called before the model is validated and the query saving it is built.
+    fn before_save(&mut self) {}
+
+    /// Called after the record is inserted into the database.
+    fn after_create(
+        &self,
+        conn: &mut ConnectionGuard,
+    ) -> impl Future<Output = Result<(), Error>> + Send {
+        let _ = conn;
+        async { Ok(()) }
+    }
+
+    /// Called after the record is updated in the database.
+    fn after_update(
+        &self,
+        conn: &mut ConnectionGuard,
+    ) -> impl Future<Output = Result<(), Error>> + Send {
+        let _ = conn;
+        async { Ok(()) }
+    }
+}
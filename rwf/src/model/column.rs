@@ -1,5 +1,5 @@
 //! Represents the database table column.
-use super::{Escape, ToSql, ToValue, Value};
+use super::{Escape, ToSql, ToValue, Value, Window};
 
 /// PostgreSQL table column.
 #[derive(Debug, Clone, PartialEq)]
@@ -7,6 +7,7 @@ pub struct Column {
     table_name: String,
     column_name: String,
     as_value: Option<Box<Value>>,
+    alias: Option<String>,
 }
 
 impl std::fmt::Display for Column {
@@ -24,13 +25,13 @@ impl ToSql for Column {
         };
 
         if self.table_name.is_empty() {
-            format!(r#"{}"{}""#, as_value, self.column_name.escape())
+            format!(r#"{}"{}""#, as_value, self.column_name.escape_identifier())
         } else {
             format!(
                 r#"{}"{}"."{}""#,
                 as_value,
-                self.table_name.escape(),
-                self.column_name.escape(),
+                self.table_name.escape_identifier(),
+                self.column_name.escape_identifier(),
             )
         }
     }
@@ -46,6 +47,7 @@ impl Column {
             table_name: table_name.to_string(),
             column_name: column_name.to_string(),
             as_value: None,
+            alias: None,
         }
     }
 
@@ -58,6 +60,16 @@ impl Column {
         Self::new("", column_name)
     }
 
+    /// Column name, without the table name.
+    pub fn column_name(&self) -> &str {
+        &self.column_name
+    }
+
+    /// Table name, empty if the column isn't qualified.
+    pub fn table_name(&self) -> &str {
+        &self.table_name
+    }
+
     pub fn qualified(&self) -> bool {
         !self.table_name.is_empty()
     }
@@ -76,6 +88,29 @@ impl Column {
         self.as_value = Some(Box::new(value.to_value()));
         self
     }
+
+    /// Name the column in the query results, e.g. `"orders"."amount" AS "order_amount"`.
+    ///
+    /// The alias is used only in the list of selected columns, and rows
+    /// returned by the query have the column under that name, so [`crate::model::FromRow`]
+    /// reads it by the alias.
+    pub fn as_alias(mut self, alias: impl ToString) -> Self {
+        self.alias = Some(alias.to_string());
+        self
+    }
+
+    /// The column alias, if any.
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_deref()
+    }
+
+    /// The column in the list of selected columns, with its alias.
+    fn to_select_sql(&self) -> String {
+        match self.alias {
+            Some(ref alias) => format!(r#"{} AS "{}""#, self.to_sql(), alias.escape_identifier()),
+            None => self.to_sql(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -85,6 +120,9 @@ pub struct Columns {
     exists: bool,
     all: bool,
     count: bool,
+    distinct: Option<Column>,
+    one: bool,
+    windows: Vec<Window>,
 }
 
 impl Default for Columns {
@@ -95,6 +133,9 @@ impl Default for Columns {
             exists: false,
             all: true,
             count: false,
+            distinct: None,
+            one: false,
+            windows: vec![],
         }
     }
 }
@@ -132,15 +173,48 @@ impl Columns {
         self
     }
 
+    /// Count distinct values of the column, i.e. `COUNT(DISTINCT column)`.
+    pub fn count_distinct(mut self, column: impl ToColumn) -> Self {
+        self.distinct = Some(column.to_column());
+        self
+    }
+
+    /// Select the constant `1` instead of columns, e.g. in `EXISTS` subqueries,
+    /// where only the existence of rows matters.
+    pub fn one(mut self) -> Self {
+        self.one = true;
+        self
+    }
+
     pub fn add_column(mut self, column: impl ToColumn) -> Self {
         self.columns.push(column.to_column());
         self
     }
+
+    /// Select a window function in addition to the columns.
+    pub fn add_window(mut self, window: Window) -> Self {
+        self.windows.push(window);
+        self
+    }
+
+    /// The same columns without their aliases and window functions, e.g. for `GROUP BY`.
+    pub fn without_aliases(&self) -> Self {
+        let mut columns = self.clone();
+        for column in columns.columns.iter_mut() {
+            column.alias = None;
+        }
+        columns.windows.clear();
+        columns
+    }
 }
 
 impl ToSql for Columns {
     fn to_sql(&self) -> String {
-        if self.exists {
+        if self.one {
+            "1".into()
+        } else if let Some(ref column) = self.distinct {
+            format!("COUNT(DISTINCT {}) AS count", column.to_sql())
+        } else if self.exists {
             "COUNT(*) AS count".into()
         } else {
             let mut columns = if self.count {
@@ -157,7 +231,8 @@ impl ToSql for Columns {
                 }
             }
 
-            columns.extend(self.columns.iter().map(|column| column.to_sql()));
+            columns.extend(self.columns.iter().map(|column| column.to_select_sql()));
+            columns.extend(self.windows.iter().map(|window| window.to_sql()));
 
             columns.join(", ")
         }
@@ -3,7 +3,7 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use thiserror::Error;
 
-use super::Value;
+use super::{ValidationErrors, Value};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -22,15 +22,40 @@ pub enum Error {
     #[error("{0}: {1}")]
     ValueError(&'static str, String),
 
-    #[error("pool timeout")]
+    #[error("connection error: {0}")]
+    Connection(String),
+
+    #[error("pool timeout: no connection became available within the checkout timeout")]
     PoolTimeout,
 
-    #[error("pool not configured")]
-    PoolNotConfigured,
+    #[error("query has {0} placeholders, but {1} values were bound: {2}")]
+    PlaceholderMismatch(usize, usize, String),
+
+    #[error(
+        "table {0} is missing columns declared by the model: {} (extra columns in the table: {})",
+        column_list(.1),
+        column_list(.2)
+    )]
+    SchemaMismatch(String, Vec<String>, Vec<String>),
+
+    #[error("invalid order: {0}")]
+    InvalidOrder(String),
 
     #[error("record not found")]
     RecordNotFound,
 
+    #[error("stale record: it was updated by someone else since it was loaded")]
+    StaleObject,
+
+    #[error("lock version column \"{0}\" must be an integer")]
+    InvalidLockVersion(String),
+
+    #[error("EXPLAIN ANALYZE executes the query, use ExplainOptions::allow_writes to analyze queries that write: {0}")]
+    ExplainWrite(String),
+
+    #[error("validation failed: {0}")]
+    Validation(ValidationErrors),
+
     #[error("unknown token in template: {0}")]
     UnknownToken(String),
 
@@ -55,6 +80,35 @@ impl Error {
     }
 }
 
+fn column_list(columns: &[String]) -> String {
+    if columns.is_empty() {
+        "none".into()
+    } else {
+        columns.join(", ")
+    }
+}
+
+/// The error was caused by the connection to the database, e.g. it was closed
+/// or the server terminated it, and not by the query.
+pub(crate) fn connection_error(error: &tokio_postgres::Error) -> bool {
+    use std::error::Error;
+    use tokio_postgres::error::Severity;
+
+    if let Some(db_error) = error.as_db_error() {
+        // The server closes the connection after a fatal error.
+        return matches!(
+            db_error.parsed_severity(),
+            Some(Severity::Fatal) | Some(Severity::Panic)
+        );
+    }
+
+    error.is_closed()
+        || error
+            .source()
+            .map(|source| source.is::<std::io::Error>())
+            .unwrap_or(false)
+}
+
 static RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#""(.*)""#).unwrap());
 
 impl From<tokio_postgres::Error> for Error {
@@ -63,10 +117,19 @@ impl From<tokio_postgres::Error> for Error {
         // Waiting for: https://github.com/sfackler/rust-postgres/pull/1185
         let dbg = format!("{:?}", error);
 
-        if dbg.starts_with("Error { kind: Column(") {
-            let column = RE.captures(&dbg).unwrap();
-            let name = column.get(1).unwrap(); // Get the text in between double quotes.
-            Error::Column(name.as_str().to_string())
+        // Get the text in between double quotes.
+        let column = if dbg.starts_with("Error { kind: Column(") {
+            RE.captures(&dbg)
+                .and_then(|captures| captures.get(1))
+                .map(|name| name.as_str().to_string())
+        } else {
+            None
+        };
+
+        if let Some(column) = column {
+            Error::Column(column)
+        } else if connection_error(&error) {
+            Error::Connection(error.to_string())
         } else {
             Error::DatabaseError(error)
         }
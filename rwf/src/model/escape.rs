@@ -7,6 +7,12 @@ use super::value::Value;
 /// The implementation is dependent on the value, but the
 /// most common one, a [`String`] (and it's reference cousin [`&str`]), are implemented.
 ///
+/// String literals and identifiers are quoted differently, so they are escaped differently too:
+/// use [`Escape::escape`] for values placed between single quotes, and [`Escape::escape_identifier`] for
+/// table and column names placed between double quotes. Backslashes don't need escaping, since they
+/// are not special in standard SQL strings. Null bytes are not removed: PostgreSQL doesn't allow them in
+/// queries, so a query containing one is rejected with an error instead of running with a different value.
+///
 /// # Example
 ///
 /// ```
@@ -14,10 +20,29 @@ use super::value::Value;
 ///
 /// let email = "guest@test.com';DROP TABLE users;";
 /// assert_eq!(email.escape(), "guest@test.com'';DROP TABLE users;");
+///
+/// let column = r#"email" FROM users; --"#;
+/// assert_eq!(column.escape_identifier(), r#"email"" FROM users; --"#);
 /// ```
 ///
 pub trait Escape {
+    /// Escape the value for use inside a string literal, e.g. `'O''Brien'`.
     fn escape(&self) -> String;
+
+    /// Escape the value for use inside a quoted identifier, e.g. `"weird""name"`.
+    fn escape_identifier(&self) -> String;
+}
+
+/// Quote the name of a table, qualified with the schema if there is one, e.g. `"tenant"."users"`.
+pub fn quote_table(schema: Option<&str>, table: &str) -> String {
+    match schema {
+        Some(schema) => format!(
+            r#""{}"."{}""#,
+            schema.escape_identifier(),
+            table.escape_identifier()
+        ),
+        None => format!(r#""{}""#, table.escape_identifier()),
+    }
 }
 
 impl Escape for Value {
@@ -40,16 +65,160 @@ impl Escape for Value {
             _ => todo!(),
         }
     }
+
+    fn escape_identifier(&self) -> String {
+        identifier(self).escape_identifier()
+    }
+}
+
+/// Text of the value used as an identifier, before it's escaped.
+fn identifier(value: &Value) -> String {
+    use Value::*;
+
+    let join = |values: &[Value]| values.iter().map(identifier).collect::<Vec<_>>().join(", ");
+
+    match value {
+        String(string) => string.clone(),
+        Integer(integer) | BigInt(integer) => integer.to_string(),
+        Int(integer) => integer.to_string(),
+        SmallInt(integer) => integer.to_string(),
+        Float(float) => float.to_string(),
+        Real(float) => float.to_string(),
+        Boolean(boolean) => boolean.to_string(),
+        TimestampT(timestamp) => timestamp.to_string(),
+        Timestamp(timestamp) => timestamp.to_string(),
+        Date(date) => date.to_string(),
+        IpAddr(ip) => ip.to_string(),
+        Uuid(uuid) => uuid.to_string(),
+        #[cfg(feature = "decimal")]
+        Decimal(decimal) => decimal.to_string(),
+        List(values) | Array(values) => join(values),
+        Record(value) => identifier(value),
+        Placeholder(number) => number.to_string(),
+        Range((start, end)) => format!("{}, {}", identifier(start), identifier(end)),
+        Column(column) => column.to_string(),
+        Json(json) => json.to_string(),
+        Optional(value) => value.as_ref().as_ref().map(identifier).unwrap_or_default(),
+        Function((name, _)) => name.clone(),
+        Null => std::string::String::new(),
+    }
+}
+
+impl Escape for str {
+    fn escape(&self) -> String {
+        self.replace('\'', "''")
+    }
+
+    fn escape_identifier(&self) -> String {
+        self.replace('"', "\"\"")
+    }
 }
 
 impl Escape for String {
     fn escape(&self) -> String {
-        self.replace("\"", "\"\"").replace("'", "''")
+        self.as_str().escape()
+    }
+
+    fn escape_identifier(&self) -> String {
+        self.as_str().escape_identifier()
     }
 }
 
 impl Escape for &str {
     fn escape(&self) -> String {
-        self.to_string().escape()
+        (**self).escape()
+    }
+
+    fn escape_identifier(&self) -> String {
+        (**self).escape_identifier()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::{Error, Pool, Query, Row};
+
+    #[test]
+    fn test_quote_table() {
+        assert_eq!(quote_table(None, "users"), r#""users""#);
+        assert_eq!(quote_table(Some("tenant"), "users"), r#""tenant"."users""#);
+        assert_eq!(quote_table(Some(r#"a"b"#), "users"), r#""a""b"."users""#);
+    }
+
+    #[test]
+    fn test_escape_literal() {
+        assert_eq!("O'Brien".escape(), "O''Brien");
+        assert_eq!(
+            format!("'{}'", "'; DROP TABLE users; --".escape()),
+            "'''; DROP TABLE users; --'"
+        );
+
+        // Double quotes and backslashes are not special inside string literals.
+        assert_eq!(r#"say "hi""#.escape(), r#"say "hi""#);
+        assert_eq!(r"C:\path\'".escape(), r"C:\path\''");
+    }
+
+    #[test]
+    fn test_escape_identifier() {
+        assert_eq!(r#"weird"name"#.escape_identifier(), r#"weird""name"#);
+        assert_eq!(
+            format!(r#""{}""#, r#"id" FROM users; --"#.escape_identifier()),
+            r#""id"" FROM users; --""#
+        );
+
+        // Single quotes are not special inside identifiers.
+        assert_eq!("O'Brien".escape_identifier(), "O'Brien");
+    }
+
+    #[test]
+    fn test_escape_value_identifier() {
+        assert_eq!(
+            Value::String(r#"weird"name"#.into()).escape_identifier(),
+            r#"weird""name"#
+        );
+        assert_eq!(Value::Integer(5).escape_identifier(), "5");
+        assert_eq!(Value::Boolean(true).escape_identifier(), "true");
+        assert_eq!(Value::Null.escape_identifier(), "");
+        assert_eq!(
+            Value::List(vec![Value::Int(1), Value::String(r#"a"b"#.into())]).escape_identifier(),
+            r#"1, a""b"#
+        );
+        assert_eq!(
+            Value::Optional(Box::new(Some(Value::Uuid(uuid::Uuid::nil())))).escape_identifier(),
+            "00000000-0000-0000-0000-000000000000"
+        );
+        assert_eq!(
+            Value::Json(serde_json::json!({"key": "value"})).escape_identifier(),
+            r#"{""key"":""value""}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_escape_null_byte() -> Result<(), Error> {
+        // Null bytes are kept, so the value doesn't silently change.
+        assert_eq!("abc\0' OR 1=1".escape(), "abc\0'' OR 1=1");
+        assert_eq!("weird\0\"name".escape_identifier(), "weird\0\"\"name");
+
+        // The query is rejected instead.
+        let pool = Pool::from_env();
+        let mut conn = pool.get().await?;
+        let query = format!(
+            "SELECT '{}' AS \"{}\"",
+            "ad\0min".escape(),
+            "name".escape_identifier()
+        );
+        assert!(Query::<Row>::raw_with(&query, vec![])
+            .execute(&mut conn)
+            .await
+            .is_err());
+
+        let query = format!("SELECT 1 AS \"{}\"", "na\0me".escape_identifier());
+        assert!(Query::<Row>::raw_with(&query, vec![])
+            .execute(&mut conn)
+            .await
+            .is_err());
+
+        Ok(())
     }
 }
@@ -1,9 +1,173 @@
 //! Represents the result of executing `EXPLAIN <query>;`
+use serde::Deserialize;
+use tokio_postgres::types::Type;
+
 use super::{Error, FromRow, Model, Value};
 
+/// Output format of `EXPLAIN`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Format {
+    /// Human-readable text, the default.
+    #[default]
+    Text,
+    /// JSON, which can be parsed into a tree of [`Plan`] nodes with [`Explain::tree`].
+    Json,
+}
+
+/// Options passed to `EXPLAIN`.
+///
+/// # Example
+///
+/// ```
+/// use rwf::model::explain::{ExplainOptions, Format};
+///
+/// let options = ExplainOptions::new().analyze().format(Format::Json);
+/// assert_eq!(options.to_string(), "EXPLAIN (ANALYZE, BUFFERS, FORMAT JSON)");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExplainOptions {
+    analyze: bool,
+    format: Format,
+    allow_writes: bool,
+}
+
+impl ExplainOptions {
+    /// Plain `EXPLAIN`, which doesn't execute the query.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Execute the query and report actual run times and buffer usage, i.e. `EXPLAIN (ANALYZE, BUFFERS)`.
+    ///
+    /// **The query is executed.** Queries which write, e.g. `UPDATE` or `INSERT`, make their changes
+    /// like they normally would, so they are refused unless [`ExplainOptions::allow_writes`] is set.
+    pub fn analyze(mut self) -> Self {
+        self.analyze = true;
+        self
+    }
+
+    /// Set the output format.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Allow analyzing queries which aren't a `SELECT`. To discard their changes,
+    /// run them inside a transaction which is then rolled back.
+    pub fn allow_writes(mut self) -> Self {
+        self.allow_writes = true;
+        self
+    }
+
+    /// The query is executed by `EXPLAIN`, but it's not allowed to write.
+    pub(crate) fn read_only(&self) -> bool {
+        self.analyze && !self.allow_writes
+    }
+}
+
+impl std::fmt::Display for ExplainOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut options = vec![];
+
+        if self.analyze {
+            options.push("ANALYZE");
+            options.push("BUFFERS");
+        }
+
+        if self.format == Format::Json {
+            options.push("FORMAT JSON");
+        }
+
+        if options.is_empty() {
+            write!(f, "EXPLAIN")
+        } else {
+            write!(f, "EXPLAIN ({})", options.join(", "))
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Explain {
     plan: String,
+    format: Format,
+}
+
+impl Explain {
+    /// Combine the rows returned by `EXPLAIN`, one for each line of the plan.
+    pub(crate) fn from_rows(rows: Vec<tokio_postgres::Row>) -> Result<Self, Error> {
+        let mut lines = vec![];
+        let mut format = Format::Text;
+
+        for row in rows {
+            let explain = Self::from_row(row)?;
+            format = explain.format;
+            lines.push(explain.plan);
+        }
+
+        if lines.is_empty() {
+            return Err(Error::RecordNotFound);
+        }
+
+        Ok(Self {
+            plan: lines.join("\n"),
+            format,
+        })
+    }
+
+    /// Format of the plan.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Parse the plan into a tree of nodes. Returns `None` unless the plan is formatted as JSON.
+    pub fn tree(&self) -> Option<Plan> {
+        #[derive(Deserialize)]
+        struct Output {
+            #[serde(rename = "Plan")]
+            plan: Plan,
+        }
+
+        if self.format != Format::Json {
+            return None;
+        }
+
+        serde_json::from_str::<Vec<Output>>(&self.plan)
+            .ok()?
+            .pop()
+            .map(|output| output.plan)
+    }
+}
+
+/// A node of the query plan, with the nodes it gets its rows from.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct Plan {
+    /// Type of the node, e.g. `"Seq Scan"`.
+    #[serde(rename = "Node Type")]
+    pub node_type: String,
+    /// Table scanned by the node, if any.
+    #[serde(rename = "Relation Name", default)]
+    pub relation_name: Option<String>,
+    /// Estimated cost of returning the first row.
+    #[serde(rename = "Startup Cost")]
+    pub startup_cost: f64,
+    /// Estimated cost of returning all rows.
+    #[serde(rename = "Total Cost")]
+    pub total_cost: f64,
+    /// Estimated number of rows returned.
+    #[serde(rename = "Plan Rows")]
+    pub plan_rows: f64,
+    /// Actual time to return all rows in milliseconds, if the query was analyzed.
+    #[serde(rename = "Actual Total Time", default)]
+    pub actual_total_time: Option<f64>,
+    /// Actual number of rows returned, if the query was analyzed.
+    #[serde(rename = "Actual Rows", default)]
+    pub actual_rows: Option<f64>,
+    /// Child nodes.
+    #[serde(rename = "Plans", default)]
+    pub plans: Vec<Plan>,
+    /// All other properties of the node, e.g. `"Filter"`.
+    #[serde(flatten)]
+    pub details: serde_json::Map<String, serde_json::Value>,
 }
 
 impl Model for Explain {
@@ -36,7 +200,66 @@ impl std::fmt::Display for Explain {
 
 impl FromRow for Explain {
     fn from_row(row: tokio_postgres::Row) -> Result<Self, Error> {
-        let plan = row.try_get(0)?;
-        Ok(Self { plan })
+        if row.columns().first().map(|column| column.type_()) == Some(&Type::JSON) {
+            let plan: serde_json::Value = row.try_get(0)?;
+            Ok(Self {
+                plan: plan.to_string(),
+                format: Format::Json,
+            })
+        } else {
+            let plan = row.try_get(0)?;
+            Ok(Self {
+                plan,
+                format: Format::Text,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_explain_options() {
+        assert_eq!(ExplainOptions::new().to_string(), "EXPLAIN");
+        assert_eq!(
+            ExplainOptions::new().analyze().to_string(),
+            "EXPLAIN (ANALYZE, BUFFERS)"
+        );
+        assert_eq!(
+            ExplainOptions::new().format(Format::Json).to_string(),
+            "EXPLAIN (FORMAT JSON)"
+        );
+    }
+
+    #[test]
+    fn test_tree() {
+        let explain = Explain {
+            plan: r#"[{"Plan": {"Node Type": "Hash Join", "Startup Cost": 1.5, "Total Cost": 10.25, "Plan Rows": 100,
+                "Hash Cond": "(orders.user_id = users.id)",
+                "Plans": [{"Node Type": "Seq Scan", "Relation Name": "orders", "Startup Cost": 0.0, "Total Cost": 5.0,
+                    "Plan Rows": 100, "Actual Total Time": 0.05, "Actual Rows": 98}]}}]"#
+                .into(),
+            format: Format::Json,
+        };
+
+        let tree = explain.tree().unwrap();
+        assert_eq!(tree.node_type, "Hash Join");
+        assert_eq!(tree.total_cost, 10.25);
+        assert_eq!(tree.actual_rows, None);
+        assert_eq!(
+            tree.details.get("Hash Cond"),
+            Some(&serde_json::json!("(orders.user_id = users.id)"))
+        );
+        assert_eq!(tree.plans.len(), 1);
+        assert_eq!(tree.plans[0].relation_name.as_deref(), Some("orders"));
+        assert_eq!(tree.plans[0].actual_rows, Some(98.0));
+
+        let explain = Explain {
+            format: Format::Text,
+            ..explain
+        };
+        assert!(explain.tree().is_none());
     }
 }
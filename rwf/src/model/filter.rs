@@ -1,5 +1,5 @@
 //! Implements the `WHERE` clause for `SELECT`, `UPDATE`, and `DELETE` statements.
-use super::{Column, ToSql, ToValue, Value};
+use super::{Column, Exists, Select, ToSql, ToValue, Value};
 
 /// The WHERE clause of a SQL query.
 #[derive(Debug, Default, Clone)]
@@ -11,6 +11,8 @@ pub struct WhereClause {
 enum Comparison {
     /// x = 1
     Equal((Column, Value)),
+    /// LOWER(x) = LOWER('a')
+    EqualCi((Column, Value)),
     /// x IN (1, 2, 3)
     In((Column, Value)),
     /// X NOT IN (1, 2, 3)
@@ -27,22 +29,47 @@ enum Comparison {
     GreaterEqualThan((Column, Value)),
     /// x <= 1
     LesserEqualThan((Column, Value)),
+    /// x #>> '{a,b}' = '1'
+    JsonPath((Column, Vec<String>, Value)),
+    /// 1 = ANY(x)
+    Contains((Column, Value)),
+    /// x IN (SELECT ...)
+    InSubquery((Column, Box<Select<Exists>>)),
+    /// x NOT IN (SELECT ...)
+    NotInSubquery((Column, Box<Select<Exists>>)),
+    /// EXISTS (SELECT ...)
+    Exists(Box<Select<Exists>>),
+    /// NOT EXISTS (SELECT ...)
+    NotExists(Box<Select<Exists>>),
 }
 
 impl Comparison {
-    fn placeholder(&self) -> bool {
+    fn placeholders(&self) -> usize {
         use Comparison::*;
 
-        match self {
-            Equal((_, v)) => v.placeholder(),
-            In((_, v)) => v.placeholder(),
-            NotIn((_, v)) => v.placeholder(),
-            NotEqual((_, v)) => v.placeholder(),
-            GreaterThan((_, v)) => v.placeholder(),
-            LesserThan((_, v)) => v.placeholder(),
-            GreaterEqualThan((_, v)) => v.placeholder(),
-            LesserEqualThan((_, v)) => v.placeholder(),
-            _ => false,
+        let value = match self {
+            Equal((_, v)) => v,
+            EqualCi((_, v)) => v,
+            In((_, v)) => v,
+            NotIn((_, v)) => v,
+            NotEqual((_, v)) => v,
+            GreaterThan((_, v)) => v,
+            LesserThan((_, v)) => v,
+            GreaterEqualThan((_, v)) => v,
+            LesserEqualThan((_, v)) => v,
+            JsonPath((_, _, v)) => v,
+            Contains((_, v)) => v,
+            Filter(filter) => return filter.placeholders(),
+            InSubquery((_, select))
+            | NotInSubquery((_, select))
+            | Exists(select)
+            | NotExists(select) => return select.scoped_where_clause().placeholders(),
+        };
+
+        match value {
+            // BETWEEN $1 AND $2
+            Value::Range((start, end)) => start.placeholder() as usize + end.placeholder() as usize,
+            value => value.placeholder() as usize,
         }
     }
 }
@@ -55,15 +82,25 @@ impl ToSql for Comparison {
             Equal((a, b)) => {
                 if b.is_null() {
                     format!("{} IS NULL", a.to_sql())
+                } else if let Value::Range(_) = b {
+                    format!("{} {}", a.to_sql(), b.to_sql())
                 } else {
                     format!("{} = {}", a.to_sql(), b.to_sql())
                 }
             }
+            EqualCi((column, value)) => {
+                format!("LOWER({}) = LOWER({})", column.to_sql(), value.to_sql())
+            }
+            // An empty list matches no rows, and none of its values equal the column.
+            In((_, Value::List(list))) if list.is_empty() => "false".to_string(),
+            NotIn((_, Value::List(list))) if list.is_empty() => "true".to_string(),
             In((column, value)) => format!("{} = ANY({})", column.to_sql(), value.to_sql()),
             NotIn((column, value)) => format!("{} <> ANY({})", column.to_sql(), value.to_sql()),
             NotEqual((column, value)) => {
                 if value.is_null() {
                     format!("{} IS NOT NULL", column.to_sql())
+                } else if let Value::Range(_) = value {
+                    format!("{} NOT {}", column.to_sql(), value.to_sql())
                 } else {
                     format!("{} <> {}", column.to_sql(), value.to_sql())
                 }
@@ -77,10 +114,73 @@ impl ToSql for Comparison {
             LesserEqualThan((column, value)) => {
                 format!("{} <= {}", column.to_sql(), value.to_sql())
             }
+            Contains((column, value)) => format!("{} = ANY({})", value.to_sql(), column.to_sql()),
+            InSubquery((column, select)) => {
+                format!("{} IN ({})", column.to_sql(), select.to_sql())
+            }
+            NotInSubquery((column, select)) => {
+                format!("{} NOT IN ({})", column.to_sql(), select.to_sql())
+            }
+            Exists(select) => format!("EXISTS ({})", select.to_sql()),
+            NotExists(select) => format!("NOT EXISTS ({})", select.to_sql()),
+            JsonPath((column, path, value)) => {
+                let path = format!(
+                    "{{{}}}",
+                    path.iter()
+                        .map(|key| json_path_key(key))
+                        .collect::<Vec<_>>()
+                        .join(",")
+                );
+                let path = path.replace('\'', "''");
+
+                if value.is_null() {
+                    format!("{} #>> '{}' IS NULL", column.to_sql(), path)
+                } else {
+                    format!("{} #>> '{}' = {}", column.to_sql(), path, value.to_sql())
+                }
+            }
+        }
+    }
+}
+
+impl Comparison {
+    /// Shift the numbers of all placeholders, e.g. `$1` becomes `$3` with an offset of 2.
+    fn offset_placeholders(&mut self, offset: i32) {
+        use Comparison::*;
+
+        match self {
+            Equal((_, v))
+            | EqualCi((_, v))
+            | In((_, v))
+            | NotIn((_, v))
+            | NotEqual((_, v))
+            | GreaterThan((_, v))
+            | LesserThan((_, v))
+            | GreaterEqualThan((_, v))
+            | LesserEqualThan((_, v))
+            | JsonPath((_, _, v))
+            | Contains((_, v)) => offset_placeholder(v, offset),
+            Filter(filter) => filter.offset_placeholders(offset),
+            InSubquery((_, select))
+            | NotInSubquery((_, select))
+            | Exists(select)
+            | NotExists(select) => select.where_clause.offset_placeholders(offset),
         }
     }
 }
 
+fn offset_placeholder(value: &mut Value, offset: i32) {
+    match value {
+        Value::Placeholder(id) => *id += offset,
+        Value::Record(value) => offset_placeholder(value, offset),
+        Value::Range((start, end)) => {
+            offset_placeholder(start, offset);
+            offset_placeholder(end, offset);
+        }
+        _ => (),
+    }
+}
+
 impl WhereClause {
     /// Add predicates to the WHERE clause using OR operator.
     pub fn or(&mut self, filter: Filter) {
@@ -92,6 +192,20 @@ impl WhereClause {
         self.filter = self.filter.and(filter);
     }
 
+    /// Add the filter as a parenthesized group, using the AND operator, e.g.
+    /// (x = 1) "group" (y = 2 OR z = 3) becomes (x = 1 AND (y = 2 OR z = 3)).
+    pub fn group(&mut self, filter: Filter) {
+        if filter.is_empty() {
+            return;
+        }
+
+        if self.filter.op == JoinOp::And {
+            self.filter.clauses.push(Comparison::Filter(filter));
+        } else {
+            self.and(filter);
+        }
+    }
+
     /// Add a single predicate to the WHERE clause, using the AND operator.
     pub fn add(&mut self, column: Column, value: impl ToValue) {
         self.filter.add(column, value);
@@ -108,6 +222,17 @@ impl WhereClause {
         self.filter = self.filter.concat(filter);
     }
 
+    /// Add predicates to the WHERE clause using AND operator, appending them to the
+    /// existing predicates if those are joined with AND too, e.g.
+    /// (x = 1 AND y = 2) and (z = 3) becomes (x = 1 AND y = 2 AND z = 3).
+    pub fn and_concat(&mut self, filter: Filter) {
+        if self.filter.op == filter.op {
+            self.concat(filter);
+        } else {
+            self.and(filter);
+        }
+    }
+
     /// Remove all predicates.
     pub fn clear(&mut self) {
         self.filter.clauses.clear();
@@ -125,6 +250,12 @@ impl WhereClause {
     pub fn placeholders(&self) -> usize {
         self.filter.placeholders()
     }
+
+    /// Shift the numbers of all placeholders by the offset, e.g. to merge
+    /// the clause into a query which already has placeholders.
+    pub fn offset_placeholders(&mut self, offset: i32) {
+        self.filter.offset_placeholders(offset);
+    }
 }
 
 impl ToSql for WhereClause {
@@ -219,6 +350,12 @@ impl Filter {
         }
     }
 
+    /// Add a predicate comparing the text column to the value, ignoring case.
+    pub fn equal_ci(&mut self, column: Column, value: impl ToValue) {
+        self.clauses
+            .push(Comparison::EqualCi((column, value.to_value())));
+    }
+
     pub fn gt(&mut self, column: Column, value: impl ToValue) {
         self.clauses
             .push(Comparison::GreaterThan((column, value.to_value())));
@@ -239,6 +376,44 @@ impl Filter {
             .push(Comparison::LesserEqualThan((column, value.to_value())));
     }
 
+    /// Add a predicate checking that the array column contains the value.
+    pub fn contains(&mut self, column: Column, value: impl ToValue) {
+        self.clauses
+            .push(Comparison::Contains((column, value.to_value())));
+    }
+
+    /// Add a predicate comparing the text value at the JSON path, e.g.
+    /// `"settings" #>> '{theme,color}' = $1`.
+    pub fn json_path(&mut self, column: Column, path: &[&str], value: impl ToValue) {
+        self.clauses.push(Comparison::JsonPath((
+            column,
+            path.iter().map(|key| key.to_string()).collect(),
+            value.to_value(),
+        )));
+    }
+
+    /// Add a predicate checking that the column is one of the values returned by the subquery.
+    pub fn in_subquery(&mut self, column: Column, select: Select<Exists>) {
+        self.clauses
+            .push(Comparison::InSubquery((column, Box::new(select))));
+    }
+
+    /// Add a predicate checking that the column is none of the values returned by the subquery.
+    pub fn not_in_subquery(&mut self, column: Column, select: Select<Exists>) {
+        self.clauses
+            .push(Comparison::NotInSubquery((column, Box::new(select))));
+    }
+
+    /// Add a predicate checking that the subquery returns at least one row.
+    pub fn exists(&mut self, select: Select<Exists>) {
+        self.clauses.push(Comparison::Exists(Box::new(select)));
+    }
+
+    /// Add a predicate checking that the subquery returns no rows.
+    pub fn not_exists(&mut self, select: Select<Exists>) {
+        self.clauses.push(Comparison::NotExists(Box::new(select)));
+    }
+
     /// Append all predicates of the filter into the current filter.
     pub fn concat(&self, filter: Filter) -> Self {
         // Concatenating filters with different operations, e.g. AND and OR
@@ -256,19 +431,13 @@ impl Filter {
     }
 
     pub fn placeholders(&self) -> usize {
-        self.clauses
-            .iter()
-            .map(|op| match op {
-                Comparison::Filter(filter) => filter.placeholders(),
-                op => {
-                    if op.placeholder() {
-                        1
-                    } else {
-                        0
-                    }
-                }
-            })
-            .sum()
+        self.clauses.iter().map(|op| op.placeholders()).sum()
+    }
+
+    fn offset_placeholders(&mut self, offset: i32) {
+        for clause in &mut self.clauses {
+            clause.offset_placeholders(offset);
+        }
     }
 
     pub fn insert_columns(&self) -> (Vec<Column>, Vec<Value>) {
@@ -313,6 +482,21 @@ impl ToSql for Filter {
     }
 }
 
+/// Quote a key of a JSON path if it contains characters
+/// special to Postgres arrays.
+fn json_path_key(key: &str) -> String {
+    if !key.is_empty()
+        && !key.eq_ignore_ascii_case("null")
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        key.to_string()
+    } else {
+        format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::super::{Column, Value};
@@ -381,4 +565,51 @@ mod test {
             r#"("table"."column_a" = 5 OR "table"."column_a" <> 125) OR ("table"."column_b" = 42 AND "table"."column_b" <> 56)"#
         );
     }
+
+    #[test]
+    fn test_json_path() {
+        let mut filter = Filter::default();
+        filter.json_path(
+            Column::new("users", "settings"),
+            &["theme", "color"],
+            Value::Placeholder(1),
+        );
+        filter.json_path(
+            Column::new("users", "settings"),
+            &["it's", "a,b"],
+            Value::Null,
+        );
+
+        assert_eq!(
+            filter.to_sql(),
+            r#""users"."settings" #>> '{theme,color}' = $1 AND "users"."settings" #>> '{"it''s","a,b"}' IS NULL"#
+        );
+        assert_eq!(filter.placeholders(), 1);
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut filter = Filter::default();
+        filter.contains(Column::new("posts", "tags"), Value::Placeholder(1));
+
+        assert_eq!(filter.to_sql(), r#"$1 = ANY("posts"."tags")"#);
+        assert_eq!(filter.placeholders(), 1);
+    }
+
+    #[test]
+    fn test_range() {
+        let mut filter = Filter::default();
+        let range = Value::Range((
+            Box::new(Value::Placeholder(1)),
+            Box::new(Value::Placeholder(2)),
+        ));
+        filter.add(Column::new("users", "created_at"), range.clone());
+        filter.add_not(Column::new("users", "id"), range);
+
+        assert_eq!(
+            filter.to_sql(),
+            r#""users"."created_at" BETWEEN $1 AND $2 AND "users"."id" NOT BETWEEN $1 AND $2"#
+        );
+        assert_eq!(filter.placeholders(), 4);
+    }
 }
@@ -1,36 +1,53 @@
 //! Implements the `SELECT` query.
-use super::{Column, Escape, FromRow, Model, Placeholders, ToColumn, ToSql, ToValue};
+use super::{
+    escape::quote_table, Column, FromRow, Model, Placeholders, ToColumn, ToSql, ToValue,
+    ValidationErrors,
+};
 use std::marker::PhantomData;
 
 #[derive(Debug, Clone)]
 pub struct Insert<T> {
+    schema: Option<String>,
     table_name: String,
     columns: Vec<Column>,
     pub placeholders: Placeholders,
     marker: PhantomData<T>,
     no_conflict: bool,
     unique_by: Vec<Column>,
+    errors: Option<ValidationErrors>,
+    callbacks: bool,
 }
 
 impl<T: Model> Insert<T> {
     pub fn new(model: T) -> Self {
-        let columns = T::column_names()
+        let mut columns = T::column_names()
             .into_iter()
             .map(|column| Column::name(column))
-            .collect();
-        let values = model.values();
+            .collect::<Vec<_>>();
+        let mut values = model.values();
+
+        // Primary keys generated by the application, e.g. UUIDs.
+        let id = model.id();
+        if !id.is_null() {
+            columns.push(Column::name(T::primary_key()));
+            values.push(id);
+        }
+
         let mut placeholders = Placeholders::new();
         for value in values {
             placeholders.add(&value);
         }
 
         Self {
+            schema: T::schema().map(|schema| schema.to_string()),
             table_name: T::table_name().to_string(),
             placeholders,
             columns,
             marker: PhantomData,
             no_conflict: false,
             unique_by: vec![],
+            errors: None,
+            callbacks: true,
         }
     }
 
@@ -42,12 +59,15 @@ impl<T: Model> Insert<T> {
         }
 
         Insert {
+            schema: T::schema().map(|schema| schema.to_string()),
             table_name: T::table_name().to_string(),
             columns: columns.iter().map(|c| c.to_column().unqualify()).collect(),
             placeholders,
             marker: PhantomData,
             no_conflict: false,
             unique_by: vec![],
+            errors: None,
+            callbacks: false,
         }
     }
 
@@ -60,6 +80,22 @@ impl<T: Model> Insert<T> {
         self.unique_by = columns.iter().map(|c| c.to_column()).collect();
         self
     }
+
+    /// The insert saves a model, so its callbacks are called after it's executed.
+    pub fn callbacks(&self) -> bool {
+        self.callbacks
+    }
+
+    /// The model failed validation, so the query must not be executed.
+    pub fn invalid(mut self, errors: ValidationErrors) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// Validation errors of the model, if it's invalid.
+    pub fn validation_errors(&self) -> Option<&ValidationErrors> {
+        self.errors.as_ref()
+    }
 }
 
 impl<T: FromRow> ToSql for Insert<T> {
@@ -103,8 +139,8 @@ impl<T: FromRow> ToSql for Insert<T> {
         };
 
         format!(
-            r#"INSERT INTO "{}" ({}) VALUES ({}) {}RETURNING *"#,
-            self.table_name.escape(),
+            r#"INSERT INTO {} ({}) VALUES ({}) {}RETURNING *"#,
+            quote_table(self.schema.as_deref(), &self.table_name),
             columns,
             placeholders,
             no_conflict,
@@ -1,5 +1,5 @@
 //! Implements joining tables in a `SELECT` query.
-use super::{Column, Escape, Model, ToSql};
+use super::{escape::quote_table, Column, Escape, Model, ToSql};
 use std::marker::PhantomData;
 
 /// Type of relationship between models.
@@ -51,9 +51,11 @@ pub trait Association<T: Model>: Model {
                 let foreign_column = Column::new(Self::table_name(), T::foreign_key());
                 Join {
                     kind: JoinKind::Inner,
+                    schema: Self::schema().map(|schema| schema.to_string()),
                     table_name,
                     table_column,
                     foreign_column,
+                    alias: None,
                 }
             }
 
@@ -65,15 +67,88 @@ pub trait Association<T: Model>: Model {
                 let foreign_column = Column::new(Self::table_name(), Self::primary_key());
                 Join {
                     kind: JoinKind::Inner,
+                    schema: Self::schema().map(|schema| schema.to_string()),
                     table_name,
                     table_column,
                     foreign_column,
+                    alias: None,
                 }
             }
         }
     }
 }
 
+/// Declare a many-to-many relationship between model `T` and `Self` through
+/// the join table of model `Through`.
+///
+/// The join table is expected to have a foreign key to both tables, e.g. `user_roles.user_id`
+/// and `user_roles.role_id`. The names default to the foreign keys of `T` and `Self`
+/// and can be overridden.
+///
+/// # Example
+///
+/// Declare a many-to-many relationship between `User` and `Role`:
+///
+/// ```ignore
+/// #[derive(Clone, macros::Model)]
+/// struct UserRole {
+///     id: Option<i64>,
+///     user_id: i64,
+///     role_id: i64,
+/// }
+///
+/// impl HasManyThrough<User, UserRole> for Role {}
+///
+/// let roles = User::related_through::<Role, UserRole>(&users);
+/// ```
+pub trait HasManyThrough<T: Model, Through: Model>: Model {
+    /// Column in the join table referencing `T`.
+    fn source_key() -> &'static str {
+        T::foreign_key()
+    }
+
+    /// Column in the join table referencing `Self`.
+    fn target_key() -> &'static str {
+        Self::foreign_key()
+    }
+
+    /// Join the join table and this table to `T`.
+    fn construct_joins() -> Joins {
+        // INNER JOIN "user_roles" ON "users"."id" = "user_roles"."user_id"
+        // INNER JOIN "roles" ON "user_roles"."role_id" = "roles"."id"
+        Joins::new()
+            .add(Join {
+                kind: JoinKind::Inner,
+                schema: Through::schema().map(|schema| schema.to_string()),
+                table_name: Through::table_name().to_string(),
+                table_column: Column::new(T::table_name(), T::primary_key()),
+                foreign_column: Column::new(Through::table_name(), Self::source_key()),
+                alias: None,
+            })
+            .add(Join {
+                kind: JoinKind::Inner,
+                schema: Self::schema().map(|schema| schema.to_string()),
+                table_name: Self::table_name().to_string(),
+                table_column: Column::new(Through::table_name(), Self::target_key()),
+                foreign_column: Column::new(Self::table_name(), Self::primary_key()),
+                alias: None,
+            })
+    }
+
+    /// Join the join table to this table.
+    fn construct_through_join() -> Join {
+        // INNER JOIN "user_roles" ON "roles"."id" = "user_roles"."role_id"
+        Join {
+            kind: JoinKind::Inner,
+            schema: Through::schema().map(|schema| schema.to_string()),
+            table_name: Through::table_name().to_string(),
+            table_column: Column::new(Self::table_name(), Self::primary_key()),
+            foreign_column: Column::new(Through::table_name(), Self::target_key()),
+            alias: None,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum JoinKind {
     Inner,
@@ -95,17 +170,75 @@ impl ToString for JoinKind {
 #[derive(Debug, Clone)]
 pub struct Join {
     kind: JoinKind,
+    schema: Option<String>,
     table_name: String,
     table_column: Column,
     foreign_column: Column,
+    alias: Option<String>,
+}
+
+impl Join {
+    /// Join the parent record of a self-referential relationship, e.g. a comment
+    /// and the comment it replies to, with the joined table renamed to the alias.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // INNER JOIN "comments" AS "parent" ON "comments"."parent_id" = "parent"."id"
+    /// let join = Join::parent::<Comment>("parent_id", "parent");
+    /// ```
+    pub fn parent<T: Model>(foreign_key: &str, alias: &str) -> Self {
+        Join {
+            kind: JoinKind::Inner,
+            schema: T::schema().map(|schema| schema.to_string()),
+            table_name: T::table_name().to_string(),
+            table_column: Column::new(T::table_name(), foreign_key),
+            foreign_column: Column::new(alias, T::primary_key()),
+            alias: Some(alias.to_string()),
+        }
+    }
+
+    /// Join the child records of a self-referential relationship, e.g. a comment
+    /// and its replies, with the joined table renamed to the alias.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// // INNER JOIN "comments" AS "replies" ON "comments"."id" = "replies"."parent_id"
+    /// let join = Join::children::<Comment>("parent_id", "replies");
+    /// ```
+    pub fn children<T: Model>(foreign_key: &str, alias: &str) -> Self {
+        Join {
+            kind: JoinKind::Inner,
+            schema: T::schema().map(|schema| schema.to_string()),
+            table_name: T::table_name().to_string(),
+            table_column: Column::new(T::table_name(), T::primary_key()),
+            foreign_column: Column::new(alias, foreign_key),
+            alias: Some(alias.to_string()),
+        }
+    }
+
+    /// Rename the joined table. Columns of the joined table need to use the alias
+    /// as their table name, e.g. `Column::new("parent", "id")`.
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.foreign_column = self.foreign_column.qualify(alias);
+        self.alias = Some(alias.to_string());
+        self
+    }
 }
 
 impl ToSql for Join {
     fn to_sql(&self) -> String {
+        let alias = match self.alias {
+            Some(ref alias) => format!(r#" AS "{}""#, alias.escape_identifier()),
+            None => "".to_string(),
+        };
+
         format!(
-            r#"{} "{}" ON {} = {}"#,
+            r#"{} {}{} ON {} = {}"#,
             self.kind.to_string(),
-            self.table_name.escape(),
+            quote_table(self.schema.as_deref(), &self.table_name),
+            alias,
             self.table_column.to_sql(),
             self.foreign_column.to_sql(),
         )
@@ -130,6 +263,14 @@ impl Joins {
     pub fn joins(&self) -> &[Join] {
         &self.joins
     }
+
+    /// Use the schema for all joined tables.
+    pub fn schema(mut self, schema: &str) -> Self {
+        for join in &mut self.joins {
+            join.schema = Some(schema.to_string());
+        }
+        self
+    }
 }
 
 impl ToSql for Joins {
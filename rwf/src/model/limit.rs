@@ -34,4 +34,15 @@ impl Limit {
         self.offset = Some(offset);
         self
     }
+
+    /// Is the number of rows limited?
+    pub fn limited(&self) -> bool {
+        self.limit.is_some()
+    }
+
+    /// Return at most one row, keeping the offset.
+    pub fn one(mut self) -> Self {
+        self.limit = Some(self.limit.unwrap_or(1).min(1));
+        self
+    }
 }
@@ -1,30 +1,119 @@
-//! Implements `FOR UPDATE` SQL locking primitive.
+//! Implements `FOR UPDATE` and `FOR SHARE` SQL locking primitives.
 use super::ToSql;
 
+/// Type of row lock.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Mode {
+    /// `FOR UPDATE`, an exclusive lock.
+    #[default]
+    Update,
+    /// `FOR SHARE`, a lock which only prevents concurrent writes.
+    Share,
+}
+
+/// What to do when rows are already locked by someone else.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Wait {
+    /// Wait until the lock is released.
+    #[default]
+    Block,
+    /// `SKIP LOCKED`, don't return locked rows.
+    SkipLocked,
+    /// `NOWAIT`, return an error.
+    Fail,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Lock {
     lock: bool,
-    skip_locked: bool,
+    mode: Mode,
+    wait: Wait,
 }
 
 impl Lock {
+    /// Lock rows using `FOR UPDATE`.
     pub fn new() -> Self {
         Self {
             lock: true,
-            skip_locked: false,
+            mode: Mode::Update,
+            wait: Wait::Block,
         }
     }
 
+    /// Lock rows using `FOR SHARE`.
+    pub fn share() -> Self {
+        Self {
+            mode: Mode::Share,
+            ..Self::new()
+        }
+    }
+
+    /// Skip rows locked by someone else. Replaces [`Lock::nowait`], since both can't be used together.
     pub fn skip_locked(mut self) -> Self {
-        self.skip_locked = true;
+        self.wait = Wait::SkipLocked;
+        self
+    }
+
+    /// Return an error instead of waiting for rows locked by someone else.
+    /// Replaces [`Lock::skip_locked`], since both can't be used together.
+    pub fn nowait(mut self) -> Self {
+        self.wait = Wait::Fail;
         self
     }
+
+    pub fn locked(&self) -> bool {
+        self.lock
+    }
 }
 
 impl ToSql for Lock {
     fn to_sql(&self) -> String {
-        let lock = if self.lock { " FOR UPDATE" } else { "" };
-        let skip_locked = if self.skip_locked { " SKIP LOCKED" } else { "" };
-        format!("{}{}", lock, skip_locked)
+        if !self.lock {
+            return "".to_string();
+        }
+
+        let mode = match self.mode {
+            Mode::Update => " FOR UPDATE",
+            Mode::Share => " FOR SHARE",
+        };
+        let wait = match self.wait {
+            Wait::Block => "",
+            Wait::SkipLocked => " SKIP LOCKED",
+            Wait::Fail => " NOWAIT",
+        };
+
+        format!("{}{}", mode, wait)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lock() {
+        assert_eq!(Lock::default().to_sql(), "");
+        assert_eq!(Lock::new().to_sql(), " FOR UPDATE");
+        assert_eq!(
+            Lock::new().skip_locked().to_sql(),
+            " FOR UPDATE SKIP LOCKED"
+        );
+        assert_eq!(Lock::new().nowait().to_sql(), " FOR UPDATE NOWAIT");
+        assert_eq!(Lock::share().to_sql(), " FOR SHARE");
+        assert_eq!(
+            Lock::share().skip_locked().to_sql(),
+            " FOR SHARE SKIP LOCKED"
+        );
+        assert_eq!(Lock::share().nowait().to_sql(), " FOR SHARE NOWAIT");
+
+        // NOWAIT and SKIP LOCKED are mutually exclusive, the last one wins.
+        assert_eq!(
+            Lock::new().skip_locked().nowait().to_sql(),
+            " FOR UPDATE NOWAIT"
+        );
+        assert_eq!(
+            Lock::share().nowait().skip_locked().to_sql(),
+            " FOR SHARE SKIP LOCKED"
+        );
     }
 }
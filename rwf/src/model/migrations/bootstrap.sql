@@ -34,6 +34,16 @@ CREATE INDEX IF NOT EXISTS rwf_jobs_runnin_idx ON rwf_jobs USING btree(start_aft
 
 CREATE INDEX IF NOT EXISTS rwf_jobs_name_completed_at_idx ON rwf_jobs USING btree(name, completed_at);
 
+ALTER TABLE rwf_jobs ADD COLUMN IF NOT EXISTS queue VARCHAR NOT NULL DEFAULT 'default';
+
+ALTER TABLE rwf_jobs ADD COLUMN IF NOT EXISTS priority INT NOT NULL DEFAULT 0;
+
+-- Pending jobs by queue, in the order they are fetched by workers
+CREATE INDEX IF NOT EXISTS rwf_jobs_queue_priority_idx ON rwf_jobs USING btree(queue, priority DESC, start_after, id) WHERE
+    completed_at IS NULL
+    AND started_at IS NULL
+    AND attempts < retries;
+
 CREATE TABLE IF NOT EXISTS rwf_requests (
     id BIGSERIAL PRIMARY KEY,
     path VARCHAR NOT NULL,
@@ -1,7 +1,7 @@
 //! Implements database migrations, a deterministic mechanism to change the database schema.
 pub mod model;
 use crate::config::get_config;
-use crate::model::{get_connection, get_pool, start_transaction, Model};
+use crate::model::{get_pool, Model, Pool, Value};
 use model::Migration;
 
 use super::Error;
@@ -20,6 +20,8 @@ use tracing::{error, info};
 /// may not be applied yet.
 pub struct Migrations {
     migrations: Vec<Migration>,
+    root: Option<PathBuf>,
+    pool: Pool,
 }
 
 static RE: Lazy<Regex> =
@@ -112,11 +114,21 @@ impl Migrations {
         }
     }
 
-    async fn load() -> Result<Self, Error> {
-        let mut conn = get_connection().await?;
-        let migrations = Migration::all().fetch_all(&mut conn).await?;
+    /// Reload the given migrations from the database. Migrations recorded in the
+    /// `"rwf_migrations"` table but not found in the folder are not included.
+    async fn load(root: Option<PathBuf>, pool: Pool, ids: &[Value]) -> Result<Self, Error> {
+        let mut conn = pool.get().await?;
+        let migrations = Migration::all()
+            .filter("id", ids)
+            .order("version")
+            .fetch_all(&mut conn)
+            .await?;
 
-        Ok(Self { migrations })
+        Ok(Self {
+            migrations,
+            root,
+            pool,
+        })
     }
 
     /// Read the `"migrations"` folder and sync all migrations
@@ -124,7 +136,26 @@ impl Migrations {
     /// actually apply the migrations, only makes sure the entries in the folder
     /// match the database table.
     pub async fn sync() -> Result<Self, Error> {
-        let checks = if let Ok(root_path) = Self::root_path() {
+        Self::sync_root(Self::root_path().ok(), get_pool()).await
+    }
+
+    /// Same as [`Migrations::sync`], except migrations are read from the specified folder
+    /// instead of `"migrations"` in the current working directory.
+    pub async fn sync_from(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+
+        if !path.is_dir() {
+            return Err(Error::MigrationError(format!(
+                r#""{}" is not a directory"#,
+                path.display()
+            )));
+        }
+
+        Self::sync_root(Some(path.to_owned()), get_pool()).await
+    }
+
+    async fn sync_root(root: Option<PathBuf>, pool: Pool) -> Result<Self, Error> {
+        let checks = if let Some(root_path) = root.clone() {
             let mut checks = HashMap::new();
 
             let mut dir_entries = read_dir(root_path).await?;
@@ -157,7 +188,7 @@ impl Migrations {
 
         let log_queries = get_config().general.log_queries;
 
-        let mut conn = start_transaction().await?;
+        let mut conn = pool.transaction().await?;
 
         // Create some necessary tables.
         // TODO: Move jobs to an internal migration.
@@ -199,7 +230,11 @@ impl Migrations {
 
         conn.commit().await?;
 
-        Ok(Self { migrations })
+        Ok(Self {
+            migrations,
+            root,
+            pool,
+        })
     }
 
     /// Apply the migrations, making changes to the database schema.
@@ -207,6 +242,12 @@ impl Migrations {
     /// The direction argument controllers if we are applying or reverting the migrations. The version
     /// argument means to perform this action up to and including that version.
     pub async fn apply(self, direction: Direction, version: Option<i64>) -> Result<Self, Error> {
+        let ids = self
+            .migrations
+            .iter()
+            .map(|migration| migration.id())
+            .collect::<Vec<_>>();
+
         let migrations = match direction {
             Direction::Up => self.migrations.into_iter().collect::<Vec<_>>(),
             Direction::Down => self.migrations.into_iter().rev().collect::<Vec<_>>(),
@@ -240,7 +281,11 @@ impl Migrations {
                 migration.name()
             );
 
-            let path = Self::root_path()?.join(migration.path(direction));
+            let root = match self.root {
+                Some(ref root) => root.clone(),
+                None => Self::root_path()?,
+            };
+            let path = root.join(migration.path(direction));
 
             let sql = read_to_string(path).await?;
             let queries = sql
@@ -249,7 +294,7 @@ impl Migrations {
                 .map(|q| q.trim().to_string())
                 .collect::<Vec<_>>();
 
-            let pool = get_pool();
+            let pool = self.pool.clone();
             let log_queries = get_config().general.log_queries;
 
             // Execute the migration in a transaction.
@@ -291,7 +336,26 @@ impl Migrations {
             .await?;
         }
 
-        Self::load().await
+        Self::load(self.root, self.pool, &ids).await
+    }
+
+    /// Revert the migration which was applied last, i.e. the one with the highest version.
+    /// Only migrations found in the folder are considered.
+    pub async fn revert_last(self) -> Result<Self, Error> {
+        let last = self
+            .migrations
+            .iter()
+            .rev()
+            .find(|migration| migration.applied_at.is_some())
+            .map(|migration| migration.version);
+
+        match last {
+            Some(version) => self.apply(Direction::Down, Some(version)).await,
+            None => {
+                info!("no migrations to revert");
+                Ok(self)
+            }
+        }
     }
 
     /// Get a list of all migrations currently found in the `"migrations"` folder.
@@ -304,6 +368,22 @@ impl Migrations {
         Migrations::sync().await?.apply(Direction::Up, None).await
     }
 
+    /// Apply all pending migrations, in order of their versions. Each migration
+    /// is executed in its own transaction, and is recorded as applied only if it succeeds.
+    /// Same as [`Migrations::migrate`].
+    pub async fn run() -> Result<Migrations, Error> {
+        Self::migrate().await
+    }
+
+    /// Revert the last applied migration, i.e. the one with the highest version. Other migrations
+    /// are not changed.
+    ///
+    /// This is different from the free function [`rollback`](fn@rollback), which reverts
+    /// every migration, like [`Migrations::flush`].
+    pub async fn rollback() -> Result<Migrations, Error> {
+        Migrations::sync().await?.revert_last().await
+    }
+
     /// Execute all migrations in the down direction. **This will effectively
     /// destroy all tables and data in your database.**
     pub async fn flush() -> Result<Migrations, Error> {
@@ -318,6 +398,8 @@ pub async fn migrate() -> Result<Migrations, Error> {
 
 /// Execute all migrations in the down direction. **This will effectively
 /// destroy all tables and data in your database.**
+///
+/// To revert only the last applied migration, use [`Migrations::rollback`] instead.
 pub async fn rollback() -> Result<Migrations, Error> {
     Migrations::sync().await?.apply(Direction::Down, None).await
 }
@@ -326,6 +408,92 @@ pub async fn rollback() -> Result<Migrations, Error> {
 mod test {
     use super::*;
 
+    async fn execute(pool: &Pool, query: &str) -> Result<(), Error> {
+        pool.get().await?.client().execute(query, &[]).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_apply_and_revert() -> Result<(), Error> {
+        use crate::model::PoolConfig;
+        use tokio::fs::write;
+
+        let dir = tempdir::TempDir::new("migrations")?;
+        write(
+            dir.path().join("1_rwf_test_notes.up.sql"),
+            "CREATE TABLE rwf_test_notes (id BIGINT); CREATE INDEX ON rwf_test_notes (id);",
+        )
+        .await?;
+        write(
+            dir.path().join("1_rwf_test_notes.down.sql"),
+            "DROP TABLE rwf_test_notes;",
+        )
+        .await?;
+
+        // Run in a throwaway schema, so the database used by other tests isn't changed.
+        // The global pool can't be shared between tests anyway, since each one has its own runtime.
+        let url = get_config().database.database_url();
+        let url = format!(
+            "{}{}options=-c%20search_path%3Drwf_test_migrations",
+            url,
+            if url.contains('?') { "&" } else { "?" }
+        );
+        let pool = Pool::new(&url, PoolConfig::default());
+        execute(&pool, "DROP SCHEMA IF EXISTS rwf_test_migrations CASCADE").await?;
+        execute(&pool, "CREATE SCHEMA rwf_test_migrations").await?;
+
+        let sync = || Migrations::sync_root(Some(dir.path().to_owned()), pool.clone());
+
+        let table_exists = || async {
+            let conn = pool.get().await?;
+            let rows = conn
+                .client()
+                .query("SELECT to_regclass('rwf_test_notes')::text", &[])
+                .await?;
+            Ok::<_, Error>(rows[0].get::<_, Option<String>>(0).is_some())
+        };
+
+        let migrations = sync().await?;
+        assert_eq!(migrations.migrations().len(), 1);
+        assert!(migrations.migrations()[0].applied_at.is_none());
+        assert!(!table_exists().await?);
+
+        // Applied from another folder, with a higher version.
+        execute(
+            &pool,
+            "INSERT INTO rwf_migrations (version, name, applied_at) VALUES (2, 'rwf_test_other', NOW())",
+        )
+        .await?;
+
+        let migrations = migrations.apply(Direction::Up, None).await?;
+        assert!(table_exists().await?);
+        assert_eq!(migrations.migrations().len(), 1);
+        assert_eq!(migrations.migrations()[0].name, "rwf_test_notes");
+        assert!(migrations.migrations()[0].applied_at.is_some());
+
+        // Already applied, so nothing happens.
+        let migrations = sync().await?.apply(Direction::Up, None).await?;
+
+        let migrations = migrations.revert_last().await?;
+        assert!(!table_exists().await?);
+        assert!(migrations.migrations()[0].applied_at.is_none());
+
+        let other = pool
+            .get()
+            .await?
+            .client()
+            .query(
+                "SELECT applied_at IS NOT NULL FROM rwf_migrations WHERE name = 'rwf_test_other'",
+                &[],
+            )
+            .await?;
+        assert!(other[0].get::<_, bool>(0));
+
+        execute(&pool, "DROP SCHEMA rwf_test_migrations CASCADE").await?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_migration_file_names() {
         let file = MigrationFile::parse("1234_name_long.up.sql").expect("migration file");
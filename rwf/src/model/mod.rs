@@ -3,10 +3,13 @@
 //! See [documentation](https://levkk.github.io/rwf/models/) for detailed examples on how to use the ORM.
 use crate::colors::MaybeColorize;
 use crate::config::get_config;
+use crate::controller::RequestId;
 
 use pool::ToConnectionRequest;
+use std::collections::HashMap;
+use std::future::Future;
 use std::time::{Duration, Instant};
-use tracing::{error, info};
+use tracing::{error, Level};
 
 pub mod callbacks;
 pub mod column;
@@ -21,34 +24,47 @@ pub mod limit;
 pub mod lock;
 pub mod migrations;
 pub mod order_by;
+pub mod page;
 pub mod picked;
 pub mod placeholders;
 pub mod pool;
+pub mod preload;
 pub mod prelude;
 pub mod row;
+pub mod schema;
 pub mod select;
 pub mod update;
+pub mod validate;
 pub mod value;
+pub mod window;
 
+pub use callbacks::Callbacks;
 pub use column::{Column, Columns, ToColumn};
 pub use error::Error;
 pub use escape::Escape;
 pub use exists::Exists;
-pub use explain::Explain;
+pub use explain::{Explain, ExplainOptions};
 pub use filter::{Filter, WhereClause};
 pub use insert::Insert;
-pub use join::{Association, AssociationType, Join, Joined, Joins};
+pub use join::{Association, AssociationType, HasManyThrough, Join, Joined, Joins};
 pub use limit::Limit;
 pub use lock::Lock;
 pub use migrations::{migrate, rollback, Migrations};
-pub use order_by::{OrderBy, OrderColumn, ToOrderBy};
+pub use order_by::{Nulls, OrderBy, OrderColumn, ToOrderBy};
+pub use page::Page;
 pub use picked::Picked;
 pub use placeholders::Placeholders;
-pub use pool::{get_connection, get_pool, start_transaction, Connection, ConnectionGuard, Pool};
+pub use pool::{
+    get_connection, get_pool, start_transaction, Connection, ConnectionGuard, Pool, PoolConfig,
+    PoolMetrics,
+};
+pub use preload::{Loaded, Preload};
 pub use row::Row;
-pub use select::Select;
+pub use select::{Deleted, Select};
 pub use update::Update;
+pub use validate::{Validate, ValidationErrors};
 pub use value::{ToValue, Value};
+pub use window::Window;
 
 /// Convert a PostgreSQL row to a Rust struct. Type conversions are handled by `tokio_postgres`. This only
 /// creates a mapping between columns and struct fields.
@@ -228,6 +244,16 @@ pub enum Query<T: FromRow + ?Sized = Row> {
     Picked(Picked<T>),
 }
 
+impl<T: FromRow> Query<T> {
+    /// The `SELECT` query, for using it as a subquery.
+    fn into_select(self) -> Select<T> {
+        match self {
+            Query::Select(select) => select,
+            query => panic!("subquery must be a SELECT query: {}", query.to_sql()),
+        }
+    }
+}
+
 impl<T: FromRow> ToSql for Query<T> {
     fn to_sql(&self) -> String {
         use Query::*;
@@ -267,10 +293,68 @@ impl<T: Model> Query<T> {
     /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users""#);
     /// ```
     pub fn select(table_name: impl ToString) -> Self {
-        Query::Select(Select::new(
-            table_name.to_string().as_str(),
-            &T::primary_key(),
-        ))
+        let table_name = table_name.to_string();
+        let deleted = match T::soft_delete_column() {
+            Some(column) => Deleted::Exclude(Column::new(table_name.as_str(), column)),
+            None => Deleted::Include,
+        };
+
+        Query::Select(
+            Select::new(table_name.as_str(), &T::primary_key())
+                .table_schema(T::schema())
+                .deleted(deleted),
+        )
+    }
+
+    /// Use the schema for the table and all joined tables, instead of the schemas declared
+    /// by their models, e.g. to query the tables of a tenant.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// # }
+    /// let query = User::filter("email", "test@test.com").schema("tenant_1");
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "tenant_1"."users" WHERE "users"."email" = $1"#
+    /// );
+    /// ```
+    pub fn schema(self, schema: &str) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.schema(schema)),
+            _ => self,
+        }
+    }
+
+    /// Create a query from arbitrary SQL. The values are bound to the placeholders
+    /// in the query (`$1`, `$2`, etc.) in the same order when it's executed, so they don't
+    /// need to be escaped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::Query;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = Query::<User>::raw_with(
+    ///     "WITH recent AS (SELECT * FROM users ORDER BY id DESC LIMIT $1) SELECT * FROM recent WHERE email = $2",
+    ///     vec![5.to_value(), "alice@test.com".to_value()],
+    /// );
+    /// ```
+    pub fn raw_with(query: impl ToString, values: Vec<Value>) -> Self {
+        Query::Raw {
+            query: query.to_string(),
+            placeholders: values.into(),
+        }
     }
 
     /// Create a query that selects one row from the relation. The rows are not ordered and any row can be returned.
@@ -426,6 +510,160 @@ impl<T: Model> Query<T> {
         }
     }
 
+    /// Filter by rows where the column is `NULL`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// #[derive(Clone, macros::Model)]
+    /// struct User {
+    ///     id: Option<i64>,
+    ///     deleted_at: Option<OffsetDateTime>,
+    /// }
+    ///
+    /// let query = User::all().filter_null("deleted_at");
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE "users"."deleted_at" IS NULL"#
+    /// );
+    /// ```
+    pub fn filter_null(self, column: impl ToColumn) -> Self {
+        self.filter(column, Value::Null)
+    }
+
+    /// Filter by rows where the column is not `NULL`.
+    pub fn filter_not_null(self, column: impl ToColumn) -> Self {
+        self.not(column, Value::Null)
+    }
+
+    /// Filter by rows where the text column is equal to the value, ignoring case,
+    /// i.e. `LOWER(column) = LOWER(value)`. Useful for columns like emails.
+    ///
+    /// Postgres can't use a regular index on the column for this filter. To make it fast,
+    /// create an index on the lowercased column, e.g. `CREATE INDEX ON users (LOWER(email))`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// #[derive(Clone, macros::Model)]
+    /// struct User {
+    ///     id: Option<i64>,
+    ///     email: String,
+    /// }
+    ///
+    /// let query = User::all().filter_ci("email", "Test@Example.com");
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE LOWER("users"."email") = LOWER($1)"#
+    /// );
+    /// ```
+    pub fn filter_ci(self, column: impl ToColumn, value: impl ToValue) -> Self {
+        use Query::*;
+        match self {
+            Select(select) => Select(select.filter_ci(column, value)),
+            _ => self,
+        }
+    }
+
+    /// Filter by rows where the array column contains the value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// #[derive(Clone, macros::Model)]
+    /// struct Post {
+    ///     id: Option<i64>,
+    ///     tags: Vec<String>,
+    /// }
+    ///
+    /// let query = Post::all().filter_array_contains("tags", "rust");
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "posts" WHERE $1 = ANY("posts"."tags")"#
+    /// );
+    /// ```
+    pub fn filter_array_contains(self, column: impl ToColumn, value: impl ToValue) -> Self {
+        use Query::*;
+        match self {
+            Select(select) => Select(select.filter_array_contains(column, value)),
+            _ => self,
+        }
+    }
+
+    /// Filter by the value found at the path in a JSON column. The value
+    /// is compared as text, since that's what the `#>>` operator returns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// #[derive(Clone, macros::Model)]
+    /// struct User {
+    ///     id: Option<i64>,
+    ///     settings: serde_json::Value,
+    /// }
+    ///
+    /// let query = User::all().filter_json_path("settings", &["theme", "color"], "dark");
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE "users"."settings" #>> '{theme,color}' = $1"#
+    /// );
+    /// ```
+    pub fn filter_json_path(
+        self,
+        column: impl ToColumn,
+        path: &[&str],
+        value: impl ToValue,
+    ) -> Self {
+        use Query::*;
+        match self {
+            Select(select) => Select(select.filter_json_path(column, path, value)),
+            _ => self,
+        }
+    }
+
+    /// Add a parenthesized group of filters, joined to the existing filters with AND.
+    /// The group is built by the closure on an empty query, and can contain other groups
+    /// and [`Query::or`] filters, to any depth. Its placeholders are renumbered to follow
+    /// the placeholders of this query.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// # }
+    /// let query = User::filter("admin", true)
+    ///     .where_group(|query| query.filter("email", "a@test.com").or(|query| query.filter("email", "b@test.com")));
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE "users"."admin" = $1 AND (("users"."email" = $2) OR ("users"."email" = $3))"#
+    /// );
+    /// ```
+    pub fn where_group(self, f: impl FnOnce(Self) -> Self) -> Self {
+        use Query::*;
+        match self {
+            Select(select) => {
+                let group = f(Select(select.empty_group()));
+                match group {
+                    Select(group) => Select(select.where_group(group)),
+                    _ => Select(select),
+                }
+            }
+            _ => self,
+        }
+    }
+
     pub fn or(self, f: fn(Self) -> Self) -> Self {
         use Query::*;
         match self {
@@ -450,6 +688,120 @@ impl<T: Model> Query<T> {
         self.not(column, value)
     }
 
+    /// Filter by rows where the column is one of the values returned by the subquery,
+    /// i.e. `column IN (SELECT ...)`. The subquery should select only one column,
+    /// e.g. using [`Query::select_columns`]. Its placeholders are merged into this query.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// # }
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct Order {
+    /// #    id: Option<i64>,
+    /// #    user_id: i64,
+    /// #    amount: f64,
+    /// # }
+    /// let orders = Order::all()
+    ///     .filter_gt("amount", 50.0)
+    ///     .select_columns(&["user_id"]);
+    /// let query = User::all().filter_in_subquery("id", orders);
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE "users"."id" IN (SELECT "orders"."user_id" FROM "orders" WHERE "orders"."amount" > $1)"#
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the subquery isn't a `SELECT`.
+    pub fn filter_in_subquery<U: FromRow>(self, column: impl ToColumn, subquery: Query<U>) -> Self {
+        match self {
+            Query::Select(select) => {
+                Query::Select(select.filter_in_subquery(column, subquery.into_select()))
+            }
+            _ => self,
+        }
+    }
+
+    /// Filter by rows where the column is none of the values returned by the subquery,
+    /// i.e. `column NOT IN (SELECT ...)`. See [`Query::filter_in_subquery`].
+    ///
+    /// # Panics
+    ///
+    /// If the subquery isn't a `SELECT`.
+    pub fn filter_not_in_subquery<U: FromRow>(
+        self,
+        column: impl ToColumn,
+        subquery: Query<U>,
+    ) -> Self {
+        match self {
+            Query::Select(select) => {
+                Query::Select(select.filter_not_in_subquery(column, subquery.into_select()))
+            }
+            _ => self,
+        }
+    }
+
+    /// Filter by rows for which the subquery returns at least one row, i.e. `EXISTS (SELECT 1 ...)`.
+    /// The subquery usually references the columns of this query, e.g. to check for related rows.
+    /// Its placeholders are merged into this query.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// # }
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct Order {
+    /// #    id: Option<i64>,
+    /// #    user_id: i64,
+    /// #    amount: f64,
+    /// # }
+    /// let orders = Order::all()
+    ///     .filter("user_id", User::column("id"))
+    ///     .filter_gt("amount", 50.0);
+    /// let query = User::all().filter_exists(orders);
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE EXISTS (SELECT 1 FROM "orders" WHERE "orders"."user_id" = "users"."id" AND "orders"."amount" > $1)"#
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the subquery isn't a `SELECT`.
+    pub fn filter_exists<U: FromRow>(self, subquery: Query<U>) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.filter_exists(subquery.into_select())),
+            _ => self,
+        }
+    }
+
+    /// Filter by rows for which the subquery returns no rows, i.e. `NOT EXISTS (SELECT 1 ...)`.
+    /// See [`Query::filter_exists`].
+    ///
+    /// # Panics
+    ///
+    /// If the subquery isn't a `SELECT`.
+    pub fn filter_not_exists<U: FromRow>(self, subquery: Query<U>) -> Self {
+        match self {
+            Query::Select(select) => {
+                Query::Select(select.filter_not_exists(subquery.into_select()))
+            }
+            _ => self,
+        }
+    }
+
     pub fn not(self, column: impl ToColumn, value: impl ToValue) -> Self {
         use Query::*;
 
@@ -468,18 +820,33 @@ impl<T: Model> Query<T> {
         }
     }
 
-    pub fn find_by(mut self, column: impl ToColumn, value: impl ToValue) -> Self {
-        use Query::*;
+    pub fn find_by(self, column: impl ToColumn, value: impl ToValue) -> Self {
+        self.unscope_where().filter(column, value).take_one()
+    }
 
-        if let Select(select::Select {
-            ref mut where_clause,
-            ..
-        }) = self
-        {
-            where_clause.clear();
+    /// Remove all filters from the query, e.g. ones added by a scope. Records
+    /// excluded by soft deletes stay excluded, see [`Query::with_deleted`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// # }
+    /// let query = User::filter("admin", true).unscope_where().filter("email", "test@test.com");
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE "users"."email" = $1"#
+    /// );
+    /// ```
+    pub fn unscope_where(self) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.unscope_where()),
+            _ => self,
         }
-
-        self.filter(column, value).take_one()
     }
 
     pub fn limit(self, limit: i64) -> Self {
@@ -503,1325 +870,5340 @@ impl<T: Model> Query<T> {
         }
     }
 
-    /// Join this relation with another relation directly related to it, either
-    /// through a foreign key.
-    ///
-    /// Joined relation must implement [`Association`] for current relation.
+    /// Order by user input, e.g. a query parameter like `"created_at DESC"`. Only columns of the model
+    /// and the `ASC`/`DESC` directions are accepted, and anything else returns [`Error::InvalidOrder`].
+    /// See [`OrderBy::parse`].
     ///
     /// # Example
     ///
     /// ```
-    /// use rwf::{model::{Association, Model}, macros::Model};
-    ///
-    /// #[derive(Clone, Default, Model)]
-    /// struct User {}
-    ///
-    /// #[derive(Clone, Default, Model)]
-    /// struct Order {}
+    /// # use rwf::prelude::*;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = User::all().try_order("email desc")?;
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" ORDER BY "email" DESC"#);
     ///
-    /// impl Association<Order> for User {}
+    /// assert!(User::all().try_order("email; DROP TABLE users").is_err());
+    /// # Ok::<(), rwf::model::Error>(())
     /// ```
-    pub fn join<F: Association<T>>(self) -> Self {
-        match self {
-            Query::Select(select) => Query::Select(select.join(F::construct_join())),
-            _ => self,
-        }
+    pub fn try_order(self, input: &str) -> Result<Self, Error> {
+        let mut columns = vec![T::primary_key()];
+        columns.extend(T::column_names());
+
+        Ok(self.order(OrderBy::parse(input, &columns)?))
     }
 
-    pub fn join_nested<F: Association<T>, G: Model>(self, joined: Joined<F, G>) -> Self {
-        match self {
-            Query::Select(select) => Query::Select(select.add_joins(joined.into())),
+    /// Replace the existing order of the query, e.g. one added by a scope, with a new one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// # }
+    /// let query = User::all().order(("created_at", "DESC")).reorder(("email", "ASC"));
+    ///
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" ORDER BY "email" ASC"#);
+    /// ```
+    pub fn reorder(self, order: impl ToOrderBy) -> Self {
+        self.unscope_order().order(order)
+    }
+
+    /// Remove the order of the query.
+    pub fn unscope_order(self) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.unscope_order()),
             _ => self,
         }
     }
 
-    pub fn lock(self) -> Self {
+    /// Remove the limit and the offset of the query.
+    pub fn unscope_limit(self) -> Self {
         match self {
-            Query::Select(select) => Query::Select(select.lock()),
+            Query::Select(select) => Query::Select(select.unscope_limit()),
             _ => self,
         }
     }
 
-    pub fn skip_locked(self) -> Self {
+    /// Return rows in random order, using `ORDER BY RANDOM()`. Ordering by other columns
+    /// doesn't make sense with a random order, so this replaces any existing order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// #[derive(Clone, macros::Model)]
+    /// struct Product {
+    ///     id: Option<i64>,
+    /// }
+    ///
+    /// let query = Product::all().order_random().take_one();
+    ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT * FROM "products" ORDER BY RANDOM() LIMIT 1"#
+    /// );
+    /// ```
+    ///
+    /// Postgres has to read and sort all matching rows to pick random ones, so this can be slow on large tables.
+    pub fn order_random(self) -> Self {
+        if let Query::Select(mut select) = self {
+            select.order_by = OrderBy::random();
+            Query::Select(select)
+        } else {
+            self
+        }
+    }
+
+    /// Join this relation with another relation directly related to it, either
+    /// through a foreign key.
+    ///
+    /// Joined relation must implement [`Association`] for current relation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use rwf::{model::{Association, Model}, macros::Model};
+    ///
+    /// #[derive(Clone, Default, Model)]
+    /// struct User {}
+    ///
+    /// #[derive(Clone, Default, Model)]
+    /// struct Order {}
+    ///
+    /// impl Association<Order> for User {}
+    /// ```
+    pub fn join<F: Association<T>>(self) -> Self {
         match self {
-            Query::Select(select) => Query::Select(select.skip_locked()),
+            Query::Select(select) => Query::Select(select.join(F::construct_join())),
             _ => self,
         }
     }
 
-    pub fn find_or_create(self) -> Self {
+    /// Preload records associated with the records returned by this query. After the
+    /// query runs, one more query is executed per association to fetch all associated records at once.
+    ///
+    /// See [`crate::model::preload`] for more details.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let users = User::all()
+    ///     .preload::<Order>()
+    ///     .fetch_all(&mut conn)
+    ///     .await?;
+    ///
+    /// let orders: &[Order] = users[0].preloaded::<Order>();
+    /// ```
+    pub fn preload<F: Association<T> + Sync + 'static>(self) -> Preload<T>
+    where
+        T: Model + Sync + 'static,
+    {
+        Preload::new(self).preload::<F>()
+    }
+
+    /// Join a model with which this model has a relationship, renaming the joined table
+    /// to the alias. Filters on the joined table need to use the alias, e.g. `Column::new("author", "email")`.
+    pub fn join_as<F: Association<T>>(self, alias: &str) -> Self {
         match self {
-            Query::Select(select) => {
-                let (columns, values) = select.insert_columns();
-                let insert = Insert::from_columns(&columns, &values);
-                Query::InsertIfNotExists {
-                    select: select.limit(1),
-                    insert,
-                    created: false,
-                }
-            }
+            Query::Select(select) => Query::Select(select.join(F::construct_join().alias(alias))),
             _ => self,
         }
     }
 
-    pub fn column(self, column: impl ToColumn) -> Self {
+    /// Join the parent records of a self-referential relationship, e.g. an employee
+    /// and their manager. See [`Join::parent`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let query = Comment::all()
+    ///     .join_parent("parent_id", "parent")
+    ///     .filter(Column::new("parent", "author_id"), 5);
+    /// ```
+    pub fn join_parent(self, foreign_key: &str, alias: &str) -> Self
+    where
+        T: Model,
+    {
         match self {
             Query::Select(select) => {
-                let select = select.select_additional(column);
-                Query::Select(select)
+                Query::Select(select.join(Join::parent::<T>(foreign_key, alias)))
             }
-
             _ => self,
         }
     }
 
-    pub fn update_all(self, attributes: &[(impl ToColumn, impl ToValue)]) -> Self {
+    /// Join the child records of a self-referential relationship, e.g. a comment and its replies.
+    /// See [`Join::children`].
+    pub fn join_children(self, foreign_key: &str, alias: &str) -> Self
+    where
+        T: Model,
+    {
         match self {
             Query::Select(select) => {
-                let update = Update::<T>::from(select);
-                let columns = attributes
-                    .iter()
-                    .map(|(c, _)| c.to_column())
-                    .collect::<Vec<_>>();
-                let values = attributes
-                    .iter()
-                    .map(|(_, v)| v.to_value())
-                    .collect::<Vec<_>>();
-                Query::Update(update.columns(&columns, &values))
+                Query::Select(select.join(Join::children::<T>(foreign_key, alias)))
             }
             _ => self,
         }
     }
 
-    pub fn unique_by(self, columns: &[impl ToColumn]) -> Self {
+    /// Join a model with which this model has a many-to-many relationship, through
+    /// the join table. See [`HasManyThrough`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let admins = User::all()
+    ///     .join_through::<UserRole, Role>()
+    ///     .filter(Role::column("name"), "admin");
+    /// ```
+    pub fn join_through<Through: Model, F: HasManyThrough<T, Through>>(self) -> Self
+    where
+        T: Model,
+    {
         match self {
-            Query::Insert(insert) => Query::Insert(insert.unique_by(columns)),
-            Query::InsertIfNotExists {
-                select,
-                insert,
-                created,
-            } => {
-                let insert = insert.unique_by(columns);
-                Query::InsertIfNotExists {
-                    select,
-                    insert,
-                    created,
+            Query::Select(mut select) => {
+                for join in F::construct_joins().joins() {
+                    select = select.join(join.clone());
                 }
+                Query::Select(select)
             }
             _ => self,
         }
     }
 
-    async fn execute_internal(
-        &self,
-        client: impl ToConnectionRequest<'_>,
-    ) -> Result<Vec<tokio_postgres::Row>, Error> {
-        let request = client.to_connection_request()?;
-        let mut conn = request.get().await?;
-
-        let client = match request.connection() {
-            Some(conn) => conn,
-            None => conn.as_mut().unwrap(),
-        };
-
-        let result = match self {
-            Query::Select(select) => {
-                let query = self.to_sql();
-                let placeholdres = { select.placeholders() };
-                let values = placeholdres.values();
-                client.query_cached(&query, &values).await
-            }
-
-            Query::Raw {
-                query,
-                placeholders,
-            } => {
-                let values = placeholders.values();
-                client.query_cached(query, &values).await
-            }
-
-            Query::Update(update) => {
-                let query = self.to_sql();
-                let values = update.placeholders.values();
-                client.query_cached(&query, &values).await
-            }
-
-            Query::Insert(insert) => {
-                let query = self.to_sql();
-                let values = insert.placeholders.values();
-                client.query_cached(&query, &values).await
-            }
-
-            Query::InsertIfNotExists { select, insert, .. } => {
-                let query = select.to_sql();
-                let values = select.placeholders().values();
-                let result = client.query_cached(&query, &values).await;
-
-                let result = match result {
-                    Ok(result) => result,
-                    Err(err) => {
-                        self.log_error(&err);
-                        return Err(err);
-                    }
-                };
-
-                if result.is_empty() {
-                    let query = insert.to_sql();
-                    let values = insert.placeholders.values();
-                    client.query_cached(&query, &values).await
-                } else {
-                    Ok(result)
-                }
-            }
-
-            Query::Picked(picked) => {
-                let select = &picked.select;
-                let query = select.to_sql();
-                let placeholdres = { select.placeholders() };
-                let values = placeholdres.values();
-                client.query_cached(&query, &values).await
-            }
-        };
-
-        match result {
-            Ok(rows) => Ok(rows),
-            Err(err) => {
-                self.log_error(&err);
-                Err(err)
-            }
+    pub fn join_nested<F: Association<T>, G: Model>(self, joined: Joined<F, G>) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.add_joins(joined.into())),
+            _ => self,
         }
     }
 
-    /// Execute the query and fetch the first row from the database.
-    pub async fn fetch(self, conn: impl ToConnectionRequest<'_>) -> Result<T, Error> {
-        match self.execute(conn).await?.first().cloned() {
-            Some(row) => Ok(row),
-            None => Err(Error::RecordNotFound),
+    pub fn lock(self) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.lock()),
+            _ => self,
         }
     }
 
-    pub async fn fetch_optional(
-        self,
-        conn: impl ToConnectionRequest<'_>,
-    ) -> Result<Option<T>, Error> {
-        match self.fetch(conn).await {
-            Ok(row) => Ok(Some(row)),
-            Err(Error::RecordNotFound) => Ok(None),
-            Err(err) => Err(err),
+    /// Lock the selected rows using `FOR SHARE`, which prevents other transactions from
+    /// updating or deleting them, but still allows them to read and share-lock them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// #[derive(Clone, macros::Model)]
+    /// struct User {
+    ///     id: Option<i64>,
+    /// }
+    ///
+    /// assert_eq!(
+    ///     User::find(1).lock_share().to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE "users"."id" = $1 LIMIT 1 FOR SHARE"#,
+    /// );
+    /// ```
+    pub fn lock_share(self) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.lock_share()),
+            _ => self,
         }
     }
 
-    /// Execute the query and fetch all rows from the database.
-    pub async fn fetch_all(self, conn: impl ToConnectionRequest<'_>) -> Result<Vec<T>, Error> {
-        self.execute(conn).await
+    /// Skip rows locked by someone else. Can't be combined with [`Query::nowait`],
+    /// so it replaces it if it was used before.
+    pub fn skip_locked(self) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.skip_locked()),
+            _ => self,
+        }
     }
 
-    /// Get the query plan from Postgres.
+    /// Return an error instead of waiting for rows locked by someone else. Can't be combined
+    /// with [`Query::skip_locked`], so it replaces it if it was used before.
     ///
-    /// Take the actual query, prepend `EXPLAIN` and execute.
-    pub async fn explain(self, conn: impl ToConnectionRequest<'_>) -> Result<Explain, Error> {
-        let query = format!("EXPLAIN {}", self.to_sql());
-        let placeholders = match self {
-            Query::Select(select) => select.placeholders,
-            Query::Update(update) => update.placeholders,
-            Query::Insert(insert) => insert.placeholders,
-            Query::Picked(picked) => picked.select.placeholders,
-            _ => todo!("explain"),
-        };
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// #[derive(Clone, macros::Model)]
+    /// struct User {
+    ///     id: Option<i64>,
+    /// }
+    ///
+    /// assert_eq!(
+    ///     User::all().lock().nowait().to_sql(),
+    ///     r#"SELECT * FROM "users" FOR UPDATE NOWAIT"#,
+    /// );
+    /// ```
+    pub fn nowait(self) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.nowait()),
+            _ => self,
+        }
+    }
 
-        let query = Query::<Explain>::Raw {
-            query,
-            placeholders,
-        };
-        match query.execute_internal(conn).await?.pop() {
-            Some(explain) => Ok(Explain::from_row(explain)?),
-            None => Err(Error::RecordNotFound),
-        }
-    }
-
-    pub async fn exists(self, conn: impl ToConnectionRequest<'_>) -> Result<bool, Error> {
-        Ok(self.count(conn).await? > 0)
-    }
-
-    pub async fn count(self, conn: impl ToConnectionRequest<'_>) -> Result<i64, Error> {
-        let query = match self {
-            Query::Select(select) => Query::Select(select.exists()),
-            _ => self,
-        };
-        let start = Instant::now();
-
-        let result = match query.execute_internal(conn).await?.pop() {
-            None => Ok(0),
-            Some(exists) => Ok(Exists::from_row(exists)?.count),
-        };
-
-        query.log(start.elapsed());
-
-        result
-    }
-
-    /// Execute a query and return an optional result.
-    pub async fn execute(self, conn: impl ToConnectionRequest<'_>) -> Result<Vec<T>, Error> {
-        let start = Instant::now();
-        let mut results = vec![];
-        let rows = self.execute_internal(conn).await?;
-        for row in rows {
-            results.push(T::from_row(row)?)
-        }
-        let time = start.elapsed();
-
-        self.log(time);
-
-        Ok(results)
-    }
-
-    fn type_name() -> String {
-        std::any::type_name::<T>()
-            .split("::")
-            .skip(1)
-            .collect::<Vec<_>>()
-            .join("::")
-    }
-
-    fn action(&self) -> &'static str {
-        match self {
-            Query::Select(_) | Query::Picked(_) => "load",
-            Query::Update(_) => "save",
-            Query::Raw { .. } => "query",
-            Query::Insert(_) => "save",
-            Query::InsertIfNotExists { .. } => "load/create",
-        }
-    }
-
-    fn log(&self, duration: Duration) {
-        if !get_config().general.log_queries {
-            return;
-        }
-
-        info!(
-            "{} {} ({:.3} ms) {}",
-            Self::type_name().green(),
-            self.action().purple(),
-            duration.as_secs_f64() * 1000.0,
-            self.to_sql()
-        );
-    }
-
-    fn log_error(&self, err: &Error) {
-        error!(
-            "{} {} {} {}",
-            Self::type_name().green(),
-            self.action().purple(),
-            self.to_sql(),
-            err,
-        )
-    }
-}
-
-pub type Scope<T> = Query<T>;
-
-/// Implements Object-relational mapping (ORM) methods for a Rust struct, turning it into a database model. This trait doesn't have to be implemented manually. You can use the [`rwf_macros::Model`] macro instead, for example:
-///
-/// ```
-/// # use rwf::prelude::*;
-/// #[derive(Clone, macros::Model)]
-/// struct User {
-///     id: Option<i64>,
-///     email: String,
-/// }
-/// ```
-///
-/// Structs that wish to implement this trait need to implement two additional traits:
-///
-/// - [`FromRow`]
-/// - [`Clone`]
-///
-/// When usindg the [`rwf_macros::Model`] derive, [`FromRow`] is derived automatically.
-pub trait Model: FromRow {
-    /// Name of the PostgreSQL table where records for this model are stored.
-    ///
-    /// The name must not be fully qualified
-    ///  e.g. `"users"` is correct, while `r#"public"."users"#` won't work.
-    ///
-    /// This method is implemented automatically by the [`rwf_macros::Model`] derive. If you wish to override
-    /// that implementation, use `#[table_name("your_name")]` derive attribute.
-    fn table_name() -> &'static str;
-
-    /// Get the fully qualified column name for this table.
+    /// Include soft deleted records, which are excluded by default.
     ///
     /// # Example
     ///
     /// ```
     /// # use rwf::prelude::*;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// let column = User::column("email");
+    /// #[derive(Clone, macros::Model)]
+    /// #[soft_delete]
+    /// struct User {
+    ///     id: Option<i64>,
+    ///     deleted_at: Option<OffsetDateTime>,
+    /// }
     ///
-    /// assert_eq!(column.to_string(), r#""users"."email""#);
+    /// assert_eq!(
+    ///     User::all().to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE "users"."deleted_at" IS NULL"#
+    /// );
+    /// assert_eq!(User::all().with_deleted().to_sql(), r#"SELECT * FROM "users""#);
     /// ```
-    fn column(name: &str) -> Column {
-        Column::new(Self::table_name(), name)
+    pub fn with_deleted(self) -> Self {
+        self.deleted(|_| Deleted::Include)
     }
 
-    /// List of names of columns stored in the PostgreSQL table.
-    ///
-    /// Names must not be fully qualified or contain
-    /// double quotes, e.g. `"id"` is correct, while `"users"."id"` won't work.
-    ///
-    /// This method is implemented automatically by the [`rwf_macros::Model`] derive.
+    /// Select soft deleted records only.
     ///
     /// # Example
+    ///
     /// ```
-    /// fn column_names() -> &'static [&'static str] {
-    ///     &["id", "email"]
+    /// # use rwf::prelude::*;
+    /// #[derive(Clone, macros::Model)]
+    /// #[soft_delete]
+    /// struct User {
+    ///     id: Option<i64>,
+    ///     deleted_at: Option<OffsetDateTime>,
     /// }
+    ///
+    /// assert_eq!(
+    ///     User::all().only_deleted().to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE "users"."deleted_at" IS NOT NULL"#
+    /// );
     /// ```
-    fn column_names() -> &'static [&'static str];
+    pub fn only_deleted(self) -> Self {
+        self.deleted(|table_name| match T::soft_delete_column() {
+            Some(column) => Deleted::Only(Column::new(table_name, column)),
+            None => Deleted::Include,
+        })
+    }
 
-    /// The primary key value, if one exists, for the instance of a model.
-    ///
-    /// Primary keys are not required to use the ORM, but are generally needed to perform
-    /// updates and deletes. If the model doesn't have a primary key, you can return [`Value::Null`].
-    ///
-    /// This method is implemented automatically by the [`rwf_macros::Model`] derive.
+    fn deleted(self, deleted: impl Fn(&str) -> Deleted) -> Self {
+        match self {
+            Query::Select(select) => {
+                let deleted = deleted(&select.table_name);
+                Query::Select(select.deleted(deleted))
+            }
+            Query::Picked(mut picked) => {
+                let deleted = deleted(&picked.select.table_name);
+                picked.select = picked.select.deleted(deleted);
+                Query::Picked(picked)
+            }
+            _ => self,
+        }
+    }
+
+    /// Execute the query on the primary database, even if the pool has read replicas.
+    /// Use this to read rows written just before, which may not have reached the replicas yet.
     ///
     /// # Example
     ///
     /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::Value;
-    /// # use rwf::macros::FromRow;
-    /// # #[derive(Clone, FromRow)]
+    /// # use rwf::macros::Model;
+    /// # use rwf::model::{Model, Query};
+    /// # #[derive(Clone, Debug, Model)]
     /// # struct User {
     /// #    id: Option<i64>,
     /// #    email: String,
     /// # }
-    /// # impl Model for User {
-    /// # fn table_name() -> &'static str { "" }
-    /// # fn values(&self) -> Vec<Value> { vec![] }
-    /// # fn column_names() -> &'static [&'static str] { &[] }
-    /// # fn foreign_key() -> &'static str { "" }
-    /// fn id(&self) -> Value {
-    ///     self.id.to_value()
-    /// }
-    /// # }
+    /// assert!(User::all().replica());
+    /// assert!(!User::all().on_primary().replica());
     /// ```
-    ///
-    fn id(&self) -> Value;
+    pub fn on_primary(self) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.on_primary()),
+            Query::Picked(mut picked) => {
+                picked.select = picked.select.on_primary();
+                Query::Picked(picked)
+            }
+            _ => self,
+        }
+    }
 
-    /// List of column values for a particular instance of a model. The values must be in the same
-    /// order as the columns in [`Model::column_names`].
-    ///
-    /// The values are Rust types converted to the [`Value`] enum. This conversion is required so
-    /// the ORM can then convert them to PostgreSQL types automatically. Most Rust types can be converted
-    /// to [`Value`] automatically as well with [`ToValue::to_value`].
+    /// The query is read-only and can be executed on a read replica.
+    pub fn replica(&self) -> bool {
+        match self {
+            Query::Select(select) => select.replica(),
+            Query::Picked(picked) => picked.select.replica(),
+            _ => false,
+        }
+    }
+
+    pub fn find_or_create(self) -> Self {
+        match self {
+            Query::Select(select) => {
+                let (columns, values) = select.insert_columns();
+                let insert = Insert::from_columns(&columns, &values);
+                Query::InsertIfNotExists {
+                    select: select.limit(1),
+                    insert,
+                    created: false,
+                }
+            }
+            _ => self,
+        }
+    }
+
+    /// Select only the given columns instead of all of them, e.g. to avoid fetching large
+    /// columns which aren't needed. Columns which aren't qualified are qualified with the table name.
     ///
-    /// This method is implemented automatically by the [`rwf_macros::Model`] derive.
+    /// The model is still built with [`FromRow`], so the columns must include all the fields
+    /// it reads, or fetching the records returns an error. To fetch fewer fields, select them
+    /// into a smaller model which reads only those fields, using the same table name.
     ///
     /// # Example
     ///
     /// ```
     /// # use rwf::prelude::*;
-    /// # use rwf::model::Value;
-    /// # use rwf::macros::FromRow;
-    /// # #[derive(Clone, FromRow)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// # impl Model for User {
-    /// # fn table_name() -> &'static str { "" }
-    /// # fn id(&self) -> Value { Value::Null }
-    /// # fn column_names() -> &'static [&'static str] { &[] }
-    /// # fn foreign_key() -> &'static str { "" }
-    /// fn values(&self) -> Vec<Value> {
-    ///     vec![
-    ///         1_i64.to_value(),
-    ///         "test@test.com".to_value(),
-    ///     ]
+    /// #[derive(Clone, macros::Model)]
+    /// #[table_name("users")]
+    /// struct UserEmail {
+    ///     id: Option<i64>,
+    ///     email: String,
     /// }
-    /// # }
-    /// ```
-    fn values(&self) -> Vec<Value>;
-
-    /// The name of a column in another PostgreSQL table which refers to this model.
-    ///
-    /// For example, if the table name for this model is `"users"`, this method could return `"user_id"`.
-    ///
-    /// This method is implemented automatically by the [`rwf_macros::Model`] derive. If you wish to override
-    /// that implementation, use `#[foreign_key("your_fk")]` derive attribute.
     ///
-    /// # Example
+    /// let query = UserEmail::all().select_columns(&["id", "email"]);
     ///
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT "users"."id", "users"."email" FROM "users""#
+    /// );
     /// ```
-    /// fn foreign_key() -> &'static str {
-    ///     "user_id"
-    /// }
-    /// ```
-    fn foreign_key() -> &'static str;
-
-    /// Name of the primary key column in the database.
-    ///
-    /// This is typically `"id"`, but can be any other column as long as it has a `UNIQUE NOT NULL` constraint
-    /// and a default value produced from a sequence.
-    fn primary_key() -> &'static str {
-        "id"
+    pub fn select_columns(self, columns: &[impl ToColumn]) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.select_columns(columns)),
+            _ => self,
+        }
     }
 
-    /// Select one record from the table. The row returned is determined by the database.
+    /// Select a window function in addition to the columns, e.g. to number or
+    /// total rows within groups. The function is used as-is, so it must not contain user input.
+    /// Partition columns which aren't qualified are qualified with the table name.
     ///
     /// # Example
+    ///
     /// ```
     /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
     /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
+    /// # struct Order {
+    /// #     id: Option<i64>,
+    /// #     user_id: i64,
+    /// #     amount: f64,
     /// # }
-    /// let query = User::take_one();
+    /// let query = Order::all().select_window(
+    ///     "ROW_NUMBER()",
+    ///     &["user_id"],
+    ///     ("amount", "DESC"),
+    ///     "rank",
+    /// );
     ///
-    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" LIMIT 1"#);
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"SELECT *, ROW_NUMBER() OVER (PARTITION BY "orders"."user_id" ORDER BY "amount" DESC) AS "rank" FROM "orders""#
+    /// );
     /// ```
-    fn take_one() -> Query<Self> {
-        Query::select(Self::table_name()).take_one()
+    pub fn select_window(
+        self,
+        function: &str,
+        partition_by: &[impl ToColumn],
+        order_by: impl ToOrderBy,
+        alias: &str,
+    ) -> Self {
+        match self {
+            Query::Select(select) => Query::Select(select.select_window(
+                function,
+                partition_by,
+                order_by.to_order_by(),
+                alias,
+            )),
+            _ => self,
+        }
     }
 
-    /// Select _n_ records from the table. The order of rows returned is determined by the database.
+    pub fn column(self, column: impl ToColumn) -> Self {
+        match self {
+            Query::Select(select) => {
+                let select = select.select_additional(column);
+                Query::Select(select)
+            }
+
+            _ => self,
+        }
+    }
+
+    /// Update all records matching the query, setting the columns to the values.
+    /// Values of the columns are sent as placeholders, numbered after the ones used by the filters.
     ///
     /// # Example
+    ///
     /// ```
     /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
     /// # #[derive(Clone, macros::Model)]
     /// # struct User {
     /// #    id: Option<i64>,
-    /// #    email: String,
+    /// #    active: bool,
+    /// #    archived: bool,
     /// # }
-    /// let query = User::take_many(25);
+    /// let query = User::filter("active", false).update_all(&[("archived", true)]);
     ///
-    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" LIMIT 25"#);
+    /// assert_eq!(
+    ///     query.to_sql(),
+    ///     r#"UPDATE "users" SET "archived" = $2 WHERE "users"."active" = $1 RETURNING *"#
+    /// );
     /// ```
-    fn take_many(n: i64) -> Query<Self> {
-        Query::select(Self::table_name()).take_many(n)
+    ///
+    /// Use [`Query::execute_returning_count`] to execute it and get the number of updated records,
+    /// without fetching them.
+    pub fn update_all(self, attributes: &[(impl ToColumn, impl ToValue)]) -> Self {
+        match self {
+            Query::Select(select) => {
+                let update = Update::<T>::from(select);
+                let columns = attributes
+                    .iter()
+                    .map(|(c, _)| c.to_column())
+                    .collect::<Vec<_>>();
+                let values = attributes
+                    .iter()
+                    .map(|(_, v)| v.to_value())
+                    .collect::<Vec<_>>();
+                Query::Update(update.columns(&columns, &values))
+            }
+            _ => self,
+        }
     }
 
-    /// Select the first record from the table, ordered by primary key.
+    pub fn unique_by(self, columns: &[impl ToColumn]) -> Self {
+        match self {
+            Query::Insert(insert) => Query::Insert(insert.unique_by(columns)),
+            Query::InsertIfNotExists {
+                select,
+                insert,
+                created,
+            } => {
+                let insert = insert.unique_by(columns);
+                Query::InsertIfNotExists {
+                    select,
+                    insert,
+                    created,
+                }
+            }
+            _ => self,
+        }
+    }
+
+    async fn execute_internal(
+        &self,
+        client: impl ToConnectionRequest<'_>,
+    ) -> Result<Vec<tokio_postgres::Row>, Error> {
+        // Invalid models are never sent to the database.
+        let errors = match self {
+            Query::Insert(insert) => insert.validation_errors(),
+            Query::Update(update) => update.validation_errors(),
+            _ => None,
+        };
+        if let Some(errors) = errors {
+            return Err(Error::Validation(errors.clone()));
+        }
+
+        if let Query::Update(update) = self {
+            if let Some(column) = update.invalid_lock_version() {
+                return Err(Error::InvalidLockVersion(column.to_string()));
+            }
+        }
+
+        let request = client.to_connection_request()?;
+        let mut conn = request.get(self.replica()).await?;
+
+        let client = match request.connection() {
+            Some(conn) => conn,
+            None => conn.as_mut().unwrap(),
+        };
+
+        let result = match self {
+            Query::Select(select) => {
+                let query = self.to_sql();
+                let placeholders = select.placeholders();
+                placeholders.check(&query)?;
+                client.query_cached(&query, &placeholders.values()).await
+            }
+
+            Query::Raw {
+                query,
+                placeholders,
+            } => {
+                placeholders.check(query)?;
+                client.query_cached(query, &placeholders.values()).await
+            }
+
+            Query::Update(update) => {
+                let query = self.to_sql();
+                update.placeholders.check(&query)?;
+                let values = update.placeholders.values();
+                let result = client.query_cached(&query, &values).await;
+
+                match result {
+                    Ok(rows) if rows.is_empty() && update.locked() => {
+                        return Err(Error::StaleObject)
+                    }
+                    result => result,
+                }
+            }
+
+            Query::Insert(insert) => {
+                let query = self.to_sql();
+                insert.placeholders.check(&query)?;
+                let values = insert.placeholders.values();
+                client.query_cached(&query, &values).await
+            }
+
+            Query::InsertIfNotExists { select, insert, .. } => {
+                let query = select.to_sql();
+                select.placeholders().check(&query)?;
+                let values = select.placeholders().values();
+                let result = client.query_cached(&query, &values).await;
+
+                let result = match result {
+                    Ok(result) => result,
+                    Err(err) => {
+                        self.log_error(&err);
+                        return Err(err);
+                    }
+                };
+
+                if result.is_empty() {
+                    let query = insert.to_sql();
+                    insert.placeholders.check(&query)?;
+                    let values = insert.placeholders.values();
+                    client.query_cached(&query, &values).await
+                } else {
+                    Ok(result)
+                }
+            }
+
+            Query::Picked(picked) => {
+                let select = &picked.select;
+                let query = select.to_sql();
+                let placeholders = select.placeholders();
+                placeholders.check(&query)?;
+                client.query_cached(&query, &placeholders.values()).await
+            }
+        };
+
+        match result {
+            Ok(rows) => Ok(rows),
+            Err(err) => {
+                self.log_error(&err);
+                Err(err)
+            }
+        }
+    }
+
+    /// Execute the query and fetch the first row from the database.
+    pub async fn fetch(self, conn: impl ToConnectionRequest<'_>) -> Result<T, Error> {
+        match self.execute(conn).await?.first().cloned() {
+            Some(row) => Ok(row),
+            None => Err(Error::RecordNotFound),
+        }
+    }
+
+    pub async fn fetch_optional(
+        self,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<Option<T>, Error> {
+        match self.fetch(conn).await {
+            Ok(row) => Ok(Some(row)),
+            Err(Error::RecordNotFound) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Execute the query and fetch all rows from the database.
+    pub async fn fetch_all(self, conn: impl ToConnectionRequest<'_>) -> Result<Vec<T>, Error> {
+        self.execute(conn).await
+    }
+
+    /// Execute the query and convert all rows into another type, which only needs to implement
+    /// [`FromRow`]. Useful for results which don't map to a model, e.g. aggregates
+    /// or columns from joined tables.
     ///
     /// # Example
-    /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// let query = User::first_one();
     ///
-    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" ORDER BY "users"."id" ASC LIMIT 1"#);
+    /// ```ignore
+    /// #[derive(Clone, macros::FromRow)]
+    /// struct OrderTotal {
+    ///     user_id: i64,
+    ///     total: f64,
+    /// }
+    ///
+    /// let totals = Query::<Row>::raw_with(
+    ///     "SELECT user_id, SUM(amount)::FLOAT8 AS total FROM orders GROUP BY 1",
+    ///     vec![],
+    /// )
+    /// .fetch_all_as::<OrderTotal>(&mut conn)
+    /// .await?;
     /// ```
-    fn first_one() -> Query<Self> {
-        Query::select(Self::table_name()).first_one()
+    pub async fn fetch_all_as<U: FromRow>(
+        self,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<Vec<U>, Error> {
+        let start = Instant::now();
+        let rows = self.execute_internal(conn).await?;
+
+        let mut results = Vec::with_capacity(rows.len());
+        for row in rows {
+            results.push(U::from_row(row)?);
+        }
+
+        self.log(start.elapsed());
+
+        Ok(results)
     }
 
-    /// Select the first _n_ records from the table, ordered by primary key.
+    /// Execute the query using a connection from the global pool and return the results.
+    ///
+    /// The `_global` methods are shortcuts for passing [`Pool::pool`] as the connection,
+    /// so call sites which don't need a transaction don't have to manage connections.
+    /// The global pool is created from the configuration when it's first used.
     ///
     /// # Example
-    /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// let query = User::first_many(25);
     ///
-    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" ORDER BY "users"."id" ASC LIMIT 25"#);
+    /// ```ignore
+    /// let user = User::find(15).fetch_global().await?;
     /// ```
-    fn first_many(n: i64) -> Query<Self> {
-        Query::select(Self::table_name()).first_many(n)
+    pub async fn execute_global(self) -> Result<Vec<T>, Error> {
+        self.execute(Pool::pool()).await
     }
 
-    /// Select all records from the table. Typically this is a starting point for filtering
-    /// by some column(s), but all records can also be returned. Order of records returned is
-    /// determined by the database, unless additional filters are specified.
+    /// Execute the query using a connection from the global pool and fetch the first row,
+    /// like [`Query::fetch`].
+    pub async fn fetch_global(self) -> Result<T, Error> {
+        self.fetch(Pool::pool()).await
+    }
+
+    /// Execute the query using a connection from the global pool and fetch the first row,
+    /// if any, like [`Query::fetch_optional`].
+    pub async fn fetch_optional_global(self) -> Result<Option<T>, Error> {
+        self.fetch_optional(Pool::pool()).await
+    }
+
+    /// Execute the query using a connection from the global pool and fetch all rows,
+    /// like [`Query::fetch_all`].
+    pub async fn fetch_all_global(self) -> Result<Vec<T>, Error> {
+        self.fetch_all(Pool::pool()).await
+    }
+
+    /// Count the records matching the query using a connection from the global pool,
+    /// like [`Query::count`].
+    pub async fn count_global(self) -> Result<i64, Error> {
+        self.count(Pool::pool()).await
+    }
+
+    /// Check if any records match the query using a connection from the global pool,
+    /// like [`Query::exists`].
+    pub async fn exists_global(self) -> Result<bool, Error> {
+        self.exists(Pool::pool()).await
+    }
+
+    /// Get the query plan from Postgres.
     ///
-    /// # Example
-    /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// let query = User::all();
+    /// Take the actual query, prepend `EXPLAIN` and execute.
+    pub async fn explain(self, conn: impl ToConnectionRequest<'_>) -> Result<Explain, Error> {
+        self.explain_with(ExplainOptions::new(), conn).await
+    }
+
+    /// Execute the query and get the query plan with actual run times and buffer usage,
+    /// using `EXPLAIN (ANALYZE, BUFFERS)`.
     ///
-    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users""#);
-    /// ```
-    fn all() -> Query<Self> {
-        Query::select(Self::table_name())
+    /// **The query is executed.** Only `SELECT` queries are allowed, since other queries
+    /// would make their changes. Use [`Query::explain_with`] and [`ExplainOptions::allow_writes`] to analyze them anyway.
+    pub async fn explain_analyze(
+        self,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<Explain, Error> {
+        self.explain_with(ExplainOptions::new().analyze(), conn)
+            .await
     }
 
-    /// Filter table records by a column. Multiple calls to filter can be chained to filter by multiple columns.
+    /// Get the query plan in the given format. Plans formatted as JSON can be parsed with [`Explain::tree`].
+    pub async fn explain_format(
+        self,
+        format: explain::Format,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<Explain, Error> {
+        self.explain_with(ExplainOptions::new().format(format), conn)
+            .await
+    }
+
+    /// Get the query plan from Postgres, using the given `EXPLAIN` options.
     ///
     /// # Example
-    /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// let query = User::filter("email", "test@test.com");
     ///
-    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" WHERE "users"."email" = $1"#);
+    /// ```rust,ignore
+    /// use rwf::model::explain::{ExplainOptions, Format};
+    ///
+    /// let plan = User::all()
+    ///     .explain_with(ExplainOptions::new().analyze().format(Format::Json), &mut conn)
+    ///     .await?
+    ///     .tree();
     /// ```
-    fn filter(column: impl ToColumn, value: impl ToValue) -> Query<Self> {
-        Query::select(Self::table_name()).filter(column, value)
-    }
+    pub async fn explain_with(
+        self,
+        options: ExplainOptions,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<Explain, Error> {
+        let query = format!("{} {}", options, self.to_sql());
+        let read_only = matches!(self, Query::Select(_) | Query::Picked(_));
 
-    /// Filter table records by a column and fetch the first matching record. The record will match
-    /// the filter, but if there are mulitple records that match, any one of them can be returned.
-    ///
-    /// # Example
-    /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// let query = User::find_by("email", "test@test.com");
-    ///
-    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" WHERE "users"."email" = $1 LIMIT 1"#);
-    /// ```
-    fn find_by(column: impl ToColumn, value: impl ToValue) -> Query<Self> {
-        Query::select(Self::table_name())
-            .find_by(column, value.to_value())
-            .take_one()
+        if options.read_only() && !read_only {
+            return Err(Error::ExplainWrite(self.to_sql()));
+        }
+
+        let placeholders = match self {
+            Query::Select(select) => select.placeholders,
+            Query::Update(update) => update.placeholders,
+            Query::Insert(insert) => insert.placeholders,
+            Query::Picked(picked) => picked.select.placeholders,
+            Query::Raw { placeholders, .. } => placeholders,
+            _ => todo!("explain"),
+        };
+
+        let query = Query::<Explain>::Raw {
+            query,
+            placeholders,
+        };
+
+        Explain::from_rows(query.execute_internal(conn).await?)
     }
 
-    /// Filter by primary key and return the matching row, if any.
+    /// Check if any record matches the query. Unlike [`Query::count`], Postgres stops
+    /// looking as soon as it finds the first matching row.
     ///
     /// # Example
+    ///
+    /// ```ignore
+    /// let taken = User::filter("email", "alice@example.com")
+    ///     .exists(&mut conn)
+    ///     .await?;
     /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// let query = User::find(1);
     ///
-    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" WHERE "users"."id" = $1 LIMIT 1"#);
+    /// ```sql
+    /// SELECT EXISTS (SELECT 1 FROM "users" WHERE "users"."email" = $1 LIMIT 1) AS "exists"
     /// ```
-    fn find(value: impl ToValue) -> Query<Self> {
-        Query::select(Self::table_name())
-            .find_by(Self::primary_key(), value.to_value())
-            .take_one()
+    pub async fn exists(self, conn: impl ToConnectionRequest<'_>) -> Result<bool, Error> {
+        let query = match self {
+            Query::Select(select) => Self::exists_query(select),
+            query => return Ok(query.count(conn).await? > 0),
+        };
+        let start = Instant::now();
+
+        let result = match query.execute_internal(conn).await?.pop() {
+            None => Ok(false),
+            Some(row) => Ok(row.try_get(0)?),
+        };
+
+        query.log(start.elapsed());
+
+        result
     }
 
-    /// Find records using an arbitrary SQL query. The caller must ensure that all required columns
-    /// are returned.
-    ///
-    /// # Example
-    /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// let query = User::find_by_sql("SELECT * FROM users WHERE email = ANY($1, $2) ORDER BY RANDOM()",
-    ///     &[
-    ///         "bob@test.com".into(),
-    ///         "alice@test.com".into(),
-    ///     ]
-    /// );
-    ///
-    /// assert_eq!(query.to_sql(), r#"SELECT * FROM users WHERE email = ANY($1, $2) ORDER BY RANDOM()"#);
-    /// ```
-    fn find_by_sql(query: impl ToString, values: &[Value]) -> Query<Self> {
+    /// Wrap the query in `SELECT EXISTS (...)`, fetching at most one row. The order of rows doesn't matter.
+    fn exists_query(mut select: Select<T>) -> Self {
+        select.columns = Columns::default().one();
+        select.order_by = OrderBy::default();
+        select.limit = select.limit.one();
+
         Query::Raw {
-            query: query.to_string(),
-            placeholders: values
-                .iter()
-                .map(|v| v.to_value())
-                .collect::<Vec<_>>()
-                .into(),
+            query: format!(r#"SELECT EXISTS ({}) AS "exists""#, select.to_sql()),
+            placeholders: select.placeholders,
         }
     }
 
-    /// Order records by a column. This method accepts any input type which implement
-    /// the [`ToOrderBy`] trait. Chaining this function allows to order by multiple columns.
-    ///
-    /// # Example
-    /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// let q1 = User::order("email");
-    /// let q2 = User::order("email DESC");
-    /// let q3 = User::order(("email", "DESC"));
-    /// let q4 = User::order((User::column("email"), "DESC"));
-    ///
-    /// assert_eq!(q1.to_sql(), r#"SELECT * FROM "users" ORDER BY email"#);
-    /// assert_eq!(q2.to_sql(), r#"SELECT * FROM "users" ORDER BY email DESC"#);
-    /// assert_eq!(q3.to_sql(), r#"SELECT * FROM "users" ORDER BY "email" DESC"#);
-    /// assert_eq!(q4.to_sql(), r#"SELECT * FROM "users" ORDER BY "users"."email" DESC"#);
-    /// ```
-    fn order(order: impl ToOrderBy) -> Query<Self> {
-        Self::all().order(order)
+    pub async fn count(self, conn: impl ToConnectionRequest<'_>) -> Result<i64, Error> {
+        let query = match self {
+            Query::Select(select) => Query::Select(select.exists()),
+            _ => self,
+        };
+
+        query.fetch_count(conn).await
     }
 
-    /// Join this model to another model with which it has a relationship. The relationship should be declared
-    /// in advance using an annotation.
+    /// Count distinct values of the column in the records matching the query,
+    /// e.g. the number of customers who placed an order.
     ///
     /// # Example
+    ///
+    /// ```ignore
+    /// let customers = Order::filter("product_id", 5)
+    ///     .count_distinct("user_id", &mut conn)
+    ///     .await?;
     /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// #[derive(Clone, macros::Model)]
-    /// #[has_many(Project)]
-    /// struct User {
-    ///    id: Option<i64>,
-    ///    email: String,
-    /// }
-    /// #[derive(Clone, macros::Model)]
-    /// #[belongs_to(User)]
-    /// struct Project {
-    ///     user_id: i64,
-    ///     name: String,
-    /// }
     ///
-    /// let join = User::join::<Project>();
+    /// ```sql
+    /// SELECT COUNT(DISTINCT "orders"."user_id") AS count FROM "orders" WHERE "orders"."product_id" = $1
     /// ```
-    fn join<F: Association<Self>>() -> Joined<Self, F> {
-        Joined::new(F::construct_join())
+    pub async fn count_distinct(
+        self,
+        column: impl ToColumn,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<i64, Error> {
+        let query = match self {
+            Query::Select(select) => Query::Select(select.count_distinct(column)),
+            _ => self,
+        };
+
+        query.fetch_count(conn).await
     }
 
-    /// Filter all records which have a relationship to this model. Used for fetching multiple records at once
-    /// in order to avoid N+1 queries.
+    /// Execute a query returning a `count` column.
+    async fn fetch_count(self, conn: impl ToConnectionRequest<'_>) -> Result<i64, Error> {
+        let start = Instant::now();
+
+        let result = match self.execute_internal(conn).await?.pop() {
+            None => Ok(0),
+            Some(exists) => Ok(Exists::from_row(exists)?.count),
+        };
+
+        self.log(start.elapsed());
+
+        result
+    }
+
+    /// Fetch a page of records along with the total number of records matching the query,
+    /// e.g. to render pagination controls. Pages start at 1. Pages before the first page
+    /// or after the last page contain no records.
     ///
     /// # Example
-    /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// #[derive(Clone, macros::Model)]
-    /// #[has_many(Project)]
-    /// struct User {
-    ///    id: Option<i64>,
-    ///    email: String,
-    /// }
-    /// #[derive(Clone, macros::Model)]
-    /// #[belongs_to(User)]
-    /// struct Project {
-    ///     user_id: i64,
-    ///     name: String,
-    /// }
-    ///
-    /// let alice = User { id: Some(1), email: "alice@test.com".into() };
-    /// let bob = User { id: Some(2), email: "bob@test.com".into() };
     ///
-    /// let projects = User::related::<Project>(&[alice, bob]);
+    /// ```ignore
+    /// let page = User::filter("admin", false)
+    ///     .order("id")
+    ///     .page(2, 25, &mut conn)
+    ///     .await?;
     ///
-    /// assert_eq!(
-    ///     projects.to_sql(),
-    ///     r#"SELECT * FROM "projects" WHERE "projects"."user_id" = ANY($1)"#
-    /// );
+    /// println!("page {} of {}", page.page, page.total_pages());
     /// ```
-    fn related<F: Association<Self>>(models: &[impl Model]) -> Query<F> {
-        let fks = models
-            .iter()
-            .filter(|model| !model.id().is_null())
-            .map(|fk| fk.id())
-            .collect::<Vec<_>>();
-        F::all().filter(Self::foreign_key(), fks.as_slice())
+    pub async fn page(
+        self,
+        page: i64,
+        per_page: i64,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<Page<T>, Error> {
+        let select = match self {
+            Query::Select(select) => select,
+            _ => return Err(Error::Unknown("page requires a SELECT query".into())),
+        };
+
+        let request = conn.to_connection_request()?;
+        let mut conn = request.get(select.replica()).await?;
+
+        let client = match request.connection() {
+            Some(conn) => conn,
+            None => conn.as_mut().unwrap(),
+        };
+
+        let per_page = per_page.max(1);
+
+        let mut count = select.clone();
+        count.order_by = OrderBy::default();
+        count.limit = Limit::default();
+        let total = Query::Select(count).count(&mut *client).await?;
+
+        let items = if page < 1 {
+            vec![]
+        } else {
+            Query::Select(select.limit(per_page).offset((page - 1) * per_page))
+                .fetch_all(&mut *client)
+                .await?
+        };
+
+        Ok(Page {
+            items,
+            total,
+            page,
+            per_page,
+        })
     }
 
-    /// Save a model into the database. If a record already exists, it will be updated. If this is a new record,
-    /// it will be inserted.
+    /// Fetch the values of a single column in the records matching the query, without constructing models.
+    /// The column replaces the columns selected by the query. Filters, ordering and limits are kept.
     ///
     /// # Example
+    ///
+    /// ```ignore
+    /// let ids = User::filter("admin", true)
+    ///     .pluck::<i64>("id", &mut conn)
+    ///     .await?;
     /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// // Save an existing user.
-    /// let user = User { id: Some(1), email: "test@test.com".into() };
-    /// assert_eq!(
-    ///     user.save().to_sql(),
-    ///     r#"UPDATE "users" SET "email" = $2 WHERE "id" = $1 RETURNING *"#,
-    /// );
     ///
-    /// // Create new user.
-    /// let new_user = User { id: None, email: "alice@test.com".into() };
-    /// assert_eq!(
-    ///     new_user.save().to_sql(),
-    ///     r#"INSERT INTO "users" ("email") VALUES ($1) RETURNING *"#,
-    /// );
+    /// ```sql
+    /// SELECT "users"."id" FROM "users" WHERE "users"."admin" = $1
     /// ```
-    fn save(self) -> Query<Self> {
-        match self.id().is_null() {
-            false => Query::Update(Update::new(self)),
-            true => Query::Insert(Insert::new(self)),
+    pub async fn pluck<V>(
+        self,
+        column: impl ToColumn,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<Vec<V>, Error>
+    where
+        V: for<'a> tokio_postgres::types::FromSql<'a>,
+    {
+        let query = self.pluck_query(&[column.to_column()])?;
+        let rows = query.execute_internal(conn).await?;
+
+        let mut values = Vec::with_capacity(rows.len());
+        for row in &rows {
+            values.push(row.try_get(0)?);
         }
+
+        Ok(values)
     }
 
-    /// Create new record of this model. All columns that have a `NOT NULL` constraint and
-    /// no default value should be provided.
+    /// Fetch the values of two columns in the records matching the query, like [`Query::pluck`].
     ///
     /// # Example
-    /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// let user = User::create(&[
-    ///     ("email", "bob@test.com"),
-    /// ]);
     ///
-    /// assert_eq!(
-    ///     user.to_sql(),
-    ///     r#"INSERT INTO "users" ("email") VALUES ($1) RETURNING *"#,
-    /// );
+    /// ```ignore
+    /// let emails = User::all()
+    ///     .pluck2::<i64, String>("id", "email", &mut conn)
+    ///     .await?;
     /// ```
-    fn create(attributes: &[(impl ToColumn, impl ToValue)]) -> Query<Self> {
-        let columns = attributes
-            .iter()
-            .map(|(c, _)| c.to_column())
-            .collect::<Vec<_>>();
-        let values = attributes
-            .iter()
-            .map(|(_, v)| v.to_value())
-            .collect::<Vec<_>>();
+    pub async fn pluck2<A, B>(
+        self,
+        first: impl ToColumn,
+        second: impl ToColumn,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<Vec<(A, B)>, Error>
+    where
+        A: for<'a> tokio_postgres::types::FromSql<'a>,
+        B: for<'a> tokio_postgres::types::FromSql<'a>,
+    {
+        let query = self.pluck_query(&[first.to_column(), second.to_column()])?;
+        let rows = query.execute_internal(conn).await?;
+
+        let mut values = Vec::with_capacity(rows.len());
+        for row in &rows {
+            values.push((row.try_get(0)?, row.try_get(1)?));
+        }
 
-        Query::Insert(Insert::from_columns(&columns, &values))
+        Ok(values)
     }
 
-    /// Find an existing record matching the column filters or create a new one
-    /// if none already exist. It's is equivalent to running [`Model::filter`]
-    /// and [`Model::create`] manually.
+    /// Select only the specified columns, qualified with the table name unless they already are.
+    fn pluck_query(self, columns: &[Column]) -> Result<Self, Error> {
+        let mut select = match self {
+            Query::Select(select) => select,
+            _ => return Err(Error::Unknown("pluck requires a SELECT query".into())),
+        };
+
+        let columns = columns
+            .iter()
+            .map(|column| {
+                if !column.qualified() {
+                    column.clone().qualify(&select.table_name)
+                } else {
+                    column.clone()
+                }
+            })
+            .collect::<Vec<_>>();
+        select.columns = Columns::pick(&columns);
+
+        Ok(Query::Select(select))
+    }
+
+    /// Fetch the values of a single column in batches of `batch_size` rows and pass
+    /// each batch to `f`.
+    ///
+    /// Rows are iterated in primary key order using keyset pagination, so only one batch
+    /// is held in memory at a time and models are never constructed. Any ordering or limit
+    /// already set on the query is replaced.
     ///
     /// # Example
+    ///
+    /// ```ignore
+    /// User::filter("admin", false)
+    ///     .pluck_in_batches::<i64, _, _>("id", 1_000, Pool::pool(), |ids| async move {
+    ///         // Enqueue a job per user, etc.
+    ///         Ok(())
+    ///     })
+    ///     .await?;
     /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
-    /// User::find_or_create_by(&[
-    ///     ("email", "john@test.com"),
-    /// ]);
-    /// ```
-    fn find_or_create_by(attributes: &[(impl ToColumn, impl ToValue)]) -> Query<Self> {
-        let columns = attributes
-            .iter()
-            .map(|(c, _)| c.to_column())
-            .collect::<Vec<_>>();
-        let values = attributes
-            .iter()
-            .map(|(_, v)| v.to_value())
-            .collect::<Vec<_>>();
+    pub async fn pluck_in_batches<V, F, Fut>(
+        self,
+        column: impl ToColumn,
+        batch_size: i64,
+        conn: impl ToConnectionRequest<'_>,
+        mut f: F,
+    ) -> Result<(), Error>
+    where
+        V: for<'a> tokio_postgres::types::FromSql<'a>,
+        F: FnMut(Vec<V>) -> Fut,
+        Fut: Future<Output = Result<(), Error>>,
+    {
+        let select = match self {
+            Query::Select(select) => select,
+            _ => {
+                return Err(Error::Unknown(
+                    "pluck_in_batches requires a SELECT query".into(),
+                ))
+            }
+        };
 
-        let mut select = Query::<Self>::select(Self::table_name());
+        let request = conn.to_connection_request()?;
+        let mut conn = request.get(select.replica()).await?;
 
-        for (column, value) in columns.iter().zip(values.iter()) {
-            select = select.filter(column.clone(), value.clone());
+        let client = match request.connection() {
+            Some(conn) => conn,
+            None => conn.as_mut().unwrap(),
+        };
+
+        let batch_size = batch_size.max(1);
+        let primary_key = Column::new(select.table_name.as_str(), &select.primary_key);
+        let column = {
+            let column = column.to_column();
+            if !column.qualified() {
+                column.qualify(&select.table_name)
+            } else {
+                column
+            }
+        };
+        let columns = Columns::pick(&[primary_key, column]);
+
+        let mut last: Option<Value> = None;
+
+        loop {
+            let rows = Self::next_batch(&select, &columns, batch_size, &mut last, client).await?;
+
+            let mut values = Vec::with_capacity(rows.len());
+            for row in &rows {
+                values.push(row.try_get(1)?);
+            }
+
+            if !values.is_empty() {
+                f(values).await?;
+            }
+
+            if (rows.len() as i64) < batch_size {
+                break;
+            }
         }
 
-        let select = match select {
+        Ok(())
+    }
+
+    /// Fetch the records matching the query in batches of `batch_size` and pass
+    /// each record to `f`, one at a time.
+    ///
+    /// Records are iterated in primary key order using keyset pagination, so only one batch
+    /// is held in memory at a time, no matter how many records match the query. Any ordering
+    /// or limit already set on the query is replaced. If only some columns are selected,
+    /// they must include the primary key.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// User::filter("admin", false)
+    ///     .find_each(1_000, Pool::pool(), |user| async move {
+    ///         // Send an email to the user, etc.
+    ///         Ok(())
+    ///     })
+    ///     .await?;
+    /// ```
+    pub async fn find_each<F, Fut>(
+        self,
+        batch_size: i64,
+        conn: impl ToConnectionRequest<'_>,
+        mut f: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(T) -> Fut,
+        Fut: Future<Output = Result<(), Error>>,
+    {
+        let select = match self {
             Query::Select(select) => select,
-            _ => unreachable!(),
+            _ => return Err(Error::Unknown("find_each requires a SELECT query".into())),
         };
 
-        let insert = Insert::<Self>::from_columns(&columns, &values);
+        let request = conn.to_connection_request()?;
+        let mut conn = request.get(select.replica()).await?;
 
-        Query::InsertIfNotExists {
-            select,
-            insert,
-            created: false,
+        let client = match request.connection() {
+            Some(conn) => conn,
+            None => conn.as_mut().unwrap(),
+        };
+
+        let batch_size = batch_size.max(1);
+        let columns = select.columns.clone();
+        let mut last: Option<Value> = None;
+
+        loop {
+            let rows = Self::next_batch(&select, &columns, batch_size, &mut last, client).await?;
+            let done = (rows.len() as i64) < batch_size;
+
+            for row in rows {
+                f(T::from_row(row)?).await?;
+            }
+
+            if done {
+                break;
+            }
         }
+
+        Ok(())
     }
 
-    /// Lock all records for the duration of the transaction. No other transaction will be able
-    /// to access those records until the current one is finished.
+    /// Fetch up to `batch_size` rows after the row with the `last` primary key, in primary key order,
+    /// and set `last` to the primary key of the last row fetched. Unlike `OFFSET`, every batch is found
+    /// using the primary key index, so batches deep into a large table are as fast as the first one.
+    async fn next_batch(
+        select: &Select<T>,
+        columns: &Columns,
+        batch_size: i64,
+        last: &mut Option<Value>,
+        client: &mut ConnectionGuard,
+    ) -> Result<Vec<tokio_postgres::Row>, Error> {
+        let primary_key = Column::new(select.table_name.as_str(), &select.primary_key);
+        let mut batch = select.clone();
+        batch.columns = columns.clone();
+
+        if let Some(last) = last.take() {
+            batch = batch.filter_gt(primary_key.clone(), last);
+        }
+
+        let query = Query::Select(batch.order_by(OrderBy::asc(primary_key)).limit(batch_size));
+
+        let start = Instant::now();
+        let rows = query.execute_internal(&mut *client).await?;
+        query.log(start.elapsed());
+
+        if let Some(row) = rows.last() {
+            *last = Some(row.try_get(select.primary_key.as_str())?);
+        }
+
+        Ok(rows)
+    }
+
+    /// Execute a query and return an optional result.
+    pub async fn execute(self, conn: impl ToConnectionRequest<'_>) -> Result<Vec<T>, Error> {
+        let start = Instant::now();
+        let request = conn.to_connection_request()?;
+        let mut conn = request.get(self.replica()).await?;
+
+        let client = match request.connection() {
+            Some(conn) => conn,
+            None => conn.as_mut().unwrap(),
+        };
+
+        let mut results = vec![];
+        let rows = self.execute_internal(&mut *client).await?;
+        for row in rows {
+            results.push(T::from_row(row)?)
+        }
+        let time = start.elapsed();
+
+        self.log(time);
+
+        match &self {
+            Query::Insert(insert) if insert.callbacks() => {
+                for record in &results {
+                    record.after_create(client).await?;
+                }
+            }
+
+            Query::Update(update) if update.callbacks() => {
+                for record in &results {
+                    record.after_update(client).await?;
+                }
+            }
+
+            _ => (),
+        }
+
+        Ok(results)
+    }
+
+    /// Execute the query and return the number of rows it changed, e.g. the number
+    /// of records updated by [`Query::update_all`]. Updated rows are not fetched.
     ///
-    /// It's not common to lock all rows in a table, so this function is typically chained with
-    /// [`Model::filter`].
+    /// Raw queries are executed the same way, so this returns the number of rows
+    /// changed by statements without a `RETURNING` clause, like `DELETE`.
     ///
     /// # Example
+    ///
+    /// ```ignore
+    /// let archived = User::filter("active", false)
+    ///     .update_all(&[("archived", true)])
+    ///     .execute_returning_count(&mut conn)
+    ///     .await?;
     /// ```
-    /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// #[derive(Clone, macros::Model)]
-    /// # struct User {
-    /// #    id: Option<i64>,
-    /// #    email: String,
-    /// # }
+    pub async fn execute_returning_count(
+        self,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<u64, Error> {
+        let query = match self {
+            // Saving a model needs the updated record, for callbacks and checking its lock version.
+            Query::Update(update) if !update.callbacks() && !update.locked() => {
+                Query::Update(update.without_returning())
+            }
+            Query::Raw { .. } => self,
+            query => return Ok(query.execute(conn).await?.len() as u64),
+        };
+
+        let start = Instant::now();
+        let request = conn.to_connection_request()?;
+        let mut conn = request.get(false).await?;
+
+        let client = match request.connection() {
+            Some(conn) => conn,
+            None => conn.as_mut().unwrap(),
+        };
+
+        let sql = query.to_sql();
+        let placeholders = match &query {
+            Query::Update(update) => &update.placeholders,
+            Query::Raw { placeholders, .. } => placeholders,
+            _ => unreachable!(),
+        };
+        placeholders.check(&sql)?;
+        let result = client.execute_cached(&sql, &placeholders.values()).await;
+
+        match result {
+            Ok(rows) => {
+                query.log(start.elapsed());
+                Ok(rows)
+            }
+            Err(err) => {
+                query.log_error(&err);
+                Err(err)
+            }
+        }
+    }
+
+    fn type_name() -> String {
+        std::any::type_name::<T>()
+            .split("::")
+            .skip(1)
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    fn action(&self) -> &'static str {
+        match self {
+            Query::Select(_) | Query::Picked(_) => "load",
+            Query::Update(_) => "save",
+            Query::Raw { .. } => "query",
+            Query::Insert(_) => "save",
+            Query::InsertIfNotExists { .. } => "load/create",
+        }
+    }
+
+    fn log(&self, duration: Duration) {
+        let general = &get_config().general;
+
+        let level = match log_level(
+            duration,
+            general.log_queries,
+            general.slow_query_threshold(),
+        ) {
+            Some(level) => level,
+            None => return,
+        };
+
+        self.log_event(level, duration, general.log_json());
+    }
+
+    fn log_event(&self, level: Level, duration: Duration, json: bool) {
+        let duration_ms = duration.as_secs_f64() * 1000.0;
+
+        // `tracing` needs the level of an event at compile time.
+        macro_rules! event {
+            ($level:expr, $time:expr) => {
+                if json {
+                    tracing::event!(
+                        $level,
+                        model = Self::type_name(),
+                        action = self.action(),
+                        duration_ms,
+                        query = self.to_sql(),
+                        request_id = RequestId::current(),
+                    )
+                } else {
+                    tracing::event!(
+                        $level,
+                        "{}{} {} ({}) {}",
+                        request_id(),
+                        Self::type_name().green(),
+                        self.action().purple(),
+                        $time,
+                        self.to_sql()
+                    )
+                }
+            };
+        }
+
+        let time = format!("{:.3} ms", duration_ms);
+
+        match level {
+            Level::WARN => event!(Level::WARN, time.red()),
+            Level::DEBUG => event!(Level::DEBUG, time),
+            _ => event!(Level::INFO, time),
+        }
+    }
+
+    fn log_error(&self, err: &Error) {
+        if get_config().general.log_json() {
+            error!(
+                model = Self::type_name(),
+                action = self.action(),
+                query = self.to_sql(),
+                request_id = RequestId::current(),
+                error = %err,
+            )
+        } else {
+            error!(
+                "{}{} {} {} {}",
+                request_id(),
+                Self::type_name().green(),
+                self.action().purple(),
+                self.to_sql(),
+                err,
+            )
+        }
+    }
+}
+
+pub type Scope<T> = Query<T>;
+
+/// Identifier of the request running the query, if any, prepended to query log lines.
+fn request_id() -> String {
+    match RequestId::current() {
+        Some(id) => format!("[{}] ", id),
+        None => String::new(),
+    }
+}
+
+/// Level to log a query at, if any. Slow queries are logged as warnings even if query logging is disabled.
+fn log_level(
+    duration: Duration,
+    log_queries: bool,
+    slow_query_threshold: Option<time::Duration>,
+) -> Option<Level> {
+    match slow_query_threshold {
+        Some(threshold) if duration >= threshold => Some(Level::WARN),
+        Some(_) if log_queries => Some(Level::DEBUG),
+        None if log_queries => Some(Level::INFO),
+        _ => None,
+    }
+}
+
+/// Implements Object-relational mapping (ORM) methods for a Rust struct, turning it into a database model. This trait doesn't have to be implemented manually. You can use the [`rwf_macros::Model`] macro instead, for example:
+///
+/// ```
+/// # use rwf::prelude::*;
+/// #[derive(Clone, macros::Model)]
+/// struct User {
+///     id: Option<i64>,
+///     email: String,
+/// }
+/// ```
+///
+/// Structs that wish to implement this trait need to implement two additional traits:
+///
+/// - [`FromRow`]
+/// - [`Clone`]
+///
+/// When usindg the [`rwf_macros::Model`] derive, [`FromRow`] is derived automatically.
+pub trait Model: FromRow {
+    /// Name of the PostgreSQL table where records for this model are stored.
     ///
-    /// let lock = User::lock()
-    ///     .filter("email", "test@test.com");
+    /// The name must not be fully qualified
+    ///  e.g. `"users"` is correct, while `r#"public"."users"#` won't work. Use [`Model::schema`] instead.
     ///
-    /// assert_eq!(
-    ///     lock.to_sql(),
-    ///     r#"SELECT * FROM "users" WHERE "users"."email" = $1 FOR UPDATE"#,
-    /// );
-    /// ```
-    fn lock() -> Query<Self> {
-        Self::all().lock()
+    /// This method is implemented automatically by the [`rwf_macros::Model`] derive. If you wish to override
+    /// that implementation, use `#[table_name("your_name")]` derive attribute.
+    fn table_name() -> &'static str;
+
+    /// Schema of the PostgreSQL table, if it's not in the search path, e.g. `"public"`. Queries
+    /// refer to the table as `"schema"."table"`.
+    ///
+    /// Use `#[schema("your_schema")]` derive attribute to set it. To change it for a single query,
+    /// use [`Query::schema`].
+    fn schema() -> Option<&'static str> {
+        None
     }
 
-    /// Refresh the record from the database. This is equivalent to calling [`Model::find`] with
-    /// the primary key as the parameter.
+    /// Get the fully qualified column name for this table.
     ///
     /// # Example
+    ///
     /// ```
     /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
     /// # #[derive(Clone, macros::Model)]
     /// # struct User {
     /// #    id: Option<i64>,
     /// #    email: String,
     /// # }
-    /// let user = User { id: Some(1), email: "test@test.com".into() };
+    /// let column = User::column("email");
     ///
-    /// assert_eq!(
-    ///     User::find(1).to_sql(),
-    ///     user.reload().to_sql()
-    /// );
+    /// assert_eq!(column.to_string(), r#""users"."email""#);
     /// ```
-    fn reload(self) -> Query<Self> {
-        Self::find(self.id())
+    fn column(name: &str) -> Column {
+        Column::new(Self::table_name(), name)
     }
 
-    /// Convert the model to JSON representation.
+    /// List of names of columns stored in the PostgreSQL table.
+    ///
+    /// Names must not be fully qualified or contain
+    /// double quotes, e.g. `"id"` is correct, while `"users"."id"` won't work.
+    ///
+    /// This method is implemented automatically by the [`rwf_macros::Model`] derive.
+    ///
+    /// # Example
+    /// ```
+    /// fn column_names() -> &'static [&'static str] {
+    ///     &["id", "email"]
+    /// }
+    /// ```
+    fn column_names() -> &'static [&'static str];
+
+    /// The primary key value, if one exists, for the instance of a model.
+    ///
+    /// Primary keys are not required to use the ORM, but are generally needed to perform
+    /// updates and deletes. If the model doesn't have a primary key, you can return [`Value::Null`].
+    ///
+    /// This method is implemented automatically by the [`rwf_macros::Model`] derive.
     ///
     /// # Example
+    ///
     /// ```
     /// # use rwf::prelude::*;
-    /// # use rwf::model::ToSql;
-    /// # #[derive(Clone, macros::Model)]
+    /// # use rwf::model::Value;
+    /// # use rwf::macros::FromRow;
+    /// # #[derive(Clone, FromRow)]
     /// # struct User {
     /// #    id: Option<i64>,
     /// #    email: String,
     /// # }
-    /// use serde_json::json;
+    /// # impl Model for User {
+    /// # fn table_name() -> &'static str { "" }
+    /// # fn values(&self) -> Vec<Value> { vec![] }
+    /// # fn column_names() -> &'static [&'static str] { &[] }
+    /// # fn foreign_key() -> &'static str { "" }
+    /// fn id(&self) -> Value {
+    ///     self.id.to_value()
+    /// }
+    /// # }
+    /// ```
     ///
-    /// let user = User { id: Some(1), email: "test@test.com".into() };
+    fn id(&self) -> Value;
+
+    /// The primary key encrypted with [`crypto::encrypt_number`](crate::crypto::encrypt_number), for building
+    /// URLs handled by the [`SecureId`](crate::controller::middleware::SecureId) middleware, e.g. `/users/<secure id>`.
+    /// Returns `None` if the record doesn't have an integer primary key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::crypto::decrypt_number;
+    /// #[derive(Clone, macros::Model)]
+    /// struct User {
+    ///     id: Option<i64>,
+    ///     email: String,
+    /// }
+    ///
+    /// let user = User { id: Some(25), email: "test@test.com".into() };
+    /// let id = user.secure_id().unwrap();
+    ///
+    /// assert_eq!(decrypt_number(&id).unwrap(), 25);
+    /// ```
+    fn secure_id(&self) -> Option<String> {
+        crate::crypto::encrypt_number(self.id().as_integer()?).ok()
+    }
+
+    /// List of column values for a particular instance of a model. The values must be in the same
+    /// order as the columns in [`Model::column_names`].
+    ///
+    /// The values are Rust types converted to the [`Value`] enum. This conversion is required so
+    /// the ORM can then convert them to PostgreSQL types automatically. Most Rust types can be converted
+    /// to [`Value`] automatically as well with [`ToValue::to_value`].
+    ///
+    /// This method is implemented automatically by the [`rwf_macros::Model`] derive.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::Value;
+    /// # use rwf::macros::FromRow;
+    /// # #[derive(Clone, FromRow)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// # impl Model for User {
+    /// # fn table_name() -> &'static str { "" }
+    /// # fn id(&self) -> Value { Value::Null }
+    /// # fn column_names() -> &'static [&'static str] { &[] }
+    /// # fn foreign_key() -> &'static str { "" }
+    /// fn values(&self) -> Vec<Value> {
+    ///     vec![
+    ///         1_i64.to_value(),
+    ///         "test@test.com".to_value(),
+    ///     ]
+    /// }
+    /// # }
+    /// ```
+    fn values(&self) -> Vec<Value>;
+
+    /// The name of a column in another PostgreSQL table which refers to this model.
+    ///
+    /// For example, if the table name for this model is `"users"`, this method could return `"user_id"`.
+    ///
+    /// This method is implemented automatically by the [`rwf_macros::Model`] derive. If you wish to override
+    /// that implementation, use `#[foreign_key("your_fk")]` derive attribute.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// fn foreign_key() -> &'static str {
+    ///     "user_id"
+    /// }
+    /// ```
+    fn foreign_key() -> &'static str;
+
+    /// Name of the primary key column in the database.
+    ///
+    /// This is typically `"id"`, but can be any other column as long as it has a `UNIQUE NOT NULL` constraint
+    /// and a default value produced from a sequence.
+    fn primary_key() -> &'static str {
+        "id"
+    }
+
+    /// The record exists in the database. Used by [`Model::save`] to decide
+    /// between an `INSERT` and an `UPDATE`.
+    ///
+    /// By default, records with a primary key are considered saved. Override this if the primary key
+    /// is generated by the application, e.g. a UUID assigned before the record is inserted.
+    ///
+    /// With the [`rwf_macros::Model`] derive, use the `persisted` attribute to name the method
+    /// to call instead.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// #[derive(Clone, macros::Model)]
+    /// #[persisted(saved)]
+    /// struct Token {
+    ///     id: Uuid,
+    ///     name: String,
+    ///     created_at: Option<OffsetDateTime>,
+    /// }
+    ///
+    /// impl Token {
+    ///     fn saved(&self) -> bool {
+    ///         self.created_at.is_some()
+    ///     }
+    /// }
     ///
+    /// let token = Token { id: Uuid::new_v4(), name: "api".into(), created_at: None };
+    /// assert!(!token.is_persisted());
     /// assert_eq!(
-    ///     user.to_json().unwrap(),
-    ///     json!({
-    ///         "id": 1,
-    ///         "email": "test@test.com",
-    ///     }),
+    ///     token.save().to_sql(),
+    ///     r#"INSERT INTO "tokens" ("name", "created_at", "id") VALUES ($1, $2, $3) RETURNING *"#,
     /// );
     /// ```
-    fn to_json(&self) -> Result<serde_json::Value, Error> {
-        let columns = Self::column_names();
-        let values = self.values();
+    fn is_persisted(&self) -> bool {
+        !self.id().is_null()
+    }
 
-        let mut map = serde_json::Map::new();
-        for (column, value) in columns.iter().zip(values.iter()) {
-            map.insert(column.to_string(), value.clone().into());
-        }
+    /// Integer column used for optimistic locking, if any. When saving the record, the update
+    /// only succeeds if the column still has the value the record was loaded with, and the column is incremented.
+    /// If the record was updated by someone else in the meantime, [`Error::StaleObject`] is returned instead.
+    ///
+    /// The `Model` derive returns `"lock_version"` if the model has a `lock_version` field.
+    /// Use `#[lock_version("your_column")]` derive attribute to use another column.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// #[derive(Clone, macros::Model)]
+    /// struct User {
+    ///     id: Option<i64>,
+    ///     email: String,
+    ///     lock_version: i64,
+    /// }
+    ///
+    /// let user = User { id: Some(1), email: "test@test.com".into(), lock_version: 3 };
+    ///
+    /// assert_eq!(User::lock_version(), Some("lock_version"));
+    /// assert_eq!(
+    ///     user.save().to_sql(),
+    ///     r#"UPDATE "users" SET "email" = $3, "lock_version" = $4 WHERE "id" = $1 AND "lock_version" = $2 RETURNING *"#,
+    /// );
+    /// ```
+    fn lock_version() -> Option<&'static str> {
+        None
+    }
 
-        map.insert("id".into(), self.id().into());
+    /// Timestamp column marking records as deleted, if the model uses soft delete. Records with the column
+    /// set are excluded from all queries, unless [`Query::with_deleted`] or [`Query::only_deleted`] is used.
+    ///
+    /// Use `#[soft_delete]` derive attribute to use the `deleted_at` column, or `#[soft_delete("your_column")]`
+    /// to use another one.
+    fn soft_delete_column() -> Option<&'static str> {
+        None
+    }
 
-        Ok(serde_json::Value::Object(map))
+    /// Mark the record as deleted by setting the soft delete column to the current time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::prelude::*;
+    /// #[derive(Clone, macros::Model)]
+    /// #[soft_delete("removed_at")]
+    /// struct User {
+    ///     id: Option<i64>,
+    ///     removed_at: Option<OffsetDateTime>,
+    /// }
+    ///
+    /// let user = User { id: Some(1), removed_at: None };
+    ///
+    /// assert_eq!(
+    ///     user.soft_delete().to_sql(),
+    ///     r#"UPDATE "users" SET "removed_at" = $2 WHERE "id" = $1 RETURNING *"#
+    /// );
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// The model doesn't use soft delete, see [`Model::soft_delete_column`].
+    fn soft_delete(self) -> Query<Self> {
+        let column = Self::soft_delete_column().unwrap_or_else(|| {
+            panic!(
+                "model \"{}\" doesn't use soft delete",
+                std::any::type_name::<Self>()
+            )
+        });
+
+        Query::Update(Update::from_columns(
+            self.id(),
+            &[column],
+            &[time::OffsetDateTime::now_utc()],
+        ))
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::join::AssociationType;
-    use super::*;
-    use tokio_postgres::row::Row;
+    /// Select one record from the table. The row returned is determined by the database.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = User::take_one();
+    ///
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" LIMIT 1"#);
+    /// ```
+    fn take_one() -> Query<Self> {
+        Query::select(Self::table_name()).take_one()
+    }
+
+    /// Select _n_ records from the table. The order of rows returned is determined by the database.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = User::take_many(25);
+    ///
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" LIMIT 25"#);
+    /// ```
+    fn take_many(n: i64) -> Query<Self> {
+        Query::select(Self::table_name()).take_many(n)
+    }
+
+    /// Select the first record from the table, ordered by primary key.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = User::first_one();
+    ///
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" ORDER BY "users"."id" ASC LIMIT 1"#);
+    /// ```
+    fn first_one() -> Query<Self> {
+        Query::select(Self::table_name()).first_one()
+    }
+
+    /// Select the first _n_ records from the table, ordered by primary key.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = User::first_many(25);
+    ///
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" ORDER BY "users"."id" ASC LIMIT 25"#);
+    /// ```
+    fn first_many(n: i64) -> Query<Self> {
+        Query::select(Self::table_name()).first_many(n)
+    }
+
+    /// Fetch the first record from the table, ordered by primary key.
+    /// Returns `None` if the table is empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let user = User::first(&mut conn).await?;
+    /// ```
+    fn first<'a>(
+        conn: impl ToConnectionRequest<'a> + Send + 'a,
+    ) -> impl Future<Output = Result<Option<Self>, Error>> + Send + 'a
+    where
+        Self: Sized + Sync + 'a,
+    {
+        Self::first_one().fetch_optional(conn)
+    }
+
+    /// Fetch the last record from the table, ordered by primary key.
+    /// Returns `None` if the table is empty.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let user = User::last(&mut conn).await?;
+    /// ```
+    fn last<'a>(
+        conn: impl ToConnectionRequest<'a> + Send + 'a,
+    ) -> impl Future<Output = Result<Option<Self>, Error>> + Send + 'a
+    where
+        Self: Sized + Sync + 'a,
+    {
+        Self::order((Self::column(Self::primary_key()), "DESC"))
+            .take_one()
+            .fetch_optional(conn)
+    }
+
+    /// Select all records from the table. Typically this is a starting point for filtering
+    /// by some column(s), but all records can also be returned. Order of records returned is
+    /// determined by the database, unless additional filters are specified.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = User::all();
+    ///
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users""#);
+    /// ```
+    fn all() -> Query<Self> {
+        Query::select(Self::table_name())
+    }
+
+    /// Filter table records by a column. Multiple calls to filter can be chained to filter by multiple columns.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = User::filter("email", "test@test.com");
+    ///
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" WHERE "users"."email" = $1"#);
+    /// ```
+    fn filter(column: impl ToColumn, value: impl ToValue) -> Query<Self> {
+        Query::select(Self::table_name()).filter(column, value)
+    }
+
+    /// Filter table records by a column and fetch the first matching record. The record will match
+    /// the filter, but if there are mulitple records that match, any one of them can be returned.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = User::find_by("email", "test@test.com");
+    ///
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" WHERE "users"."email" = $1 LIMIT 1"#);
+    /// ```
+    fn find_by(column: impl ToColumn, value: impl ToValue) -> Query<Self> {
+        Query::select(Self::table_name())
+            .find_by(column, value.to_value())
+            .take_one()
+    }
+
+    /// Filter by primary key and return the matching row, if any.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = User::find(1);
+    ///
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM "users" WHERE "users"."id" = $1 LIMIT 1"#);
+    /// ```
+    fn find(value: impl ToValue) -> Query<Self> {
+        Query::select(Self::table_name())
+            .find_by(Self::primary_key(), value.to_value())
+            .take_one()
+    }
+
+    /// Find records using an arbitrary SQL query. The caller must ensure that all required columns
+    /// are returned.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let query = User::find_by_sql("SELECT * FROM users WHERE email = ANY($1, $2) ORDER BY RANDOM()",
+    ///     &[
+    ///         "bob@test.com".into(),
+    ///         "alice@test.com".into(),
+    ///     ]
+    /// );
+    ///
+    /// assert_eq!(query.to_sql(), r#"SELECT * FROM users WHERE email = ANY($1, $2) ORDER BY RANDOM()"#);
+    /// ```
+    fn find_by_sql(query: impl ToString, values: &[Value]) -> Query<Self> {
+        Query::raw_with(query, values.to_vec())
+    }
+
+    /// Order records by a column. This method accepts any input type which implement
+    /// the [`ToOrderBy`] trait. Chaining this function allows to order by multiple columns.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let q1 = User::order("email");
+    /// let q2 = User::order("email DESC");
+    /// let q3 = User::order(("email", "DESC"));
+    /// let q4 = User::order((User::column("email"), "DESC"));
+    ///
+    /// assert_eq!(q1.to_sql(), r#"SELECT * FROM "users" ORDER BY email"#);
+    /// assert_eq!(q2.to_sql(), r#"SELECT * FROM "users" ORDER BY email DESC"#);
+    /// assert_eq!(q3.to_sql(), r#"SELECT * FROM "users" ORDER BY "email" DESC"#);
+    /// assert_eq!(q4.to_sql(), r#"SELECT * FROM "users" ORDER BY "users"."email" DESC"#);
+    /// ```
+    fn order(order: impl ToOrderBy) -> Query<Self> {
+        Self::all().order(order)
+    }
+
+    /// Join this model to another model with which it has a relationship. The relationship should be declared
+    /// in advance using an annotation.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// #[derive(Clone, macros::Model)]
+    /// #[has_many(Project)]
+    /// struct User {
+    ///    id: Option<i64>,
+    ///    email: String,
+    /// }
+    /// #[derive(Clone, macros::Model)]
+    /// #[belongs_to(User)]
+    /// struct Project {
+    ///     user_id: i64,
+    ///     name: String,
+    /// }
+    ///
+    /// let join = User::join::<Project>();
+    /// ```
+    fn join<F: Association<Self>>() -> Joined<Self, F> {
+        Joined::new(F::construct_join())
+    }
+
+    /// Filter all records which have a relationship to this model. Used for fetching multiple records at once
+    /// in order to avoid N+1 queries.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// #[derive(Clone, macros::Model)]
+    /// #[has_many(Project)]
+    /// struct User {
+    ///    id: Option<i64>,
+    ///    email: String,
+    /// }
+    /// #[derive(Clone, macros::Model)]
+    /// #[belongs_to(User)]
+    /// struct Project {
+    ///     user_id: i64,
+    ///     name: String,
+    /// }
+    ///
+    /// let alice = User { id: Some(1), email: "alice@test.com".into() };
+    /// let bob = User { id: Some(2), email: "bob@test.com".into() };
+    ///
+    /// let projects = User::related::<Project>(&[alice, bob]);
+    ///
+    /// assert_eq!(
+    ///     projects.to_sql(),
+    ///     r#"SELECT * FROM "projects" WHERE "projects"."user_id" = ANY($1)"#
+    /// );
+    /// ```
+    fn related<F: Association<Self>>(models: &[impl Model]) -> Query<F> {
+        let fks = models
+            .iter()
+            .filter(|model| !model.id().is_null())
+            .map(|fk| fk.id())
+            .collect::<Vec<_>>();
+        F::all().filter(Self::foreign_key(), fks.as_slice())
+    }
+
+    /// Fetch all records related to these models, like [`Model::related`], grouped by the primary key
+    /// of the model they belong to. Models without related records are not in the map.
+    ///
+    /// Only integer primary keys are supported.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let orders = User::related_grouped::<Order>(&users, &mut conn).await?;
+    ///
+    /// for user in &users {
+    ///     let user_orders = orders.get(&user.id.unwrap()).cloned().unwrap_or_default();
+    /// }
+    /// ```
+    fn related_grouped<'a, F: Association<Self> + Sync>(
+        models: &[impl Model],
+        conn: impl ToConnectionRequest<'a> + Send,
+    ) -> impl Future<Output = Result<HashMap<i64, Vec<F>>, Error>> + Send {
+        let query = Self::related::<F>(models);
+
+        async move {
+            let mut grouped: HashMap<i64, Vec<F>> = HashMap::new();
+
+            for record in query.fetch_all(conn).await? {
+                let key = preload::column_value(&record, Self::foreign_key())
+                    .and_then(|value| value.as_integer());
+
+                if let Some(key) = key {
+                    grouped.entry(key).or_default().push(record);
+                }
+            }
+
+            Ok(grouped)
+        }
+    }
+
+    /// Check that the table of this model has all the columns declared by the model,
+    /// returning [`Error::SchemaMismatch`] listing the missing columns if it doesn't. Extra columns in the table
+    /// are allowed.
+    ///
+    /// Useful to call when the application starts, to catch models which don't match the database after a migration,
+    /// before queries start failing.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// #[cfg(debug_assertions)]
+    /// User::verify_schema(&mut conn).await?;
+    /// ```
+    fn verify_schema<'a>(
+        conn: impl ToConnectionRequest<'a> + Send,
+    ) -> impl Future<Output = Result<(), Error>> + Send {
+        schema::verify::<Self>(conn)
+    }
+
+    /// Filter all records which have a many-to-many relationship to these models, through
+    /// the join table. Used for fetching multiple records at once in order to avoid N+1 queries.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let roles = User::related_through::<Role, UserRole>(&users);
+    ///
+    /// assert_eq!(
+    ///     roles.to_sql(),
+    ///     r#"SELECT "roles".* FROM "roles" INNER JOIN "user_roles" ON "roles"."id" = "user_roles"."role_id" WHERE "user_roles"."user_id" = ANY($1)"#
+    /// );
+    /// ```
+    fn related_through<F: HasManyThrough<Self, Through>, Through: Model>(
+        models: &[impl Model],
+    ) -> Query<F> {
+        let fks = models
+            .iter()
+            .filter(|model| !model.id().is_null())
+            .map(|fk| fk.id())
+            .collect::<Vec<_>>();
+
+        match F::all() {
+            Query::Select(select) => Query::Select(select.join(F::construct_through_join())),
+            query => query,
+        }
+        .filter(
+            Column::new(Through::table_name(), F::source_key()),
+            fks.as_slice(),
+        )
+    }
+
+    /// Save a model into the database. If a record already exists, it will be updated. If this is a new record,
+    /// it will be inserted.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// // Save an existing user.
+    /// let user = User { id: Some(1), email: "test@test.com".into() };
+    /// assert_eq!(
+    ///     user.save().to_sql(),
+    ///     r#"UPDATE "users" SET "email" = $2 WHERE "id" = $1 RETURNING *"#,
+    /// );
+    ///
+    /// // Create new user.
+    /// let new_user = User { id: None, email: "alice@test.com".into() };
+    /// assert_eq!(
+    ///     new_user.save().to_sql(),
+    ///     r#"INSERT INTO "users" ("email") VALUES ($1) RETURNING *"#,
+    /// );
+    /// ```
+    ///
+    /// [`Model::before_save`] is called first, and the model is then validated with [`Model::validation`].
+    /// If it's invalid, executing the query returns [`Error::Validation`] without sending it to the database. Once
+    /// the query is executed, [`Model::after_create`] or [`Model::after_update`] is called.
+    fn save(mut self) -> Query<Self> {
+        self.before_save();
+        let errors = self.validation().err();
+
+        match (self.is_persisted(), errors) {
+            (true, None) => Query::Update(Update::new(self)),
+            (true, Some(errors)) => Query::Update(Update::new(self).invalid(errors)),
+            (false, None) => Query::Insert(Insert::new(self)),
+            (false, Some(errors)) => Query::Insert(Insert::new(self).invalid(errors)),
+        }
+    }
+
+    /// Validate the model before it's saved. Valid by default.
+    ///
+    /// The `Model` derive calls [`Validate::validate`] if the struct has the `#[validate]` attribute.
+    fn validation(&self) -> Result<(), ValidationErrors> {
+        Ok(())
+    }
+
+    /// Called by [`Model::save`] before the query is built. Does nothing by default.
+    ///
+    /// The `Model` derive calls [`Callbacks::before_save`] if the struct has the `#[callbacks]` attribute.
+    fn before_save(&mut self) {}
+
+    /// Called after the query returned by [`Model::save`] inserts the record. Does nothing by default.
+    ///
+    /// The `Model` derive calls [`Callbacks::after_create`] if the struct has the `#[callbacks]` attribute.
+    fn after_create<'a>(
+        &'a self,
+        conn: &'a mut ConnectionGuard,
+    ) -> impl Future<Output = Result<(), Error>> + Send + 'a {
+        let _ = conn;
+        async { Ok(()) }
+    }
+
+    /// Called after the query returned by [`Model::save`] updates the record. Does nothing by default.
+    ///
+    /// The `Model` derive calls [`Callbacks::after_update`] if the struct has the `#[callbacks]` attribute.
+    fn after_update<'a>(
+        &'a self,
+        conn: &'a mut ConnectionGuard,
+    ) -> impl Future<Output = Result<(), Error>> + Send + 'a {
+        let _ = conn;
+        async { Ok(()) }
+    }
+
+    /// Create new record of this model. All columns that have a `NOT NULL` constraint and
+    /// no default value should be provided.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let user = User::create(&[
+    ///     ("email", "bob@test.com"),
+    /// ]);
+    ///
+    /// assert_eq!(
+    ///     user.to_sql(),
+    ///     r#"INSERT INTO "users" ("email") VALUES ($1) RETURNING *"#,
+    /// );
+    /// ```
+    fn create(attributes: &[(impl ToColumn, impl ToValue)]) -> Query<Self> {
+        let columns = attributes
+            .iter()
+            .map(|(c, _)| c.to_column())
+            .collect::<Vec<_>>();
+        let values = attributes
+            .iter()
+            .map(|(_, v)| v.to_value())
+            .collect::<Vec<_>>();
+
+        Query::Insert(Insert::from_columns(&columns, &values))
+    }
+
+    /// Find an existing record matching the column filters or create a new one
+    /// if none already exist. It's is equivalent to running [`Model::filter`]
+    /// and [`Model::create`] manually.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// User::find_or_create_by(&[
+    ///     ("email", "john@test.com"),
+    /// ]);
+    /// ```
+    fn find_or_create_by(attributes: &[(impl ToColumn, impl ToValue)]) -> Query<Self> {
+        let columns = attributes
+            .iter()
+            .map(|(c, _)| c.to_column())
+            .collect::<Vec<_>>();
+        let values = attributes
+            .iter()
+            .map(|(_, v)| v.to_value())
+            .collect::<Vec<_>>();
+
+        let mut select = Query::<Self>::select(Self::table_name());
+
+        for (column, value) in columns.iter().zip(values.iter()) {
+            select = select.filter(column.clone(), value.clone());
+        }
+
+        let select = match select {
+            Query::Select(select) => select,
+            _ => unreachable!(),
+        };
+
+        let insert = Insert::<Self>::from_columns(&columns, &values);
+
+        Query::InsertIfNotExists {
+            select,
+            insert,
+            created: false,
+        }
+    }
+
+    /// Lock all records for the duration of the transaction. No other transaction will be able
+    /// to access those records until the current one is finished.
+    ///
+    /// It's not common to lock all rows in a table, so this function is typically chained with
+    /// [`Model::filter`].
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    ///
+    /// let lock = User::lock()
+    ///     .filter("email", "test@test.com");
+    ///
+    /// assert_eq!(
+    ///     lock.to_sql(),
+    ///     r#"SELECT * FROM "users" WHERE "users"."email" = $1 FOR UPDATE"#,
+    /// );
+    /// ```
+    fn lock() -> Query<Self> {
+        Self::all().lock()
+    }
+
+    /// Refresh the record from the database. This is equivalent to calling [`Model::find`] with
+    /// the primary key as the parameter.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// let user = User { id: Some(1), email: "test@test.com".into() };
+    ///
+    /// assert_eq!(
+    ///     User::find(1).to_sql(),
+    ///     user.reload().to_sql()
+    /// );
+    /// ```
+    fn reload(self) -> Query<Self> {
+        Self::find(self.id())
+    }
+
+    /// Convert the model to JSON representation.
+    ///
+    /// # Example
+    /// ```
+    /// # use rwf::prelude::*;
+    /// # use rwf::model::ToSql;
+    /// # #[derive(Clone, macros::Model)]
+    /// # struct User {
+    /// #    id: Option<i64>,
+    /// #    email: String,
+    /// # }
+    /// use serde_json::json;
+    ///
+    /// let user = User { id: Some(1), email: "test@test.com".into() };
+    ///
+    /// assert_eq!(
+    ///     user.to_json().unwrap(),
+    ///     json!({
+    ///         "id": 1,
+    ///         "email": "test@test.com",
+    ///     }),
+    /// );
+    /// ```
+    fn to_json(&self) -> Result<serde_json::Value, Error> {
+        let columns = Self::column_names();
+        let values = self.values();
+
+        let mut map = serde_json::Map::new();
+        for (column, value) in columns.iter().zip(values.iter()) {
+            map.insert(column.to_string(), value.clone().into());
+        }
+
+        map.insert("id".into(), self.id().into());
+
+        Ok(serde_json::Value::Object(map))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::join::AssociationType;
+    use super::*;
+    use tokio_postgres::row::Row;
+
+    #[derive(Debug, Clone, Default)]
+    struct User {
+        id: i64,
+        email: String,
+        password: String,
+    }
+
+    impl Model for User {
+        fn id(&self) -> Value {
+            Value::Integer(self.id)
+        }
+
+        fn table_name() -> &'static str {
+            "users"
+        }
+
+        fn foreign_key() -> &'static str {
+            "user_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["email", "password"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.email.to_value(), self.password.to_value()]
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct Order {
+        id: i64,
+        user_id: i64,
+        amount: f64,
+    }
+
+    impl Model for Order {
+        fn id(&self) -> Value {
+            Value::Integer(self.id)
+        }
+
+        fn table_name() -> &'static str {
+            "orders"
+        }
+
+        fn foreign_key() -> &'static str {
+            "order_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["user_id", "amount"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.user_id.to_value(), self.amount.to_value()]
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct OrderItem {
+        id: i64,
+        order_id: i64,
+        product_id: i64,
+    }
+
+    impl Model for OrderItem {
+        fn id(&self) -> Value {
+            Value::Integer(self.id)
+        }
+
+        fn table_name() -> &'static str {
+            "order_items"
+        }
+
+        fn foreign_key() -> &'static str {
+            "order_item_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["order_id", "product_id"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.order_id.to_value(), self.product_id.to_value()]
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct Product {
+        id: i64,
+        name: String,
+    }
+
+    impl Model for Product {
+        fn id(&self) -> Value {
+            Value::Integer(self.id)
+        }
+
+        fn table_name() -> &'static str {
+            "products"
+        }
+
+        fn foreign_key() -> &'static str {
+            "product_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["name"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.name.to_value()]
+        }
+    }
+
+    impl Association<User> for Order {}
+
+    impl Association<Order> for User {
+        fn association_type() -> AssociationType {
+            AssociationType::HasMany
+        }
+    }
+
+    impl Association<Order> for OrderItem {}
+
+    impl Association<OrderItem> for Order {
+        fn association_type() -> AssociationType {
+            AssociationType::HasMany
+        }
+    }
+
+    impl Association<Product> for OrderItem {}
+    impl Association<OrderItem> for Product {
+        fn association_type() -> AssociationType {
+            AssociationType::HasMany
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct Role {
+        id: i64,
+        name: String,
+    }
+
+    impl Model for Role {
+        fn id(&self) -> Value {
+            Value::Integer(self.id)
+        }
+
+        fn table_name() -> &'static str {
+            "roles"
+        }
+
+        fn foreign_key() -> &'static str {
+            "role_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["name"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.name.to_value()]
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct UserRole {
+        id: i64,
+        user_id: i64,
+        role_id: i64,
+    }
+
+    impl Model for UserRole {
+        fn id(&self) -> Value {
+            Value::Integer(self.id)
+        }
+
+        fn table_name() -> &'static str {
+            "user_roles"
+        }
+
+        fn foreign_key() -> &'static str {
+            "user_role_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["user_id", "role_id"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.user_id.to_value(), self.role_id.to_value()]
+        }
+    }
+
+    impl HasManyThrough<User, UserRole> for Role {}
+
+    #[derive(Debug, Clone)]
+    struct Token {
+        id: uuid::Uuid,
+        name: String,
+        created_at: Option<time::OffsetDateTime>,
+    }
+
+    impl Model for Token {
+        fn id(&self) -> Value {
+            self.id.to_value()
+        }
+
+        fn table_name() -> &'static str {
+            "tokens"
+        }
+
+        fn foreign_key() -> &'static str {
+            "token_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["name"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.name.to_value()]
+        }
+
+        fn is_persisted(&self) -> bool {
+            self.created_at.is_some()
+        }
+    }
+
+    impl FromRow for Token {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            Ok(Token {
+                id: row.try_get("id")?,
+                name: row.try_get("name")?,
+                created_at: row.try_get("created_at")?,
+            })
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct Profile {
+        id: Option<i64>,
+        settings: serde_json::Value,
+    }
+
+    impl Model for Profile {
+        fn id(&self) -> Value {
+            self.id.to_value()
+        }
+
+        fn table_name() -> &'static str {
+            "profiles"
+        }
+
+        fn foreign_key() -> &'static str {
+            "profile_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["settings"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.settings.to_value()]
+        }
+    }
+
+    impl FromRow for Profile {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            Ok(Profile {
+                id: row.try_get("id")?,
+                settings: row.try_get("settings")?,
+            })
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct Post {
+        id: Option<i64>,
+        tags: Vec<String>,
+    }
+
+    impl Model for Post {
+        fn id(&self) -> Value {
+            self.id.to_value()
+        }
+
+        fn table_name() -> &'static str {
+            "posts"
+        }
+
+        fn foreign_key() -> &'static str {
+            "post_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["tags"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.tags.to_value()]
+        }
+    }
+
+    impl FromRow for Post {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            Ok(Post {
+                id: row.try_get("id")?,
+                tags: row.try_get("tags")?,
+            })
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct Event {
+        id: Option<i64>,
+        created_at: time::OffsetDateTime,
+        day: time::Date,
+    }
+
+    impl Model for Event {
+        fn id(&self) -> Value {
+            self.id.to_value()
+        }
+
+        fn table_name() -> &'static str {
+            "events"
+        }
+
+        fn foreign_key() -> &'static str {
+            "event_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["created_at", "day"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.created_at.to_value(), self.day.to_value()]
+        }
+    }
+
+    impl FromRow for Event {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            Ok(Event {
+                id: row.try_get("id")?,
+                created_at: row.try_get("created_at")?,
+                day: row.try_get("day")?,
+            })
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    #[derive(Debug, Clone)]
+    struct Payment {
+        id: Option<i64>,
+        amount: rust_decimal::Decimal,
+    }
+
+    #[cfg(feature = "decimal")]
+    impl Model for Payment {
+        fn id(&self) -> Value {
+            self.id.to_value()
+        }
+
+        fn table_name() -> &'static str {
+            "payments"
+        }
+
+        fn foreign_key() -> &'static str {
+            "payment_id"
+        }
+
+        fn column_names() -> &'static [&'static str] {
+            &["amount"]
+        }
+
+        fn values(&self) -> Vec<Value> {
+            vec![self.amount.to_value()]
+        }
+    }
+
+    #[cfg(feature = "decimal")]
+    impl FromRow for Payment {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            Ok(Payment {
+                id: row.try_get("id")?,
+                amount: row.try_get("amount")?,
+            })
+        }
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    struct Note {
+        id: Option<i64>,
+        title: String,
+        views: i64,
+        body: Option<String>,
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    #[table_name("notes")]
+    struct NoteCreatedAt {
+        id: Option<i64>,
+        created_at: time::OffsetDateTime,
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    #[table_name("notes")]
+    struct NoteHeadline {
+        id: Option<i64>,
+        headline: String,
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    struct Document {
+        id: Option<i64>,
+        title: String,
+        lock_version: i32,
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    #[table_name("documents")]
+    #[lock_version("revision")]
+    struct Revision {
+        id: Option<i64>,
+        title: String,
+        revision: Option<i64>,
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    #[validate]
+    struct Account {
+        id: Option<i64>,
+        email: String,
+        name: String,
+    }
+
+    impl Validate for Account {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            let mut errors = ValidationErrors::new();
+
+            if !self.email.contains('@') {
+                errors.add("email", "is not an email");
+            }
+
+            if self.name.is_empty() {
+                errors.add("name", "can't be blank");
+            }
+
+            errors.result()
+        }
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    #[callbacks]
+    struct Subscriber {
+        id: Option<i64>,
+        email: String,
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    struct SubscriberEvent {
+        id: Option<i64>,
+        subscriber_id: i64,
+        event: String,
+    }
+
+    impl Callbacks for Subscriber {
+        fn before_save(&mut self) {
+            self.email = self.email.trim().to_lowercase();
+        }
+
+        async fn after_create(&self, conn: &mut ConnectionGuard) -> Result<(), Error> {
+            SubscriberEvent::create(&[
+                ("subscriber_id", self.id.to_value()),
+                ("event", "created".to_value()),
+            ])
+            .execute(conn)
+            .await?;
+            Ok(())
+        }
+
+        async fn after_update(&self, conn: &mut ConnectionGuard) -> Result<(), Error> {
+            SubscriberEvent::create(&[
+                ("subscriber_id", self.id.to_value()),
+                ("event", "updated".to_value()),
+            ])
+            .execute(conn)
+            .await?;
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    struct Member {
+        id: Option<i64>,
+        admin: bool,
+        created_at: time::OffsetDateTime,
+        deleted_at: Option<time::OffsetDateTime>,
+    }
+
+    #[crate::macros::scopes]
+    impl Member {
+        fn active(query: Scope<Member>) -> Scope<Member> {
+            query.filter_null("deleted_at")
+        }
+
+        fn admins(query: Scope<Member>) -> Scope<Member> {
+            query.filter("admin", true)
+        }
+
+        fn created_after(query: Scope<Member>, time: time::OffsetDateTime) -> Scope<Member> {
+            query.filter_gt("created_at", time)
+        }
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    #[soft_delete]
+    struct Comment {
+        id: Option<i64>,
+        body: String,
+        deleted_at: Option<time::OffsetDateTime>,
+    }
+
+    #[derive(Debug, Clone, crate::macros::Model)]
+    #[soft_delete("archived_at")]
+    struct Thread {
+        id: Option<i64>,
+        archived_at: Option<time::OffsetDateTime>,
+    }
+
+    impl FromRow for Role {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            Ok(Role {
+                id: row.get("id"),
+                name: row.get("name"),
+            })
+        }
+    }
+
+    impl FromRow for UserRole {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            Ok(UserRole {
+                id: row.get("id"),
+                user_id: row.get("user_id"),
+                role_id: row.get("role_id"),
+            })
+        }
+    }
+
+    impl FromRow for User {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            let id: i64 = row.get("id");
+            let email: String = row.get("email");
+            let password: String = row.get("password");
+
+            Ok(User {
+                id,
+                email,
+                password,
+            })
+        }
+    }
+
+    impl FromRow for Order {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            let id: i64 = row.get("id");
+            let user_id: i64 = row.get("user_id");
+            let amount: f64 = row.get("amount");
+
+            Ok(Order {
+                id,
+                user_id,
+                amount,
+            })
+        }
+    }
+
+    impl FromRow for OrderItem {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            let id: i64 = row.get("id");
+            let order_id: i64 = row.get("order_id");
+            let product_id: i64 = row.get("product_id");
+
+            Ok(OrderItem {
+                id,
+                order_id,
+                product_id,
+            })
+        }
+    }
+
+    impl FromRow for Product {
+        fn from_row(row: Row) -> Result<Self, Error> {
+            let id: i64 = row.get("id");
+            let name: String = row.get("name");
+
+            Ok(Product { id, name })
+        }
+    }
+
+    #[test]
+    fn test_join() {
+        let query = User::all().join::<Order>().first_one();
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "users".* FROM "users" INNER JOIN "orders" ON "users"."id" = "orders"."user_id" ORDER BY "users"."id" ASC LIMIT 1"#
+        );
+
+        let query = Order::all().join::<User>();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "orders".* FROM "orders" INNER JOIN "users" ON "orders"."user_id" = "users"."id""#
+        );
+
+        // Order that have a user with id = 5.
+        let query = Order::all()
+            .join::<User>()
+            .find_by(User::column("id"), 5_i64.to_value());
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "orders".* FROM "orders" INNER JOIN "users" ON "orders"."user_id" = "users"."id" WHERE "users"."id" = $1 LIMIT 1"#
+        );
+
+        let query = User::all()
+            .join::<Order>()
+            .filter("id", 5)
+            .filter(Order::column("amount"), 42.0);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "users".* FROM "users" INNER JOIN "orders" ON "users"."id" = "orders"."user_id" WHERE "users"."id" = $1 AND "orders"."amount" = $2"#
+        );
+
+        let query = User::all()
+            .join::<Order>()
+            .join_nested(Order::join::<OrderItem>().join::<Product>())
+            .filter(Product::column("name"), "test_product");
+
+        println!("{}", query.to_sql());
+    }
+
+    #[test]
+    fn test_related() {
+        let users = [1, 2, 3].map(|id| User {
+            id,
+            ..Default::default()
+        });
+        let query = User::related::<Order>(&users);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "orders" WHERE "orders"."user_id" = ANY($1)"#
+        );
+
+        let select = match query {
+            Query::Select(select) => select,
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            select.placeholders().get(1),
+            Some(&Value::List(vec![
+                Value::Integer(1),
+                Value::Integer(2),
+                Value::Integer(3)
+            ]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_schema() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute(
+                "CREATE TEMPORARY TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, name VARCHAR)",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TEMPORARY TABLE orders (id BIGSERIAL PRIMARY KEY, user_id BIGINT NOT NULL, amount FLOAT8 NOT NULL)",
+                &[],
+            )
+            .await?;
+
+        Order::verify_schema(&mut transaction).await?;
+
+        match User::verify_schema(&mut transaction).await {
+            Err(Error::SchemaMismatch(table, missing, extra)) => {
+                assert_eq!(table, r#""users""#);
+                assert_eq!(missing, vec!["password"]);
+                assert_eq!(extra, vec!["name"]);
+            }
+            result => panic!("expected schema mismatch, got {:?}", result),
+        }
+
+        match OrderItem::verify_schema(&mut transaction).await {
+            Err(Error::SchemaMismatch(_, missing, _)) => {
+                assert_eq!(missing, vec!["id", "order_id", "product_id"])
+            }
+            result => panic!("expected schema mismatch, got {:?}", result),
+        }
+
+        transaction.rollback().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_related_records() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute(
+                "CREATE TEMPORARY TABLE orders (id BIGSERIAL PRIMARY KEY, user_id BIGINT NOT NULL, amount FLOAT8 NOT NULL)",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "INSERT INTO orders (user_id, amount) VALUES (1, 5.0), (2, 10.0), (1, 15.0), (3, 20.0)",
+                &[],
+            )
+            .await?;
+
+        let users = [1, 2, 4].map(|id| User {
+            id,
+            ..Default::default()
+        });
+
+        let orders = User::related::<Order>(&users)
+            .order("id")
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(
+            orders.iter().map(|order| order.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        let grouped = User::related_grouped::<Order>(&users, &mut transaction).await?;
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(
+            grouped[&1].iter().map(|order| order.amount).sum::<f64>(),
+            20.0
+        );
+        assert_eq!(grouped[&2].len(), 1);
+        assert!(!grouped.contains_key(&4));
+
+        transaction.rollback().await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_self_join() {
+        let query = User::all()
+            .join_parent("manager_id", "manager")
+            .filter(Column::new("manager", "email"), "boss@test.com");
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "users".* FROM "users" INNER JOIN "users" AS "manager" ON "users"."manager_id" = "manager"."id" WHERE "manager"."email" = $1"#
+        );
+
+        let query = User::all()
+            .join_children("manager_id", "reports")
+            .filter(Column::new("reports", "id"), 5);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "users".* FROM "users" INNER JOIN "users" AS "reports" ON "users"."id" = "reports"."manager_id" WHERE "reports"."id" = $1"#
+        );
+
+        let query = Order::all()
+            .join_as::<User>("buyer")
+            .filter(Column::new("buyer", "email"), "test@test.com");
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "orders".* FROM "orders" INNER JOIN "users" AS "buyer" ON "orders"."user_id" = "buyer"."id" WHERE "buyer"."email" = $1"#
+        );
+    }
+
+    #[test]
+    fn test_join_through() {
+        let query = User::all()
+            .join_through::<UserRole, Role>()
+            .filter(Role::column("name"), "admin");
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "users".* FROM "users" INNER JOIN "user_roles" ON "users"."id" = "user_roles"."user_id" INNER JOIN "roles" ON "user_roles"."role_id" = "roles"."id" WHERE "roles"."name" = $1"#
+        );
+
+        let users = [
+            User {
+                id: 1,
+                ..Default::default()
+            },
+            User {
+                id: 2,
+                ..Default::default()
+            },
+        ];
+        let query = User::related_through::<Role, UserRole>(&users);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "roles".* FROM "roles" INNER JOIN "user_roles" ON "roles"."id" = "user_roles"."role_id" WHERE "user_roles"."user_id" = ANY($1)"#
+        );
+    }
+
+    #[test]
+    fn test_take_one() {
+        let query = User::take_one().to_sql();
+
+        assert_eq!(query, r#"SELECT * FROM "users" LIMIT 1"#);
+    }
+
+    #[test]
+    fn test_take_many() {
+        let query = User::take_many(25).to_sql();
+
+        assert_eq!(query, r#"SELECT * FROM "users" LIMIT 25"#);
+    }
+
+    #[test]
+    fn test_first_one() {
+        let query = User::first_one().to_sql();
+
+        assert_eq!(
+            query,
+            r#"SELECT * FROM "users" ORDER BY "users"."id" ASC LIMIT 1"#
+        );
+    }
+
+    #[test]
+    fn test_first_many() {
+        let query = User::first_many(25).to_sql();
+
+        assert_eq!(
+            query,
+            r#"SELECT * FROM "users" ORDER BY "users"."id" ASC LIMIT 25"#
+        );
+    }
+
+    #[test]
+    fn test_all() {
+        let query = User::all().to_sql();
+
+        assert_eq!(query, r#"SELECT * FROM "users""#);
+    }
+
+    #[test]
+    fn test_filter() {
+        let query = User::filter("email", "test@test.com")
+            .filter("password", "not_encrypted")
+            .filter("id", 5)
+            .filter("id", [1_i64, 2, 3].as_slice());
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."email" = $1 AND "users"."password" = $2 AND "users"."id" = $3 AND "users"."id" = ANY($4)"#
+        );
+    }
+
+    #[test]
+    fn test_update_all() {
+        let query = User::filter("active", false).update_all(&[("archived", true)]);
+        assert_eq!(
+            query.to_sql(),
+            r#"UPDATE "users" SET "archived" = $2 WHERE "users"."active" = $1 RETURNING *"#
+        );
+
+        let query =
+            User::filter("active", false).update_all(&[("archived", true), ("active", true)]);
+        match query {
+            Query::Update(update) => {
+                assert_eq!(update.placeholders.get(1), Some(&Value::Boolean(false)),);
+                assert_eq!(update.placeholders.get(2), Some(&Value::Boolean(true)));
+                assert_eq!(update.placeholders.get(3), Some(&Value::Boolean(true)));
+                assert_eq!(
+                    update.without_returning().to_sql(),
+                    r#"UPDATE "users" SET "archived" = $2, "active" = $3 WHERE "users"."active" = $1"#
+                );
+            }
+            _ => panic!("update_all should return an update"),
+        }
+    }
+
+    #[test]
+    fn test_scopes() {
+        let query = Member::active().order("id");
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "members" WHERE "members"."deleted_at" IS NULL ORDER BY id"#
+        );
+
+        let query = Member::active()
+            .admins()
+            .created_after(time::OffsetDateTime::now_utc())
+            .filter("id", 5);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "members" WHERE "members"."deleted_at" IS NULL AND "members"."admin" = $1 AND "members"."created_at" > $2 AND "members"."id" = $3"#
+        );
+
+        let query = Member::filter("id", 5).active();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "members" WHERE "members"."id" = $1 AND "members"."deleted_at" IS NULL"#
+        );
+    }
+
+    #[test]
+    fn test_soft_delete() {
+        assert_eq!(Comment::soft_delete_column(), Some("deleted_at"));
+        assert_eq!(Thread::soft_delete_column(), Some("archived_at"));
+        assert_eq!(User::soft_delete_column(), None);
+
+        assert_eq!(
+            Comment::all().to_sql(),
+            r#"SELECT * FROM "comments" WHERE "comments"."deleted_at" IS NULL"#
+        );
+        assert_eq!(
+            Thread::all().to_sql(),
+            r#"SELECT * FROM "threads" WHERE "threads"."archived_at" IS NULL"#
+        );
+
+        let query = Comment::filter("body", "test").filter("id", 5);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "comments" WHERE "comments"."body" = $1 AND "comments"."id" = $2 AND "comments"."deleted_at" IS NULL"#
+        );
+
+        let query = Comment::find(5);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "comments" WHERE "comments"."id" = $1 AND "comments"."deleted_at" IS NULL LIMIT 1"#
+        );
+
+        let query = Comment::filter("body", "a").or(|query| query.filter("body", "b"));
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "comments" WHERE (("comments"."body" = $1) OR ("comments"."body" = $2)) AND ("comments"."deleted_at" IS NULL)"#
+        );
+
+        let query = Comment::filter("body", "test").with_deleted();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "comments" WHERE "comments"."body" = $1"#
+        );
+
+        let query = Comment::filter("body", "test").only_deleted();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "comments" WHERE "comments"."body" = $1 AND "comments"."deleted_at" IS NOT NULL"#
+        );
+
+        let query = Comment::all().update_all(&[("body", "test")]);
+        assert_eq!(
+            query.to_sql(),
+            r#"UPDATE "comments" SET "body" = $1 WHERE "comments"."deleted_at" IS NULL RETURNING *"#
+        );
+
+        let comment = Comment {
+            id: Some(1),
+            body: "test".into(),
+            deleted_at: None,
+        };
+        assert_eq!(
+            comment.soft_delete().to_sql(),
+            r#"UPDATE "comments" SET "deleted_at" = $2 WHERE "id" = $1 RETURNING *"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete_records() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE comments (id BIGSERIAL PRIMARY KEY, body VARCHAR NOT NULL, deleted_at TIMESTAMPTZ)",
+                &[],
+            )
+            .await?;
+
+        for body in ["first", "second"] {
+            Comment::create(&[("body", body)])
+                .execute(&mut transaction)
+                .await?;
+        }
+
+        let comment = Comment::find_by("body", "first")
+            .fetch(&mut transaction)
+            .await?;
+        let deleted = comment
+            .clone()
+            .soft_delete()
+            .fetch(&mut transaction)
+            .await?;
+        assert!(deleted.deleted_at.is_some());
+
+        assert_eq!(Comment::all().count(&mut transaction).await?, 1);
+        assert!(Comment::find(comment.id)
+            .fetch_optional(&mut transaction)
+            .await?
+            .is_none());
+        assert_eq!(
+            Comment::all()
+                .with_deleted()
+                .count(&mut transaction)
+                .await?,
+            2
+        );
+
+        let deleted = Comment::all()
+            .only_deleted()
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].body, "first");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_level() {
+        let fast = Duration::from_millis(5);
+        let slow = Duration::from_millis(500);
+        let threshold = Some(time::Duration::milliseconds(100));
+
+        assert_eq!(log_level(slow, true, None), Some(Level::INFO));
+        assert_eq!(log_level(slow, false, None), None);
+        assert_eq!(log_level(fast, true, threshold), Some(Level::DEBUG));
+        assert_eq!(log_level(fast, false, threshold), None);
+        assert_eq!(log_level(slow, true, threshold), Some(Level::WARN));
+        assert_eq!(log_level(slow, false, threshold), Some(Level::WARN));
+    }
+
+    #[test]
+    fn test_lock() {
+        let query = User::filter("id", 1);
+
+        assert_eq!(
+            query.clone().lock().to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."id" = $1 FOR UPDATE"#
+        );
+        assert_eq!(
+            query.clone().lock().skip_locked().to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."id" = $1 FOR UPDATE SKIP LOCKED"#
+        );
+        assert_eq!(
+            query.clone().lock().nowait().to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."id" = $1 FOR UPDATE NOWAIT"#
+        );
+        assert_eq!(
+            query.clone().lock_share().to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."id" = $1 FOR SHARE"#
+        );
+        assert_eq!(
+            query.clone().lock_share().skip_locked().to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."id" = $1 FOR SHARE SKIP LOCKED"#
+        );
+        assert_eq!(
+            query.clone().lock_share().nowait().to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."id" = $1 FOR SHARE NOWAIT"#
+        );
+        assert_eq!(
+            query.clone().lock().skip_locked().nowait().to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."id" = $1 FOR UPDATE NOWAIT"#
+        );
+        assert!(!query.lock_share().replica());
+    }
+
+    #[test]
+    fn test_unscope() {
+        let scope = User::filter("admin", true)
+            .order(("created_at", "DESC"))
+            .limit(10)
+            .offset(20);
+
+        let query = scope.clone().reorder(("email", "ASC"));
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."admin" = $1 ORDER BY "email" ASC, "id" ASC LIMIT 10 OFFSET 20"#
+        );
+
+        let query = scope.clone().unscope_order().unscope_limit();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."admin" = $1"#
+        );
+
+        let query = scope.unscope_where().filter("email", "test@test.com");
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."email" = $1 ORDER BY "created_at" DESC, "id" ASC LIMIT 10 OFFSET 20"#
+        );
+        let select = match query {
+            Query::Select(select) => select,
+            _ => unreachable!(),
+        };
+        assert_eq!(select.placeholders().len(), 1);
+
+        // Soft delete filter is not removed.
+        let query = Comment::filter("body", "test").unscope_where();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "comments" WHERE "comments"."deleted_at" IS NULL"#
+        );
+    }
+
+    #[test]
+    fn test_order_multiple() {
+        let query = User::all().order(&[("email", "DESC"), ("created_at", "ASC NULLS LAST")]);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" ORDER BY "email" DESC, "created_at" ASC NULLS LAST"#
+        );
+
+        let query = User::all()
+            .order(("email", "DESC", Nulls::First))
+            .order("id");
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" ORDER BY "email" DESC NULLS FIRST, id"#
+        );
+    }
+
+    #[test]
+    fn test_order_tie_breaker() {
+        let query = User::order(("created_at", "ASC")).take_many(25);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" ORDER BY "created_at" ASC, "id" ASC LIMIT 25"#
+        );
+
+        // Without a limit, the order isn't changed.
+        let query = User::order(("created_at", "ASC"));
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" ORDER BY "created_at" ASC"#
+        );
+
+        // The order already includes the primary key.
+        let query = User::order(("created_at", "DESC"))
+            .order("id DESC")
+            .take_many(25);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" ORDER BY "created_at" DESC, id DESC LIMIT 25"#
+        );
+
+        let query = User::all().first_many(25);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" ORDER BY "users"."id" ASC LIMIT 25"#
+        );
+
+        // Joined tables have their own primary keys.
+        let query = Order::all()
+            .join::<User>()
+            .order((User::column("id"), "ASC"))
+            .take_many(25);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "orders".* FROM "orders" INNER JOIN "users" ON "orders"."user_id" = "users"."id" ORDER BY "users"."id" ASC, "orders"."id" ASC LIMIT 25"#
+        );
+    }
+
+    #[test]
+    fn test_order_random() {
+        let query = User::all().order("email").order_random().take_one();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" ORDER BY RANDOM() LIMIT 1"#
+        );
+
+        let query = User::filter("admin", true).order_random().take_many(5);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."admin" = $1 ORDER BY RANDOM() LIMIT 5"#
+        );
+
+        // The limit doesn't reset the order, unlike `first_one`.
+        let query = User::all().order_random().first_one();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" ORDER BY RANDOM() LIMIT 1"#
+        );
+    }
+
+    #[test]
+    fn test_select_column_alias() {
+        let query = Order::all().join::<User>().select_columns(&[
+            Order::column("amount").as_alias("order_amount"),
+            User::column("email"),
+        ]);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "orders"."amount" AS "order_amount", "users"."email" FROM "orders" INNER JOIN "users" ON "orders"."user_id" = "users"."id""#
+        );
+
+        let query = Order::all().column(Column::name("amount").as_alias("total"));
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT *, "amount" AS "total" FROM "orders""#
+        );
+    }
+
+    #[test]
+    fn test_select_columns() {
+        let query = User::all()
+            .select_columns(&["id", "email"])
+            .filter("admin", true);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "users"."id", "users"."email" FROM "users" WHERE "users"."admin" = $1"#
+        );
+
+        let query = Order::all()
+            .join::<User>()
+            .select_columns(&[Order::column("id"), User::column("email")]);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "orders"."id", "users"."email" FROM "orders" INNER JOIN "users" ON "orders"."user_id" = "users"."id""#
+        );
+
+        // The order of joining and selecting columns doesn't matter.
+        let query = Order::all()
+            .select_columns(&["id"])
+            .join::<User>()
+            .take_one();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "orders"."id" FROM "orders" INNER JOIN "users" ON "orders"."user_id" = "users"."id" LIMIT 1"#
+        );
+    }
+
+    #[test]
+    fn test_count_distinct() {
+        let select = match Order::all()
+            .join::<User>()
+            .filter("amount", 5.0)
+            .order("id")
+        {
+            Query::Select(select) => select.count_distinct("user_id"),
+            _ => unreachable!(),
+        };
+
+        assert_eq!(
+            select.to_sql(),
+            r#"SELECT COUNT(DISTINCT "orders"."user_id") AS count FROM "orders" INNER JOIN "users" ON "orders"."user_id" = "users"."id" WHERE "orders"."amount" = $1"#
+        );
+
+        let select = match Order::all() {
+            Query::Select(select) => select.count_distinct(User::column("email")),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            select.to_sql(),
+            r#"SELECT COUNT(DISTINCT "users"."email") AS count FROM "orders""#
+        );
+    }
+
+    #[test]
+    fn test_exists() {
+        let select = match Order::filter("amount", 5.0).order("id") {
+            Query::Select(select) => select,
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            Query::exists_query(select).to_sql(),
+            r#"SELECT EXISTS (SELECT 1 FROM "orders" WHERE "orders"."amount" = $1 LIMIT 1) AS "exists""#
+        );
+
+        let select = match Comment::filter("body", "test").limit(10).offset(5) {
+            Query::Select(select) => select,
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            Query::exists_query(select).to_sql(),
+            r#"SELECT EXISTS (SELECT 1 FROM "comments" WHERE "comments"."body" = $1 AND "comments"."deleted_at" IS NULL LIMIT 1 OFFSET 5) AS "exists""#
+        );
+    }
+
+    #[test]
+    fn test_filter_empty_list() {
+        let query = User::all().filter("id", &[] as &[i64]);
+        assert_eq!(query.to_sql(), r#"SELECT * FROM "users" WHERE false"#);
+
+        let query = User::filter("email", "test@test.com")
+            .filter("id", Vec::<i64>::new().as_slice())
+            .filter_not("email", &[] as &[&str])
+            .filter("password", "secret");
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."email" = $1 AND false AND true AND "users"."password" = $2"#
+        );
+
+        let select = match query {
+            Query::Select(select) => select,
+            _ => unreachable!(),
+        };
+        assert_eq!(select.placeholders().len(), 2);
+        assert_eq!(
+            select.placeholders().get(2),
+            Some(&Value::String("secret".into()))
+        );
+    }
+
+    #[test]
+    fn test_filter_ci() {
+        let query = User::filter("admin", true)
+            .filter_ci("email", "Test@Example.com")
+            .take_one();
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."admin" = $1 AND LOWER("users"."email") = LOWER($2) LIMIT 1"#
+        );
+
+        let query = User::all()
+            .where_group(|query| query.filter_ci("email", "A@test.com"))
+            .filter_ci(User::column("email"), "B@test.com");
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE (LOWER("users"."email") = LOWER($1)) AND LOWER("users"."email") = LOWER($2)"#
+        );
+    }
+
+    #[test]
+    fn test_schema() {
+        #[derive(Clone, crate::macros::Model)]
+        #[schema("billing")]
+        #[allow(dead_code)]
+        struct Invoice {
+            id: Option<i64>,
+            paid: bool,
+        }
+
+        assert_eq!(Invoice::schema(), Some("billing"));
+        assert_eq!(Invoice::table_name(), "invoices");
+        assert_eq!(User::schema(), None);
+
+        let query = User::all()
+            .join::<Order>()
+            .filter(Order::column("amount"), 42.0)
+            .schema("tenant");
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT "users".* FROM "tenant"."users" INNER JOIN "tenant"."orders" ON "users"."id" = "orders"."user_id" WHERE "orders"."amount" = $1"#
+        );
+
+        let query = Invoice::find(5);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "billing"."invoices" WHERE "invoices"."id" = $1 LIMIT 1"#
+        );
+
+        let query = Invoice::all().update_all(&[("paid", true)]);
+        assert!(query
+            .to_sql()
+            .starts_with(r#"UPDATE "billing"."invoices" SET"#));
+
+        let invoice = Invoice {
+            id: Some(5),
+            paid: true,
+        };
+        assert_eq!(
+            invoice.save().to_sql(),
+            r#"UPDATE "billing"."invoices" SET "paid" = $2 WHERE "id" = $1 RETURNING *"#
+        );
+    }
+
+    #[test]
+    fn test_log_json() {
+        let query = User::find(5);
+        let lines = crate::logging::capture_json(|| {
+            query.log_event(Level::INFO, Duration::from_millis(2), true);
+            query.log_event(Level::WARN, Duration::from_millis(200), true);
+        });
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["level"], "INFO");
+        assert_eq!(lines[0]["model"], "model::test::User");
+        assert_eq!(lines[0]["action"], "load");
+        assert_eq!(lines[0]["query"], query.to_sql());
+        assert!(lines[0]["duration_ms"].as_f64().unwrap() >= 2.0);
+        assert!(lines[0].get("request_id").is_none());
+        assert_eq!(lines[1]["level"], "WARN");
+    }
+
+    #[test]
+    fn test_table_name() {
+        #[derive(Clone, crate::macros::Model)]
+        struct Category {
+            id: Option<i64>,
+        }
+
+        #[derive(Clone, crate::macros::Model)]
+        struct ProductCategory {
+            id: Option<i64>,
+        }
+
+        #[derive(Clone, crate::macros::Model)]
+        struct Address {
+            id: Option<i64>,
+        }
+
+        #[derive(Clone, crate::macros::Model)]
+        struct Tax {
+            id: Option<i64>,
+        }
+
+        #[derive(Clone, crate::macros::Model)]
+        struct Branch {
+            id: Option<i64>,
+        }
+
+        #[derive(Clone, crate::macros::Model)]
+        #[table_name = "people"]
+        struct Human {
+            id: Option<i64>,
+        }
+
+        assert_eq!(Note::table_name(), "notes");
+        assert_eq!(Category::table_name(), "categories");
+        assert_eq!(ProductCategory::table_name(), "product_categories");
+        assert_eq!(ProductCategory::foreign_key(), "product_category_id");
+        assert_eq!(Address::table_name(), "addresses");
+        assert_eq!(Tax::table_name(), "taxes");
+        assert_eq!(Branch::table_name(), "branches");
+        assert_eq!(Human::table_name(), "people");
+        assert_eq!(NoteHeadline::table_name(), "notes");
+    }
+
+    #[test]
+    fn test_where_group() {
+        let query = User::filter("admin", true)
+            .where_group(|query| {
+                query.filter("email", "a@test.com").or(|query| {
+                    query.filter("email", "b@test.com").where_group(|query| {
+                        query.filter_gt("id", 5).or(|query| query.filter("id", 1))
+                    })
+                })
+            })
+            .filter("email", "c@test.com");
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."admin" = $1 AND (("users"."email" = $2) OR ("users"."email" = $3 AND (("users"."id" > $4) OR ("users"."id" = $5)))) AND "users"."email" = $6"#
+        );
+
+        let select = match query {
+            Query::Select(select) => select,
+            _ => unreachable!(),
+        };
+        assert_eq!(select.placeholders().len(), 6);
+        assert_eq!(select.placeholders().get(1), Some(&Value::Boolean(true)));
+        assert_eq!(
+            select.placeholders().get(3),
+            Some(&Value::String("b@test.com".into()))
+        );
+        assert_eq!(select.placeholders().get(4), Some(&Value::Int(5)));
+        assert_eq!(select.placeholders().get(5), Some(&Value::Int(1)));
+        assert_eq!(
+            select.placeholders().get(6),
+            Some(&Value::String("c@test.com".into()))
+        );
+
+        // Empty groups don't change the query.
+        let query = User::filter("admin", true).where_group(|query| query);
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."admin" = $1"#
+        );
+    }
+
+    #[test]
+    fn test_filter_in_subquery() {
+        let orders = Order::all()
+            .filter_gt("amount", 50.0)
+            .filter(Order::column("user_id"), User::column("id"))
+            .select_columns(&["user_id"]);
+        let query = User::all()
+            .filter("admin", true)
+            .filter_in_subquery("id", orders)
+            .filter("email", "test@test.com");
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."admin" = $1 AND "users"."id" IN (SELECT "orders"."user_id" FROM "orders" WHERE "orders"."amount" > $2 AND "orders"."user_id" = "users"."id") AND "users"."email" = $3"#
+        );
+
+        let select = match query {
+            Query::Select(select) => select,
+            _ => unreachable!(),
+        };
+        assert_eq!(select.placeholders().get(1), Some(&Value::Boolean(true)));
+        assert_eq!(select.placeholders().get(2), Some(&Value::Float(50.0)));
+        assert_eq!(
+            select.placeholders().get(3),
+            Some(&Value::String("test@test.com".into()))
+        );
+
+        // Nested subqueries share the same placeholders too.
+        let users = User::filter("email", "a@test.com").select_columns(&["id"]);
+        let orders = Order::filter("amount", 5.0)
+            .filter_not_in_subquery("user_id", users)
+            .select_columns(&["id"]);
+        let query = Order::filter("amount", 10.0).filter_in_subquery("id", orders);
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "orders" WHERE "orders"."amount" = $1 AND "orders"."id" IN (SELECT "orders"."id" FROM "orders" WHERE "orders"."amount" = $2 AND "orders"."user_id" NOT IN (SELECT "users"."id" FROM "users" WHERE "users"."email" = $3))"#
+        );
+
+        let select = match query {
+            Query::Select(select) => select,
+            _ => unreachable!(),
+        };
+        assert_eq!(select.placeholders().len(), 3);
+        assert_eq!(
+            select.placeholders().get(3),
+            Some(&Value::String("a@test.com".into()))
+        );
+    }
+
+    #[test]
+    fn test_filter_exists() {
+        let orders = Order::all()
+            .filter("user_id", User::column("id"))
+            .filter_gt("amount", 50.0);
+        let query = User::filter("email", "test@test.com")
+            .filter_exists(orders)
+            .filter_not_exists(Order::filter("user_id", User::column("id")).filter("amount", 0.0));
+
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."email" = $1 AND EXISTS (SELECT 1 FROM "orders" WHERE "orders"."user_id" = "users"."id" AND "orders"."amount" > $2) AND NOT EXISTS (SELECT 1 FROM "orders" WHERE "orders"."user_id" = "users"."id" AND "orders"."amount" = $3)"#
+        );
+
+        // The values of the subqueries are bound in order.
+        let select = match query {
+            Query::Select(select) => select,
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            select.placeholders().get(1),
+            Some(&Value::String("test@test.com".into()))
+        );
+        assert_eq!(select.placeholders().get(2), Some(&Value::Float(50.0)));
+        assert_eq!(select.placeholders().get(3), Some(&Value::Float(0.0)));
+    }
+
+    #[test]
+    fn test_find_by() {
+        let query = User::find_by("email", "test@test.com");
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "users" WHERE "users"."email" = $1 LIMIT 1"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fetch() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .query("DROP TABLE IF EXISTS users CASCADE", &[])
+            .await?;
+
+        transaction
+            .client()
+            .query(
+                "CREATE TABLE IF NOT EXISTS users (id BIGINT, email VARCHAR, password VARCHAR);",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .query(
+                "INSERT INTO users VALUES (1, 'test@test.com', 'not_encrypted');",
+                &[],
+            )
+            .await?;
+
+        let user = User::order(("email", "ASC"))
+            .first_one()
+            .fetch(&mut transaction)
+            .await?;
+
+        assert_eq!(user.email, "test@test.com");
+
+        let users = User::all().fetch_all(&mut transaction).await?;
+
+        assert_eq!(users.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_explain() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("CREATE TABLE IF NOT EXISTS users (id BIGINT);", &[])
+            .await?;
+
+        let explain = User::all().explain(&mut transaction).await?;
+        assert!(explain.to_string().starts_with("Seq Scan on users"));
+
+        let explain = User::filter("id", 5).explain(&mut transaction).await?;
+        assert_eq!(explain.to_string().lines().count(), 2);
+
+        let explain = User::all().explain_analyze(&mut transaction).await?;
+        assert!(explain.to_string().contains("actual"));
+
+        let explain = User::all()
+            .explain_format(explain::Format::Json, &mut transaction)
+            .await?;
+        let tree = explain.tree().unwrap();
+        assert_eq!(tree.node_type, "Seq Scan");
+        assert_eq!(tree.relation_name.as_deref(), Some("users"));
+        assert!(tree.actual_rows.is_none());
+
+        let explain = User::all()
+            .explain_with(
+                ExplainOptions::new()
+                    .analyze()
+                    .format(explain::Format::Json),
+                &mut transaction,
+            )
+            .await?;
+        assert_eq!(explain.tree().unwrap().actual_rows, Some(0.0));
+
+        // ANALYZE executes the query, so writes are refused unless explicitly allowed.
+        let update = User::all().update_all(&[("id", 1_i64)]);
+        assert!(matches!(
+            update.clone().explain_analyze(&mut transaction).await,
+            Err(Error::ExplainWrite(_))
+        ));
+        update
+            .explain_with(
+                ExplainOptions::new().analyze().allow_writes(),
+                &mut transaction,
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_or_create() -> Result<(), Error> {
+        let pool = Pool::from_env();
+
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS users CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE IF NOT EXISTS users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL);", &[])
+            .await?;
+
+        let query = User::all()
+            .filter("email", "test@test.com")
+            .filter("password", "password")
+            .find_or_create();
+        let sql = query.to_sql();
+        assert_eq!(
+            sql,
+            r#"SELECT * FROM "users" WHERE "users"."email" = $1 AND "users"."password" = $2 LIMIT 1; INSERT INTO "users" ("email", "password") VALUES ($1, $2) RETURNING *;"#
+        );
+
+        query.fetch(&mut transaction).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_first_last() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS users CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL)", &[])
+            .await?;
+
+        assert!(User::first(&mut transaction).await?.is_none());
+        assert!(User::last(&mut transaction).await?.is_none());
+
+        transaction
+            .client()
+            .execute("INSERT INTO users (email, password) SELECT 'user' || n || '@test.com', 'password' FROM generate_series(1, 3) n", &[])
+            .await?;
+
+        let first = User::first(&mut transaction).await?.expect("first user");
+        assert_eq!(first.id, 1);
+        assert_eq!(first.email, "user1@test.com");
+
+        let last = User::last(&mut transaction).await?.expect("last user");
+        assert_eq!(last.id, 3);
+        assert_eq!(last.email, "user3@test.com");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_page() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS users CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL)", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("INSERT INTO users (email, password) SELECT 'user' || n || '@test.com', 'password' FROM generate_series(1, 25) n", &[])
+            .await?;
+
+        let page = User::order("id").page(2, 10, &mut transaction).await?;
+        assert_eq!(page.total, 25);
+        assert_eq!(page.total_pages(), 3);
+        assert_eq!(
+            page.items.iter().map(|u| u.id).collect::<Vec<_>>(),
+            (11..=20).collect::<Vec<_>>()
+        );
+        assert!(page.has_next());
+        assert!(page.has_previous());
+
+        let page = User::order("id").page(3, 10, &mut transaction).await?;
+        assert_eq!(page.items.len(), 5);
+        assert!(!page.has_next());
+
+        for number in [0, 4] {
+            let page = User::order("id").page(number, 10, &mut transaction).await?;
+            assert!(page.items.is_empty());
+            assert_eq!(page.total, 25);
+        }
+
+        let page = User::all()
+            .filter_gt("id", 20)
+            .page(1, 10, &mut transaction)
+            .await?;
+        assert_eq!(page.total, 5);
+        assert_eq!(page.total_pages(), 1);
+        assert_eq!(page.items.len(), 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_preload() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        for query in [
+            "DROP TABLE IF EXISTS users, orders, order_items CASCADE",
+            "CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL)",
+            "CREATE TABLE orders (id BIGSERIAL PRIMARY KEY, user_id BIGINT NOT NULL, amount DOUBLE PRECISION NOT NULL)",
+            "CREATE TABLE order_items (id BIGSERIAL PRIMARY KEY, order_id BIGINT NOT NULL, product_id BIGINT NOT NULL)",
+            "INSERT INTO users (email, password) VALUES ('alice@test.com', 'a'), ('bob@test.com', 'b'), ('eve@test.com', 'e')",
+            "INSERT INTO orders (user_id, amount) VALUES (1, 10.0), (1, 20.0), (2, 30.0)",
+            "INSERT INTO order_items (order_id, product_id) VALUES (1, 1), (1, 2), (3, 1)",
+        ] {
+            transaction.client().execute(query, &[]).await?;
+        }
+
+        let users = User::order("id")
+            .preload::<Order>()
+            .fetch_all(&mut transaction)
+            .await?;
+        let orders = users
+            .iter()
+            .map(|user| user.preloaded::<Order>().len())
+            .collect::<Vec<_>>();
+        assert_eq!(orders, vec![2, 1, 0]);
+        assert_eq!(users[0].email, "alice@test.com");
+        assert!(users[0].preloaded::<OrderItem>().is_empty());
+
+        let orders = Order::order("id")
+            .preload::<User>()
+            .preload::<OrderItem>()
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(orders.len(), 3);
+
+        let emails = orders
+            .iter()
+            .map(|order| order.preloaded::<User>()[0].email.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            emails,
+            vec!["alice@test.com", "alice@test.com", "bob@test.com"]
+        );
+
+        let items = orders
+            .iter()
+            .map(|order| order.preloaded::<OrderItem>().len())
+            .collect::<Vec<_>>();
+        assert_eq!(items, vec![2, 0, 1]);
+
+        let user = User::find(3)
+            .preload::<Order>()
+            .fetch(&mut transaction)
+            .await?;
+        assert!(user.preloaded::<Order>().is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_uuid_primary_key() -> Result<(), Error> {
+        let id = uuid::Uuid::new_v4();
+        let token = Token {
+            id,
+            name: "api".into(),
+            created_at: None,
+        };
+
+        assert_eq!(
+            token.clone().save().to_sql(),
+            r#"INSERT INTO "tokens" ("name", "id") VALUES ($1, $2) RETURNING *"#
+        );
+
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS tokens", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE tokens (id UUID PRIMARY KEY, name VARCHAR NOT NULL, created_at TIMESTAMPTZ NOT NULL DEFAULT NOW())", &[])
+            .await?;
+
+        let mut token = token.save().fetch(&mut transaction).await?;
+        assert_eq!(token.id, id);
+        assert!(token.is_persisted());
+
+        token.name = "admin".into();
+        let query = token.save();
+        assert_eq!(
+            query.to_sql(),
+            r#"UPDATE "tokens" SET "name" = $2 WHERE "id" = $1 RETURNING *"#
+        );
+        query.execute(&mut transaction).await?;
+
+        let token = Token::find(id).fetch(&mut transaction).await?;
+        assert_eq!(token.name, "admin");
+
+        let other = Token {
+            id: uuid::Uuid::new_v4(),
+            name: "cli".into(),
+            created_at: None,
+        }
+        .save()
+        .fetch(&mut transaction)
+        .await?;
+
+        let tokens = Token::all()
+            .filter("id", [id, other.id].as_slice())
+            .order("name")
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(
+            tokens.iter().map(|t| t.id).collect::<Vec<_>>(),
+            vec![id, other.id]
+        );
+
+        // UUIDs passed as strings are bound as UUIDs.
+        let token = Token::filter("id", other.id.to_string().as_str())
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(token.name, "cli");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS profiles", &[])
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE profiles (id BIGSERIAL PRIMARY KEY, settings JSONB NOT NULL)",
+                &[],
+            )
+            .await?;
+
+        for settings in [
+            serde_json::json!({"theme": {"color": "dark"}, "beta": true, "font": 12}),
+            serde_json::json!({"theme": {"color": "light"}, "beta": false}),
+            serde_json::json!("plain"),
+        ] {
+            Profile { id: None, settings }
+                .save()
+                .execute(&mut transaction)
+                .await?;
+        }
+
+        let profile = Profile::all()
+            .filter_json_path("settings", &["theme", "color"], "dark")
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(profile.settings["font"], 12);
+
+        let profiles = Profile::all()
+            .filter_json_path("settings", &["beta"], false)
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].settings["theme"]["color"], "light");
+
+        let profile = Profile::all()
+            .filter_json_path("settings", &["font"], 12)
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(profile.settings["beta"], true);
+
+        let profile = Profile::all()
+            .filter_json_path("settings", &["theme"], Value::Null)
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(profile.settings, serde_json::json!("plain"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_array() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS posts", &[])
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE posts (id BIGSERIAL PRIMARY KEY, tags TEXT[] NOT NULL)",
+                &[],
+            )
+            .await?;
+
+        for tags in [vec!["rust", "orm"], vec!["rust"], vec![]] {
+            Post {
+                id: None,
+                tags: tags.into_iter().map(String::from).collect(),
+            }
+            .save()
+            .execute(&mut transaction)
+            .await?;
+        }
+
+        let posts = Post::all()
+            .filter_array_contains("tags", "rust")
+            .order("id")
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(
+            posts.iter().map(|p| p.tags.clone()).collect::<Vec<_>>(),
+            vec![
+                vec!["rust".to_string(), "orm".to_string()],
+                vec!["rust".to_string()]
+            ]
+        );
+
+        let post = Post::all()
+            .filter("tags", Vec::<String>::new())
+            .fetch(&mut transaction)
+            .await?;
+        assert!(post.tags.is_empty());
+
+        let tags: Value = transaction
+            .client()
+            .query_one("SELECT tags FROM posts ORDER BY id LIMIT 1", &[])
+            .await?
+            .try_get(0)?;
+        assert_eq!(
+            tags,
+            Value::Array(vec!["rust".to_value(), "orm".to_value()])
+        );
+
+        let mixed = Post::all()
+            .filter("tags", vec!["rust".to_value(), 5_i64.to_value()])
+            .fetch_all(&mut transaction)
+            .await;
+        assert!(mixed.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timestamps() -> Result<(), Error> {
+        use time::{Duration, OffsetDateTime};
+
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS events", &[])
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE events (id BIGSERIAL PRIMARY KEY, created_at TIMESTAMPTZ NOT NULL, day DATE NOT NULL)",
+                &[],
+            )
+            .await?;
+
+        // 2024-10-16 12:00 UTC
+        let start = OffsetDateTime::from_unix_timestamp(1729080000).unwrap();
+        for hours in 0..5 {
+            let created_at = start + Duration::hours(hours * 12);
+            Event {
+                id: None,
+                created_at,
+                day: created_at.date(),
+            }
+            .save()
+            .execute(&mut transaction)
+            .await?;
+        }
+
+        let events = Event::all()
+            .filter_gt("created_at", start + Duration::hours(24))
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(events.len(), 2);
+
+        let query = Event::all()
+            .filter(
+                "created_at",
+                start + Duration::hours(12)..=start + Duration::hours(36),
+            )
+            .order("id");
+        assert_eq!(
+            query.to_sql(),
+            r#"SELECT * FROM "events" WHERE "events"."created_at" BETWEEN $1 AND $2 ORDER BY id"#
+        );
+        let events = query.fetch_all(&mut transaction).await?;
+        assert_eq!(
+            events.iter().map(|e| e.created_at).collect::<Vec<_>>(),
+            vec![
+                start + Duration::hours(12),
+                start + Duration::hours(24),
+                start + Duration::hours(36)
+            ]
+        );
+
+        let count = Event::all()
+            .filter("day", start.date())
+            .count(&mut transaction)
+            .await?;
+        assert_eq!(count, 1);
+
+        let event = Event::all()
+            .filter(
+                "day",
+                start.date().next_day().unwrap()..=start.date().next_day().unwrap(),
+            )
+            .not("created_at", start + Duration::hours(12))
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(event.day, start.date().next_day().unwrap());
+        assert_eq!(event.created_at, start + Duration::hours(24));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "decimal")]
+    async fn test_decimal() -> Result<(), Error> {
+        use rust_decimal::Decimal;
+
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS payments", &[])
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE payments (id BIGSERIAL PRIMARY KEY, amount NUMERIC(12, 2) NOT NULL)",
+                &[],
+            )
+            .await?;
+
+        // 0.1 + 0.2 != 0.3 with floats.
+        for amount in [
+            Decimal::new(10, 2),
+            Decimal::new(20, 2),
+            Decimal::new(1999, 2),
+        ] {
+            Payment { id: None, amount }
+                .save()
+                .execute(&mut transaction)
+                .await?;
+        }
+
+        let payments = Payment::all()
+            .filter_lt("amount", Decimal::new(1, 0))
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(
+            payments.iter().map(|p| p.amount).sum::<Decimal>(),
+            Decimal::new(30, 2)
+        );
+
+        let total: Value = transaction
+            .client()
+            .query_one("SELECT SUM(amount) FROM payments", &[])
+            .await?
+            .try_get(0)?;
+        assert_eq!(total, Value::Decimal(Decimal::new(2029, 2)));
+
+        let payment = Payment::all()
+            .filter("amount", Decimal::new(30, 2) - Decimal::new(10, 2))
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(payment.amount.to_string(), "0.20");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_column_alias() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS notes", &[])
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE notes (id BIGSERIAL PRIMARY KEY, title VARCHAR, views BIGINT, body TEXT, created_at TIMESTAMPTZ)",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .execute("INSERT INTO notes (title) VALUES ('hello')", &[])
+            .await?;
+
+        let note = NoteHeadline::all()
+            .select_columns(&["id".to_column(), Column::name("title").as_alias("headline")])
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(note.headline, "hello");
 
-    #[derive(Debug, Clone, Default)]
-    struct User {
-        id: i64,
-        email: String,
-        password: String,
+        Ok(())
     }
 
-    impl Model for User {
-        fn id(&self) -> Value {
-            Value::Integer(self.id)
+    #[tokio::test]
+    async fn test_enum_values() -> Result<(), Error> {
+        #[derive(Debug, Clone, PartialEq, crate::macros::Enum)]
+        enum TicketStatus {
+            Open,
+            InProgress,
+            #[label("done")]
+            Closed,
         }
 
-        fn table_name() -> &'static str {
-            "users"
+        #[derive(Debug, Clone, crate::macros::Model)]
+        struct Ticket {
+            id: Option<i64>,
+            status: TicketStatus,
+            previous: Option<TicketStatus>,
         }
 
-        fn foreign_key() -> &'static str {
-            "user_id"
-        }
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
 
-        fn column_names() -> &'static [&'static str] {
-            &["email", "password"]
-        }
+        transaction
+            .client()
+            .execute(
+                "CREATE TYPE ticket_status AS ENUM ('open', 'in_progress', 'done')",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE tickets (id BIGSERIAL PRIMARY KEY, status ticket_status NOT NULL, previous ticket_status)",
+                &[],
+            )
+            .await?;
 
-        fn values(&self) -> Vec<Value> {
-            vec![self.email.to_value(), self.password.to_value()]
+        let ticket = Ticket {
+            id: None,
+            status: TicketStatus::InProgress,
+            previous: Some(TicketStatus::Open),
         }
-    }
+        .save()
+        .fetch(&mut transaction)
+        .await?;
+        assert_eq!(ticket.status, TicketStatus::InProgress);
+        assert_eq!(ticket.previous, Some(TicketStatus::Open));
+
+        Ticket::create(&[("status", TicketStatus::Closed)])
+            .execute(&mut transaction)
+            .await?;
 
-    #[derive(Debug, Clone, Default)]
-    struct Order {
-        id: i64,
-        user_id: i64,
-        amount: f64,
-    }
+        let closed = Ticket::filter("status", TicketStatus::Closed)
+            .fetch(&mut transaction)
+            .await?;
+        assert_eq!(closed.status, TicketStatus::Closed);
+        assert_eq!(closed.previous, None);
 
-    impl Model for Order {
-        fn id(&self) -> Value {
-            Value::Integer(self.id)
-        }
+        let status: String = transaction
+            .client()
+            .query_one(
+                "SELECT status::text FROM tickets WHERE id = $1",
+                &[&closed.id],
+            )
+            .await?
+            .get(0);
+        assert_eq!(status, "done");
 
-        fn table_name() -> &'static str {
-            "orders"
-        }
+        let rows = Query::<super::Row>::raw_with("SELECT status FROM tickets ORDER BY id", vec![])
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(
+            rows[0].clone().values()?.get("status"),
+            Some(&Value::String("in_progress".into()))
+        );
 
-        fn foreign_key() -> &'static str {
-            "order_id"
-        }
+        transaction.rollback().await?;
 
-        fn column_names() -> &'static [&'static str] {
-            &["user_id", "amount"]
-        }
+        Ok(())
+    }
 
-        fn values(&self) -> Vec<Value> {
-            vec![self.user_id.to_value(), self.amount.to_value()]
+    #[tokio::test]
+    async fn test_fetch_all_as() -> Result<(), Error> {
+        #[derive(Clone, crate::macros::FromRow)]
+        struct UserOrders {
+            email: String,
+            orders: i64,
+            total: f64,
         }
-    }
 
-    #[derive(Debug, Clone, Default)]
-    struct OrderItem {
-        id: i64,
-        order_id: i64,
-        product_id: i64,
-    }
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
 
-    impl Model for OrderItem {
-        fn id(&self) -> Value {
-            Value::Integer(self.id)
-        }
+        transaction
+            .client()
+            .execute(
+                "CREATE TEMPORARY TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL)",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TEMPORARY TABLE orders (id BIGSERIAL PRIMARY KEY, user_id BIGINT NOT NULL, amount FLOAT8 NOT NULL)",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "INSERT INTO users (email) VALUES ('a@test.com'), ('b@test.com')",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "INSERT INTO orders (user_id, amount) VALUES (1, 5.0), (1, 10.0), (2, 1.0)",
+                &[],
+            )
+            .await?;
 
-        fn table_name() -> &'static str {
-            "order_items"
-        }
+        let totals = Query::<super::Row>::raw_with(
+            "SELECT users.email, COUNT(*) AS orders, SUM(orders.amount) AS total
+            FROM users INNER JOIN orders ON orders.user_id = users.id
+            WHERE orders.amount > $1
+            GROUP BY users.email ORDER BY users.email",
+            vec![2.0.to_value()],
+        )
+        .fetch_all_as::<UserOrders>(&mut transaction)
+        .await?;
 
-        fn foreign_key() -> &'static str {
-            "order_item_id"
-        }
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals[0].email, "a@test.com");
+        assert_eq!(totals[0].orders, 2);
+        assert_eq!(totals[0].total, 15.0);
 
-        fn column_names() -> &'static [&'static str] {
-            &["order_id", "product_id"]
-        }
+        transaction.rollback().await?;
 
-        fn values(&self) -> Vec<Value> {
-            vec![self.order_id.to_value(), self.product_id.to_value()]
-        }
+        Ok(())
     }
 
-    #[derive(Debug, Clone, Default)]
-    struct Product {
-        id: i64,
-        name: String,
-    }
+    #[tokio::test]
+    async fn test_from_row_null() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
 
-    impl Model for Product {
-        fn id(&self) -> Value {
-            Value::Integer(self.id)
-        }
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS notes", &[])
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE notes (id BIGSERIAL PRIMARY KEY, title VARCHAR, views BIGINT, body TEXT, created_at TIMESTAMPTZ)",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "INSERT INTO notes (title, views, body) VALUES (NULL, NULL, NULL), ('hello', 5, 'world')",
+                &[],
+            )
+            .await?;
 
-        fn table_name() -> &'static str {
-            "products"
-        }
+        let notes = Note::all().order("id").fetch_all(&mut transaction).await?;
+        assert_eq!(notes[0].title, "");
+        assert_eq!(notes[0].views, 0);
+        assert_eq!(notes[0].body, None);
+        assert_eq!(notes[1].title, "hello");
+        assert_eq!(notes[1].views, 5);
+        assert_eq!(notes[1].body.as_deref(), Some("world"));
 
-        fn foreign_key() -> &'static str {
-            "product_id"
-        }
+        // No default value for timestamps.
+        assert!(NoteCreatedAt::all()
+            .fetch_all(&mut transaction)
+            .await
+            .is_err());
 
-        fn column_names() -> &'static [&'static str] {
-            &["name"]
-        }
+        Ok(())
+    }
 
-        fn values(&self) -> Vec<Value> {
-            vec![self.name.to_value()]
+    #[tokio::test]
+    async fn test_lock_version_not_integer() -> Result<(), Error> {
+        #[derive(Clone, crate::macros::Model)]
+        struct Document {
+            id: Option<i64>,
+            lock_version: String,
         }
-    }
 
-    impl Association<User> for Order {}
+        let document = Document {
+            id: Some(1),
+            lock_version: "1".into(),
+        };
+        let result = document.save().fetch(Pool::from_env()).await;
+        assert!(
+            matches!(result, Err(Error::InvalidLockVersion(ref column)) if column == "lock_version")
+        );
 
-    impl Association<Order> for User {
-        fn association_type() -> AssociationType {
-            AssociationType::HasMany
-        }
+        Ok(())
     }
 
-    impl Association<Order> for OrderItem {}
+    #[tokio::test]
+    async fn test_lock_version() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
 
-    impl Association<OrderItem> for Order {
-        fn association_type() -> AssociationType {
-            AssociationType::HasMany
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS documents", &[])
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE documents (id BIGSERIAL PRIMARY KEY, title VARCHAR NOT NULL, lock_version INTEGER NOT NULL DEFAULT 0, revision BIGINT)",
+                &[],
+            )
+            .await?;
+
+        let document = Document {
+            id: None,
+            title: "draft".into(),
+            lock_version: 0,
         }
+        .save()
+        .fetch(&mut transaction)
+        .await?;
+        assert_eq!(document.lock_version, 0);
+
+        let mut first = Document::find(document.id).fetch(&mut transaction).await?;
+        let mut second = first.clone();
+
+        first.title = "first".into();
+        let first = first.save().fetch(&mut transaction).await?;
+        assert_eq!(first.lock_version, 1);
+
+        // Loaded before the first update.
+        second.title = "second".into();
+        let stale = second.save().execute(&mut transaction).await;
+        assert!(matches!(stale, Err(Error::StaleObject)));
+
+        let document = Document::find(document.id).fetch(&mut transaction).await?;
+        assert_eq!(document.title, "first");
+
+        // Configured column, starting at NULL.
+        assert_eq!(Revision::lock_version(), Some("revision"));
+        let mut revision = Revision::find(document.id).fetch(&mut transaction).await?;
+        assert_eq!(revision.revision, None);
+        revision.title = "revised".into();
+        let revision = revision.save().fetch(&mut transaction).await?;
+        assert_eq!(revision.revision, Some(1));
+
+        let mut revision = revision.clone();
+        revision.revision = Some(5);
+        assert!(matches!(
+            revision.save().execute(&mut transaction).await,
+            Err(Error::StaleObject)
+        ));
+
+        Ok(())
     }
 
-    impl Association<Product> for OrderItem {}
-    impl Association<OrderItem> for Product {
-        fn association_type() -> AssociationType {
-            AssociationType::HasMany
+    #[tokio::test]
+    async fn test_validation() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        // The table doesn't exist, so the query would fail if it was sent to the database.
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS accounts", &[])
+            .await?;
+
+        let account = Account {
+            id: None,
+            email: "test".into(),
+            name: "".into(),
+        };
+
+        match account.clone().save().execute(&mut transaction).await {
+            Err(Error::Validation(errors)) => {
+                assert_eq!(errors.get("email"), &["is not an email"]);
+                assert_eq!(errors.get("name"), &["can't be blank"]);
+            }
+            result => panic!("expected validation error, got {:?}", result),
         }
-    }
 
-    impl FromRow for User {
-        fn from_row(row: Row) -> Result<Self, Error> {
-            let id: i64 = row.get("id");
-            let email: String = row.get("email");
-            let password: String = row.get("password");
+        let update = Account {
+            id: Some(1),
+            ..account
+        };
+        assert!(matches!(
+            update.save().fetch(&mut transaction).await,
+            Err(Error::Validation(_))
+        ));
 
-            Ok(User {
-                id,
-                email,
-                password,
-            })
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE accounts (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, name VARCHAR NOT NULL)",
+                &[],
+            )
+            .await?;
+
+        let account = Account {
+            id: None,
+            email: "test@test.com".into(),
+            name: "Test".into(),
         }
+        .save()
+        .fetch(&mut transaction)
+        .await?;
+        assert!(account.id.is_some());
+
+        Ok(())
     }
 
-    impl FromRow for Order {
-        fn from_row(row: Row) -> Result<Self, Error> {
-            let id: i64 = row.get("id");
-            let user_id: i64 = row.get("user_id");
-            let amount: f64 = row.get("amount");
+    #[tokio::test]
+    async fn test_callbacks() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
 
-            Ok(Order {
-                id,
-                user_id,
-                amount,
-            })
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE subscribers (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL)",
+                &[],
+            )
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "CREATE TABLE subscriber_events (id BIGSERIAL PRIMARY KEY, subscriber_id BIGINT NOT NULL, event VARCHAR NOT NULL)",
+                &[],
+            )
+            .await?;
+
+        let subscriber = Subscriber {
+            id: None,
+            email: " Test@Example.com ".into(),
+        }
+        .save()
+        .fetch(&mut transaction)
+        .await?;
+        assert_eq!(subscriber.email, "test@example.com");
+
+        let subscriber = Subscriber {
+            email: "OTHER@example.com".into(),
+            ..subscriber
         }
-    }
+        .save()
+        .fetch(&mut transaction)
+        .await?;
+        assert_eq!(subscriber.email, "other@example.com");
+
+        let events = SubscriberEvent::filter("subscriber_id", subscriber.id)
+            .order("id")
+            .fetch_all(&mut transaction)
+            .await?
+            .into_iter()
+            .map(|event| event.event)
+            .collect::<Vec<_>>();
+        assert_eq!(events, vec!["created", "updated"]);
 
-    impl FromRow for OrderItem {
-        fn from_row(row: Row) -> Result<Self, Error> {
-            let id: i64 = row.get("id");
-            let order_id: i64 = row.get("order_id");
-            let product_id: i64 = row.get("product_id");
+        // Bulk updates don't save models, so no callbacks are called.
+        Subscriber::all()
+            .update_all(&[("email", "bulk@example.com")])
+            .execute(&mut transaction)
+            .await?;
+        assert_eq!(SubscriberEvent::all().count(&mut transaction).await?, 2);
 
-            Ok(OrderItem {
-                id,
-                order_id,
-                product_id,
-            })
-        }
+        Ok(())
     }
 
-    impl FromRow for Product {
-        fn from_row(row: Row) -> Result<Self, Error> {
-            let id: i64 = row.get("id");
-            let name: String = row.get("name");
+    #[tokio::test]
+    async fn test_execute_returning_count() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
 
-            Ok(Product { id, name })
-        }
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS members CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE members (id BIGSERIAL PRIMARY KEY, admin BOOLEAN NOT NULL, created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(), deleted_at TIMESTAMPTZ)", &[])
+            .await?;
+        transaction
+            .client()
+            .execute(
+                "INSERT INTO members (admin) VALUES (false), (false), (true)",
+                &[],
+            )
+            .await?;
+
+        let updated = Member::filter("admin", false)
+            .update_all(&[("deleted_at", time::OffsetDateTime::now_utc())])
+            .execute_returning_count(&mut transaction)
+            .await?;
+        assert_eq!(updated, 2);
+        assert_eq!(Member::active().count(&mut transaction).await?, 1);
+
+        let updated = Member::filter("id", 1234_i64)
+            .update_all(&[("admin", true)])
+            .execute_returning_count(&mut transaction)
+            .await?;
+        assert_eq!(updated, 0);
+
+        let deleted = Query::<Member>::raw_with(
+            "DELETE FROM members WHERE admin = $1",
+            vec![false.to_value()],
+        )
+        .execute_returning_count(&mut transaction)
+        .await?;
+        assert_eq!(deleted, 2);
+        assert_eq!(Member::all().count(&mut transaction).await?, 1);
+
+        transaction.rollback().await?;
+
+        Ok(())
     }
 
-    #[test]
-    fn test_join() {
-        let query = User::all().join::<Order>().first_one();
+    #[tokio::test]
+    async fn test_count_distinct_records() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
+
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS orders CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE orders (id BIGSERIAL PRIMARY KEY, user_id BIGINT NOT NULL, amount DOUBLE PRECISION NOT NULL)", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("INSERT INTO orders (user_id, amount) VALUES (1, 5.0), (1, 5.0), (2, 5.0), (3, 10.0)", &[])
+            .await?;
 
         assert_eq!(
-            query.to_sql(),
-            r#"SELECT "users".* FROM "users" INNER JOIN "orders" ON "users"."id" = "orders"."user_id" ORDER BY "users"."id" ASC LIMIT 1"#
+            Order::all()
+                .count_distinct("user_id", &mut transaction)
+                .await?,
+            3
         );
-
-        let query = Order::all().join::<User>();
         assert_eq!(
-            query.to_sql(),
-            r#"SELECT "orders".* FROM "orders" INNER JOIN "users" ON "orders"."user_id" = "users"."id""#
+            Order::filter("amount", 5.0)
+                .order("id")
+                .count_distinct("user_id", &mut transaction)
+                .await?,
+            2
+        );
+        assert_eq!(Order::all().count(&mut transaction).await?, 4);
+        assert!(
+            Order::filter("amount", 10.0)
+                .exists(&mut transaction)
+                .await?
+        );
+        assert!(
+            !Order::filter("amount", 5.0)
+                .offset(3)
+                .exists(&mut transaction)
+                .await?
+        );
+        assert!(
+            !Order::filter("user_id", 4_i64)
+                .exists(&mut transaction)
+                .await?
         );
 
-        // Order that have a user with id = 5.
-        let query = Order::all()
-            .join::<User>()
-            .find_by(User::column("id"), 5_i64.to_value());
+        transaction.rollback().await?;
 
-        assert_eq!(
-            query.to_sql(),
-            r#"SELECT "orders".* FROM "orders" INNER JOIN "users" ON "orders"."user_id" = "users"."id" WHERE "users"."id" = $1 LIMIT 1"#
-        );
+        Ok(())
+    }
 
-        let query = User::all()
-            .join::<Order>()
-            .filter("id", 5)
-            .filter(Order::column("amount"), 42.0);
+    #[tokio::test]
+    async fn test_filter_in_subquery_records() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
 
-        assert_eq!(
-            query.to_sql(),
-            r#"SELECT "users".* FROM "users" INNER JOIN "orders" ON "users"."id" = "orders"."user_id" WHERE "users"."id" = $1 AND "orders"."amount" = $2"#
-        );
+        for query in [
+            "DROP TABLE IF EXISTS orders CASCADE",
+            "DROP TABLE IF EXISTS users CASCADE",
+            "CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL)",
+            "CREATE TABLE orders (id BIGSERIAL PRIMARY KEY, user_id BIGINT NOT NULL, amount DOUBLE PRECISION NOT NULL)",
+            "INSERT INTO users (email, password) SELECT 'user' || n || '@test.com', 'password' FROM generate_series(1, 4) n",
+            "INSERT INTO orders (user_id, amount) VALUES (1, 100.0), (2, 10.0), (3, 75.0)",
+        ] {
+            transaction.client().execute(query, &[]).await?;
+        }
 
-        let query = User::all()
-            .join::<Order>()
-            .join_nested(Order::join::<OrderItem>().join::<Product>())
-            .filter(Product::column("name"), "test_product");
+        let big_spenders = || {
+            Order::all()
+                .filter_gt("amount", 50.0)
+                .select_columns(&["user_id"])
+        };
 
-        println!("{}", query.to_sql());
-    }
+        let users = User::all()
+            .filter_not("email", "user3@test.com")
+            .filter_in_subquery("id", big_spenders())
+            .order("id")
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1]);
 
-    #[test]
-    fn test_related() {
-        // let query = User::related::<Order>([1, 2].as_slice());
-        // println!("{}", query.to_sql());
-    }
+        let users = User::all()
+            .filter_not_in_subquery("id", big_spenders())
+            .order("id")
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![2, 4]);
 
-    #[test]
-    fn test_take_one() {
-        let query = User::take_one().to_sql();
+        transaction.rollback().await?;
 
-        assert_eq!(query, r#"SELECT * FROM "users" LIMIT 1"#);
+        Ok(())
     }
 
-    #[test]
-    fn test_take_many() {
-        let query = User::take_many(25).to_sql();
+    #[tokio::test]
+    async fn test_filter_exists_records() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
 
-        assert_eq!(query, r#"SELECT * FROM "users" LIMIT 25"#);
-    }
+        for query in [
+            "DROP TABLE IF EXISTS orders CASCADE",
+            "DROP TABLE IF EXISTS users CASCADE",
+            "CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL)",
+            "CREATE TABLE orders (id BIGSERIAL PRIMARY KEY, user_id BIGINT NOT NULL, amount DOUBLE PRECISION NOT NULL)",
+            "INSERT INTO users (email, password) SELECT 'user' || n || '@test.com', 'password' FROM generate_series(1, 4) n",
+            "INSERT INTO orders (user_id, amount) VALUES (1, 100.0), (2, 10.0), (3, 75.0)",
+        ] {
+            transaction.client().execute(query, &[]).await?;
+        }
 
-    #[test]
-    fn test_first_one() {
-        let query = User::first_one().to_sql();
+        let big_orders = || {
+            Order::all()
+                .filter("user_id", User::column("id"))
+                .filter_gt("amount", 50.0)
+        };
 
-        assert_eq!(
-            query,
-            r#"SELECT * FROM "users" ORDER BY "users"."id" ASC LIMIT 1"#
-        );
-    }
+        let users = User::all()
+            .filter_not("email", "user3@test.com")
+            .filter_exists(big_orders())
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![1]);
 
-    #[test]
-    fn test_first_many() {
-        let query = User::first_many(25).to_sql();
+        let users = User::all()
+            .filter_not_exists(big_orders())
+            .order("id")
+            .fetch_all(&mut transaction)
+            .await?;
+        assert_eq!(users.iter().map(|u| u.id).collect::<Vec<_>>(), vec![2, 4]);
 
-        assert_eq!(
-            query,
-            r#"SELECT * FROM "users" ORDER BY "users"."id" ASC LIMIT 25"#
-        );
+        transaction.rollback().await?;
+
+        Ok(())
     }
 
-    #[test]
-    fn test_all() {
-        let query = User::all().to_sql();
+    #[tokio::test]
+    async fn test_raw_with() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut transaction = pool.transaction().await?;
 
-        assert_eq!(query, r#"SELECT * FROM "users""#);
-    }
+        transaction
+            .client()
+            .execute("DROP TABLE IF EXISTS users CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL)", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("INSERT INTO users (email, password) SELECT 'user' || n || '@test.com', 'password' FROM generate_series(1, 10) n", &[])
+            .await?;
 
-    #[test]
-    fn test_filter() {
-        let query = User::filter("email", "test@test.com")
-            .filter("password", "not_encrypted")
-            .filter("id", 5)
-            .filter("id", [1_i64, 2, 3].as_slice());
+        let users = Query::<User>::raw_with(
+            "WITH ranked AS (SELECT *, ROW_NUMBER() OVER (ORDER BY id DESC) AS rank FROM users)
+            SELECT id, email, password FROM ranked WHERE rank <= $1 AND email <> $2 ORDER BY id",
+            vec![3_i64.to_value(), "user9@test.com".to_value()],
+        )
+        .fetch_all(&mut transaction)
+        .await?;
 
         assert_eq!(
-            query.to_sql(),
-            r#"SELECT * FROM "users" WHERE "users"."email" = $1 AND "users"."password" = $2 AND "users"."id" = $3 AND "users"."id" = ANY($4)"#
+            users.iter().map(|user| user.id).collect::<Vec<_>>(),
+            vec![8, 10]
         );
+
+        // Values are bound, not interpolated.
+        let users = Query::<User>::raw_with(
+            "SELECT * FROM users WHERE email = $1",
+            vec!["' OR 1 = 1 --".to_value()],
+        )
+        .fetch_all(&mut transaction)
+        .await?;
+        assert!(users.is_empty());
+
+        transaction.rollback().await?;
+
+        Ok(())
     }
 
     #[test]
-    fn test_find_by() {
-        let query = User::find_by("email", "test@test.com");
+    fn test_pluck_query() {
+        let query = User::filter("email", "test@test.com")
+            .order("id")
+            .pluck_query(&["id".to_column(), Column::new("orders", "id")])
+            .unwrap();
         assert_eq!(
             query.to_sql(),
-            r#"SELECT * FROM "users" WHERE "users"."email" = $1 LIMIT 1"#
+            r#"SELECT "users"."id", "orders"."id" FROM "users" WHERE "users"."email" = $1 ORDER BY id"#
         );
+
+        assert!(User::all()
+            .update_all(&[("email", "test@test.com")])
+            .pluck_query(&["id".to_column()])
+            .is_err());
     }
 
     #[tokio::test]
-    async fn test_fetch() -> Result<(), Error> {
+    async fn test_pluck() -> Result<(), Error> {
         let pool = Pool::from_env();
         let mut transaction = pool.transaction().await?;
 
         transaction
             .client()
-            .query("DROP TABLE IF EXISTS users CASCADE", &[])
+            .execute("DROP TABLE IF EXISTS users CASCADE", &[])
             .await?;
-
         transaction
             .client()
-            .query(
-                "CREATE TABLE IF NOT EXISTS users (id BIGINT, email VARCHAR, password VARCHAR);",
-                &[],
-            )
+            .execute("CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL)", &[])
             .await?;
         transaction
             .client()
-            .query(
-                "INSERT INTO users VALUES (1, 'test@test.com', 'not_encrypted');",
-                &[],
-            )
+            .execute("INSERT INTO users (email, password) SELECT 'user' || n || '@test.com', 'password' FROM generate_series(1, 10) n", &[])
             .await?;
 
-        let user = User::order(("email", "ASC"))
-            .first_one()
-            .fetch(&mut transaction)
+        let ids = User::all()
+            .filter_gt("id", 7)
+            .order("id")
+            .pluck::<i64>("id", &mut transaction)
             .await?;
+        assert_eq!(ids, vec![8, 9, 10]);
 
-        assert_eq!(user.email, "test@test.com");
-
-        let users = User::all().fetch_all(&mut transaction).await?;
+        let users = User::filter("email", "user2@test.com")
+            .pluck2::<i64, String>("id", "email", &mut transaction)
+            .await?;
+        assert_eq!(users, vec![(2, "user2@test.com".to_string())]);
 
-        assert_eq!(users.len(), 1);
+        transaction.rollback().await?;
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_explain() -> Result<(), Error> {
+    async fn test_pluck_in_batches() -> Result<(), Error> {
         let pool = Pool::from_env();
         let mut transaction = pool.transaction().await?;
 
         transaction
             .client()
-            .execute("CREATE TABLE IF NOT EXISTS users (id BIGINT);", &[])
+            .execute("DROP TABLE IF EXISTS users CASCADE", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL)", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("INSERT INTO users (email, password) SELECT 'user' || n || '@test.com', 'password' FROM generate_series(1, 25) n", &[])
             .await?;
 
-        let explain = User::all().explain(&mut transaction).await?;
-        assert!(explain.to_string().starts_with("Seq Scan on users"));
+        let mut ids = vec![];
+        let mut batches = 0;
+
+        User::all()
+            .pluck_in_batches::<i64, _, _>("id", 10, &mut transaction, |batch| {
+                assert!(batch.len() <= 10);
+                batches += 1;
+                ids.extend(batch);
+                async { Ok(()) }
+            })
+            .await?;
+
+        assert_eq!(batches, 3);
+        assert_eq!(ids, (1..=25).collect::<Vec<i64>>());
+
+        let mut emails = vec![];
+        User::all()
+            .filter_gt("id", 20)
+            .pluck_in_batches::<String, _, _>("email", 2, &mut transaction, |batch| {
+                emails.extend(batch);
+                async { Ok(()) }
+            })
+            .await?;
+
+        assert_eq!(emails.len(), 5);
+        assert_eq!(emails[0], "user21@test.com");
 
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_find_or_create() -> Result<(), Error> {
+    async fn test_placeholder_mismatch() -> Result<(), Error> {
         let pool = Pool::from_env();
 
+        let result = User::find_by_sql(
+            "SELECT * FROM users WHERE id = $1 AND email = $2",
+            &[1.to_value()],
+        )
+        .fetch_all(pool.clone())
+        .await;
+        assert!(matches!(result, Err(Error::PlaceholderMismatch(2, 1, _))));
+
+        let result = User::find_by_sql("SELECT * FROM users", &[1.to_value()])
+            .fetch_all(pool.clone())
+            .await;
+        assert!(matches!(result, Err(Error::PlaceholderMismatch(0, 1, _))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fetch_global() -> Result<(), Error> {
+        let query = "SELECT id, 'user' || id || '@test.com' AS email, 'password' AS password FROM generate_series(1::bigint, 3::bigint) id";
+
+        let user = User::find_by_sql(query, &[]).fetch_global().await?;
+        assert_eq!(user.email, "user1@test.com");
+
+        let users = User::find_by_sql(query, &[]).fetch_all_global().await?;
+        assert_eq!(users.len(), 3);
+
+        let user = User::find_by_sql(format!("{} WHERE id > $1", query), &[5.to_value()])
+            .fetch_optional_global()
+            .await?;
+        assert!(user.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_each() -> Result<(), Error> {
+        let pool = Pool::from_env();
         let mut transaction = pool.transaction().await?;
 
         transaction
@@ -1830,24 +6212,50 @@ mod test {
             .await?;
         transaction
             .client()
-            .execute("CREATE TABLE IF NOT EXISTS users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL);", &[])
+            .execute("CREATE TABLE users (id BIGSERIAL PRIMARY KEY, email VARCHAR NOT NULL, password VARCHAR NOT NULL)", &[])
+            .await?;
+        transaction
+            .client()
+            .execute("INSERT INTO users (email, password) SELECT 'user' || n || '@test.com', 'password' FROM generate_series(1, 25) n", &[])
             .await?;
 
-        let query = User::all()
-            .filter("email", "test@test.com")
-            .filter("password", "password")
-            .find_or_create();
-        let sql = query.to_sql();
-        assert_eq!(
-            sql,
-            r#"SELECT * FROM "users" WHERE "users"."email" = $1 AND "users"."password" = $2 LIMIT 1; INSERT INTO "users" ("email", "password") VALUES ($1, $2) RETURNING *;"#
-        );
+        let mut ids = vec![];
+        User::all()
+            .order(("email", "DESC"))
+            .find_each(10, &mut transaction, |user| {
+                ids.push(user.id);
+                async { Ok(()) }
+            })
+            .await?;
 
-        query.fetch(&mut transaction).await?;
+        assert_eq!(ids, (1..=25).collect::<Vec<i64>>());
+
+        let mut emails = vec![];
+        User::all()
+            .filter_gt("id", 20)
+            .find_each(2, &mut transaction, |user| {
+                emails.push(user.email);
+                async { Ok(()) }
+            })
+            .await?;
+
+        assert_eq!(emails.len(), 5);
+        assert_eq!(emails[0], "user21@test.com");
 
         Ok(())
     }
 
+    #[test]
+    fn test_secure_id() {
+        let user = User {
+            id: 1234,
+            ..Default::default()
+        };
+        let secure_id = user.secure_id().unwrap();
+
+        assert_eq!(crate::crypto::decrypt_number(&secure_id).unwrap(), 1234);
+    }
+
     #[test]
     fn test_unique_by() {
         let query = User::create(&[("email", "test@test.com")])
@@ -1,11 +1,90 @@
 //! Implements the `ORDER BY` SQL primitive.
-use super::{Column, Escape, ToSql};
+use super::{Column, Error, Escape, ToSql};
+
+/// Position of `NULL` values in the order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Nulls {
+    /// `NULLS FIRST`
+    First,
+    /// `NULLS LAST`
+    Last,
+}
+
+impl ToSql for Nulls {
+    fn to_sql(&self) -> String {
+        match self {
+            Nulls::First => "NULLS FIRST",
+            Nulls::Last => "NULLS LAST",
+        }
+        .to_string()
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum OrderColumn {
     Asc(Column),
     Desc(Column),
     Raw(String),
+    /// Column with `NULL` values placed first or last.
+    Nulls(Box<OrderColumn>, Nulls),
+}
+
+impl OrderColumn {
+    /// Order by the column in the direction, e.g. `"DESC"` or `"ASC NULLS LAST"`.
+    /// Directions which aren't recognized are used as-is.
+    fn parse(column: Column, direction: &str) -> Self {
+        let direction = direction.to_ascii_uppercase();
+        let words = direction.split_whitespace().collect::<Vec<_>>();
+
+        let (order, nulls) = match words.as_slice() {
+            [order] => (*order, None),
+            [order, "NULLS", "FIRST"] => (*order, Some(Nulls::First)),
+            [order, "NULLS", "LAST"] => (*order, Some(Nulls::Last)),
+            _ => ("", None),
+        };
+
+        let column = match order {
+            "ASC" => OrderColumn::Asc(column),
+            "DESC" => OrderColumn::Desc(column),
+            _ => return OrderColumn::Raw(format!("{} {}", column.to_sql(), direction.escape())),
+        };
+
+        match nulls {
+            Some(nulls) => column.nulls(nulls),
+            None => column,
+        }
+    }
+
+    /// Is the order by this column? Raw orders are checked by their first word, e.g. `"id DESC"`.
+    fn is_column(&self, column: &Column) -> bool {
+        use OrderColumn::*;
+
+        match self {
+            Asc(ordered) | Desc(ordered) => {
+                ordered.column_name() == column.column_name()
+                    && (!ordered.qualified() || ordered.table_name() == column.table_name())
+            }
+            Raw(raw) => {
+                let name = raw.split_whitespace().next().unwrap_or_default();
+                let mut parts = name.rsplit('.').map(|part| part.trim_matches('"'));
+                let column_name = parts.next().unwrap_or_default();
+
+                column_name == column.column_name()
+                    && parts
+                        .next()
+                        .is_none_or(|table| table == column.table_name())
+            }
+            Nulls(ordered, _) => ordered.is_column(column),
+        }
+    }
+
+    /// Place `NULL` values first or last.
+    pub fn nulls(self, nulls: Nulls) -> Self {
+        match self {
+            OrderColumn::Nulls(column, _) => OrderColumn::Nulls(column, nulls),
+            column => OrderColumn::Nulls(Box::new(column), nulls),
+        }
+    }
 }
 
 impl ToSql for OrderColumn {
@@ -16,6 +95,7 @@ impl ToSql for OrderColumn {
             Asc(column) => format!("{} ASC", column.to_sql()),
             Desc(column) => format!("{} DESC", column.to_sql()),
             Raw(raw) => raw.clone(),
+            Nulls(column, nulls) => format!("{} {}", column.to_sql(), nulls.to_sql()),
         }
     }
 }
@@ -35,11 +115,7 @@ impl ToOrderBy for &str {
 impl ToOrderBy for [&str; 2] {
     fn to_order_by(&self) -> OrderBy {
         OrderBy {
-            order_by: vec![OrderColumn::Raw(format!(
-                "{} {}",
-                Column::name(self[0]).to_sql(),
-                self[1].to_ascii_uppercase().escape()
-            ))],
+            order_by: vec![OrderColumn::parse(Column::name(self[0]), self[1])],
         }
     }
 }
@@ -53,15 +129,49 @@ impl ToOrderBy for (&str, &str) {
 impl ToOrderBy for (Column, &str) {
     fn to_order_by(&self) -> OrderBy {
         OrderBy {
-            order_by: vec![OrderColumn::Raw(format!(
-                "{} {}",
-                self.0.to_sql(),
-                self.1.to_ascii_uppercase().escape()
-            ))],
+            order_by: vec![OrderColumn::parse(self.0.clone(), self.1)],
         }
     }
 }
 
+impl ToOrderBy for (&str, &str, Nulls) {
+    fn to_order_by(&self) -> OrderBy {
+        (self.0, self.1).to_order_by().nulls(self.2)
+    }
+}
+
+impl ToOrderBy for (Column, &str, Nulls) {
+    fn to_order_by(&self) -> OrderBy {
+        (self.0.clone(), self.1).to_order_by().nulls(self.2)
+    }
+}
+
+impl ToOrderBy for OrderBy {
+    fn to_order_by(&self) -> OrderBy {
+        self.clone()
+    }
+}
+
+impl<T: ToOrderBy> ToOrderBy for &[T] {
+    fn to_order_by(&self) -> OrderBy {
+        self.iter().fold(OrderBy::default(), |order_by, column| {
+            order_by + column.to_order_by()
+        })
+    }
+}
+
+impl<T: ToOrderBy, const N: usize> ToOrderBy for &[T; N] {
+    fn to_order_by(&self) -> OrderBy {
+        self.as_slice().to_order_by()
+    }
+}
+
+impl<T: ToOrderBy> ToOrderBy for Vec<T> {
+    fn to_order_by(&self) -> OrderBy {
+        self.as_slice().to_order_by()
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct OrderBy {
     pub order_by: Vec<OrderColumn>,
@@ -80,9 +190,100 @@ impl OrderBy {
         }
     }
 
+    /// Random order, i.e. `ORDER BY RANDOM()`.
+    pub fn random() -> Self {
+        Self {
+            order_by: vec![OrderColumn::Raw("RANDOM()".into())],
+        }
+    }
+
+    /// Parse an order from user input, e.g. a query parameter like `"created_at DESC, id"`.
+    ///
+    /// Unlike ordering by a string with [`ToOrderBy`], which is used as-is, only the given columns
+    /// and the `ASC`/`DESC` directions, optionally with `NULLS FIRST`/`NULLS LAST`, are accepted. Anything else is
+    /// an error, so the input can't change the query.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{OrderBy, ToSql};
+    /// let order = OrderBy::parse("created_at desc, id", &["id", "created_at"]).unwrap();
+    /// assert_eq!(order.to_sql(), r#" ORDER BY "created_at" DESC, "id" ASC"#);
+    ///
+    /// assert!(OrderBy::parse("id; DROP TABLE users", &["id"]).is_err());
+    /// ```
+    pub fn parse(input: &str, columns: &[&str]) -> Result<Self, Error> {
+        let mut order_by = OrderBy::default();
+
+        for part in input.split(',') {
+            let (column, direction) = match part.trim().split_once(char::is_whitespace) {
+                Some((column, direction)) => (column, direction.trim()),
+                None => (part.trim(), "ASC"),
+            };
+
+            if !columns.contains(&column) {
+                return Err(Error::InvalidOrder(format!(
+                    "unknown column \"{}\"",
+                    column
+                )));
+            }
+
+            match OrderColumn::parse(Column::name(column), direction) {
+                OrderColumn::Raw(_) => {
+                    return Err(Error::InvalidOrder(format!(
+                        "unknown direction \"{}\"",
+                        direction
+                    )))
+                }
+                column => order_by.order_by.push(column),
+            }
+        }
+
+        Ok(order_by)
+    }
+
     pub fn is_empty(&self) -> bool {
         self.order_by.is_empty()
     }
+
+    /// Is the order random, i.e. `ORDER BY RANDOM()`?
+    pub fn is_random(&self) -> bool {
+        self.order_by
+            .iter()
+            .any(|column| matches!(column, OrderColumn::Raw(raw) if raw == "RANDOM()"))
+    }
+
+    /// Does the order include the column? Unqualified columns in the order
+    /// match the column from any table.
+    pub fn includes(&self, column: &Column) -> bool {
+        self.order_by
+            .iter()
+            .any(|ordered| ordered.is_column(column))
+    }
+
+    /// Order by the column last, e.g. to break ties between rows that are equal in all other columns.
+    pub fn then_asc(mut self, column: Column) -> Self {
+        self.order_by.push(OrderColumn::Asc(column));
+        self
+    }
+
+    /// Place `NULL` values of the last column first or last.
+    pub fn nulls(mut self, nulls: Nulls) -> Self {
+        if let Some(column) = self.order_by.pop() {
+            self.order_by.push(column.nulls(nulls));
+        }
+        self
+    }
+
+    /// Place `NULL` values of the last column first, i.e. `NULLS FIRST`.
+    pub fn nulls_first(self) -> Self {
+        self.nulls(Nulls::First)
+    }
+
+    /// Place `NULL` values of the last column last, i.e. `NULLS LAST`.
+    pub fn nulls_last(self) -> Self {
+        self.nulls(Nulls::Last)
+    }
 }
 
 impl std::ops::Add for OrderBy {
@@ -120,4 +321,60 @@ mod test {
         let _order_by = "created_at ASC".to_order_by();
         let _order_by = ["created_at", "ASC"].to_order_by();
     }
+
+    #[test]
+    fn test_order_by_nulls() {
+        let order_by = ("created_at", "desc nulls first").to_order_by();
+        assert_eq!(
+            order_by.to_sql(),
+            r#" ORDER BY "created_at" DESC NULLS FIRST"#
+        );
+
+        let order_by = [("a", "DESC"), ("b", "ASC")].as_slice().to_order_by();
+        assert_eq!(order_by.to_sql(), r#" ORDER BY "a" DESC, "b" ASC"#);
+
+        let order_by =
+            (OrderBy::desc(Column::name("a")) + OrderBy::asc(Column::name("b"))).nulls_last();
+        assert_eq!(
+            order_by.to_sql(),
+            r#" ORDER BY "a" DESC, "b" ASC NULLS LAST"#
+        );
+
+        let order_by = ("a", "ASC", Nulls::First).to_order_by().nulls(Nulls::Last);
+        assert_eq!(order_by.to_sql(), r#" ORDER BY "a" ASC NULLS LAST"#);
+
+        // Unknown directions are used as-is.
+        let order_by = ("a", "using <").to_order_by();
+        assert_eq!(order_by.to_sql(), r#" ORDER BY "a" USING <"#);
+    }
+
+    #[test]
+    fn test_order_by_parse() {
+        let columns = ["id", "email", "created_at"];
+
+        let order_by = OrderBy::parse("email", &columns).unwrap();
+        assert_eq!(order_by.to_sql(), r#" ORDER BY "email" ASC"#);
+
+        let order_by = OrderBy::parse(" created_at  DESC NULLS last ,id asc", &columns).unwrap();
+        assert_eq!(
+            order_by.to_sql(),
+            r#" ORDER BY "created_at" DESC NULLS LAST, "id" ASC"#
+        );
+
+        for input in [
+            "password",
+            "Email",
+            "",
+            "id,",
+            "\"id\"",
+            "id; DROP TABLE users",
+            "id DESC; DROP TABLE users",
+            "id DESC, (SELECT 1)",
+            "id USING <",
+            "id ASC NULLS",
+        ] {
+            let err = OrderBy::parse(input, &columns).unwrap_err();
+            assert!(matches!(err, Error::InvalidOrder(_)), "{}", input);
+        }
+    }
 }
@@ -0,0 +1,31 @@
+//! Represents the result of `Query::page`.
+
+/// A page of records and the total number of records matching the query.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    /// Records on this page.
+    pub items: Vec<T>,
+    /// Total number of records matching the query, on all pages.
+    pub total: i64,
+    /// Page number, starting at 1.
+    pub page: i64,
+    /// Maximum number of records on a page.
+    pub per_page: i64,
+}
+
+impl<T> Page<T> {
+    /// Total number of pages.
+    pub fn total_pages(&self) -> i64 {
+        (self.total + self.per_page - 1) / self.per_page
+    }
+
+    /// There is a page after this one.
+    pub fn has_next(&self) -> bool {
+        self.page < self.total_pages()
+    }
+
+    /// There is a page before this one.
+    pub fn has_previous(&self) -> bool {
+        self.page > 1
+    }
+}
@@ -1,5 +1,5 @@
 //! `$1`, `$2`, etc. query placeholders used by prepared statements.
-use super::Value;
+use super::{Error, Value};
 
 #[derive(Debug, Clone, Default)]
 pub struct Placeholders {
@@ -28,9 +28,103 @@ impl Placeholders {
             .collect()
     }
 
+    /// Number of placeholders.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Append the values of other placeholders, numbering them after the existing ones.
+    pub fn extend(&mut self, placeholders: Placeholders) {
+        self.values.extend(placeholders.values);
+    }
+
     pub fn id(&self) -> i32 {
         self.values().len() as i32 + 1
     }
+
+    /// Make sure the query uses as many placeholders as there are values, so a mismatch
+    /// is reported before the query is sent to the database.
+    pub fn check(&self, query: &str) -> Result<(), Error> {
+        let expected = highest_placeholder(query);
+
+        if expected != self.len() {
+            Err(Error::PlaceholderMismatch(
+                expected,
+                self.len(),
+                query.to_string(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The highest `$n` placeholder in the query. Placeholders inside string
+/// literals, dollar-quoted strings, quoted identifiers and comments are ignored.
+fn highest_placeholder(query: &str) -> usize {
+    let mut highest = 0;
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                // Quotes are escaped by doubling them, which this handles as two strings.
+                for next in chars.by_ref() {
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+
+            '-' if chars.peek() == Some(&'-') => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+
+            '$' => {
+                let mut number = String::new();
+                while let Some(digit) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    number.push(*digit);
+                    chars.next();
+                }
+
+                if let Ok(number) = number.parse::<usize>() {
+                    highest = highest.max(number);
+                    continue;
+                }
+
+                // Dollar-quoted string, e.g. `$$text$$` or `$tag$text$tag$`.
+                let mut tag = String::new();
+                while let Some(c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                    tag.push(*c);
+                    chars.next();
+                }
+
+                if chars.peek() == Some(&'$') {
+                    chars.next();
+                    let delimiter = format!("${}$", tag);
+                    let mut body = String::new();
+                    for next in chars.by_ref() {
+                        body.push(next);
+                        if body.ends_with(&delimiter) {
+                            break;
+                        }
+                    }
+                }
+            }
+
+            _ => (),
+        }
+    }
+
+    highest
 }
 
 impl From<Vec<Value>> for Placeholders {
@@ -38,3 +132,28 @@ impl From<Vec<Value>> for Placeholders {
         Placeholders { values }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_highest_placeholder() {
+        assert_eq!(highest_placeholder("SELECT 1"), 0);
+        assert_eq!(
+            highest_placeholder(r#"SELECT * FROM "users" WHERE "id" = $2 AND "email" = $10"#),
+            10
+        );
+        assert_eq!(
+            highest_placeholder("SELECT '$5', \"$6\" -- $7\nFROM users WHERE id = $1"),
+            1
+        );
+        assert_eq!(highest_placeholder("SELECT $$text$$"), 0);
+        assert_eq!(highest_placeholder("SELECT $$text $1$$, $2"), 2);
+        assert_eq!(
+            highest_placeholder("DO $body$ BEGIN PERFORM $3; $$ $4 $$; END $body$; SELECT $1"),
+            1
+        );
+        assert_eq!(highest_placeholder("SELECT 'it''s $2', $1"), 1);
+    }
+}
@@ -19,6 +19,7 @@ use std::sync::{
 use std::time::Instant;
 
 use super::Error;
+use crate::model::error::connection_error;
 
 #[derive(Debug)]
 struct ConnectionInner {
@@ -95,34 +96,73 @@ impl Connection {
         query: &str,
         params: &[&(dyn ToSql + Sync)],
     ) -> Result<Vec<Row>, Error> {
-        let statement = if let Some(statement) = self.cache.get(query) {
-            statement
-        } else {
-            let statement = self.client().prepare(query).await?;
-            self.cache.insert(query.to_string(), statement);
-            &self.cache[query]
-        };
+        let statement = self.prepare_cached(query).await?;
 
-        match self.client().query(statement, &params).await {
+        match self.client().query(&statement, params).await {
             Ok(rows) => Ok(rows),
-            Err(err) => {
-                // If schema changed, we better close this connection entirely
-                // than evicting prepared statements one by one.
-                // TODO: find and use the error code instead of using the English
-                // error message which will be translated on databases running in other locales.
-                if let Some(db_error) = err.as_db_error() {
-                    if db_error.message() == "cached plan must not change result type" {
-                        self.inner.bad.store(true, Ordering::Relaxed);
-                    }
-                }
-                Err(Error::DatabaseError(err))
+            Err(err) => Err(self.query_error(err)),
+        }
+    }
+
+    /// Execute the query like [`Connection::query_cached`], but return the number of rows
+    /// it changed instead of the rows it returned.
+    pub async fn execute_cached(
+        &mut self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, Error> {
+        let statement = self.prepare_cached(query).await?;
+
+        match self.client().execute(&statement, params).await {
+            Ok(rows) => Ok(rows),
+            Err(err) => Err(self.query_error(err)),
+        }
+    }
+
+    /// Get the prepared statement for the query, preparing it if we haven't seen it before.
+    async fn prepare_cached(&mut self, query: &str) -> Result<Statement, Error> {
+        if let Some(statement) = self.cache.get(query) {
+            return Ok(statement.clone());
+        }
+
+        match self.client().prepare(query).await {
+            Ok(statement) => {
+                self.cache.insert(query.to_string(), statement.clone());
+                Ok(statement)
+            }
+            Err(err) => Err(self.error(err)),
+        }
+    }
+
+    /// Convert an error returned by executing a prepared statement.
+    fn query_error(&self, err: tokio_postgres::Error) -> Error {
+        // If schema changed, we better close this connection entirely
+        // than evicting prepared statements one by one.
+        // TODO: find and use the error code instead of using the English
+        // error message which will be translated on databases running in other locales.
+        if let Some(db_error) = err.as_db_error() {
+            if db_error.message() == "cached plan must not change result type" {
+                self.inner.bad.store(true, Ordering::Relaxed);
             }
         }
+
+        self.error(err)
+    }
+
+    /// Convert a driver error, marking the connection as broken if it can't be used anymore,
+    /// so it doesn't go back into the pool.
+    fn error(&self, err: tokio_postgres::Error) -> Error {
+        if connection_error(&err) {
+            self.inner.bad.store(true, Ordering::Relaxed);
+        }
+
+        err.into()
     }
 
-    /// Is the connection broken?
+    /// Is the connection broken? A connection whose driver task has stopped,
+    /// e.g. because the runtime that spawned it shut down, is broken too.
     pub fn bad(&self) -> bool {
-        self.inner.bad.load(Ordering::Relaxed)
+        self.inner.bad.load(Ordering::Relaxed) || self.client.is_closed()
     }
 
     /// Forcibly close the connection once it's returned to the pool.
@@ -11,6 +11,14 @@
 //!
 //! This implementation uses FIFO to increase connection re-use.
 //!
+//! ## Read replicas
+//!
+//! The pool can be created with a list of read replicas using [`Pool::with_replicas`]. Connections to replicas
+//! are obtained with [`Pool::replica`] and `SELECT` queries executed on the pool use them automatically, unless
+//! the query locks rows or is marked with [`crate::model::Query::on_primary`]. Replicas are checked periodically and
+//! the ones that can't be reached are taken out of rotation until they recover. If no replica is available,
+//! the primary is used.
+//!
 //! ## Get a connection
 //!
 //! ```ignore
@@ -35,7 +43,7 @@ use std::collections::VecDeque;
 use std::future::Future;
 use std::ops::{Deref, DerefMut};
 use std::sync::{
-    atomic::{AtomicUsize, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
     Arc,
 };
 use std::time::Instant;
@@ -54,7 +62,8 @@ pub use transaction::Transaction;
 
 static POOL: OnceCell<Pool> = OnceCell::new();
 
-/// Get the connection pool.
+/// Get the connection pool. The pool is created from the configuration
+/// the first time it's used, so it doesn't need to be set up beforehand.
 ///
 /// Use [`Pool::pool`] instead.
 pub fn get_pool() -> Pool {
@@ -97,9 +106,12 @@ impl<'a> ConnectionRequest<'a> {
         }
     }
 
-    pub(crate) async fn get(&self) -> Result<Option<ConnectionGuard>, Error> {
+    /// Get a connection from the pool, if needed. Read-only queries can set `replica`
+    /// to use a read replica, if the pool has any.
+    pub(crate) async fn get(&self, replica: bool) -> Result<Option<ConnectionGuard>, Error> {
         match self {
             ConnectionRequest::Fulfilled(_) => Ok(None),
+            ConnectionRequest::Pool(pool) if replica => Ok(Some(pool.replica().await?)),
             ConnectionRequest::Pool(pool) => Ok(Some(pool.get().await?)),
         }
     }
@@ -241,6 +253,10 @@ pub struct PoolConfig {
 
     /// Maximum time a connection remains open and available while not in use.
     pub idle_timeout: Duration,
+
+    /// Number of idle connections the pool keeps open, even if they
+    /// exceed the idle timeout. Connections are created in the background.
+    pub min_idle: usize,
 }
 
 impl Default for PoolConfig {
@@ -249,14 +265,42 @@ impl Default for PoolConfig {
             pool_size: 10,
             checkout_timeout: Duration::from_secs(5),
             idle_timeout: Duration::from_secs(3600),
+            min_idle: 0,
         }
     }
 }
 
+/// Snapshot of the pool state, returned by [`Pool::metrics`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolMetrics {
+    /// Connections checked out by callers or being created.
+    pub in_use: usize,
+    /// Open connections waiting in the pool.
+    pub idle: usize,
+    /// Callers waiting for a connection to be returned into the pool.
+    pub waiters: usize,
+}
+
+/// Read replica of the primary database.
+#[derive(Debug)]
+struct Replica {
+    pool: Pool,
+    healthy: AtomicBool,
+    checking: AtomicBool,
+}
+
+#[derive(Debug, Default)]
+struct Replicas {
+    replicas: Vec<Replica>,
+    next: AtomicUsize,
+}
+
 /// Connection pool that automatically manages connections.
 #[derive(Debug)]
 pub struct Pool {
     inner: Arc<Mutex<PoolInner>>,
+    waiters: Arc<AtomicUsize>,
+    replicas: Arc<Replicas>,
     checkin_notify: Arc<Notify>,
     database_url: String,
     config: PoolConfig,
@@ -268,6 +312,8 @@ impl Clone for Pool {
     fn clone(&self) -> Self {
         let clone = Self {
             inner: self.inner.clone(),
+            waiters: self.waiters.clone(),
+            replicas: self.replicas.clone(),
             checkin_notify: self.checkin_notify.clone(),
             database_url: self.database_url.clone(),
             config: self.config.clone(),
@@ -290,11 +336,38 @@ impl Pool {
     /// * `pool_config` - Pool configuration options.
     ///
     pub fn new(database_url: &str, config: PoolConfig) -> Self {
+        Self::with_replicas(database_url, config, &[])
+    }
+
+    /// Create new connection pool with read replicas. Each replica gets its own pool
+    /// using the same configuration options.
+    ///
+    /// # Arguments
+    ///
+    /// * `database_url` - Postgres-style connection URL of the primary.
+    /// * `pool_config` - Pool configuration options.
+    /// * `replicas` - Postgres-style connection URLs of the read replicas.
+    ///
+    pub fn with_replicas(database_url: &str, config: PoolConfig, replicas: &[String]) -> Self {
+        let replicas = Replicas {
+            replicas: replicas
+                .iter()
+                .map(|url| Replica {
+                    pool: Self::new(url, config.clone()),
+                    healthy: AtomicBool::new(true),
+                    checking: AtomicBool::new(false),
+                })
+                .collect(),
+            next: AtomicUsize::new(0),
+        };
+
         let pool = Self {
             inner: Arc::new(Mutex::new(PoolInner {
                 connections: VecDeque::new(),
                 expected: 0,
             })),
+            waiters: Arc::new(AtomicUsize::new(0)),
+            replicas: Arc::new(replicas),
             checkin_notify: Arc::new(Notify::new()),
             database_url: database_url.to_string(),
             config,
@@ -331,17 +404,22 @@ impl Pool {
     pub fn from_env() -> Self {
         let config = get_config().database.clone();
         let database_url = config.database_url();
-        Self::new(
+        Self::with_replicas(
             &database_url,
             PoolConfig {
                 pool_size: config.pool_size,
                 idle_timeout: config.idle_timeout().unsigned_abs(),
                 checkout_timeout: config.checkout_timeout().unsigned_abs(),
+                min_idle: config.min_idle,
             },
+            &config.replicas,
         )
     }
 
     /// Get a connection from the pool or wait until one is available.
+    ///
+    /// Returns [`Error::PoolTimeout`] if no connection is available within
+    /// the checkout timeout.
     pub async fn get(&self) -> Result<ConnectionGuard, Error> {
         match timeout(self.config.checkout_timeout, self.get_internal()).await {
             Ok(result) => result,
@@ -352,10 +430,64 @@ impl Pool {
         }
     }
 
+    /// Get a connection to a read replica. Replicas are used in round-robin order.
+    /// If the pool doesn't have replicas or none of them are reachable, a connection
+    /// to the primary is returned instead.
+    pub async fn replica(&self) -> Result<ConnectionGuard, Error> {
+        let replicas = &self.replicas.replicas;
+
+        if !replicas.is_empty() {
+            let next = self.replicas.next.fetch_add(1, Ordering::Relaxed);
+
+            for i in 0..replicas.len() {
+                let replica = &replicas[(next + i) % replicas.len()];
+
+                if !replica.healthy.load(Ordering::Relaxed) {
+                    continue;
+                }
+
+                match replica.pool.get().await {
+                    Ok(connection) => return Ok(connection),
+                    // Replica is busy, but it's alive.
+                    Err(Error::PoolTimeout) => continue,
+                    Err(err) => {
+                        tracing::error!("replica is down, removing from rotation: {:?}", err);
+                        replica.healthy.store(false, Ordering::Relaxed);
+                    }
+                }
+            }
+        }
+
+        self.get().await
+    }
+
+    /// Number of read replicas currently in rotation.
+    pub fn healthy_replicas(&self) -> usize {
+        self.replicas
+            .replicas
+            .iter()
+            .filter(|replica| replica.healthy.load(Ordering::Relaxed))
+            .count()
+    }
+
+    /// Get the global connection pool. The pool is created from the `[database]`
+    /// configuration the first time it's used.
     pub fn pool() -> Self {
         get_pool()
     }
 
+    /// Get current pool metrics, e.g. to export them to a monitoring system.
+    pub fn metrics(&self) -> PoolMetrics {
+        let inner = self.inner.lock();
+        let idle = inner.connections.len();
+
+        PoolMetrics {
+            in_use: inner.expected - idle,
+            idle,
+            waiters: self.waiters.load(Ordering::Relaxed),
+        }
+    }
+
     pub async fn connection() -> Result<ConnectionGuard, Error> {
         let pool = get_pool();
         pool.get().await
@@ -388,6 +520,46 @@ impl Pool {
         Ok(result)
     }
 
+    /// Run the closure inside a transaction. The transaction is committed if the closure
+    /// returns `Ok` and rolled back if it returns an error or panics, so it can't be forgotten.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let user = Pool::pool()
+    ///     .in_transaction(async |transaction| {
+    ///         let user = User::create(&[("email", "test@test.com")])
+    ///             .fetch(&mut *transaction)
+    ///             .await?;
+    ///         Signup::create(&[("user_id", user.id)])
+    ///             .execute(&mut *transaction)
+    ///             .await?;
+    ///
+    ///         Ok(user)
+    ///     })
+    ///     .await?;
+    /// ```
+    pub async fn in_transaction<R>(
+        &self,
+        f: impl AsyncFnOnce(&mut Transaction) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let mut transaction = self.transaction().await?;
+
+        // If the closure panics, the transaction is dropped, which rolls it back.
+        match f(&mut transaction).await {
+            Ok(result) => {
+                transaction.commit().await?;
+                Ok(result)
+            }
+
+            Err(err) => {
+                // Return the error of the closure, it's more useful than an error rolling back.
+                let _ = transaction.rollback().await;
+                Err(err)
+            }
+        }
+    }
+
     pub async fn with_connection<Fut, R>(
         &self,
         f: impl FnOnce(ConnectionGuard) -> Fut,
@@ -442,6 +614,7 @@ impl Pool {
                     }
                 }
             } else {
+                let _waiter = Waiter::new(&self.waiters);
                 self.checkin_notify.notified().await;
             }
         }
@@ -468,18 +641,85 @@ impl Pool {
         self.inner.lock().expected -= 1;
     }
 
+    /// Check that replicas are reachable. Replicas that are down are removed
+    /// from rotation and put back once they recover.
+    fn check_replicas(&self) {
+        for (index, replica) in self.replicas.replicas.iter().enumerate() {
+            if replica.checking.swap(true, Ordering::Relaxed) {
+                continue;
+            }
+
+            let replicas = self.replicas.clone();
+            spawn(async move {
+                let replica = &replicas.replicas[index];
+                let healthy = match replica.pool.get().await {
+                    Ok(mut connection) => connection.query_cached("SELECT 1", &[]).await.is_ok(),
+                    // All connections are checked out, so the replica is busy but alive.
+                    Err(Error::PoolTimeout) => {
+                        replica.pool.metrics().in_use >= replica.pool.config.pool_size
+                    }
+                    Err(_) => false,
+                };
+
+                if replica.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+                    if healthy {
+                        tracing::info!("replica is back up, adding to rotation");
+                    } else {
+                        tracing::error!("replica is down, removing from rotation");
+                    }
+                }
+
+                replica.checking.store(false, Ordering::Relaxed);
+            });
+        }
+    }
+
     fn maintenance(&self) {
+        self.check_replicas();
+
         let now = Instant::now();
         let mut inner = self.inner.lock();
 
         let before = inner.connections.len();
-        inner.connections.retain(|c| {
-            let age = now.duration_since(c.last_used());
-            let too_old = age > self.config.idle_timeout;
-            !c.bad() && !too_old
-        });
+        inner.connections.retain(|c| !c.bad());
+
+        // Close idle connections oldest first, keeping at least `min_idle` open.
+        while inner.connections.len() > self.config.min_idle {
+            let too_old = inner
+                .connections
+                .front()
+                .map(|c| now.duration_since(c.last_used()) > self.config.idle_timeout)
+                .unwrap_or(false);
+
+            if too_old {
+                inner.connections.pop_front();
+            } else {
+                break;
+            }
+        }
+
         let removed = before - inner.connections.len();
         inner.expected -= removed;
+
+        let missing = self
+            .config
+            .min_idle
+            .saturating_sub(inner.connections.len())
+            .min(self.config.pool_size.saturating_sub(inner.expected));
+        inner.expected += missing;
+
+        for _ in 0..missing {
+            let pool = self.clone();
+            spawn(async move {
+                match Connection::new(&pool.database_url).await {
+                    Ok(connection) => pool.checkin(connection, false),
+                    Err(err) => {
+                        tracing::error!("failed to open idle connection: {:?}", err);
+                        pool.inner.lock().expected -= 1;
+                    }
+                }
+            });
+        }
     }
 
     async fn checkin_rollback(&self, mut connection: Connection) {
@@ -496,6 +736,25 @@ impl Pool {
     }
 }
 
+/// Counts callers waiting for a connection. Decrements the count
+/// when the caller stops waiting, including on timeout.
+struct Waiter<'a> {
+    waiters: &'a AtomicUsize,
+}
+
+impl<'a> Waiter<'a> {
+    fn new(waiters: &'a AtomicUsize) -> Self {
+        waiters.fetch_add(1, Ordering::Relaxed);
+        Self { waiters }
+    }
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        self.waiters.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 impl Drop for Pool {
     fn drop(&mut self) {
         let ref_count = self.ref_count.fetch_sub(1, Ordering::SeqCst);
@@ -556,6 +815,124 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_in_transaction() -> Result<(), Error> {
+        use crate::model::{Exists, Query};
+
+        let pool = Pool::from_env();
+        pool.get()
+            .await?
+            .client()
+            .execute(
+                "CREATE TABLE IF NOT EXISTS pool_in_transaction (id BIGINT PRIMARY KEY)",
+                &[],
+            )
+            .await?;
+
+        let count = |id: i64| {
+            let pool = pool.clone();
+            async move {
+                let rows = pool
+                    .get()
+                    .await?
+                    .client()
+                    .query("SELECT id FROM pool_in_transaction WHERE id = $1", &[&id])
+                    .await?;
+                Ok::<_, Error>(rows.len())
+            }
+        };
+        let insert = |id: i64| {
+            Query::<Exists>::raw_with(
+                "INSERT INTO pool_in_transaction (id) VALUES ($1) ON CONFLICT DO NOTHING",
+                vec![id.into()],
+            )
+        };
+
+        let result = pool
+            .in_transaction(async |transaction| {
+                insert(1).execute(&mut *transaction).await?;
+                insert(2).execute(&mut *transaction).await?;
+                Ok(5)
+            })
+            .await?;
+        assert_eq!(result, 5);
+        assert_eq!(count(1).await?, 1);
+        assert_eq!(count(2).await?, 1);
+
+        let result = pool
+            .in_transaction(async |transaction| {
+                insert(3).execute(&mut *transaction).await?;
+                Err::<(), _>(Error::RecordNotFound)
+            })
+            .await;
+        assert!(matches!(result, Err(Error::RecordNotFound)));
+        assert_eq!(count(3).await?, 0);
+
+        let inner = pool.clone();
+        let result = tokio::spawn(async move {
+            inner
+                .in_transaction(async |transaction| {
+                    insert(4).execute(&mut *transaction).await?;
+                    panic!("closure panicked");
+                    #[allow(unreachable_code)]
+                    Ok(())
+                })
+                .await
+        })
+        .await;
+        assert!(result.unwrap_err().is_panic());
+        assert_eq!(count(4).await?, 0);
+
+        pool.get()
+            .await?
+            .client()
+            .execute("DROP TABLE pool_in_transaction", &[])
+            .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_connection_error() -> Result<(), Error> {
+        let pool = Pool::from_env();
+        let mut conn = pool.get().await?;
+        let pid: i32 = conn
+            .query_cached("SELECT pg_backend_pid()", &[])
+            .await?
+            .remove(0)
+            .get(0);
+
+        pool.get()
+            .await?
+            .client()
+            .execute("SELECT pg_terminate_backend($1)", &[&pid])
+            .await?;
+
+        // The server terminates the connection asynchronously.
+        let mut result = Ok(vec![]);
+        for _ in 0..10 {
+            result = conn.query_cached("SELECT 1", &[]).await;
+            if result.is_err() {
+                break;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+
+        assert!(matches!(result, Err(Error::Connection(_))));
+        assert!(conn.bad());
+
+        // The connection is closed now.
+        let result = conn.query_cached("SELECT 1", &[]).await;
+        assert!(matches!(result, Err(Error::Connection(_))));
+        drop(conn);
+
+        // Broken connections are not returned to the pool.
+        let mut conn = pool.get().await?;
+        assert_eq!(conn.query_cached("SELECT 1", &[]).await?.len(), 1);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_bad_pool() {
         env::set_var("RWF_DATABASE_CHECKOUT_TIMEOUT", "500");
@@ -581,4 +958,137 @@ mod test {
         let _conn = pool.get().await.unwrap();
         assert_eq!(pool.inner.lock().expected, 2);
     }
+
+    #[tokio::test]
+    async fn test_metrics() -> Result<(), Error> {
+        let pool = Pool::new(
+            &get_config().database.database_url(),
+            PoolConfig {
+                pool_size: 2,
+                checkout_timeout: Duration::from_millis(250),
+                idle_timeout: Duration::from_millis(0),
+                min_idle: 1,
+            },
+        );
+        assert_eq!(pool.metrics(), PoolMetrics::default());
+
+        let first = pool.get().await?;
+        let second = pool.get().await?;
+        assert_eq!(
+            pool.metrics(),
+            PoolMetrics {
+                in_use: 2,
+                idle: 0,
+                waiters: 0
+            }
+        );
+
+        let waiting = pool.clone();
+        let waiter = spawn(async move { waiting.get().await.map(|_| ()) });
+        sleep(Duration::from_millis(50)).await;
+        assert_eq!(pool.metrics().waiters, 1);
+
+        assert!(matches!(waiter.await.unwrap(), Err(Error::PoolTimeout)));
+        assert_eq!(pool.metrics().waiters, 0);
+
+        drop(first);
+        drop(second);
+        assert_eq!(pool.metrics().idle, 2);
+
+        // Idle timeout is exceeded, but one connection is kept open.
+        pool.maintenance();
+        assert_eq!(
+            pool.metrics(),
+            PoolMetrics {
+                in_use: 0,
+                idle: 1,
+                waiters: 0
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_min_idle() {
+        let pool = Pool::new(
+            &get_config().database.database_url(),
+            PoolConfig {
+                min_idle: 2,
+                ..Default::default()
+            },
+        );
+
+        pool.maintenance();
+
+        for _ in 0..50 {
+            if pool.metrics().idle == 2 {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+
+        assert_eq!(pool.metrics().idle, 2);
+        assert_eq!(pool.metrics().in_use, 0);
+    }
+
+    async fn application_name(conn: &mut ConnectionGuard) -> String {
+        conn.query_cached("SELECT current_setting('application_name')", &[])
+            .await
+            .unwrap()[0]
+            .get(0)
+    }
+
+    #[tokio::test]
+    async fn test_replicas() -> Result<(), Error> {
+        let url = get_config().database.database_url();
+        let replica = format!("{}?application_name=replica", url);
+        let down = "postgresql://root@127.0.0.1:1/root".to_string();
+
+        let pool = Pool::with_replicas(
+            &format!("{}?application_name=primary", url),
+            PoolConfig::default(),
+            &[replica, down],
+        );
+        assert_eq!(pool.healthy_replicas(), 2);
+
+        for _ in 0..4 {
+            let mut conn = pool.replica().await?;
+            assert_eq!(application_name(&mut conn).await, "replica");
+        }
+
+        // The replica that's down was tried and taken out of rotation.
+        assert_eq!(pool.healthy_replicas(), 1);
+
+        let mut conn = pool.get().await?;
+        assert_eq!(application_name(&mut conn).await, "primary");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replicas_down() -> Result<(), Error> {
+        let url = get_config().database.database_url();
+        let pool = Pool::with_replicas(
+            &url,
+            PoolConfig::default(),
+            &["postgresql://root@127.0.0.1:1/root".to_string()],
+        );
+
+        // Health check removes the replica from rotation.
+        pool.check_replicas();
+        for _ in 0..50 {
+            if pool.healthy_replicas() == 0 {
+                break;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(pool.healthy_replicas(), 0);
+
+        // Falls back to the primary.
+        let mut conn = pool.replica().await?;
+        assert_eq!(conn.query_cached("SELECT 1", &[]).await?.len(), 1);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,280 @@
+//! Preload associations of records returned by a query.
+//!
+//! After the query runs, one additional query is executed per association to fetch the associated
+//! records of all returned records at once, avoiding N+1 queries. Unlike joins, associated records are kept
+//! as separate models and attached to the record they belong to.
+//!
+//! # Example
+//!
+//! ```ignore
+//! let users = User::all()
+//!     .preload::<Order>()
+//!     .preload::<Project>()
+//!     .fetch_all(&mut conn)
+//!     .await?;
+//!
+//! for user in &users {
+//!     println!("{} has {} orders", user.email, user.preloaded::<Order>().len());
+//! }
+//! ```
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use super::{
+    pool::ToConnectionRequest, Association, AssociationType, ConnectionGuard, Error, Model, Query,
+    Value,
+};
+use crate::async_trait;
+use uuid::Uuid;
+
+/// Query with associations to preload. Created by [`Query::preload`].
+pub struct Preload<T: Model> {
+    query: Query<T>,
+    loaders: Vec<Box<dyn Loader<T>>>,
+}
+
+impl<T: Model + Sync + 'static> Preload<T> {
+    pub(crate) fn new(query: Query<T>) -> Self {
+        Self {
+            query,
+            loaders: vec![],
+        }
+    }
+
+    /// Preload another association.
+    pub fn preload<F: Association<T> + Sync + 'static>(mut self) -> Self {
+        self.loaders
+            .push(Box::new(AssociationLoader::<T, F>::new()));
+        self
+    }
+
+    /// Execute the query and preload the associations of all returned records.
+    pub async fn fetch_all(
+        self,
+        conn: impl ToConnectionRequest<'_>,
+    ) -> Result<Vec<Loaded<T>>, Error> {
+        let request = conn.to_connection_request()?;
+        let mut conn = request.get(self.query.replica()).await?;
+
+        let client = match request.connection() {
+            Some(conn) => conn,
+            None => conn.as_mut().unwrap(),
+        };
+
+        let records = self.query.fetch_all(&mut *client).await?;
+        let mut associations = records.iter().map(|_| HashMap::new()).collect::<Vec<_>>();
+
+        if !records.is_empty() {
+            for loader in &self.loaders {
+                let loaded = loader.load(&records, client).await?;
+
+                for (associations, loaded) in associations.iter_mut().zip(loaded) {
+                    associations.insert(loader.association(), loaded);
+                }
+            }
+        }
+
+        Ok(records
+            .into_iter()
+            .zip(associations)
+            .map(|(record, associations)| Loaded {
+                record,
+                associations,
+            })
+            .collect())
+    }
+
+    /// Execute the query, preloading the associations, and return the first record.
+    pub async fn fetch(self, conn: impl ToConnectionRequest<'_>) -> Result<Loaded<T>, Error> {
+        match self.fetch_all(conn).await?.into_iter().next() {
+            Some(record) => Ok(record),
+            None => Err(Error::RecordNotFound),
+        }
+    }
+}
+
+/// Record with its preloaded associations. Dereferences to the record.
+#[derive(Debug)]
+pub struct Loaded<T> {
+    record: T,
+    associations: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl<T> Loaded<T> {
+    /// Records associated with this record. Empty if the association wasn't preloaded.
+    pub fn preloaded<F: 'static>(&self) -> &[F] {
+        self.associations
+            .get(&TypeId::of::<F>())
+            .and_then(|records| records.downcast_ref::<Vec<F>>())
+            .map(|records| records.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Get the record, discarding the preloaded associations.
+    pub fn into_inner(self) -> T {
+        self.record
+    }
+}
+
+impl<T> Deref for Loaded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.record
+    }
+}
+
+#[async_trait]
+trait Loader<T>: Send + Sync {
+    /// Load associated records, returning a `Vec<F>` for each record, in the same order.
+    async fn load(
+        &self,
+        records: &[T],
+        conn: &mut ConnectionGuard,
+    ) -> Result<Vec<Box<dyn Any + Send + Sync>>, Error>;
+
+    fn association(&self) -> TypeId;
+}
+
+struct AssociationLoader<T, F> {
+    _phantom: PhantomData<fn() -> (T, F)>,
+}
+
+impl<T, F> AssociationLoader<T, F> {
+    fn new() -> Self {
+        Self {
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: Model + Sync + 'static, F: Association<T> + Sync + 'static> Loader<T>
+    for AssociationLoader<T, F>
+{
+    async fn load(
+        &self,
+        records: &[T],
+        conn: &mut ConnectionGuard,
+    ) -> Result<Vec<Box<dyn Any + Send + Sync>>, Error> {
+        // Column on `F` and `T` holding the keys to match.
+        let (related, related_key, key) = match F::association_type() {
+            // `F` has the foreign key of `T`, e.g. `orders.user_id = users.id`.
+            AssociationType::BelongsTo => {
+                (T::related::<F>(records), T::foreign_key(), T::primary_key())
+            }
+
+            // `T` has the foreign key of `F`, e.g. `users.id = orders.user_id`.
+            AssociationType::HasMany | AssociationType::HasOne => {
+                let keys = records
+                    .iter()
+                    .filter_map(|record| column_value(record, F::foreign_key()))
+                    .collect::<Vec<_>>();
+                (
+                    F::all().filter(F::primary_key(), keys.as_slice()),
+                    F::primary_key(),
+                    F::foreign_key(),
+                )
+            }
+        };
+
+        let mut grouped: HashMap<Key, Vec<F>> = HashMap::new();
+        for record in related.fetch_all(&mut *conn).await? {
+            if let Some(key) = column_value(&record, related_key).and_then(Key::of) {
+                grouped.entry(key).or_default().push(record);
+            }
+        }
+
+        Ok(records
+            .iter()
+            .map(|record| {
+                let related = column_value(record, key)
+                    .and_then(Key::of)
+                    .and_then(|key| grouped.get(&key).cloned())
+                    .unwrap_or_default();
+                Box::new(related) as Box<dyn Any + Send + Sync>
+            })
+            .collect())
+    }
+
+    fn association(&self) -> TypeId {
+        TypeId::of::<F>()
+    }
+}
+
+/// Get the value of a column from a model. Returns `None` if the value is `NULL`.
+pub(crate) fn column_value<M: Model>(model: &M, column: &str) -> Option<Value> {
+    let value = if column == M::primary_key() {
+        model.id()
+    } else {
+        let index = M::column_names().iter().position(|name| *name == column)?;
+        model.values().into_iter().nth(index)?
+    };
+
+    normalize(value)
+}
+
+fn normalize(value: Value) -> Option<Value> {
+    match value {
+        Value::Optional(value) => (*value).and_then(normalize),
+        Value::Null => None,
+        // Foreign keys and primary keys can be different integer types.
+        Value::BigInt(value) => Some(Value::Integer(value)),
+        Value::Int(value) => Some(Value::Integer(value as i64)),
+        Value::SmallInt(value) => Some(Value::Integer(value as i64)),
+        value => Some(value),
+    }
+}
+
+/// Key matching associated records to the records they belong to.
+/// Primary and foreign keys are integers, UUIDs or strings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Integer(i64),
+    Uuid(Uuid),
+    String(String),
+}
+
+impl Key {
+    /// Key of a normalized column value. Values of other types can't be keys,
+    /// so records with them are not matched.
+    fn of(value: Value) -> Option<Self> {
+        match value {
+            Value::Integer(value) => Some(Key::Integer(value)),
+            Value::Uuid(value) => Some(Key::Uuid(value)),
+            Value::String(value) => Some(Key::String(value)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_key() {
+        // Primary and foreign keys can be different integer types.
+        let keys = [Value::BigInt(5), Value::Int(5), Value::SmallInt(5)]
+            .into_iter()
+            .map(|value| normalize(value).and_then(Key::of))
+            .collect::<Vec<_>>();
+        assert!(keys.iter().all(|key| *key == Some(Key::Integer(5))));
+
+        assert_eq!(
+            normalize(Value::Optional(Box::new(Some(Value::String("5".into()))))).and_then(Key::of),
+            Some(Key::String("5".into()))
+        );
+        assert_ne!(
+            Key::of(Value::String("5".into())),
+            Key::of(Value::Integer(5))
+        );
+        assert_eq!(
+            normalize(Value::Optional(Box::new(None))).and_then(Key::of),
+            None
+        );
+        assert_eq!(Key::of(Value::Float(5.0)), None);
+    }
+}
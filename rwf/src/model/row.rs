@@ -1,7 +1,7 @@
 //! Represents a single database row for raw queries.
 use super::{Error, FromRow, Model, Value};
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, marker::PhantomData, sync::Arc};
 
 #[derive(Debug, Clone)]
 pub struct Row {
@@ -64,6 +64,67 @@ impl Row {
     }
 }
 
+/// Get the value of a column, using `default` if the column is `NULL`. If there is
+/// no default, `NULL` is an error, unless the field is an `Option`.
+///
+/// Used by the `FromRow` and `Model` derive macros.
+#[doc(hidden)]
+pub fn try_get_or_default<'a, T: tokio_postgres::types::FromSql<'a>>(
+    row: &'a tokio_postgres::Row,
+    column: &str,
+    default: Option<T>,
+) -> Result<T, Error> {
+    match row.try_get::<_, Option<T>>(column)? {
+        Some(value) => Ok(value),
+        None => match default {
+            Some(value) => Ok(value),
+            None => Ok(row.try_get(column)?),
+        },
+    }
+}
+
+/// Get the default value of `T` if it implements [`Default`], without
+/// requiring it to, e.g.:
+///
+/// ```
+/// use rwf::model::row::{NullDefault, DefaultForNull};
+///
+/// assert_eq!((&&NullDefault::<String>::new()).default_for_null(), Some(String::new()));
+/// assert_eq!((&&NullDefault::<time::OffsetDateTime>::new()).default_for_null(), None);
+/// ```
+#[doc(hidden)]
+pub struct NullDefault<T>(PhantomData<T>);
+
+impl<T> NullDefault<T> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Default for NullDefault<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[doc(hidden)]
+pub trait DefaultForNull<T> {
+    fn default_for_null(&self) -> Option<T>;
+}
+
+// Method resolution picks this implementation first, if `T: Default`.
+impl<T: Default> DefaultForNull<T> for &NullDefault<T> {
+    fn default_for_null(&self) -> Option<T> {
+        Some(T::default())
+    }
+}
+
+impl<T> DefaultForNull<T> for NullDefault<T> {
+    fn default_for_null(&self) -> Option<T> {
+        None
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::super::{Query, ToSql};
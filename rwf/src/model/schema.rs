@@ -0,0 +1,87 @@
+//! Compare models to the tables in the database.
+use super::{
+    escape::quote_table, pool::ToConnectionRequest, Error, FromRow, Model, Query, Row, Value,
+};
+
+/// Columns of the table, in the order they were created. The table is found
+/// using the `search_path`, the same way queries find it.
+static COLUMNS: &str = r#"SELECT attname::text AS "name"
+FROM pg_attribute
+WHERE attrelid = to_regclass($1::text) AND attnum > 0 AND NOT attisdropped
+ORDER BY attnum"#;
+
+/// Column of a table in the database.
+#[derive(Debug, Clone)]
+struct TableColumn {
+    name: String,
+}
+
+impl FromRow for TableColumn {
+    fn from_row(row: tokio_postgres::Row) -> Result<Self, Error> {
+        Ok(Self {
+            name: row.try_get("name")?,
+        })
+    }
+}
+
+/// Check that the table of the model has all the columns declared by the model.
+pub(crate) async fn verify<'a, T: Model>(conn: impl ToConnectionRequest<'a>) -> Result<(), Error> {
+    let table = quote_table(T::schema(), T::table_name());
+    let columns = Query::<Row>::raw_with(COLUMNS, vec![Value::String(table.clone())])
+        .fetch_all_as::<TableColumn>(conn)
+        .await?
+        .into_iter()
+        .map(|column| column.name)
+        .collect::<Vec<_>>();
+
+    let mut expected = vec![T::primary_key()];
+    expected.extend(T::column_names());
+
+    compare(&table, &expected, &columns)
+}
+
+/// Find columns missing from the table. Extra columns are allowed, since models
+/// can read a subset of the table, but they are listed in the error to help find renamed columns.
+fn compare(table: &str, expected: &[&str], actual: &[String]) -> Result<(), Error> {
+    let missing = expected
+        .iter()
+        .filter(|column| !actual.iter().any(|name| name == *column))
+        .map(|column| column.to_string())
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let extra = actual
+        .iter()
+        .filter(|name| !expected.contains(&name.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Err(Error::SchemaMismatch(table.to_string(), missing, extra))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let actual = vec!["id".to_string(), "email".to_string(), "name".to_string()];
+
+        assert!(compare(r#""users""#, &["id", "email"], &actual).is_ok());
+
+        let err = compare(r#""users""#, &["id", "email", "admin"], &actual).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"table "users" is missing columns declared by the model: admin (extra columns in the table: name)"#
+        );
+
+        let err = compare(r#""users""#, &["id"], &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"table "users" is missing columns declared by the model: id (extra columns in the table: none)"#
+        );
+    }
+}
@@ -1,9 +1,10 @@
 //! Implements the `SELECT` query.
 use crate::model::{
     column::ToColumn,
+    escape::quote_table,
     filter::{Filter, JoinOp},
-    Column, Columns, Escape, FromRow, Join, Joins, Limit, Lock, OrderBy, Placeholders, ToSql,
-    ToValue, Value, WhereClause,
+    Column, Columns, Exists, FromRow, Join, Joins, Limit, Lock, OrderBy, Placeholders, ToSql,
+    ToValue, Value, WhereClause, Window,
 };
 
 use std::marker::PhantomData;
@@ -18,10 +19,24 @@ enum Op {
     LesserEqualThan,
 }
 
+/// Which records of a model with soft delete are selected.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum Deleted {
+    /// All records, deleted or not.
+    #[default]
+    Include,
+    /// Records which are not deleted, i.e. the column is `NULL`.
+    Exclude(Column),
+    /// Deleted records only.
+    Only(Column),
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct Select<T: FromRow + ?Sized> {
     pub table_name: String,
     pub primary_key: String,
+    schema: Option<String>,
+    schema_override: Option<String>,
     pub columns: Columns,
     pub order_by: OrderBy,
     pub limit: Limit,
@@ -30,6 +45,8 @@ pub struct Select<T: FromRow + ?Sized> {
     pub joins: Joins,
     lock: Lock,
     group: bool,
+    primary: bool,
+    deleted: Deleted,
     _phantom: PhantomData<T>,
 }
 
@@ -39,6 +56,8 @@ impl<T: FromRow> Select<T> {
         Self {
             table_name: table_name.to_string(),
             primary_key: primary_key.to_string(),
+            schema: None,
+            schema_override: None,
             columns: Columns::default(),
             order_by: OrderBy::default(),
             limit: Limit::default(),
@@ -47,10 +66,36 @@ impl<T: FromRow> Select<T> {
             joins: Joins::default(),
             lock: Lock::default(),
             group: false,
+            primary: false,
+            deleted: Deleted::Include,
             _phantom: PhantomData,
         }
     }
 
+    /// Schema of the table, if it's not in the search path.
+    pub fn table_schema(mut self, schema: Option<&str>) -> Self {
+        self.schema = schema.map(|schema| schema.to_string());
+        self
+    }
+
+    /// Use the schema for the table and all joined tables, instead of
+    /// the schemas of their models.
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema_override = Some(schema.to_string());
+        self
+    }
+
+    /// Schema the table is in, if any.
+    pub fn schema_name(&self) -> Option<&str> {
+        self.schema_override.as_deref().or(self.schema.as_deref())
+    }
+
+    /// Select deleted records, records which aren't deleted, or both.
+    pub fn deleted(mut self, deleted: Deleted) -> Self {
+        self.deleted = deleted;
+        self
+    }
+
     /// Add a LIMIT to the query.
     pub fn limit(mut self, limit: i64) -> Self {
         self.limit = Limit::new(limit);
@@ -69,16 +114,59 @@ impl<T: FromRow> Select<T> {
         self
     }
 
+    /// Remove the ORDER BY clause.
+    pub fn unscope_order(mut self) -> Self {
+        self.order_by = OrderBy::default();
+        self
+    }
+
+    /// Remove the LIMIT and OFFSET.
+    pub fn unscope_limit(mut self) -> Self {
+        self.limit = Limit::default();
+        self
+    }
+
+    /// Remove all filters, together with their placeholders.
+    pub fn unscope_where(mut self) -> Self {
+        self.where_clause = WhereClause::default();
+        self.placeholders = Placeholders::default();
+        self
+    }
+
     pub fn lock(mut self) -> Self {
         self.lock = Lock::new();
         self
     }
 
+    /// Lock the selected rows using `FOR SHARE`.
+    pub fn lock_share(mut self) -> Self {
+        self.lock = Lock::share();
+        self
+    }
+
     pub fn skip_locked(mut self) -> Self {
         self.lock = self.lock.skip_locked();
         self
     }
 
+    /// Fail instead of waiting for rows locked by someone else.
+    pub fn nowait(mut self) -> Self {
+        self.lock = self.lock.nowait();
+        self
+    }
+
+    /// Execute the query on the primary, even if the pool has read replicas.
+    pub fn on_primary(mut self) -> Self {
+        self.primary = true;
+        self
+    }
+
+    /// The query can be executed on a read replica. Queries that lock rows
+    /// always go to the primary.
+    pub fn replica(&self) -> bool {
+        !self.primary && !self.lock.locked()
+    }
+
     pub fn exists(mut self) -> Self {
         self.columns = self.columns.exists();
         self
@@ -107,11 +195,19 @@ impl<T: FromRow> Select<T> {
         // Null is handled by the filter.
         let value = if !value.is_null() {
             match value {
+                // Matches nothing, so the filter is rendered without a placeholder.
+                Value::List(ref list) if list.is_empty() => Value::Record(Box::new(value)),
+
                 Value::List(_) => {
                     let placeholder = self.placeholders.add(&value);
                     Value::Record(Box::new(placeholder))
                 }
 
+                Value::Range((start, end)) => Value::Range((
+                    Box::new(self.placeholders.add(&start)),
+                    Box::new(self.placeholders.add(&end)),
+                )),
+
                 Value::Column(ref _column) => value,
                 Value::Function(ref _function) => value,
 
@@ -138,6 +234,109 @@ impl<T: FromRow> Select<T> {
         self
     }
 
+    /// Prepare a query to be used as a subquery of this one. Its placeholders
+    /// are renumbered to follow the placeholders of this query and its values are added to them,
+    /// so both queries share the same placeholders.
+    fn subquery<U: FromRow>(&mut self, subquery: Select<U>) -> Select<Exists> {
+        let mut subquery = subquery.cast::<Exists>();
+        let placeholders = std::mem::take(&mut subquery.placeholders);
+
+        subquery
+            .where_clause
+            .offset_placeholders(self.placeholders.len() as i32);
+        self.placeholders.extend(placeholders);
+
+        subquery
+    }
+
+    /// Filter by rows where the column is one of the values returned by the subquery.
+    pub fn filter_in_subquery<U: FromRow>(
+        mut self,
+        column: impl ToColumn,
+        subquery: Select<U>,
+    ) -> Self {
+        let column = self.qualify(column);
+        let subquery = self.subquery(subquery);
+
+        let mut filter = Filter::default();
+        filter.in_subquery(column, subquery);
+        self.where_clause.concat(filter);
+
+        self
+    }
+
+    /// Filter by rows where the column is none of the values returned by the subquery.
+    pub fn filter_not_in_subquery<U: FromRow>(
+        mut self,
+        column: impl ToColumn,
+        subquery: Select<U>,
+    ) -> Self {
+        let column = self.qualify(column);
+        let subquery = self.subquery(subquery);
+
+        let mut filter = Filter::default();
+        filter.not_in_subquery(column, subquery);
+        self.where_clause.concat(filter);
+
+        self
+    }
+
+    /// Filter by rows for which the subquery returns at least one row. The subquery
+    /// selects `1`, since only the existence of its rows matters.
+    pub fn filter_exists<U: FromRow>(mut self, subquery: Select<U>) -> Self {
+        let mut subquery = self.subquery(subquery);
+        subquery.columns = Columns::default().one();
+
+        let mut filter = Filter::default();
+        filter.exists(subquery);
+        self.where_clause.concat(filter);
+
+        self
+    }
+
+    /// Filter by rows for which the subquery returns no rows.
+    pub fn filter_not_exists<U: FromRow>(mut self, subquery: Select<U>) -> Self {
+        let mut subquery = self.subquery(subquery);
+        subquery.columns = Columns::default().one();
+
+        let mut filter = Filter::default();
+        filter.not_exists(subquery);
+        self.where_clause.concat(filter);
+
+        self
+    }
+
+    /// Qualify the column with the table name, unless it's already qualified.
+    fn qualify(&self, column: impl ToColumn) -> Column {
+        let column = column.to_column();
+        if !column.qualified() {
+            column.qualify(&self.table_name)
+        } else {
+            column
+        }
+    }
+
+    /// Change the type of records returned by the query.
+    fn cast<U: FromRow>(self) -> Select<U> {
+        Select {
+            table_name: self.table_name,
+            primary_key: self.primary_key,
+            schema: self.schema,
+            schema_override: self.schema_override,
+            columns: self.columns,
+            order_by: self.order_by,
+            limit: self.limit,
+            placeholders: self.placeholders,
+            where_clause: self.where_clause,
+            joins: self.joins,
+            lock: self.lock,
+            group: self.group,
+            primary: self.primary,
+            deleted: self.deleted,
+            _phantom: PhantomData,
+        }
+    }
+
     pub fn filter_and(mut self, column: impl ToColumn, value: impl ToValue) -> Self {
         self = self.filter(column, value, JoinOp::And, Op::Equals);
         self
@@ -178,6 +377,79 @@ impl<T: FromRow> Select<T> {
         self
     }
 
+    /// Filter by rows where the text column is equal to the value, ignoring case.
+    pub fn filter_ci(mut self, column: impl ToColumn, value: impl ToValue) -> Self {
+        let column = self.qualify(column);
+        let value = self.placeholders.add(&value.to_value());
+
+        let mut filter = Filter::default();
+        filter.equal_ci(column, value);
+        self.where_clause.concat(filter);
+
+        self
+    }
+
+    /// Filter by rows where the array column contains the value.
+    pub fn filter_array_contains(mut self, column: impl ToColumn, value: impl ToValue) -> Self {
+        let column = {
+            let column = column.to_column();
+            if !column.qualified() {
+                column.qualify(&self.table_name)
+            } else {
+                column
+            }
+        };
+        let value = self.placeholders.add(&value.to_value());
+
+        let mut filter = Filter::default();
+        filter.contains(column, value);
+        self.where_clause.concat(filter);
+
+        self
+    }
+
+    /// Filter by the text value found at the path in a JSON column, i.e. using the `#>>` operator.
+    pub fn filter_json_path(
+        mut self,
+        column: impl ToColumn,
+        path: &[&str],
+        value: impl ToValue,
+    ) -> Self {
+        let column = {
+            let column = column.to_column();
+            if !column.qualified() {
+                column.qualify(&self.table_name)
+            } else {
+                column
+            }
+        };
+
+        // `#>>` returns text, so the value is compared as text, e.g. `true` or `42`.
+        let value = match value.to_value() {
+            Value::Optional(value) => match *value {
+                Some(value) => value,
+                None => Value::Null,
+            },
+            value => value,
+        };
+        let value = match value {
+            Value::Null => Value::Null,
+            Value::String(string) => self.placeholders.add(&Value::String(string)),
+            value => self
+                .placeholders
+                .add(&Value::String(match serde_json::Value::from(value) {
+                    serde_json::Value::String(string) => string,
+                    value => value.to_string(),
+                })),
+        };
+
+        let mut filter = Filter::default();
+        filter.json_path(column, path, value);
+        self.where_clause.concat(filter);
+
+        self
+    }
+
     pub fn join(mut self, join: Join) -> Self {
         self.joins = self.joins.add(join);
         self.columns = self.columns.table_name(&self.table_name);
@@ -204,6 +476,21 @@ impl<T: FromRow> Select<T> {
         &self.where_clause
     }
 
+    /// The WHERE clause, including the filter on the soft delete column, if any.
+    pub fn scoped_where_clause(&self) -> WhereClause {
+        let mut where_clause = self.where_clause.clone();
+        let mut filter = Filter::default();
+
+        match &self.deleted {
+            Deleted::Include => return where_clause,
+            Deleted::Exclude(column) => filter.add(column.clone(), Value::Null),
+            Deleted::Only(column) => filter.add_not(column.clone(), Value::Null),
+        }
+
+        where_clause.and_concat(filter);
+        where_clause
+    }
+
     pub fn insert_columns(&self) -> (Vec<Column>, Vec<Value>) {
         let (columns, values) = self.where_clause.insert_columns();
         let mut actual_values = vec![];
@@ -224,12 +511,73 @@ impl<T: FromRow> Select<T> {
         (columns, actual_values)
     }
 
+    /// Create an empty query on the same table, with its own placeholders, to build
+    /// a group of predicates for [`Select::where_group`].
+    pub fn empty_group(&self) -> Self {
+        Select::new(&self.table_name, &self.primary_key)
+    }
+
+    /// Add the predicates of the group to this query as a parenthesized group, joined
+    /// with AND. The group's placeholders are renumbered to follow this query's placeholders.
+    pub fn where_group(mut self, group: Select<T>) -> Self {
+        let mut where_clause = group.where_clause;
+        where_clause.offset_placeholders(self.placeholders.len() as i32);
+        self.placeholders.extend(group.placeholders);
+        self.where_clause.group(where_clause.filter());
+
+        self
+    }
+
     pub fn or(&self) -> Self {
         let mut select = Select::new(&self.table_name, &self.primary_key);
         select.placeholders = self.placeholders.clone();
         select
     }
 
+    /// Select only the given columns instead of all of them. Columns which aren't
+    /// qualified are qualified with the table name, so they aren't ambiguous when joining.
+    pub fn select_columns(mut self, columns: &[impl ToColumn]) -> Self {
+        let columns = columns
+            .iter()
+            .map(|column| {
+                let column = column.to_column();
+                if !column.qualified() {
+                    column.qualify(&self.table_name)
+                } else {
+                    column
+                }
+            })
+            .collect::<Vec<_>>();
+        self.columns = Columns::pick(&columns);
+        self
+    }
+
+    /// Select a window function in addition to the columns. Partition columns which
+    /// aren't qualified are qualified with the table name.
+    pub fn select_window(
+        mut self,
+        function: &str,
+        partition_by: &[impl ToColumn],
+        order_by: OrderBy,
+        alias: &str,
+    ) -> Self {
+        let partition_by = partition_by
+            .iter()
+            .map(|column| {
+                let column = column.to_column();
+                if !column.qualified() {
+                    column.qualify(&self.table_name)
+                } else {
+                    column
+                }
+            })
+            .collect();
+        self.columns =
+            self.columns
+                .add_window(Window::new(function, partition_by, order_by, alias));
+        self
+    }
+
     pub fn select_additional(mut self, column: impl ToColumn) -> Self {
         self.columns = self.columns.add_column(column);
         self
@@ -245,23 +593,70 @@ impl<T: FromRow> Select<T> {
         self.columns = self.columns.count();
         self
     }
+
+    /// Count distinct values of the column instead of returning rows. The order
+    /// is removed, since the count is a single row.
+    pub fn count_distinct(mut self, column: impl ToColumn) -> Self {
+        let column = column.to_column();
+        let column = if !column.qualified() {
+            column.qualify(&self.table_name)
+        } else {
+            column
+        };
+
+        self.columns = self.columns.count_distinct(column);
+        self.order_by = OrderBy::default();
+        self
+    }
+}
+
+impl<T: FromRow> Select<T> {
+    /// Order by the primary key last when the query has a limit, so rows equal in all ordered columns
+    /// don't move between pages. Random order, grouped queries and orders which already include
+    /// the primary key are left as-is.
+    fn stable_order_by(&self) -> OrderBy {
+        let primary_key = Column::new(&self.table_name, &self.primary_key);
+
+        if !self.limit.limited()
+            || self.group
+            || self.order_by.is_empty()
+            || self.order_by.is_random()
+            || self.order_by.includes(&primary_key)
+        {
+            return self.order_by.clone();
+        }
+
+        // Unqualified columns would be ambiguous when joining.
+        let primary_key = if self.joins.joins().is_empty() {
+            primary_key.unqualify()
+        } else {
+            primary_key
+        };
+
+        self.order_by.clone().then_asc(primary_key)
+    }
 }
 
 impl<T: FromRow> ToSql for Select<T> {
     fn to_sql(&self) -> String {
         let group = if self.group {
-            format!("GROUP BY {} ", self.columns.to_sql())
+            format!("GROUP BY {} ", self.columns.without_aliases().to_sql())
         } else {
             "".to_string()
         };
+        let joins = match self.schema_override {
+            Some(ref schema) => self.joins.clone().schema(schema),
+            None => self.joins.clone(),
+        };
+
         format!(
-            r#"SELECT {} FROM "{}"{}{}{}{}{}{}"#,
+            r#"SELECT {} FROM {}{}{}{}{}{}{}"#,
             self.columns.to_sql(),
-            self.table_name.escape(),
-            self.joins.to_sql(),
-            self.where_clause.to_sql(),
+            quote_table(self.schema_name(), &self.table_name),
+            joins.to_sql(),
+            self.scoped_where_clause().to_sql(),
             group,
-            self.order_by.to_sql(),
+            self.stable_order_by().to_sql(),
             self.limit.to_sql(),
             self.lock.to_sql(),
         )
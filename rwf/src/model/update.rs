@@ -1,35 +1,94 @@
 //! Implements the `UPDATE` statement.
 use super::{
-    Column, Escape, FromRow, Model, Placeholders, Select, ToColumn, ToSql, ToValue, WhereClause,
+    escape::quote_table, Column, FromRow, Model, Placeholders, Select, ToColumn, ToSql, ToValue,
+    ValidationErrors, Value, WhereClause,
 };
 use std::marker::PhantomData;
 
 #[derive(Debug, Clone)]
 pub struct Update<T> {
+    schema: Option<String>,
     table_name: String,
     primary_key: String,
     pub placeholders: Placeholders,
     columns: Vec<Column>,
     where_clause: WhereClause,
+    locked: bool,
+    invalid_lock_version: Option<String>,
+    errors: Option<ValidationErrors>,
+    callbacks: bool,
+    returning: bool,
     marker: PhantomData<T>,
 }
 
 impl<T: Model> Update<T> {
     pub fn empty() -> Self {
         Self {
+            schema: T::schema().map(|schema| schema.to_string()),
             table_name: T::table_name().to_string(),
             primary_key: T::primary_key().to_string(),
             placeholders: Placeholders::new(),
             columns: vec![],
             where_clause: WhereClause::default(),
+            locked: false,
+            invalid_lock_version: None,
+            errors: None,
+            callbacks: false,
+            returning: true,
             marker: PhantomData,
         }
     }
 
     pub fn new(model: T) -> Self {
         let columns = T::column_names();
-        let values = model.values();
-        Self::from_columns(model.id(), &columns, &values)
+        let mut values = model.values();
+
+        let lock = T::lock_version().and_then(|lock| {
+            columns
+                .iter()
+                .position(|column| *column == lock)
+                .map(|index| (lock, index))
+        });
+
+        let mut update = match lock {
+            Some((lock, index)) => {
+                let mut update = Self::empty();
+
+                let id_placeholder = update.placeholders.add(&model.id());
+                update
+                    .where_clause
+                    .add(Column::name(&update.primary_key), id_placeholder);
+
+                // Only update the record if nobody else did since it was loaded.
+                let version = &values[index];
+                let (version, next) = if version.is_null() {
+                    (Value::Null, Value::Int(1))
+                } else {
+                    let next = match version.clone().exists() {
+                        Value::Integer(v) => Value::Integer(v + 1),
+                        Value::BigInt(v) => Value::BigInt(v + 1),
+                        Value::Int(v) => Value::Int(v + 1),
+                        Value::SmallInt(v) => Value::SmallInt(v + 1),
+                        // Returned as an error when the query is executed.
+                        version => {
+                            update.invalid_lock_version = Some(lock.to_string());
+                            version
+                        }
+                    };
+                    (update.placeholders.add(version), next)
+                };
+                update.where_clause.add(Column::name(lock), version);
+                update.locked = true;
+                values[index] = next;
+
+                update.columns(columns, &values)
+            }
+
+            None => Self::from_columns(model.id(), &columns, &values),
+        };
+
+        update.callbacks = true;
+        update
     }
 
     /// Create an update query for specific columns and values only.
@@ -49,6 +108,38 @@ impl<T: Model> Update<T> {
         update.columns(columns, values)
     }
 
+    /// The update is checked against the lock version of the record.
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+
+    /// The update saves a model, so its callbacks are called after it's executed.
+    pub fn callbacks(&self) -> bool {
+        self.callbacks
+    }
+
+    /// Don't return the updated rows, e.g. when only the number of updated rows is needed.
+    pub fn without_returning(mut self) -> Self {
+        self.returning = false;
+        self
+    }
+
+    /// The model failed validation, so the query must not be executed.
+    pub fn invalid(mut self, errors: ValidationErrors) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    /// The lock version column of the model, if its value isn't an integer.
+    pub fn invalid_lock_version(&self) -> Option<&str> {
+        self.invalid_lock_version.as_deref()
+    }
+
+    /// Validation errors of the model, if it's invalid.
+    pub fn validation_errors(&self) -> Option<&ValidationErrors> {
+        self.errors.as_ref()
+    }
+
     pub fn columns(mut self, columns: &[impl ToColumn], values: &[impl ToValue]) -> Self {
         for (column, value) in columns.iter().zip(values.iter()) {
             self.columns.push(column.to_column());
@@ -61,7 +152,8 @@ impl<T: Model> Update<T> {
 impl<T: Model> From<Select<T>> for Update<T> {
     fn from(select: Select<T>) -> Update<T> {
         let mut update = Update::empty();
-        update.where_clause = select.where_clause;
+        update.schema = select.schema_name().map(|schema| schema.to_string());
+        update.where_clause = select.scoped_where_clause();
         update.placeholders = select.placeholders;
 
         update
@@ -82,10 +174,11 @@ impl<T: FromRow> ToSql for Update<T> {
             .join(", ");
 
         format!(
-            r#"UPDATE "{}" SET {}{} RETURNING *"#,
-            self.table_name.escape(),
+            r#"UPDATE {} SET {}{}{}"#,
+            quote_table(self.schema.as_deref(), &self.table_name),
             sets,
             self.where_clause.to_sql(),
+            if self.returning { " RETURNING *" } else { "" },
         )
     }
 }
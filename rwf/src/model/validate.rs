@@ -0,0 +1,151 @@
+//! Validate models before they are saved.
+//!
+//! Validation runs when the query returned by [`Model::save`](super::Model::save) is executed. If the model
+//! is invalid, the query is not sent to the database and [`Error::Validation`](super::Error::Validation)
+//! is returned instead, containing the errors for each field.
+//!
+//! # Example
+//!
+//! ```
+//! use rwf::prelude::*;
+//! use rwf::model::{Validate, ValidationErrors};
+//!
+//! #[derive(Clone, macros::Model)]
+//! #[validate]
+//! struct User {
+//!     id: Option<i64>,
+//!     email: String,
+//! }
+//!
+//! impl Validate for User {
+//!     fn validate(&self) -> Result<(), ValidationErrors> {
+//!         let mut errors = ValidationErrors::new();
+//!
+//!         if !self.email.contains('@') {
+//!             errors.add("email", "is not a valid email address");
+//!         }
+//!
+//!         errors.result()
+//!     }
+//! }
+//!
+//! let user = User { id: None, email: "test".into() };
+//! let errors = user.validation().unwrap_err();
+//!
+//! assert_eq!(errors.get("email"), &["is not a valid email address"]);
+//! ```
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::view::{template::Error as TemplateError, ToTemplateValue, Value};
+
+/// Check that a model is valid before saving it.
+///
+/// To run it when saving the model, use the `#[validate]` attribute of the `Model` derive,
+/// or call it from [`Model::validation`](super::Model::validation) when implementing the trait manually.
+pub trait Validate {
+    /// Validate the model, returning the errors for each invalid field.
+    fn validate(&self) -> Result<(), ValidationErrors>;
+}
+
+/// Validation error messages, grouped by field name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct ValidationErrors {
+    errors: BTreeMap<String, Vec<String>>,
+}
+
+impl ValidationErrors {
+    /// No errors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an error message for the field.
+    pub fn add(&mut self, field: impl ToString, message: impl ToString) {
+        self.errors
+            .entry(field.to_string())
+            .or_default()
+            .push(message.to_string());
+    }
+
+    /// Error messages for the field. Empty if the field is valid.
+    pub fn get(&self, field: &str) -> &[String] {
+        self.errors
+            .get(field)
+            .map(|messages| messages.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Fields with errors, and their error messages.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.errors
+            .iter()
+            .map(|(field, messages)| (field.as_str(), messages.as_slice()))
+    }
+
+    /// There are no errors.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// `Ok` if there are no errors, `Err` with the errors otherwise.
+    pub fn result(self) -> Result<(), Self> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let errors = self
+            .iter()
+            .map(|(field, messages)| format!("{} {}", field, messages.join(", ")))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        write!(f, "{}", errors)
+    }
+}
+
+impl ToTemplateValue for ValidationErrors {
+    fn to_template_value(&self) -> Result<Value, TemplateError> {
+        let mut hash = std::collections::HashMap::new();
+
+        for (field, messages) in self.iter() {
+            hash.insert(field.to_string(), messages.to_vec().to_template_value()?);
+        }
+
+        Ok(Value::Hash(hash))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validation_errors() {
+        let mut errors = ValidationErrors::new();
+        assert_eq!(errors.clone().result(), Ok(()));
+
+        errors.add("name", "can't be blank");
+        errors.add("email", "is too short");
+        errors.add("email", "is not an email");
+
+        assert_eq!(errors.get("email"), &["is too short", "is not an email"]);
+        assert!(errors.get("password").is_empty());
+        assert_eq!(
+            errors.to_string(),
+            "email is too short, is not an email; name can't be blank"
+        );
+        assert_eq!(
+            serde_json::to_string(&errors).unwrap(),
+            r#"{"email":["is too short","is not an email"],"name":["can't be blank"]}"#
+        );
+        assert!(errors.result().is_err());
+    }
+}
@@ -1,7 +1,7 @@
 //! Handles conversions between database types and Rust types.
 use bytes::BytesMut;
-use time::{OffsetDateTime, PrimitiveDateTime};
-use tokio_postgres::types::{to_sql_checked, IsNull, Type};
+use time::{Date, OffsetDateTime, PrimitiveDateTime};
+use tokio_postgres::types::{to_sql_checked, IsNull, Kind, Type};
 use uuid::Uuid;
 
 use std::{net::IpAddr, ops::RangeInclusive};
@@ -35,12 +35,20 @@ pub enum Value {
     TimestampT(OffsetDateTime),
     /// `TIMESTAMP`
     Timestamp(PrimitiveDateTime),
+    /// `DATE`
+    Date(Date),
     /// `INET`
     IpAddr(IpAddr),
     /// `UUID`
     Uuid(Uuid),
+    /// `NUMERIC`, exact decimal number, e.g. money.
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
     /// List (Postgres array) of values, e.g. `{1, 2, 3}`.
     List(Vec<Value>),
+    /// Array column, e.g. `TEXT[]`. Unlike [`Value::List`], it's compared
+    /// as a whole and not used for `IN` filters. All elements must be the same type.
+    Array(Vec<Value>),
     /// Tuple (also known as "record") of values, e.g. `(1, 2, 3)`.
     Record(Box<Value>),
     /// Placeholder in a prepared statemnt, e.g. `$1`.
@@ -92,6 +100,25 @@ impl Value {
         }
     }
 
+    /// Get the integer, if this is an integer value of any size, e.g. a primary key.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use rwf::model::{Value, ToValue};
+    /// assert_eq!(Some(5_i32).to_value().as_integer(), Some(5));
+    /// assert_eq!("5".to_value().as_integer(), None);
+    /// ```
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(n) | Value::BigInt(n) => Some(*n),
+            Value::Int(n) => Some(*n as i64),
+            Value::SmallInt(n) => Some(*n as i64),
+            Value::Optional(value) => value.as_ref().as_ref().and_then(|value| value.as_integer()),
+            _ => None,
+        }
+    }
+
     /// Create a database function call without arguments. This can be used
     /// to safely execute functions from user-supplied data without worrying SQL injection attacks.
     ///
@@ -226,6 +253,20 @@ impl ToValue for Option<Uuid> {
     }
 }
 
+#[cfg(feature = "decimal")]
+impl ToValue for rust_decimal::Decimal {
+    fn to_value(&self) -> Value {
+        Value::Decimal(*self)
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl ToValue for Option<rust_decimal::Decimal> {
+    fn to_value(&self) -> Value {
+        Value::Optional(Box::new(self.as_ref().map(|v| v.to_value())))
+    }
+}
+
 impl ToValue for Value {
     fn to_value(&self) -> Value {
         self.clone()
@@ -268,6 +309,24 @@ impl ToValue for &[f64] {
     }
 }
 
+impl ToValue for &[Uuid] {
+    fn to_value(&self) -> Value {
+        Value::List(self.iter().map(|v| v.to_value()).collect::<Vec<_>>())
+    }
+}
+
+impl<T: ToValue> ToValue for Vec<T> {
+    fn to_value(&self) -> Value {
+        Value::Array(self.iter().map(|v| v.to_value()).collect())
+    }
+}
+
+impl<T: ToValue> ToValue for Option<Vec<T>> {
+    fn to_value(&self) -> Value {
+        Value::Optional(Box::new(self.as_ref().map(|v| v.to_value())))
+    }
+}
+
 impl ToValue for Column {
     fn to_value(&self) -> Value {
         Value::Column(self.clone())
@@ -325,6 +384,12 @@ impl ToValue for serde_json::Value {
     }
 }
 
+impl ToValue for Option<serde_json::Value> {
+    fn to_value(&self) -> Value {
+        Value::Optional(Box::new(self.as_ref().map(|v| v.to_value())))
+    }
+}
+
 impl ToValue for OffsetDateTime {
     fn to_value(&self) -> Value {
         Value::TimestampT(*self)
@@ -337,6 +402,48 @@ impl ToValue for Option<OffsetDateTime> {
     }
 }
 
+impl ToValue for PrimitiveDateTime {
+    fn to_value(&self) -> Value {
+        Value::Timestamp(*self)
+    }
+}
+
+impl ToValue for Option<PrimitiveDateTime> {
+    fn to_value(&self) -> Value {
+        Value::Optional(Box::new(self.as_ref().map(|v| v.to_value())))
+    }
+}
+
+impl ToValue for Date {
+    fn to_value(&self) -> Value {
+        Value::Date(*self)
+    }
+}
+
+impl ToValue for Option<Date> {
+    fn to_value(&self) -> Value {
+        Value::Optional(Box::new(self.as_ref().map(|v| v.to_value())))
+    }
+}
+
+impl ToValue for RangeInclusive<OffsetDateTime> {
+    fn to_value(&self) -> Value {
+        Value::Range((
+            Box::new(self.start().to_value()),
+            Box::new(self.end().to_value()),
+        ))
+    }
+}
+
+impl ToValue for RangeInclusive<Date> {
+    fn to_value(&self) -> Value {
+        Value::Range((
+            Box::new(self.start().to_value()),
+            Box::new(self.end().to_value()),
+        ))
+    }
+}
+
 impl ToValue for bool {
     fn to_value(&self) -> Value {
         Value::Boolean(*self)
@@ -351,7 +458,22 @@ impl tokio_postgres::types::ToSql for Value {
     ) -> Result<IsNull, Box<(dyn std::error::Error + Send + Sync + 'static)>> {
         use std::ops::Deref;
         match self {
-            Value::String(string) => string.to_sql(ty, out),
+            // Strings and numbers converted from JSON are written as JSON.
+            Value::String(_)
+            | Value::Integer(_)
+            | Value::Float(_)
+            | Value::Boolean(_)
+            | Value::List(_)
+            | Value::Array(_)
+                if matches!(ty, &Type::JSON | &Type::JSONB) =>
+            {
+                serde_json::Value::from(self.clone()).to_sql(ty, out)
+            }
+            // UUIDs are often passed around as strings, e.g. from URL parameters.
+            Value::String(string) => match ty {
+                &Type::UUID => Uuid::parse_str(string)?.to_sql(ty, out),
+                _ => string.to_sql(ty, out),
+            },
             Value::Integer(integer) => integer.to_sql(ty, out),
 
             // Rust default number is an i32.
@@ -367,9 +489,30 @@ impl tokio_postgres::types::ToSql for Value {
             Value::Boolean(b) => b.to_sql(ty, out),
             Value::TimestampT(timestamp) => timestamp.to_sql(ty, out),
             Value::Timestamp(timestamp) => timestamp.to_sql(ty, out),
+            Value::Date(date) => date.to_sql(ty, out),
             Value::IpAddr(ip) => ip.to_sql(ty, out),
             Value::Uuid(uuid) => uuid.to_sql(ty, out),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(decimal) => decimal.to_sql(ty, out),
             Value::List(values) => values.to_sql(ty, out),
+            Value::Array(values) => {
+                // The element type is inferred by the database from the column,
+                // so mixing types would be encoded incorrectly.
+                let mut elements = values.iter().filter_map(|value| match value {
+                    Value::Optional(value) => value.as_ref().as_ref(),
+                    Value::Null => None,
+                    value => Some(value),
+                });
+
+                if let Some(first) = elements.next() {
+                    let first = std::mem::discriminant(first);
+                    if elements.any(|value| std::mem::discriminant(value) != first) {
+                        return Err(Error::OrmSerializationError(self.clone()).boxed());
+                    }
+                }
+
+                values.to_sql(ty, out)
+            }
             Value::Json(json) => json.to_sql(ty, out),
             Value::Optional(value) => {
                 if let Some(value) = value.deref() {
@@ -408,7 +551,17 @@ impl<'a> tokio_postgres::types::FromSql<'a> for Value {
             &Type::INET => Ok(Value::IpAddr(IpAddr::from_sql(ty, raw)?)),
             &Type::TIMESTAMPTZ => Ok(Value::TimestampT(OffsetDateTime::from_sql(ty, raw)?)),
             &Type::TIMESTAMP => Ok(Value::Timestamp(PrimitiveDateTime::from_sql(ty, raw)?)),
+            &Type::DATE => Ok(Value::Date(Date::from_sql(ty, raw)?)),
             &Type::UUID => Ok(Value::Uuid(Uuid::from_sql(ty, raw)?)),
+            #[cfg(feature = "decimal")]
+            &Type::NUMERIC => Ok(Value::Decimal(rust_decimal::Decimal::from_sql(ty, raw)?)),
+            ty if matches!(ty.kind(), Kind::Array(_)) => {
+                Ok(Value::Array(Vec::<Value>::from_sql(ty, raw)?))
+            }
+            // Enums are sent as their labels.
+            ty if matches!(ty.kind(), Kind::Enum(_)) => {
+                Ok(Value::String(String::from_sql(ty, raw)?))
+            }
 
             ty => todo!("unimplemented conversion from {:?} to rust", ty),
         }
@@ -438,7 +591,17 @@ impl ToSql for Value {
             Float(float) => float.to_string(),
             Real(float) => float.to_string(),
             IpAddr(ip) => ip.to_string(),
-            Uuid(uuid) => uuid.to_string(),
+            Uuid(uuid) => format!("'{}'::uuid", uuid),
+            #[cfg(feature = "decimal")]
+            Decimal(decimal) => format!("'{}'::numeric", decimal),
+            TimestampT(timestamp) => format!(
+                "'{}'::timestamptz",
+                timestamp
+                    .format(&time::format_description::well_known::Rfc3339)
+                    .unwrap_or_default()
+            ),
+            Timestamp(timestamp) => format!("'{}'::timestamp", timestamp),
+            Date(date) => format!("'{}'::date", date),
             Placeholder(number) => format!("${}", number),
             Range((a, b)) => format!("BETWEEN {} AND {}", a.to_sql(), b.to_sql()),
             List(values) => format!(
@@ -449,6 +612,14 @@ impl ToSql for Value {
                     .collect::<Vec<_>>()
                     .join(", ")
             ),
+            Array(values) => format!(
+                "ARRAY[{}]",
+                values
+                    .iter()
+                    .map(|value| value.to_sql())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
             Value::Json(value) => format!(
                 "'{}'::jsonb",
                 serde_json::to_string(value)
@@ -462,7 +633,7 @@ impl ToSql for Value {
             Column(column) => column.to_sql(),
             Function((name, args)) => format!(
                 r#""{}"({})"#,
-                name.escape().to_lowercase(),
+                name.escape_identifier().to_lowercase(),
                 args.into_iter()
                     .map(|v| v.to_sql())
                     .collect::<Vec<_>>()
@@ -512,6 +683,9 @@ impl From<Value> for serde_json::Value {
             Value::Json(json) => json,
             Value::IpAddr(ip) => serde_json::Value::String(ip.to_string()),
             Value::Uuid(uuid) => serde_json::Value::String(uuid.to_string()),
+            // Kept as a string so no precision is lost.
+            #[cfg(feature = "decimal")]
+            Value::Decimal(decimal) => serde_json::Value::String(decimal.to_string()),
             Value::Optional(value) => match *value {
                 Some(value) => value.into(),
                 None => serde_json::Value::Null,
@@ -525,7 +699,8 @@ impl From<Value> for serde_json::Value {
                 use time::format_description::well_known::Rfc2822;
                 serde_json::Value::String(timestamp.format(&Rfc2822).unwrap())
             }
-            Value::List(list) => {
+            Value::Date(date) => serde_json::Value::String(date.to_string()),
+            Value::List(list) | Value::Array(list) => {
                 let mut values = vec![];
                 for v in list {
                     values.push(v.into());
@@ -558,4 +733,60 @@ mod test {
 
         assert_eq!(value.to_sql(), r#""lower"('my string')"#);
     }
+
+    #[test]
+    fn test_uuid() {
+        let uuid = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        let value = uuid.to_value();
+
+        assert_eq!(value, Value::Uuid(uuid));
+        assert_eq!(
+            value.to_sql(),
+            "'67e55044-10b1-426f-9247-bb680e5fe0c8'::uuid"
+        );
+
+        let mut out = BytesMut::new();
+        tokio_postgres::types::ToSql::to_sql(
+            &Value::String(uuid.to_string()),
+            &Type::UUID,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(&out[..], uuid.as_bytes());
+
+        assert!(tokio_postgres::types::ToSql::to_sql(
+            &Value::String("not a uuid".into()),
+            &Type::UUID,
+            &mut BytesMut::new(),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_dates() {
+        let timestamp = OffsetDateTime::from_unix_timestamp(1729080000).unwrap();
+
+        assert_eq!(
+            timestamp.to_value().to_sql(),
+            "'2024-10-16T12:00:00Z'::timestamptz"
+        );
+        assert_eq!(timestamp.date().to_value().to_sql(), "'2024-10-16'::date");
+        assert_eq!(
+            Value::new(timestamp.date()..=timestamp.date()).to_sql(),
+            "BETWEEN '2024-10-16'::date AND '2024-10-16'::date"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "decimal")]
+    fn test_decimal() {
+        use rust_decimal::Decimal;
+
+        let value = Decimal::new(1050, 2).to_value();
+        assert_eq!(value.to_sql(), "'10.50'::numeric");
+        assert_eq!(
+            serde_json::Value::from(value),
+            serde_json::Value::String("10.50".into())
+        );
+    }
 }
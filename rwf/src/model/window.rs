@@ -0,0 +1,82 @@
+//! Implements window functions, e.g. `ROW_NUMBER() OVER (PARTITION BY ... ORDER BY ...)`.
+use super::{Column, Escape, OrderBy, ToSql};
+
+/// A window function in the list of selected columns.
+///
+/// The function, e.g. `ROW_NUMBER()` or `SUM("amount")`, is used as-is, so it must not contain user input.
+/// Partition and order columns are quoted.
+#[derive(Debug, Clone)]
+pub struct Window {
+    function: String,
+    partition_by: Vec<Column>,
+    order_by: OrderBy,
+    alias: String,
+}
+
+impl Window {
+    /// Create new window function, named with the alias in the query results.
+    pub fn new(
+        function: impl ToString,
+        partition_by: Vec<Column>,
+        order_by: OrderBy,
+        alias: impl ToString,
+    ) -> Self {
+        Self {
+            function: function.to_string(),
+            partition_by,
+            order_by,
+            alias: alias.to_string(),
+        }
+    }
+}
+
+impl ToSql for Window {
+    fn to_sql(&self) -> String {
+        let mut over = vec![];
+
+        if !self.partition_by.is_empty() {
+            over.push(format!(
+                "PARTITION BY {}",
+                self.partition_by
+                    .iter()
+                    .map(|column| column.to_sql())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        if !self.order_by.is_empty() {
+            over.push(self.order_by.to_sql().trim_start().to_string());
+        }
+
+        format!(
+            r#"{} OVER ({}) AS "{}""#,
+            self.function,
+            over.join(" "),
+            self.alias.escape_identifier()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::model::ToOrderBy;
+
+    #[test]
+    fn test_window() {
+        let window = Window::new(
+            "ROW_NUMBER()",
+            vec![Column::new("orders", "user_id")],
+            ("amount", "DESC").to_order_by(),
+            "rank",
+        );
+        assert_eq!(
+            window.to_sql(),
+            r#"ROW_NUMBER() OVER (PARTITION BY "orders"."user_id" ORDER BY "amount" DESC) AS "rank""#
+        );
+
+        let window = Window::new("COUNT(*)", vec![], OrderBy::default(), "total");
+        assert_eq!(window.to_sql(), r#"COUNT(*) OVER () AS "total""#);
+    }
+}
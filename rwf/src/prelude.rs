@@ -11,6 +11,7 @@ pub use crate::config::Config;
 pub use crate::controller::{auth::SessionAuth, AuthHandler};
 pub use crate::controller::{
     Authentication, Controller, Error, ModelController, PageController, RestController, SessionId,
+    WebsocketController,
 };
 pub use crate::http::{Cookie, CookieBuilder, Message, Method, Request, Response, ToMessage};
 pub use crate::job::{queue_async, queue_delay, Job};
@@ -25,8 +26,11 @@ pub use time::{Duration, OffsetDateTime};
 pub use tokio;
 
 pub use macros::{
-    context, controller, crud, engine, render, render_include, rest, route, turbo_stream,
+    context, controller, crud, engine, render, render_include, rest, route, scopes, turbo_stream,
 };
 pub use rwf_macros as macros;
 pub use serde::{Deserialize, Serialize};
 pub use uuid::Uuid;
+
+#[cfg(feature = "decimal")]
+pub use rust_decimal::Decimal;
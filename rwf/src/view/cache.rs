@@ -2,8 +2,8 @@
 //!
 //! Using the cache ensures that templates are only compiled once, increasing their
 //! execution speed considerably.
-//! The template cache is enabled by default in production (`release`), and disabled
-//! in development (`debug`).
+//! In production (`release`), cached templates are never reloaded. In development (`debug`),
+//! a template is compiled again when the modification time of its file changes.
 //!
 //! [`Template::load`] uses the template cache automatically.
 use super::{template::Error, Template};
@@ -11,15 +11,50 @@ use crate::config::get_config;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use once_cell::sync::Lazy;
 use parking_lot::{Mutex, MutexGuard};
 
 static TEMPLATES: Lazy<Mutex<Templates>> = Lazy::new(|| Mutex::new(Templates::new()));
 
+/// Version of a template file. Modification times can be too coarse to tell apart
+/// two writes made quickly one after another, e.g. truncating the file and writing it,
+/// so the size of the file is checked as well.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Version {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl Version {
+    /// Version of the file, if the platform supports modification times.
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Compiled template and the version of the file it was read from.
+struct Entry {
+    template: Arc<Template>,
+    /// `None` if the template was compiled from a string.
+    version: Option<Version>,
+}
+
+impl Entry {
+    fn new(template: Arc<Template>, version: Option<Version>) -> Self {
+        Self { template, version }
+    }
+}
+
 /// Templates cache.
 pub struct Templates {
-    templates: HashMap<PathBuf, Arc<Template>>,
+    templates: HashMap<PathBuf, Entry>,
 }
 
 impl Templates {
@@ -33,6 +68,9 @@ impl Templates {
     /// Retrieve a template from the cache. If the template doesn't exist, it will be fetched
     /// from disk and compiled.
     ///
+    /// If `cache_templates` is disabled, which is the default in development, the template is compiled
+    /// again when its file is modified.
+    ///
     /// # Implementation note
     ///
     /// While this has to be done while holding the global template lock, this operation will be
@@ -45,19 +83,16 @@ impl Templates {
     pub fn get(&mut self, path: impl AsRef<Path> + Copy) -> Result<Arc<Template>, Error> {
         let cache_templates = get_config().general.cache_templates;
 
-        if let Some(t) = self.templates.get(path.as_ref()) {
-            return Ok(t.clone());
+        if let Some(entry) = self.templates.get(path.as_ref()) {
+            if cache_templates
+                || entry.version.is_none()
+                || entry.version == Version::of(path.as_ref())
+            {
+                return Ok(entry.template.clone());
+            }
         }
 
-        let template = Arc::new(Template::new(path)?);
-
-        if cache_templates {
-            self.templates
-                .insert(path.as_ref().to_owned(), template.clone());
-            Ok(template)
-        } else {
-            Ok(template)
-        }
+        self.load(path)
     }
 
     /// Compile the template from source and store it in the cache. Requires a globally unique
@@ -69,15 +104,15 @@ impl Templates {
     ) -> Result<Arc<Template>, Error> {
         let cache_templates = get_config().general.cache_templates;
 
-        if let Some(t) = self.templates.get(path.as_ref()) {
-            return Ok(t.clone());
+        if let Some(entry) = self.templates.get(path.as_ref()) {
+            return Ok(entry.template.clone());
         }
 
         let template = Arc::new(Template::from_str(src)?);
 
         if cache_templates {
             self.templates
-                .insert(path.as_ref().to_owned(), template.clone());
+                .insert(path.as_ref().to_owned(), Entry::new(template.clone(), None));
             Ok(template)
         } else {
             Ok(template)
@@ -87,10 +122,7 @@ impl Templates {
     /// Load and compile a template and store it in the cache. This will ensure
     /// the template is loaded from cache at runtime.
     pub fn preload(&mut self, path: impl AsRef<Path> + Copy) -> Result<(), Error> {
-        let template = Arc::new(Template::new(path)?);
-        self.templates
-            .insert(path.as_ref().to_owned(), template.clone());
-
+        self.load(path)?;
         Ok(())
     }
 
@@ -110,13 +142,148 @@ impl Templates {
     pub fn preload_str(&mut self, path: impl AsRef<Path> + Copy, src: &str) -> Result<(), Error> {
         let template = Arc::new(Template::from_str(src)?);
         self.templates
-            .insert(path.as_ref().to_owned(), template.clone());
+            .insert(path.as_ref().to_owned(), Entry::new(template, None));
 
         Ok(())
     }
 
+    /// Remove all templates from the cache.
+    pub fn clear(&mut self) {
+        self.templates.clear();
+    }
+
+    /// Read the template from disk, compile it and store it in the cache.
+    fn load(&mut self, path: impl AsRef<Path> + Copy) -> Result<Arc<Template>, Error> {
+        // Read the version first, so a change made while the template
+        // is read and compiled is picked up on the next request.
+        let version = Version::of(path.as_ref());
+        let template = Arc::new(Template::new(path)?);
+        self.templates.insert(
+            path.as_ref().to_owned(),
+            Entry::new(template.clone(), version),
+        );
+
+        Ok(template)
+    }
+
+    /// Path of a template referenced by another template, i.e. a layout or a partial.
+    /// Relative paths are resolved against the `templates_root` setting, if it's set.
+    pub fn path(path: impl AsRef<Path>) -> PathBuf {
+        resolve(
+            get_config().general.templates_root.as_deref(),
+            path.as_ref(),
+        )
+    }
+
     /// Obtain a lock to the global template cache.
     pub fn cache() -> MutexGuard<'static, Templates> {
         TEMPLATES.lock()
     }
 }
+
+fn resolve(root: Option<&Path>, path: &Path) -> PathBuf {
+    match root {
+        Some(root) if path.is_relative() => root.join(path),
+        _ => path.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::{write, File};
+    use std::time::Duration;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_resolve() {
+        let root = Path::new("/srv/app");
+        assert_eq!(
+            resolve(Some(root), Path::new("templates/layout.html")),
+            PathBuf::from("/srv/app/templates/layout.html")
+        );
+        assert_eq!(
+            resolve(Some(root), Path::new("/tmp/layout.html")),
+            PathBuf::from("/tmp/layout.html")
+        );
+        assert_eq!(
+            resolve(None, Path::new("templates/layout.html")),
+            PathBuf::from("templates/layout.html")
+        );
+    }
+
+    #[test]
+    fn test_reload_modified() {
+        let dir = TempDir::new("templates").unwrap();
+        let path = dir.path().join("index.html");
+        write(&path, "<%= 1 %>").unwrap();
+
+        let mut templates = Templates::new();
+        let template = templates.get(&path).unwrap();
+        assert_eq!(template.render_default().unwrap(), "1");
+        assert!(Arc::ptr_eq(&template, &templates.get(&path).unwrap()));
+
+        // Modification times can be too coarse to tell two writes apart, so set it explicitly.
+        write(&path, "<%= 2 %>").unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+
+        let template = templates.get(&path).unwrap();
+        assert_eq!(template.render_default().unwrap(), "2");
+
+        templates.clear();
+        write(&path, "<%= 3 %>").unwrap();
+        let template = templates.get(&path).unwrap();
+        assert_eq!(template.render_default().unwrap(), "3");
+
+        // Modified within the same tick, but the size is different.
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        write(&path, "<%= 42 %>").unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let template = templates.get(&path).unwrap();
+        assert_eq!(template.render_default().unwrap(), "42");
+    }
+
+    #[test]
+    fn test_reload_concurrent() {
+        let dir = TempDir::new("templates").unwrap();
+        let path = dir.path().join("index.html");
+        let tmp = dir.path().join("index.html.tmp");
+        write(&path, "a").unwrap();
+
+        let readers = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..250 {
+                        let rendered = Template::load(&path).unwrap().render_default().unwrap();
+                        assert!(!rendered.is_empty() && rendered.chars().all(|c| c == 'a'));
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Replace the file like editors do, so the readers see either the old or the new template.
+        for len in 2..50 {
+            write(&tmp, "a".repeat(len)).unwrap();
+            std::fs::rename(&tmp, &path).unwrap();
+        }
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let rendered = Template::load(&path).unwrap().render_default().unwrap();
+        assert_eq!(rendered, "a".repeat(49));
+    }
+}
@@ -8,14 +8,17 @@ use std::path::{Path, PathBuf};
 /// Template error.
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("syntax error")]
+    #[error("syntax error on line {}, column {}", .0.line(), .0.column())]
     Syntax(TokenWithContext),
 
-    #[error("expression syntax error")]
+    #[error("expression syntax error on line {}, column {}", .0.line(), .0.column())]
     ExpressionSyntax(TokenWithContext),
 
-    #[error("expected token \"{0}\", but have token \"{0}\" instead")]
-    WrongToken(TokenWithContext, Token),
+    #[error("unexpected token \"{:?}\" on line {}, column {}, expected \"{expected:?}\"", .got.token(), .got.line(), .got.column())]
+    WrongToken {
+        got: TokenWithContext,
+        expected: Token,
+    },
 
     #[error("reached end of file while performing \"{0}\", did you forget a closing tag?")]
     Eof(&'static str),
@@ -29,6 +32,9 @@ pub enum Error {
     #[error("template \"{0}\" does not exist")]
     TemplateDoesNotExist(PathBuf),
 
+    #[error("layout \"{0}\" extends itself, directly or through other layouts")]
+    CyclicExtends(PathBuf),
+
     #[error("serialization error")]
     SerializationError,
 
@@ -47,9 +53,11 @@ impl Error {
     // TODO: this function is iffy. Needs more work.
     pub fn pretty(self, source: &str, path: Option<impl AsRef<Path> + Copy>) -> Self {
         let token = match self {
+            // Already pretty, e.g. a syntax error in a partial.
+            Error::Pretty(_) => return self,
             Error::Syntax(ref token) => token,
             Error::ExpressionSyntax(ref token) => token,
-            Error::WrongToken(ref token, _) => token,
+            Error::WrongToken { got: ref token, .. } => token,
             _ => {
                 if let Some(path) = path {
                     let prefix = "---> ";
@@ -69,7 +77,7 @@ impl Error {
         let error_msg = match self {
             Error::Syntax(ref _token) => "syntax error".to_string(),
             Error::ExpressionSyntax(ref _token) => "expression syntax error".to_string(),
-            Error::WrongToken(ref _token, _) => "unexpected token".to_string(),
+            Error::WrongToken { .. } => "unexpected token".to_string(),
             _ => "".to_string(),
         };
 
@@ -145,6 +153,9 @@ impl Error {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::view::template::Template;
+    use std::fs::write;
+    use tempdir::TempDir;
 
     #[test]
     fn test_underline() {
@@ -162,4 +173,38 @@ mod test {
             "  | \n1 | <% if apples %>\n  |         ^ syntax error"
         );
     }
+
+    #[test]
+    fn test_wrong_token() {
+        let error = Error::WrongToken {
+            got: TokenWithContext::new(Token::Else, 2, 5),
+            expected: Token::BlockEnd,
+        };
+        assert_eq!(
+            error.to_string(),
+            r#"unexpected token "Else" on line 2, column 5, expected "BlockEnd""#
+        );
+    }
+
+    #[test]
+    fn test_line_number() {
+        let error = Error::ExpressionSyntax(TokenWithContext::new(Token::End, 3, 12));
+        assert_eq!(
+            error.to_string(),
+            "expression syntax error on line 3, column 12"
+        );
+
+        let error = Template::from_str("<h1>Title</h1>\n<p>\n  <%= 1 + %>\n</p>").unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("3 | <%= 1 + %>"), "{}", message);
+
+        let dir = TempDir::new("templates").unwrap();
+        let path = dir.path().join("broken.html");
+        write(&path, "<p>\n  <% if %>\n</p>").unwrap();
+
+        let message = Template::load(&path).unwrap_err().to_string();
+        let location = format!("{}:2:", path.display());
+        assert!(message.contains(&location), "{}", message);
+        assert!(message.contains("2 | <% if %>"), "{}", message);
+    }
 }
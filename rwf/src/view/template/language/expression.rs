@@ -48,6 +48,14 @@ pub enum Expression {
         args: Vec<Expression>,
     },
 
+    // Conditional expression, e.g. `active ? "on" : "off"`.
+    // Only the branch that's taken is evaluated.
+    Ternary {
+        condition: Box<Expression>,
+        if_true: Box<Expression>,
+        if_false: Box<Expression>,
+    },
+
     Interpreter,
 }
 
@@ -104,7 +112,6 @@ impl Expression {
             }
 
             Expression::Function { term, name, args } => {
-                let value = term.evaluate(context)?;
                 let name = match name.evaluate(context)? {
                     Value::String(name) => name,
                     name => {
@@ -115,6 +122,14 @@ impl Expression {
                     }
                 };
 
+                // An undefined variable can be replaced with a default value,
+                // e.g. `<%= nickname.default("Anonymous") %>`.
+                let value = match term.evaluate(context) {
+                    Ok(value) => value,
+                    Err(Error::UndefinedVariable(_)) if name == "default" => Value::Null,
+                    Err(err) => return Err(err),
+                };
+
                 // Allow to pass undefined variables to a function.
                 // Typically that's not great, but the purpose of this function
                 // is to catch such cases and replace with a default value (presumably defined).
@@ -139,6 +154,28 @@ impl Expression {
                 Ok(value.call(&name, &args, context)?)
             }
 
+            Expression::Ternary {
+                condition,
+                if_true,
+                if_false,
+            } => {
+                // Same as if statements, an undefined variable is false.
+                let truthy = match condition.evaluate(context) {
+                    Ok(value) => value.truthy(),
+                    Err(Error::UndefinedVariable(name)) => match condition.as_ref() {
+                        Expression::Term { .. } => false,
+                        _ => return Err(Error::UndefinedVariable(name)),
+                    },
+                    Err(err) => return Err(err),
+                };
+
+                if truthy {
+                    if_true.evaluate(context)
+                } else {
+                    if_false.evaluate(context)
+                }
+            }
+
             Expression::Interpreter => Ok(Value::Interpreter),
         }
     }
@@ -334,7 +371,12 @@ impl Expression {
                     let next = iter.next().ok_or(Error::Eof("expected closing bracket"))?;
                     match next.token() {
                         Token::SquareBracketEnd => (),
-                        token => return Err(Error::WrongToken(next, token)),
+                        _ => {
+                            return Err(Error::WrongToken {
+                                got: next,
+                                expected: Token::SquareBracketEnd,
+                            })
+                        }
                     }
                     Expression::Function {
                         term: Box::new(expr),
@@ -354,6 +396,38 @@ impl Expression {
     pub fn parse(
         iter: &mut Peekable<impl Iterator<Item = TokenWithContext>>,
     ) -> Result<Self, Error> {
+        let condition = Self::binary(iter)?;
+
+        // The ternary operator has the lowest precedence and is right-associative,
+        // e.g. `a ? b : c ? d : e` is `a ? b : (c ? d : e)`.
+        match iter.peek().map(|t| t.token()) {
+            Some(Token::QuestionMark) => {
+                let _ = iter.next().ok_or(Error::Eof("ternary question mark"))?;
+                let if_true = Self::parse(iter)?;
+
+                let colon = iter.next().ok_or(Error::Eof("ternary colon"))?;
+                if colon.token() != Token::Colon {
+                    return Err(Error::WrongToken {
+                        got: colon,
+                        expected: Token::Colon,
+                    });
+                }
+
+                let if_false = Self::parse(iter)?;
+
+                Ok(Expression::Ternary {
+                    condition: Box::new(condition),
+                    if_true: Box::new(if_true),
+                    if_false: Box::new(if_false),
+                })
+            }
+
+            _ => Ok(condition),
+        }
+    }
+
+    /// Parse an expression made of terms and binary operators.
+    fn binary(iter: &mut Peekable<impl Iterator<Item = TokenWithContext>>) -> Result<Self, Error> {
         // Get the left term, if one exists.
         // TODO: support unary operations.
         let left = Self::term(iter)?;
@@ -376,8 +450,11 @@ impl Expression {
                 let next = iter.peek();
 
                 match next.map(|t| t.token()) {
-                    // Expression is over.
-                    Some(Token::BlockEnd) | None => Ok(Expression::Binary {
+                    // Expression is over, or it's the condition or a branch of a ternary.
+                    Some(Token::BlockEnd)
+                    | Some(Token::QuestionMark)
+                    | Some(Token::Colon)
+                    | None => Ok(Expression::Binary {
                         left: Box::new(left),
                         op,
                         right: Box::new(right),
@@ -390,7 +467,7 @@ impl Expression {
                             let _ = iter.next().ok_or(Error::Eof("parse second op"))?;
 
                             // Get the right term.
-                            let right2 = Expression::parse(iter)?;
+                            let right2 = Expression::binary(iter)?;
 
                             // Check operator precendence.
                             if second_op < op {
@@ -470,6 +547,54 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_comparison() -> Result<(), Error> {
+        assert_eq!("<% 5 >= 5 %>".evaluate_default()?, Value::Boolean(true));
+        assert_eq!("<% 3 <= 2 %>".evaluate_default()?, Value::Boolean(false));
+        assert_eq!("<% 2 <= 3 %>".evaluate_default()?, Value::Boolean(true));
+        assert_eq!("<% 2 > 3 %>".evaluate_default()?, Value::Boolean(false));
+        assert_eq!("<% 2 < 3 %>".evaluate_default()?, Value::Boolean(true));
+        assert_eq!("<% 3>=3 %>".evaluate_default()?, Value::Boolean(true));
+        assert_eq!("<% 3<3 %>".evaluate_default()?, Value::Boolean(false));
+
+        // Integers are compared with floats like they are added to them.
+        assert_eq!("<% 5 >= 4.5 %>".evaluate_default()?, Value::Boolean(true));
+        assert_eq!("<% 4.5 <= 4 %>".evaluate_default()?, Value::Boolean(false));
+        assert_eq!("<% 5.0 >= 5 %>".evaluate_default()?, Value::Boolean(true));
+
+        assert_eq!(
+            r#"<% "apple" <= "banana" %>"#.evaluate_default()?,
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            r#"<% "b" >= "banana" %>"#.evaluate_default()?,
+            Value::Boolean(false)
+        );
+
+        // Comparisons are evaluated after math and before logical operators.
+        assert_eq!("<% 2 + 3 >= 5 %>".evaluate_default()?, Value::Boolean(true));
+        assert_eq!(
+            "<% 1 <= 2 && 3 >= 4 %>".evaluate_default()?,
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            "<% 1 <= 2 == true %>".evaluate_default()?,
+            Value::Boolean(true)
+        );
+
+        // Values which can't be compared aren't ordered.
+        assert_eq!(
+            r#"<% "5" >= 5 %>"#.evaluate_default()?,
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            r#"<% "5" <= 5 %>"#.evaluate_default()?,
+            Value::Boolean(false)
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_list() -> Result<(), Error> {
         let mut context = Context::default();
@@ -570,6 +695,22 @@ mod test {
             "<% (((variable.upcase * 2) * 1).downcase).upcase %>".evaluate(&context)?,
             Value::String("HELLOHELLO".into())
         );
+        assert_eq!(
+            r#"<% "{}, {}!".format(variable.capitalize, 5) %>"#.evaluate(&context)?,
+            Value::String("Hello, 5!".into())
+        );
+        assert_eq!(
+            r#"<% [1,2,3].join("-") %>"#.evaluate_default()?,
+            Value::String("1-2-3".into())
+        );
+        assert_eq!(
+            r#"<% ["admin", "user"].contains("admin") %>"#.evaluate_default()?,
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            r#"<% "user".contains("admin") %>"#.evaluate_default()?,
+            Value::Boolean(false)
+        );
 
         Ok(())
     }
@@ -590,6 +731,286 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_mod() -> Result<(), Error> {
+        assert_eq!("<% 7 % 3 %>".evaluate_default()?, Value::Integer(1));
+        assert_eq!("<% 7 %3 %>".evaluate_default()?, Value::Integer(1));
+        assert_eq!("<% -7 % 3 %>".evaluate_default()?, Value::Integer(-1));
+        assert_eq!("<% 7.5 % 2 %>".evaluate_default()?, Value::Float(1.5));
+        assert_eq!("<% 1 + 7 % 3 %>".evaluate_default()?, Value::Integer(2));
+        assert_eq!("<% 7 % 3 * 2 %>".evaluate_default()?, Value::Integer(2));
+
+        let mut context = Context::default();
+        context.set("index", 4)?;
+        assert_eq!(
+            "<% index % 2 == 0 %>".evaluate(&context)?,
+            Value::Boolean(true)
+        );
+
+        assert!(matches!(
+            "<% 7 % 0 %>".evaluate_default(),
+            Err(Error::Runtime(_))
+        ));
+        assert!(matches!(
+            "<% 7 / 0 %>".evaluate_default(),
+            Err(Error::Runtime(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rounding() -> Result<(), Error> {
+        assert_eq!("<% 5.6.round %>".evaluate_default()?, Value::Float(6.0));
+        assert_eq!("<% 5.4.round %>".evaluate_default()?, Value::Float(5.0));
+        assert_eq!(
+            "<% 10.456.round(2) %>".evaluate_default()?,
+            Value::Float(10.46)
+        );
+        assert_eq!(
+            "<% 10.456.round(0) %>".evaluate_default()?,
+            Value::Float(10.0)
+        );
+        assert_eq!(
+            "<% 1234.5.round(-2) %>".evaluate_default()?,
+            Value::Float(1200.0)
+        );
+        assert_eq!(
+            "<% (2.5 * 3).floor %>".evaluate_default()?,
+            Value::Float(7.0)
+        );
+        assert_eq!(
+            "<% (2.5 * 3).ceil %>".evaluate_default()?,
+            Value::Float(8.0)
+        );
+        assert_eq!("<% (0 - 2.5).abs %>".evaluate_default()?, Value::Float(2.5));
+
+        assert_eq!("<% 5.round %>".evaluate_default()?, Value::Integer(5));
+        assert_eq!("<% 5.round(2) %>".evaluate_default()?, Value::Integer(5));
+        assert_eq!(
+            "<% 1250.round(-2) %>".evaluate_default()?,
+            Value::Integer(1300)
+        );
+        assert_eq!("<% 5.floor %>".evaluate_default()?, Value::Integer(5));
+        assert_eq!("<% 5.ceil %>".evaluate_default()?, Value::Integer(5));
+        assert_eq!("<% (0 - 5).abs %>".evaluate_default()?, Value::Integer(5));
+
+        assert!(r#"<% 5.5.round("two") %>"#.evaluate_default().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_method() -> Result<(), Error> {
+        let mut context = Context::default();
+        context.set("name", "Alice")?;
+        context.set("empty", "")?;
+        context.set("zero", 0)?;
+        context.set(
+            "user",
+            Value::Hash(HashMap::from([("nickname".to_string(), Value::Null)])),
+        )?;
+
+        let anonymous = Value::String("Anonymous".into());
+
+        assert_eq!(
+            r#"<% name.default("Anonymous") %>"#.evaluate(&context)?,
+            Value::String("Alice".into())
+        );
+        assert_eq!(
+            r#"<% zero.default(5) %>"#.evaluate(&context)?,
+            Value::Integer(0)
+        );
+        assert_eq!(
+            r#"<% empty.default("Anonymous") %>"#.evaluate(&context)?,
+            anonymous
+        );
+        assert_eq!(
+            r#"<% user.nickname.default("Anonymous") %>"#.evaluate(&context)?,
+            anonymous
+        );
+        assert_eq!(
+            r#"<% user.email.default("Anonymous") %>"#.evaluate(&context)?,
+            anonymous
+        );
+        assert_eq!(
+            r#"<% missing.default("Anonymous") %>"#.evaluate(&context)?,
+            anonymous
+        );
+        assert_eq!(
+            r#"<% missing.nickname.default("Anonymous") %>"#.evaluate(&context)?,
+            anonymous
+        );
+
+        // Undefined variables are still an error everywhere else.
+        assert!(matches!(
+            r#"<% missing.upcase %>"#.evaluate(&context),
+            Err(Error::UndefinedVariable(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ternary() -> Result<(), Error> {
+        let mut context = Context::default();
+        context.set("active", true)?;
+        context.set("inactive", false)?;
+        context.set("count", 3)?;
+
+        assert_eq!(
+            r#"<% active ? "on" : "off" %>"#.evaluate(&context)?,
+            Value::String("on".into())
+        );
+        assert_eq!(
+            r#"<% inactive ? "on" : "off" %>"#.evaluate(&context)?,
+            Value::String("off".into())
+        );
+
+        // Lowest precedence.
+        assert_eq!(
+            r#"<% count + 1 == 4 ? count * 2 : count - 1 %>"#.evaluate(&context)?,
+            Value::Integer(6)
+        );
+        assert_eq!(
+            r#"<% 1 + 2 * 3 > 10 ? "big" : "small" %>"#.evaluate(&context)?,
+            Value::String("small".into())
+        );
+
+        // Nested, right-associative.
+        let nested = r#"<% count == 1 ? "one" : count == 2 ? "two" : "many" %>"#;
+        assert_eq!(nested.evaluate(&context)?, Value::String("many".into()));
+        context.set("count", 2)?;
+        assert_eq!(nested.evaluate(&context)?, Value::String("two".into()));
+        assert_eq!(
+            r#"<% active ? inactive ? 1 : 2 : 3 %>"#.evaluate(&context)?,
+            Value::Integer(2)
+        );
+
+        // Truthiness is the same as with if statements.
+        assert_eq!(
+            r#"<% "" ? "yes" : "no" %>"#.evaluate(&context)?,
+            Value::String("no".into())
+        );
+        assert_eq!(
+            r#"<% missing ? "yes" : "no" %>"#.evaluate(&context)?,
+            Value::String("no".into())
+        );
+
+        // Only the branch that's taken is evaluated.
+        assert_eq!(
+            r#"<% active ? "ok" : missing.upcase %>"#.evaluate(&context)?,
+            Value::String("ok".into())
+        );
+
+        assert!(r#"<% active ? "on" "off" %>"#.evaluate(&context).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_range() -> Result<(), Error> {
+        let list =
+            |values: &[i64]| Value::List(values.iter().map(|v| Value::Integer(*v)).collect());
+
+        assert_eq!("<% 1..4 %>".evaluate_default()?, list(&[1, 2, 3]));
+        assert_eq!("<% 1..=4 %>".evaluate_default()?, list(&[1, 2, 3, 4]));
+        assert_eq!("<% -1..1 %>".evaluate_default()?, list(&[-1, 0]));
+        assert_eq!("<% 3..3 %>".evaluate_default()?, list(&[]));
+        assert_eq!("<% 3..=3 %>".evaluate_default()?, list(&[3]));
+
+        let mut context = Context::default();
+        context.set("total_pages", 3)?;
+        assert_eq!(
+            "<% 1..total_pages + 1 %>".evaluate(&context)?,
+            list(&[1, 2, 3])
+        );
+        assert_eq!(
+            "<% (1..=total_pages).len %>".evaluate(&context)?,
+            Value::Integer(3)
+        );
+
+        // Reversed ranges are empty, like in Rust.
+        assert_eq!("<% 5..1 %>".evaluate_default()?, list(&[]));
+
+        assert!(matches!(
+            "<% 0..1000000000 %>".evaluate_default(),
+            Err(Error::Runtime(_))
+        ));
+        assert!(matches!(
+            r#"<% 1.."5" %>"#.evaluate_default(),
+            Err(Error::Runtime(_))
+        ));
+
+        // Floats still work.
+        assert_eq!("<% 1.5 + 1 %>".evaluate_default()?, Value::Float(2.5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_nested_access() -> Result<(), Error> {
+        let mut context = Context::default();
+        context.set(
+            "settings",
+            Value::Hash(HashMap::from([(
+                "theme".to_string(),
+                Value::Hash(HashMap::from([(
+                    "color".to_string(),
+                    Value::String("blue".into()),
+                )])),
+            )])),
+        )?;
+        context.set(
+            "users",
+            Value::List(vec![Value::Hash(HashMap::from([(
+                "name".to_string(),
+                Value::String("Alice".into()),
+            )]))]),
+        )?;
+
+        assert_eq!(
+            "<% settings.theme.color %>".evaluate(&context)?,
+            Value::String("blue".into())
+        );
+        assert_eq!(
+            "<% users.0.name %>".evaluate(&context)?,
+            Value::String("Alice".into())
+        );
+        assert_eq!(
+            "<% settings.theme.color.upcase %>".evaluate(&context)?,
+            Value::String("BLUE".into())
+        );
+
+        // Missing keys and intermediate values are null.
+        assert_eq!("<% settings.theme.font %>".evaluate(&context)?, Value::Null);
+        assert_eq!(
+            "<% settings.missing.color %>".evaluate(&context)?,
+            Value::Null
+        );
+        assert_eq!("<% users.1.name %>".evaluate(&context)?, Value::Null);
+        assert_eq!(
+            r#"<% settings.missing.color.default("red") %>"#.evaluate(&context)?,
+            Value::String("red".into())
+        );
+
+        // Keys take precedence over functions with the same name.
+        context.set(
+            "product",
+            Value::Hash(HashMap::from([(
+                "size".to_string(),
+                Value::String("XL".into()),
+            )])),
+        )?;
+        assert_eq!(
+            "<% product.size %>".evaluate(&context)?,
+            Value::String("XL".into())
+        );
+        assert_eq!("<% settings.size %>".evaluate(&context)?, Value::Integer(1));
+
+        Ok(())
+    }
+
     #[test]
     fn test_unary() -> Result<(), Error> {
         assert_eq!(
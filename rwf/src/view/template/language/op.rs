@@ -4,6 +4,9 @@ use super::super::Error;
 
 use std::cmp::Ordering;
 
+/// Largest number of elements a range can produce, e.g. `1..10_000`.
+const MAX_RANGE_LEN: i64 = 10_000;
+
 /// List of supported operations, e.g. addition, equality, etc.
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Op {
@@ -21,6 +24,8 @@ pub enum Op {
     GreaterEqualThan,
     LessThan,
     LessEqualThan,
+    Range,
+    RangeInclusive,
 }
 
 impl PartialOrd for Op {
@@ -74,9 +79,59 @@ impl Op {
             Op::Add => Ok(left.add(right)),
             Op::Sub => Ok(left.sub(right)),
             Op::Mult => Ok(left.mul(right)),
-            Op::Div => Ok(left.div(right)),
-            _ => todo!(),
+            Op::Div | Op::Mod => {
+                // Integers can't be divided by zero. Floats can, and result in infinity or NaN.
+                if let (Value::Integer(_), Value::Integer(0)) = (left, right) {
+                    return Err(Error::Runtime(format!(
+                        "division by zero: {} {} 0",
+                        left,
+                        if self == &Op::Div { "/" } else { "%" }
+                    )));
+                }
+
+                if self == &Op::Div {
+                    Ok(left.div(right))
+                } else {
+                    Ok(left.rem(right))
+                }
+            }
+            Op::Range | Op::RangeInclusive => Self::range(left, right, self == &Op::RangeInclusive),
+            Op::Not => Ok(Value::Null),
+        }
+    }
+
+    /// List of integers from `start` to `end`, e.g. `1..4` is `[1, 2, 3]`. A range which ends before it
+    /// starts is empty.
+    fn range(start: &Value, end: &Value, inclusive: bool) -> Result<Value, Error> {
+        let (start, end) = match (start, end) {
+            (Value::Integer(start), Value::Integer(end)) => (*start, *end),
+            (start, end) => {
+                return Err(Error::Runtime(format!(
+                    "range bounds must be integers, got {} and {}",
+                    start.type_name(),
+                    end.type_name()
+                )))
+            }
+        };
+
+        let len = (end as i128 - start as i128 + inclusive as i128).max(0);
+        if len > MAX_RANGE_LEN as i128 {
+            return Err(Error::Runtime(format!(
+                "range {}..{}{} has more than {} elements",
+                start,
+                if inclusive { "=" } else { "" },
+                end,
+                MAX_RANGE_LEN
+            )));
         }
+
+        let range = if inclusive {
+            (start..=end).map(Value::Integer).collect()
+        } else {
+            (start..end).map(Value::Integer).collect()
+        };
+
+        Ok(Value::List(range))
     }
 
     /// Calculate operator precendence, i.e. in an expression with multiple
@@ -88,9 +143,10 @@ impl Op {
             Op::And => 11,
             Op::Or => 12,
             Op::Add | Op::Sub => 4,
+            Op::Range | Op::RangeInclusive => 5,
             Op::Mult | Op::Div | Op::Mod => 3,
+            Op::GreaterThan | Op::GreaterEqualThan | Op::LessThan | Op::LessEqualThan => 6,
             Op::Equals | Op::NotEquals => 7,
-            _ => todo!(),
         }
     }
 }
@@ -111,6 +167,9 @@ impl From<Token> for Option<Op> {
             Token::Minus => Op::Sub,
             Token::Mult => Op::Mult,
             Token::Div => Op::Div,
+            Token::Mod => Op::Mod,
+            Token::Range => Op::Range,
+            Token::RangeInclusive => Op::RangeInclusive,
             _ => return None,
         })
     }
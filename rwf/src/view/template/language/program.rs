@@ -1,8 +1,13 @@
 //! Executable template.
 //!
 //! A program is a list of statements.
-use super::super::{Context, Error, TokenWithContext, Tokenize};
-use super::Statement;
+use super::super::{Context, Error, Template, TokenWithContext, Tokenize};
+use super::{statement::Blocks, Statement};
+use crate::view::Templates;
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Executable program.
 #[derive(Debug, Clone)]
@@ -13,14 +18,82 @@ pub struct Program {
 impl Program {
     /// Evaluate the program given the context. The context contains variable definitions.
     pub fn evaluate(&self, context: &Context) -> Result<String, Error> {
+        self.evaluate_blocks(context, &Blocks::new())
+    }
+
+    /// Evaluate the program, replacing the bodies of its blocks with the ones
+    /// defined by templates extending it.
+    ///
+    /// If the program extends a layout, the layout is rendered instead, with the blocks
+    /// defined by this program. Anything outside of those blocks is ignored.
+    pub fn evaluate_blocks(&self, context: &Context, blocks: &Blocks) -> Result<String, Error> {
+        if self.extends().is_some() {
+            let layouts = self.layouts()?;
+
+            // Blocks of templates furthest from the layout take precedence.
+            let mut merged = Blocks::new();
+            for layout in layouts.iter().rev() {
+                merged.extend(layout.program.blocks());
+            }
+            merged.extend(self.blocks());
+            merged.extend(blocks.iter());
+
+            if let Some(layout) = layouts.last() {
+                return layout.render_blocks(context, &merged);
+            }
+        }
+
+        // Variables assigned by the program are visible to the rest of it.
+        let mut context = Cow::Borrowed(context);
         let mut result = String::new();
         for statement in &self.statements {
-            result.push_str(&statement.evaluate(context)?);
+            result.push_str(&statement.evaluate_scope(&mut context, blocks)?);
         }
 
         Ok(result)
     }
 
+    /// The layout this program extends, if any.
+    fn extends(&self) -> Option<&Path> {
+        self.statements
+            .iter()
+            .find_map(|statement| match statement {
+                Statement::Extends(path) => Some(path.as_path()),
+                _ => None,
+            })
+    }
+
+    /// Layouts extended by this program, starting with the one it extends directly.
+    fn layouts(&self) -> Result<Vec<Arc<Template>>, Error> {
+        let mut visited: Vec<PathBuf> = vec![];
+        let mut layouts = vec![];
+        let mut next = self.extends().map(Templates::path);
+
+        while let Some(path) = next {
+            if visited.contains(&path) {
+                return Err(Error::CyclicExtends(path));
+            }
+
+            let layout = Template::load(&path)?;
+            next = layout.program.extends().map(Templates::path);
+            visited.push(path);
+            layouts.push(layout);
+        }
+
+        Ok(layouts)
+    }
+
+    /// Blocks defined at the top level of the program.
+    fn blocks(&self) -> Blocks<'_> {
+        self.statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Block { name, body } => Some((name.as_str(), body.as_slice())),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Parse the program from a list of tokens.
     pub fn parse(tokens: Vec<TokenWithContext>) -> Result<Self, Error> {
         let mut iter = tokens.into_iter().peekable();
@@ -74,6 +147,73 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_extends() -> Result<(), Error> {
+        use crate::view::Templates;
+
+        Templates::cache().preload_str(
+            "templates/test_extends/layout.html",
+            r#"<title><% block title %>Default title<% end %></title><main><% block content %>Default content<% end %></main>"#,
+        )?;
+        Templates::cache().preload_str(
+            "templates/test_extends/base.html",
+            r#"<% extends "templates/test_extends/layout.html" %><% block title %>Base: <%= title %><% end %>"#,
+        )?;
+
+        let mut context = Context::new();
+        context.set("title", "Home")?;
+        context.set("items", vec![1, 2])?;
+
+        // One block overridden, the other one uses the default body.
+        let program = Program::from_str(
+            r#"<% extends "templates/test_extends/layout.html" %>
+            Ignored text.
+            <% block content %><% for item in items %><p><%= item %></p><% end %><% end %>"#,
+        )?;
+        assert_eq!(
+            program.evaluate(&context)?,
+            "<title>Default title</title><main><p>1</p><p>2</p></main>"
+        );
+
+        // Blocks of the most derived template win, other blocks are inherited.
+        let program = Program::from_str(
+            r#"<% extends "templates/test_extends/base.html" %><% block content %>Page<% end %>"#,
+        )?;
+        assert_eq!(
+            program.evaluate(&context)?,
+            "<title>Base: Home</title><main>Page</main>"
+        );
+
+        let program = Program::from_str(
+            r#"<% extends "templates/test_extends/base.html" %><% block title %>Override<% end %>"#,
+        )?;
+        assert_eq!(
+            program.evaluate(&context)?,
+            "<title>Override</title><main>Default content</main>"
+        );
+
+        // Templates can't extend each other in a cycle.
+        Templates::cache().preload_str(
+            "templates/test_extends/cycle_a.html",
+            r#"<% extends "templates/test_extends/cycle_b.html" %>"#,
+        )?;
+        Templates::cache().preload_str(
+            "templates/test_extends/cycle_b.html",
+            r#"<% extends "templates/test_extends/cycle_a.html" %>"#,
+        )?;
+        let program = Program::from_str(r#"<% extends "templates/test_extends/cycle_a.html" %>"#)?;
+        assert!(matches!(
+            program.evaluate(&context),
+            Err(Error::CyclicExtends(path)) if path == Path::new("templates/test_extends/cycle_a.html")
+        ));
+
+        // Templates which don't extend anything render their blocks' bodies.
+        let program = Program::from_str("<% block content %>Hello<% end %>")?;
+        assert_eq!(program.evaluate(&context)?, "Hello");
+
+        Ok(())
+    }
+
     #[test]
     fn test_secure_links() -> Result<(), Error> {
         let program = r#"
@@ -4,15 +4,24 @@ use super::{
     super::{Context, Error, Token, TokenWithContext, Tokenize, Value},
     Expression, Term,
 };
+use crate::view::Templates;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::iter::{Iterator, Peekable};
 
 use std::path::PathBuf;
 
+/// Bodies of blocks defined by templates extending a layout, by block name.
+pub type Blocks<'a> = HashMap<&'a str, &'a [Statement]>;
+
 macro_rules! expect {
     ($got:expr, $expected:expr) => {
         if $got.token() != $expected {
             // println!("{}:{}", file!(), line!());
-            return Err(Error::WrongToken($got, $expected));
+            return Err(Error::WrongToken {
+                got: $got,
+                expected: $expected,
+            });
         }
     };
 }
@@ -55,6 +64,21 @@ pub enum Statement {
     },
 
     Render(PathBuf),
+
+    // `<% extends "templates/layout.html" %>`
+    Extends(PathBuf),
+
+    // `<% block content %>default content<% end %>`
+    Block {
+        name: String,
+        body: Vec<Statement>,
+    },
+
+    // `<% set total = price * quantity %>`
+    Set {
+        name: String,
+        expression: Expression,
+    },
 }
 
 impl Statement {
@@ -66,10 +90,27 @@ impl Statement {
 
     /// Evaluate a statement given the context.
     pub fn evaluate(&self, context: &Context) -> Result<String, Error> {
+        self.evaluate_blocks(context, &Blocks::new())
+    }
+
+    /// Evaluate a statement given the context, replacing the bodies of blocks
+    /// with the ones defined by templates extending this one.
+    pub fn evaluate_blocks(&self, context: &Context, blocks: &Blocks) -> Result<String, Error> {
+        self.evaluate_scope(&mut Cow::Borrowed(context), blocks)
+    }
+
+    /// Evaluate a statement in a scope. Variables assigned with `set` are stored in the scope,
+    /// so they are visible to the statements evaluated after this one. The context is
+    /// copied only once a variable is assigned.
+    pub(crate) fn evaluate_scope(
+        &self,
+        context: &mut Cow<'_, Context>,
+        blocks: &Blocks,
+    ) -> Result<String, Error> {
         match self {
             Statement::Render(path) => {
-                let template = Template::load(&path)?;
-                template.render(context)
+                let template = Template::load(&Templates::path(path))?;
+                template.render(context.as_ref())
             }
             Statement::PrintText(text) => Ok(text.clone()),
             Statement::If {
@@ -91,11 +132,11 @@ impl Statement {
 
                 if truthy {
                     for statement in if_body {
-                        result.push_str(&statement.evaluate(&context)?);
+                        result.push_str(&statement.evaluate_scope(context, blocks)?);
                     }
                 } else {
                     for statement in else_body {
-                        result.push_str(&statement.evaluate(&context)?);
+                        result.push_str(&statement.evaluate_scope(context, blocks)?);
                     }
                 }
 
@@ -116,7 +157,7 @@ impl Statement {
             } => {
                 let mut result = String::new();
                 let list = list.evaluate(context)?;
-                let mut for_context = context.clone();
+                let mut for_context = context.as_ref().clone();
                 let values = match list {
                     Value::List(values) => values,
                     Value::Hash(hash) => hash
@@ -144,13 +185,33 @@ impl Statement {
                         _ => todo!(),            // Function call is interesting
                     };
 
+                    // Each iteration has its own scope, so variables assigned inside
+                    // the loop don't leak out of it.
+                    let mut scope = Cow::Borrowed(&for_context);
                     for statement in body {
-                        result.push_str(&statement.evaluate(&for_context)?);
+                        result.push_str(&statement.evaluate_scope(&mut scope, blocks)?);
                     }
                 }
 
                 Ok(result)
             }
+            Statement::Block { name, body } => {
+                let mut result = String::new();
+                let body = blocks.get(name.as_str()).copied().unwrap_or(body);
+
+                for statement in body {
+                    result.push_str(&statement.evaluate_scope(context, blocks)?);
+                }
+
+                Ok(result)
+            }
+            // The program renders the layout instead of the template extending it.
+            Statement::Extends(_) => Ok(String::new()),
+            Statement::Set { name, expression } => {
+                let value = expression.evaluate(context)?;
+                context.to_mut().set(name, value)?;
+                Ok(String::new())
+            }
             statement => todo!("evaluating {:?}", statement),
         }
     }
@@ -193,6 +254,51 @@ impl Statement {
                     block_end!(iter);
                     return Ok(Statement::Else);
                 }
+                Token::Extends => {
+                    let path = iter.next().ok_or(Error::Eof("statement extends"))?;
+                    block_end!(iter);
+
+                    match path.token() {
+                        Token::Value(Value::String(path)) => {
+                            return Ok(Statement::Extends(PathBuf::from(path)))
+                        }
+                        _ => return Err(Error::Syntax(path)),
+                    }
+                }
+                Token::Set => {
+                    let name = iter.next().ok_or(Error::Eof("statement set"))?;
+                    let name = match name.token() {
+                        Token::Variable(name) => name,
+                        _ => return Err(Error::Syntax(name)),
+                    };
+
+                    let assign = iter.next().ok_or(Error::Eof("statement set"))?;
+                    expect!(assign, Token::Assign);
+
+                    let expression = Expression::parse(iter)?;
+                    block_end!(iter);
+
+                    return Ok(Statement::Set { name, expression });
+                }
+                Token::Block => {
+                    let name = iter.next().ok_or(Error::Eof("statement block"))?;
+                    let name = match name.token() {
+                        Token::Variable(name) => name,
+                        _ => return Err(Error::Syntax(name)),
+                    };
+                    block_end!(iter);
+
+                    let mut body = vec![];
+
+                    loop {
+                        match Statement::parse(iter)? {
+                            Statement::End => break,
+                            statement => body.push(statement),
+                        }
+                    }
+
+                    return Ok(Statement::Block { name, body });
+                }
                 Token::If | Token::ElseIf => {
                     let else_if = next.token() == Token::ElseIf;
                     let (mut if_body, mut else_body) = (vec![], vec![]);
@@ -301,7 +407,7 @@ impl Statement {
 mod test {
     use super::*;
     use crate::view::template::{
-        language::expression::Evaluate,
+        language::{expression::Evaluate, Program},
         lexer::{Tokenize, Value},
     };
 
@@ -338,6 +444,34 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_statements_elsif_chain() -> Result<(), Error> {
+        let template = "<% if value == 1 %>one<% elsif value == 2 %>two<% elsif value %>truthy<% else %>falsy<% end %>";
+
+        for (value, expected) in [
+            (Value::Integer(1), "one"),
+            (Value::Integer(2), "two"),
+            (Value::Integer(3), "truthy"),
+            (Value::String("hello".into()), "truthy"),
+            (Value::List(vec![Value::Integer(0)]), "truthy"),
+            (Value::Integer(0), "falsy"),
+            (Value::String("".into()), "falsy"),
+            (Value::List(vec![]), "falsy"),
+            (Value::Null, "falsy"),
+        ] {
+            let mut context = Context::default();
+            context.set("value", value)?;
+            assert_eq!(Statement::from_str(template)?.evaluate(&context)?, expected);
+        }
+
+        // Undefined variables are falsy.
+        let result = Statement::from_str("<% if missing %>yes<% else %>no<% end %>")?
+            .evaluate(&Context::default())?;
+        assert_eq!(result, "no");
+
+        Ok(())
+    }
+
     #[test]
     fn test_print_expression() -> Result<(), Error> {
         let t1 = "<%= variable %>";
@@ -351,6 +485,32 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_print_escaped() -> Result<(), Error> {
+        let mut context = Context::default();
+        context.set("body", "<script>alert('hi & bye')</script>")?;
+        context.set("title", r#""quoted""#)?;
+
+        let render = |template: &str| Program::from_str(template)?.evaluate(&context);
+
+        let result = render("<p><%= body %></p>")?;
+        assert_eq!(
+            result,
+            "<p>&lt;script&gt;alert(&#x27;hi &amp; bye&#x27;)&lt;/script&gt;</p>"
+        );
+
+        let result = render("<p><%= body.raw %></p>")?;
+        assert_eq!(result, "<p><script>alert('hi & bye')</script></p>");
+
+        let result = render("<p><%- body %></p>")?;
+        assert_eq!(result, "<p><script>alert('hi & bye')</script></p>");
+
+        let result = render(r#"<a title="<%= title %>">"#)?;
+        assert_eq!(result, r#"<a title="&quot;quoted&quot;">"#);
+
+        Ok(())
+    }
+
     #[test]
     fn test_for_loop() -> Result<(), Error> {
         let t1 = r#"<% for a in [1, "hello", 3.45, variable] %><li><%= a %></li><% end %>"#
@@ -380,6 +540,57 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_for_range() -> Result<(), Error> {
+        let mut context = Context::default();
+        context.set("total_pages", 3)?;
+
+        let program = Program::from_str("<% for i in 1..=total_pages %><%= i %>,<% end %>")?;
+        assert_eq!(program.evaluate(&context)?, "1,2,3,");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set() -> Result<(), Error> {
+        let mut context = Context::default();
+        context.set("price", 5)?;
+        context.set("qty", 3)?;
+        context.set("name", "outer")?;
+
+        let render = |template: &str| Program::from_str(template)?.evaluate(&context);
+
+        assert_eq!(
+            render("<% set total = price * qty %>Total: <%= total %>")?,
+            "Total: 15"
+        );
+
+        // Visible in nested blocks and after them.
+        assert_eq!(
+            render(
+                "<% set total = price * qty %><% for i in [1, 2] %><%= total + i %>,<% end %><% if true %><% set total = 0 %><% end %><%= total %>"
+            )?,
+            "16,17,0"
+        );
+
+        // Shadows an outer variable inside the loop, without leaking out of it.
+        assert_eq!(
+            render(
+                "<% for item in [1, 2] %><%= name %>=<% set name = item %><%= name %> <% end %><%= name %>"
+            )?,
+            "outer=1 outer=2 outer"
+        );
+
+        // The context passed in isn't changed.
+        render("<% set name = \"changed\" %>")?;
+        assert_eq!(context.get("name"), Some(Value::String("outer".into())));
+
+        assert!(Program::from_str("<% set = 5 %>").is_err());
+        assert!(Program::from_str("<% set total 5 %>").is_err());
+
+        Ok(())
+    }
+
     #[test]
     fn test_newline() {
         // Make sure lexer doesn't interpret new lines as something.
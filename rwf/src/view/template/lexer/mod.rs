@@ -98,7 +98,7 @@ impl<'a> Lexer<'a> {
     /// Tokens are processed one character at a time. Multi-character tokens like `if`
     /// or `for` are buffered and parsed as a string.
     pub fn tokens(mut self) -> Result<Vec<TokenWithContext>, Error> {
-        let mut iter = self.source.chars();
+        let mut iter = self.source.chars().peekable();
 
         while let Some(c) = iter.next() {
             self.column += 1;
@@ -112,6 +112,17 @@ impl<'a> Lexer<'a> {
                     self.column -= 1;
                 } // Handle column count on Windows.
 
+                // Inside a code block, a "less than" comparison, e.g. `<% 1 < 2 %>`.
+                '<' if self.code_block => {
+                    self.drain_buffer();
+
+                    if iter.next_if_eq(&'=').is_some() {
+                        self.tokens.push(self.add_token(Token::LessEqualThan));
+                    } else {
+                        self.tokens.push(self.add_token(Token::LessThan));
+                    }
+                }
+
                 // Possibly a code block start tag.
                 '<' => {
                     let n = iter.next();
@@ -172,6 +183,28 @@ impl<'a> Lexer<'a> {
                     }
                 }
 
+                // A "greater than" comparison. The code block end tag `%>` is handled with `%`.
+                '>' if self.code_block => {
+                    self.drain_buffer();
+
+                    if iter.next_if_eq(&'=').is_some() {
+                        self.tokens.push(self.add_token(Token::GreaterEqualThan));
+                    } else {
+                        self.tokens.push(self.add_token(Token::GreaterThan));
+                    }
+                }
+
+                // A range, e.g. `<% 1..5 %>` or `<% 1..=5 %>`.
+                '.' if self.code_block && iter.next_if_eq(&'.').is_some() => {
+                    self.drain_buffer();
+
+                    if iter.next_if_eq(&'=').is_some() {
+                        self.tokens.push(self.add_token(Token::RangeInclusive));
+                    } else {
+                        self.tokens.push(self.add_token(Token::Range));
+                    }
+                }
+
                 '.' => {
                     // If we're parsing a number, keep the dot for the floating point
                     // notation. Otherwise, it's an accessor for a method call or object attribute.
@@ -239,45 +272,26 @@ impl<'a> Lexer<'a> {
 
                 // Potentially a code block end tag.
                 '%' => {
-                    let n = iter.next();
-
-                    match n {
-                        Some('>') => {
-                            // We are parsing a code block, so this tells us the code is over.
-                            if self.code_block {
-                                self.drain_buffer();
-                                self.tokens.push(self.add_token(Token::BlockEnd));
-                                self.code_block = false;
-                            } else {
-                                // Just a random `%>` tag in the template, means nothing
-                                // without a starting tag.
-                                self.buffer.push('%');
-                                self.buffer.push('>');
-                            }
-                        }
-
-                        Some(c) => {
-                            // If we're parsing code, then this is a modulus operator, e.g. `5 % 3 == 2`
-                            if self.code_block {
-                                self.drain_buffer();
-                                self.tokens.push(self.add_token(Token::Mod));
-                            } else {
-                                self.buffer.push('%');
-                                self.buffer.push(c);
-                            }
-                        }
-
-                        None => {
-                            // A mod operator with nothing after it. Syntax error,
-                            // but we don't need to handle it here.
-                            if self.code_block {
-                                self.drain_buffer();
-                                self.tokens.push(self.add_token(Token::Mod));
-                            } else {
-                                // A template ending with a "%" for some reason.
-                                self.buffer.push('%');
-                            }
+                    if iter.next_if_eq(&'>').is_some() {
+                        // We are parsing a code block, so this tells us the code is over.
+                        if self.code_block {
+                            self.drain_buffer();
+                            self.tokens.push(self.add_token(Token::BlockEnd));
+                            self.code_block = false;
+                        } else {
+                            // Just a random `%>` tag in the template, means nothing
+                            // without a starting tag.
+                            self.buffer.push('%');
+                            self.buffer.push('>');
                         }
+                    } else if self.code_block {
+                        // If we're parsing code, then this is a modulus operator, e.g. `5 % 3 == 2`.
+                        // The character after it is left for the next token, e.g. `5 %3`.
+                        self.drain_buffer();
+                        self.tokens.push(self.add_token(Token::Mod));
+                    } else {
+                        // Just a "%" in the template, e.g. `<p>100%</p>`.
+                        self.buffer.push('%');
                     }
                 }
 
@@ -408,6 +422,35 @@ impl<'a> Lexer<'a> {
                     }
                 }
 
+                // Assignment, e.g. `<% set total = 5 %>`, or an equality comparison.
+                '=' if self.code_block => {
+                    self.drain_buffer();
+
+                    if iter.next_if_eq(&'=').is_some() {
+                        self.tokens.push(self.add_token(Token::Equals));
+                    } else {
+                        self.tokens.push(self.add_token(Token::Assign));
+                    }
+                }
+
+                '?' => {
+                    if self.code_block {
+                        self.drain_buffer();
+                        self.tokens.push(self.add_token(Token::QuestionMark));
+                    } else {
+                        self.buffer.push(c);
+                    }
+                }
+
+                ':' => {
+                    if self.code_block {
+                        self.drain_buffer();
+                        self.tokens.push(self.add_token(Token::Colon));
+                    } else {
+                        self.buffer.push(c);
+                    }
+                }
+
                 c => self.buffer.push(c),
             }
         }
@@ -435,6 +478,9 @@ impl<'a> Lexer<'a> {
                     "for" => self.tokens.push(self.add_token(Token::For)),
                     "in" => self.tokens.push(self.add_token(Token::In)),
                     "do" => self.tokens.push(self.add_token(Token::Do)),
+                    "extends" => self.tokens.push(self.add_token(Token::Extends)),
+                    "block" => self.tokens.push(self.add_token(Token::Block)),
+                    "set" => self.tokens.push(self.add_token(Token::Set)),
                     "&&" => self.tokens.push(self.add_token(Token::And)),
                     "||" => self.tokens.push(self.add_token(Token::Or)),
                     "==" => self.tokens.push(self.add_token(Token::Equals)),
@@ -19,6 +19,13 @@ pub enum Token {
     // `<% else %>`
     Else,
     End,
+    // `<% extends "layout.html" %>`
+    Extends,
+    // `<% block content %>`
+    Block,
+    // `<% set total = price * quantity %>`
+    Set,
+    Assign,
     BlockStart,
     BlockStartPrint,
     BlockStartPrintRaw,
@@ -45,11 +52,18 @@ pub enum Token {
     GreaterEqualThan,
     LessThan,
     LessEqualThan,
+    // `<% 1..5 %>`
+    Range,
+    // `<% 1..=5 %>`
+    RangeInclusive,
     SquareBracketStart,
     SquareBracketEnd,
     Comma,
     RoundBracketStart,
     RoundBracketEnd,
+    // `<% active ? "on" : "off" %>`
+    QuestionMark,
+    Colon,
 }
 
 impl Token {
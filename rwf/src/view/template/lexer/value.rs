@@ -156,6 +156,18 @@ impl Value {
         }
     }
 
+    /// Remainder of dividing the two numbers, e.g. `7 % 3 == 1`. Floats
+    /// are supported the same way as Rust's `%` operator, e.g. `7.5 % 2 == 1.5`.
+    pub fn rem(&self, other: &Self) -> Self {
+        match (self, other) {
+            (Value::Integer(i1), Value::Integer(i2)) => Value::Integer(i1.wrapping_rem(*i2)),
+            (Value::Integer(i1), Value::Float(f2)) => Value::Float(*i1 as f64 % f2),
+            (Value::Float(f1), Value::Integer(i2)) => Value::Float(f1 % *i2 as f64),
+            (Value::Float(f1), Value::Float(f2)) => Value::Float(f1 % f2),
+            _ => Value::Null,
+        }
+    }
+
     pub fn mul(&self, other: &Self) -> Self {
         match (self, other) {
             (Value::Integer(i1), Value::Integer(i2)) => Value::Integer(i1 * i2),
@@ -187,6 +199,21 @@ impl Value {
     ) -> Result<Self, Error> {
         match method_name {
             "nil" | "null" | "blank" => return Ok(Value::Boolean(self == &Value::Null)),
+            "default" if self != &Value::Interpreter => {
+                return match args {
+                    [default] => Ok(match self {
+                        Value::Null => default.clone(),
+                        Value::String(s) if s.is_empty() => default.clone(),
+                        value => value.clone(),
+                    }),
+                    _ => Err(Error::Runtime(
+                        "default takes one argument: the default value".into(),
+                    )),
+                }
+            }
+            "raw" | "safe" if self != &Value::Interpreter && args.is_empty() => {
+                return Ok(Value::SafeString(self.to_string()))
+            }
             "integer" => {
                 return Ok(Value::Boolean(match self {
                     Value::Integer(_) => true,
@@ -212,6 +239,20 @@ impl Value {
         Ok(match self {
             Value::Integer(value) => match method_name {
                 "abs" => Value::Integer((*value).abs()),
+                "floor" | "ceil" => Value::Integer(*value),
+                // Integers are already rounded, unless rounding to tens, hundreds, etc.
+                "round" => match args {
+                    [] => Value::Integer(*value),
+                    [Value::Integer(places)] if *places >= 0 => Value::Integer(*value),
+                    [Value::Integer(places)] => {
+                        let factor = 10f64.powi(places.unsigned_abs() as i32);
+                        Value::Integer(((*value as f64 / factor).round() * factor) as i64)
+                    }
+                    _ => return Err(Error::Runtime(
+                        "round takes one optional integer argument: the number of decimal places"
+                            .into(),
+                    )),
+                },
                 "to_string" | "to_s" => Value::String(value.to_string()),
                 "to_f" | "to_float" => Value::Float(*value as f64),
                 "clamp_zero" => Value::Integer(std::cmp::max(0, *value)),
@@ -248,7 +289,17 @@ impl Value {
                 "abs" => Value::Float(value.abs()),
                 "ceil" => Value::Float(value.ceil()),
                 "floor" => Value::Float(value.floor()),
-                "round" => Value::Float(value.round()),
+                "round" => match args {
+                    [] => Value::Float(value.round()),
+                    [Value::Integer(places)] => {
+                        let factor = 10f64.powi(*places as i32);
+                        Value::Float((value * factor).round() / factor)
+                    }
+                    _ => return Err(Error::Runtime(
+                        "round takes one optional integer argument: the number of decimal places"
+                            .into(),
+                    )),
+                },
                 "to_string" | "to_s" => Value::String(value.to_string()),
                 "to_i" | "to_integer" => Value::Integer(*value as i64),
                 _ => return Err(Error::UnknownMethod(method_name.into(), "float")),
@@ -264,9 +315,38 @@ impl Value {
                 "title" => Value::String(crate::title_case(&value)),
                 "urlencode" => Value::String(crate::http::urlencode(&value)),
                 "urldecode" => Value::String(crate::http::urldecode(&value)),
-                "len" => Value::Integer(value.len() as i64),
+                "len" | "size" => Value::Integer(value.chars().count() as i64),
                 "is_empty" | "blank" | "empty" => Value::Boolean(value.is_empty()),
                 "br" => Value::SafeString(crate::safe_html(value).replace("\n", "<br>")),
+                "contains" | "includes" => match &args {
+                    &[needle] => Value::Boolean(value.contains(&needle.to_string())),
+                    _ => {
+                        return Err(Error::Runtime(
+                            "contains takes one argument: the substring".into(),
+                        ))
+                    }
+                },
+                "format" => {
+                    let placeholders = value.matches("{}").count();
+
+                    if placeholders != args.len() {
+                        return Err(Error::Runtime(format!(
+                            "format expects {} arguments, but was given {}",
+                            placeholders,
+                            args.len()
+                        )));
+                    }
+
+                    let mut result = String::new();
+                    for (i, part) in value.split("{}").enumerate() {
+                        if i > 0 {
+                            result.push_str(&args[i - 1].to_string());
+                        }
+                        result.push_str(part);
+                    }
+
+                    Value::String(result)
+                }
                 "replace" | "sub" => match &args {
                     &[v, r] => Value::String(value.replace(&v.to_string(), &r.to_string())),
                     _ => {
@@ -314,7 +394,8 @@ impl Value {
                         Value::List(list.clone().into_iter().rev().collect::<Vec<_>>())
                     }
 
-                    "contains" => match &args {
+                    // Elements are compared the same way as with `==`.
+                    "contains" | "includes" => match &args {
                         &[needle] => Value::Boolean(list.contains(&needle)),
 
                         _ => Value::Boolean(false),
@@ -322,12 +403,36 @@ impl Value {
 
                     "empty" | "blank" | "is_empty" => Value::Boolean(list.is_empty()),
 
-                    "len" => Value::Integer(list.len() as i64),
+                    "len" | "size" => Value::Integer(list.len() as i64),
+
+                    // Nested lists and hashes are written with brackets, e.g. `[1, 2]`.
+                    "join" => {
+                        let separator = match args {
+                            [] => String::new(),
+                            [separator] => separator.to_string(),
+                            _ => {
+                                return Err(Error::Runtime(
+                                    "join takes one argument: the separator".into(),
+                                ))
+                            }
+                        };
+
+                        Value::String(
+                            list.iter()
+                                .map(|value| format!("{}", value))
+                                .collect::<Vec<_>>()
+                                .join(&separator),
+                        )
+                    }
 
                     _ => return Err(Error::UnknownMethod(method_name.into(), "list")),
                 },
             },
 
+            // Keys stored in the hash take precedence over functions with the same name,
+            // e.g. `product.size`.
+            Value::Hash(hash) if hash.contains_key(method_name) => hash[method_name].clone(),
+
             Value::Hash(hash) => match method_name {
                 "keys" => Value::List(hash.keys().map(|k| Value::String(k.clone())).collect()),
                 "values" => Value::List(hash.values().cloned().collect()),
@@ -340,12 +445,13 @@ impl Value {
                         .collect::<Vec<_>>(),
                 ),
                 "empty" | "blank" | "is_empty" => Value::Boolean(hash.is_empty()),
-                key => match hash.get(key) {
-                    Some(value) => value.clone(),
-                    None => Value::Null,
-                },
+                "len" | "size" => Value::Integer(hash.len() as i64),
+                _ => Value::Null,
             },
 
+            // Accessing an attribute of a missing value, e.g. `settings.missing.color`.
+            Value::Null if args.is_empty() => Value::Null,
+
             Value::Interpreter => match method_name {
                 "encrypt_number" => match &args {
                     &[Value::Integer(n)] => match crate::crypto::encrypt_number(*n) {
@@ -618,9 +724,12 @@ impl ToTemplateValue for crate::model::Value {
                 use time::format_description::well_known::Rfc2822;
                 timestamp.format(&Rfc2822)?.to_template_value()
             }
+            ModelValue::Date(date) => Ok(Value::String(date.to_string())),
             ModelValue::IpAddr(addr) => Ok(Value::String(addr.to_string())),
             ModelValue::Uuid(uuid) => Ok(Value::String(uuid.to_string())),
-            ModelValue::List(list) => {
+            #[cfg(feature = "decimal")]
+            ModelValue::Decimal(decimal) => Ok(Value::String(decimal.to_string())),
+            ModelValue::List(list) | ModelValue::Array(list) => {
                 let mut new_list = vec![];
                 for item in list.iter() {
                     new_list.push(item.clone().to_template_value()?);
@@ -723,6 +832,15 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_raw() {
+        let v = Value::String("<b>bold</b>".into()).call("raw", &[], &Context::default());
+        assert_eq!(v.unwrap(), Value::SafeString("<b>bold</b>".into()));
+
+        let v = Value::Integer(5).call("safe", &[], &Context::default());
+        assert_eq!(v.unwrap(), Value::SafeString("5".into()));
+    }
+
     #[test]
     fn test_replace() {
         let v = Value::String("Hey Alice, this is Bob".into())
@@ -741,6 +859,153 @@ mod test {
         assert_eq!(v, Value::String("Hey Alice, this is Alice".into()));
     }
 
+    #[test]
+    fn test_format() {
+        let format = |s: &str, args: &[Value]| {
+            Value::String(s.into()).call("format", args, &Context::default())
+        };
+
+        assert_eq!(
+            format("Hello!", &[]).unwrap(),
+            Value::String("Hello!".into())
+        );
+        assert_eq!(
+            format("Hello, {}!", &[Value::String("Alice".into())]).unwrap(),
+            Value::String("Hello, Alice!".into())
+        );
+        assert_eq!(
+            format(
+                "{} has {} messages{}",
+                &[
+                    Value::String("Bob".into()),
+                    Value::Integer(5),
+                    Value::String(".".into())
+                ]
+            )
+            .unwrap(),
+            Value::String("Bob has 5 messages.".into())
+        );
+
+        assert!(matches!(format("Hello, {}!", &[]), Err(Error::Runtime(_))));
+        assert!(matches!(
+            format("Hello!", &[Value::Integer(1)]),
+            Err(Error::Runtime(_))
+        ));
+    }
+
+    #[test]
+    fn test_len() {
+        let call = |value: Value, method: &str| value.call(method, &[], &Context::default());
+
+        let list = Value::List(vec![Value::Integer(1), Value::String("two".into())]);
+        assert_eq!(call(list.clone(), "len").unwrap(), Value::Integer(2));
+        assert_eq!(call(list.clone(), "size").unwrap(), Value::Integer(2));
+        assert_eq!(call(list, "is_empty").unwrap(), Value::Boolean(false));
+
+        let empty = Value::List(vec![]);
+        assert_eq!(call(empty.clone(), "len").unwrap(), Value::Integer(0));
+        assert_eq!(call(empty, "is_empty").unwrap(), Value::Boolean(true));
+
+        // Characters, not bytes.
+        let string = Value::String("héllo wörld".into());
+        assert_eq!(call(string.clone(), "len").unwrap(), Value::Integer(11));
+        assert_eq!(call(string.clone(), "size").unwrap(), Value::Integer(11));
+        assert_eq!(call(string, "is_empty").unwrap(), Value::Boolean(false));
+        assert_eq!(
+            call(Value::String("".into()), "is_empty").unwrap(),
+            Value::Boolean(true)
+        );
+
+        let hash = Value::Hash(HashMap::from([
+            ("one".to_string(), Value::Integer(1)),
+            ("two".to_string(), Value::Integer(2)),
+        ]));
+        assert_eq!(call(hash.clone(), "len").unwrap(), Value::Integer(2));
+        assert_eq!(call(hash.clone(), "size").unwrap(), Value::Integer(2));
+        assert_eq!(call(hash, "is_empty").unwrap(), Value::Boolean(false));
+        assert_eq!(
+            call(Value::Hash(HashMap::new()), "is_empty").unwrap(),
+            Value::Boolean(true)
+        );
+    }
+
+    #[test]
+    fn test_join() {
+        let list = Value::List(vec![
+            Value::Integer(1),
+            Value::String("two".into()),
+            Value::List(vec![Value::Float(3.5), Value::Boolean(true)]),
+        ]);
+
+        assert_eq!(
+            list.call("join", &[Value::String(", ".into())], &Context::default())
+                .unwrap(),
+            Value::String("1, two, [3.5, true]".into())
+        );
+        assert_eq!(
+            list.call("join", &[], &Context::default()).unwrap(),
+            Value::String("1two[3.5, true]".into())
+        );
+        assert_eq!(
+            Value::List(vec![])
+                .call("join", &[Value::String("-".into())], &Context::default())
+                .unwrap(),
+            Value::String("".into())
+        );
+        assert!(list
+            .call(
+                "join",
+                &[Value::String("-".into()), Value::String("+".into())],
+                &Context::default()
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_contains() {
+        let contains = |value: &Value, needle: Value| {
+            value
+                .call("contains", &[needle], &Context::default())
+                .unwrap()
+        };
+
+        let roles = Value::List(vec![
+            Value::String("admin".into()),
+            Value::String("user".into()),
+            Value::Integer(5),
+        ]);
+        assert_eq!(
+            contains(&roles, Value::String("admin".into())),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            contains(&roles, Value::String("guest".into())),
+            Value::Boolean(false)
+        );
+        assert_eq!(contains(&roles, Value::Integer(5)), Value::Boolean(true));
+        // Same as `5 == "5"`.
+        assert_eq!(
+            contains(&roles, Value::String("5".into())),
+            Value::Boolean(false)
+        );
+
+        let string = Value::String("hello world".into());
+        assert_eq!(
+            contains(&string, Value::String("lo wo".into())),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            contains(&string, Value::String("bye".into())),
+            Value::Boolean(false)
+        );
+        assert_eq!(
+            Value::String("version 2".into())
+                .call("includes", &[Value::Integer(2)], &Context::default())
+                .unwrap(),
+            Value::Boolean(true)
+        );
+    }
+
     #[test]
     fn test_title_case() {
         let v = Value::String("hello world, how are you?".into())
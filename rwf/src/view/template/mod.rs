@@ -43,7 +43,7 @@ impl Template {
         };
 
         Ok(Template {
-            program: Program::from_str(&text)?,
+            program: Program::from_str(&text).map_err(|err| err.pretty(&text, Some(path)))?,
             path: Some(path.as_ref().to_owned()),
         })
     }
@@ -61,7 +61,8 @@ impl Template {
     /// ```
     pub fn from_str(template: &str) -> Result<Self, Error> {
         Ok(Template {
-            program: Program::from_str(template)?,
+            program: Program::from_str(template)
+                .map_err(|err| err.pretty(template, None::<&str>))?,
             path: None,
         })
     }
@@ -83,6 +84,25 @@ impl Template {
         }
     }
 
+    /// Render the template, replacing the bodies of its blocks with the ones defined
+    /// by a template extending it.
+    pub(crate) fn render_blocks(
+        &self,
+        context: &Context,
+        blocks: &language::statement::Blocks,
+    ) -> Result<String, Error> {
+        match self.program.evaluate_blocks(context, blocks) {
+            Ok(result) => Ok(result),
+            Err(err) => {
+                if let Some(path) = &self.path {
+                    Err(err.pretty_from_path(path))
+                } else {
+                    Err(err)
+                }
+            }
+        }
+    }
+
     /// [`Self::render`] with an empty context. Used for templates that don't use any variables, or only
     /// have globally defined variables.
     pub fn render_default(&self) -> Result<String, Error> {
@@ -103,6 +123,12 @@ impl Template {
         Self::cached(path)
     }
 
+    /// Remove all templates from the cache, so they are read from disk and compiled
+    /// again the next time they are loaded. Useful in tests.
+    pub fn clear_cache() {
+        Templates::cache().clear();
+    }
+
     /// Set global default values for variables. If the variable isn't defined
     /// in a template context, and a default exists, the default value will be used instead.
     pub fn defaults(context: Context) {